@@ -0,0 +1,4 @@
+fn main() {
+    // The migrations are embedded with `include_dir!`, which doesn't track the files.
+    println!("cargo:rerun-if-changed=migrations");
+}
@@ -0,0 +1,2 @@
+ALTER TABLE todos ADD COLUMN IF NOT EXISTS completed_at timestamp with time zone;
+UPDATE todos SET completed_at = created WHERE completed AND completed_at IS NULL;
@@ -0,0 +1,13 @@
+CREATE TABLE IF NOT EXISTS tags(
+  id serial primary key,
+  name varchar(30) NOT NULL UNIQUE,
+  colour varchar(7) NOT NULL default '#6b7280'
+);
+
+CREATE TABLE IF NOT EXISTS todo_tags(
+  todo_id integer NOT NULL REFERENCES todos(id) ON DELETE CASCADE,
+  tag_id integer NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+  PRIMARY KEY (todo_id, tag_id)
+);
+
+CREATE INDEX IF NOT EXISTS todo_tags_tag_id ON todo_tags(tag_id);
@@ -0,0 +1 @@
+ALTER TABLE todos ADD COLUMN IF NOT EXISTS priority smallint NOT NULL default 0 CHECK (priority BETWEEN 0 AND 4);
@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS checklist_items(
+  id serial primary key,
+  todo_id integer NOT NULL REFERENCES todos(id) ON DELETE CASCADE,
+  position integer NOT NULL,
+  text varchar(200) NOT NULL,
+  done boolean NOT NULL default false
+);
+
+CREATE INDEX IF NOT EXISTS checklist_items_todo_id ON checklist_items(todo_id, position);
@@ -0,0 +1,2 @@
+ALTER TABLE todos ADD COLUMN IF NOT EXISTS parent_id integer REFERENCES todos(id) ON DELETE CASCADE;
+CREATE INDEX IF NOT EXISTS todos_parent_id ON todos(parent_id);
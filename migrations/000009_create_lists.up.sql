@@ -0,0 +1,14 @@
+CREATE TABLE IF NOT EXISTS lists(
+  id serial primary key,
+  name varchar(50) NOT NULL UNIQUE,
+  colour varchar(7) NOT NULL default '#6b7280',
+  position integer NOT NULL
+);
+
+-- Existing todo items move into a default list.
+INSERT INTO lists(name, position) SELECT 'Inbox', 0 WHERE NOT EXISTS (SELECT 1 FROM lists);
+
+ALTER TABLE todos ADD COLUMN IF NOT EXISTS list_id integer REFERENCES lists(id) ON DELETE CASCADE;
+UPDATE todos SET list_id = (SELECT min(id) FROM lists) WHERE list_id IS NULL;
+ALTER TABLE todos ALTER COLUMN list_id SET NOT NULL;
+CREATE INDEX IF NOT EXISTS todos_list_id ON todos(list_id);
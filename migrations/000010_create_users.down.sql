@@ -0,0 +1,11 @@
+-- Fails if two users have a tag or a list with the same name.
+ALTER TABLE lists DROP CONSTRAINT IF EXISTS lists_owner_id_name_key;
+ALTER TABLE lists ADD CONSTRAINT lists_name_key UNIQUE (name);
+ALTER TABLE tags DROP CONSTRAINT IF EXISTS tags_owner_id_name_key;
+ALTER TABLE tags ADD CONSTRAINT tags_name_key UNIQUE (name);
+
+DROP INDEX IF EXISTS todos_owner_id;
+ALTER TABLE lists DROP COLUMN IF EXISTS owner_id;
+ALTER TABLE tags DROP COLUMN IF EXISTS owner_id;
+ALTER TABLE todos DROP COLUMN IF EXISTS owner_id;
+DROP TABLE IF EXISTS users;
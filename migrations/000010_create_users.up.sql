@@ -0,0 +1,19 @@
+CREATE TABLE IF NOT EXISTS users(
+  id serial primary key,
+  username varchar(50) NOT NULL UNIQUE,
+  password_hash text NOT NULL,
+  created timestamp with time zone NOT NULL default now()
+);
+
+-- Rows created before accounts existed have no owner, and stay hidden until
+-- `leptos-todo-new claim <username>` hands them to a user.
+ALTER TABLE todos ADD COLUMN IF NOT EXISTS owner_id integer REFERENCES users(id) ON DELETE CASCADE;
+ALTER TABLE tags ADD COLUMN IF NOT EXISTS owner_id integer REFERENCES users(id) ON DELETE CASCADE;
+ALTER TABLE lists ADD COLUMN IF NOT EXISTS owner_id integer REFERENCES users(id) ON DELETE CASCADE;
+CREATE INDEX IF NOT EXISTS todos_owner_id ON todos(owner_id);
+
+-- Tag and list names only have to be unique for each user.
+ALTER TABLE tags DROP CONSTRAINT IF EXISTS tags_name_key;
+ALTER TABLE tags ADD CONSTRAINT tags_owner_id_name_key UNIQUE (owner_id, name);
+ALTER TABLE lists DROP CONSTRAINT IF EXISTS lists_name_key;
+ALTER TABLE lists ADD CONSTRAINT lists_owner_id_name_key UNIQUE (owner_id, name);
@@ -0,0 +1,6 @@
+DROP TRIGGER IF EXISTS lists_notify ON lists;
+DROP TRIGGER IF EXISTS tags_notify ON tags;
+DROP TRIGGER IF EXISTS todo_tags_notify ON todo_tags;
+DROP TRIGGER IF EXISTS checklist_items_notify ON checklist_items;
+DROP TRIGGER IF EXISTS todos_notify ON todos;
+DROP FUNCTION IF EXISTS notify_todo_change();
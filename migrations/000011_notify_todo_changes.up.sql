@@ -0,0 +1,29 @@
+-- Announces on the todo_changes channel whose data changed, as the user id. Postgres
+-- folds identical notifications from one transaction together, so each user is
+-- announced once per transaction however many rows it touches.
+CREATE OR REPLACE FUNCTION notify_todo_change() RETURNS trigger AS $$
+DECLARE
+    owner integer;
+BEGIN
+    IF TG_TABLE_NAME IN ('checklist_items', 'todo_tags') THEN
+        SELECT owner_id INTO owner FROM todos WHERE id = COALESCE(NEW.todo_id, OLD.todo_id);
+    ELSE
+        owner := COALESCE(NEW.owner_id, OLD.owner_id);
+    END IF;
+    IF owner IS NOT NULL THEN
+        PERFORM pg_notify('todo_changes', owner::text);
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER todos_notify AFTER INSERT OR UPDATE OR DELETE ON todos
+    FOR EACH ROW EXECUTE FUNCTION notify_todo_change();
+CREATE TRIGGER checklist_items_notify AFTER INSERT OR UPDATE OR DELETE ON checklist_items
+    FOR EACH ROW EXECUTE FUNCTION notify_todo_change();
+CREATE TRIGGER todo_tags_notify AFTER INSERT OR UPDATE OR DELETE ON todo_tags
+    FOR EACH ROW EXECUTE FUNCTION notify_todo_change();
+CREATE TRIGGER tags_notify AFTER INSERT OR UPDATE OR DELETE ON tags
+    FOR EACH ROW EXECUTE FUNCTION notify_todo_change();
+CREATE TRIGGER lists_notify AFTER INSERT OR UPDATE OR DELETE ON lists
+    FOR EACH ROW EXECUTE FUNCTION notify_todo_change();
@@ -0,0 +1,2 @@
+-- The secret in the URL of each user's calendar feed, if they turned it on.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS calendar_token text UNIQUE;
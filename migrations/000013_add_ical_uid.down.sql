@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS todos_owner_id_ical_uid;
+ALTER TABLE todos DROP COLUMN IF EXISTS ical_uid;
@@ -0,0 +1,4 @@
+-- The UID of the iCalendar VTODO an item was imported from, so importing the same file
+-- again skips it.
+ALTER TABLE todos ADD COLUMN IF NOT EXISTS ical_uid text;
+CREATE UNIQUE INDEX IF NOT EXISTS todos_owner_id_ical_uid ON todos(owner_id, ical_uid);
@@ -0,0 +1,24 @@
+CREATE TABLE IF NOT EXISTS todos(
+  id integer primary key autoincrement,
+  title text NOT NULL,
+  description text CHECK (length(description) <= 200),
+  completed integer NOT NULL default 0,
+  created text NOT NULL default (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+  due_date text NOT NULL
+);
+
+-- Full-text index over titles, kept in sync with triggers.
+CREATE VIRTUAL TABLE IF NOT EXISTS todos_fts USING fts5(title, content='todos', content_rowid='id');
+
+CREATE TRIGGER IF NOT EXISTS todos_fts_insert AFTER INSERT ON todos BEGIN
+  INSERT INTO todos_fts(rowid, title) VALUES (new.id, new.title);
+END;
+
+CREATE TRIGGER IF NOT EXISTS todos_fts_delete AFTER DELETE ON todos BEGIN
+  INSERT INTO todos_fts(todos_fts, rowid, title) VALUES ('delete', old.id, old.title);
+END;
+
+CREATE TRIGGER IF NOT EXISTS todos_fts_update AFTER UPDATE OF title ON todos BEGIN
+  INSERT INTO todos_fts(todos_fts, rowid, title) VALUES ('delete', old.id, old.title);
+  INSERT INTO todos_fts(rowid, title) VALUES (new.id, new.title);
+END;
@@ -0,0 +1,2 @@
+ALTER TABLE todos ADD COLUMN completed_at text;
+UPDATE todos SET completed_at = created WHERE completed = 1;
@@ -0,0 +1,13 @@
+CREATE TABLE tags(
+  id integer primary key autoincrement,
+  name text NOT NULL UNIQUE,
+  colour text NOT NULL default '#6b7280'
+);
+
+CREATE TABLE todo_tags(
+  todo_id integer NOT NULL REFERENCES todos(id) ON DELETE CASCADE,
+  tag_id integer NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+  PRIMARY KEY (todo_id, tag_id)
+);
+
+CREATE INDEX todo_tags_tag_id ON todo_tags(tag_id);
@@ -0,0 +1 @@
+ALTER TABLE todos ADD COLUMN priority integer NOT NULL default 0 CHECK (priority BETWEEN 0 AND 4);
@@ -0,0 +1,9 @@
+CREATE TABLE checklist_items(
+  id integer primary key autoincrement,
+  todo_id integer NOT NULL REFERENCES todos(id) ON DELETE CASCADE,
+  position integer NOT NULL,
+  text text NOT NULL CHECK (length(text) <= 200),
+  done integer NOT NULL default 0
+);
+
+CREATE INDEX checklist_items_todo_id ON checklist_items(todo_id, position);
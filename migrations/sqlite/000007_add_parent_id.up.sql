@@ -0,0 +1,2 @@
+ALTER TABLE todos ADD COLUMN parent_id integer REFERENCES todos(id) ON DELETE CASCADE;
+CREATE INDEX todos_parent_id ON todos(parent_id);
@@ -0,0 +1,14 @@
+CREATE TABLE lists(
+  id integer primary key autoincrement,
+  name text NOT NULL UNIQUE,
+  colour text NOT NULL default '#6b7280',
+  position integer NOT NULL
+);
+
+-- Existing todo items move into a default list.
+INSERT INTO lists(name, position) VALUES('Inbox', 0);
+
+-- SQLite can't add a NOT NULL foreign key column, so the repository always sets it.
+ALTER TABLE todos ADD COLUMN list_id integer REFERENCES lists(id) ON DELETE CASCADE;
+UPDATE todos SET list_id = (SELECT min(id) FROM lists);
+CREATE INDEX todos_list_id ON todos(list_id);
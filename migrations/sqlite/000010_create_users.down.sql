@@ -0,0 +1,23 @@
+-- Fails if two users have a tag or a list with the same name.
+CREATE TABLE old_lists(
+  id integer primary key autoincrement,
+  name text NOT NULL UNIQUE,
+  colour text NOT NULL default '#6b7280',
+  position integer NOT NULL
+);
+INSERT INTO old_lists(id, name, colour, position) SELECT id, name, colour, position FROM lists;
+DROP TABLE lists;
+ALTER TABLE old_lists RENAME TO lists;
+
+CREATE TABLE old_tags(
+  id integer primary key autoincrement,
+  name text NOT NULL UNIQUE,
+  colour text NOT NULL default '#6b7280'
+);
+INSERT INTO old_tags(id, name, colour) SELECT id, name, colour FROM tags;
+DROP TABLE tags;
+ALTER TABLE old_tags RENAME TO tags;
+
+DROP INDEX todos_owner_id;
+ALTER TABLE todos DROP COLUMN owner_id;
+DROP TABLE users;
@@ -0,0 +1,37 @@
+CREATE TABLE users(
+  id integer primary key autoincrement,
+  username text NOT NULL UNIQUE,
+  password_hash text NOT NULL,
+  created text NOT NULL default (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+-- Rows created before accounts existed have no owner, and stay hidden until
+-- `leptos-todo-new claim <username>` hands them to a user.
+ALTER TABLE todos ADD COLUMN owner_id integer REFERENCES users(id) ON DELETE CASCADE;
+CREATE INDEX todos_owner_id ON todos(owner_id);
+
+-- Tag and list names only have to be unique for each user. SQLite can't change a
+-- table's constraints, so both tables are rebuilt; the runner turns foreign key
+-- enforcement off while it migrates, so dropping the old tables cascades nowhere.
+CREATE TABLE new_tags(
+  id integer primary key autoincrement,
+  name text NOT NULL,
+  colour text NOT NULL default '#6b7280',
+  owner_id integer REFERENCES users(id) ON DELETE CASCADE,
+  UNIQUE (owner_id, name)
+);
+INSERT INTO new_tags(id, name, colour) SELECT id, name, colour FROM tags;
+DROP TABLE tags;
+ALTER TABLE new_tags RENAME TO tags;
+
+CREATE TABLE new_lists(
+  id integer primary key autoincrement,
+  name text NOT NULL,
+  colour text NOT NULL default '#6b7280',
+  position integer NOT NULL,
+  owner_id integer REFERENCES users(id) ON DELETE CASCADE,
+  UNIQUE (owner_id, name)
+);
+INSERT INTO new_lists(id, name, colour, position) SELECT id, name, colour, position FROM lists;
+DROP TABLE lists;
+ALTER TABLE new_lists RENAME TO lists;
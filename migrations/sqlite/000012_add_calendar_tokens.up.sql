@@ -0,0 +1,4 @@
+-- The secret in the URL of each user's calendar feed, if they turned it on. SQLite
+-- can't add a UNIQUE column, so the index enforces it.
+ALTER TABLE users ADD COLUMN calendar_token text;
+CREATE UNIQUE INDEX users_calendar_token ON users(calendar_token);
@@ -0,0 +1,2 @@
+DROP INDEX todos_owner_id_ical_uid;
+ALTER TABLE todos DROP COLUMN ical_uid;
@@ -1,5 +1,13 @@
+use crate::components::auth::{LoginPage, SignupPage};
+use crate::components::backup::BackupPage;
+use crate::components::calendar::CalendarPage;
+use crate::components::completed::CompletedTodoList;
+use crate::components::import::ImportPage;
+use crate::components::list::{provide_lists, ListsPage};
 use crate::components::sidebar::HeaderWithNavbar;
+use crate::components::tag::TagsPage;
 use crate::components::todo::TodoList;
+use crate::components::trash::TrashList;
 use leptos::{component, view, IntoView};
 use leptos_meta::{provide_meta_context, Stylesheet, Title};
 use leptos_router::{Route, Router, Routes};
@@ -8,6 +16,8 @@ use leptos_router::{Route, Router, Routes};
 pub fn App() -> impl IntoView {
     // Provides context that manages stylesheets, titles, meta tags, etc.
     provide_meta_context();
+    // The sidebar and the pages share the lists and their counts.
+    provide_lists();
 
     view! {
         // injects a stylesheet into the document <head>
@@ -21,7 +31,17 @@ pub fn App() -> impl IntoView {
             <main>
                 <Routes>
                     <Route path="" view=TodoList/>
+                    <Route path="/list/:id" view=TodoList/>
+                    <Route path="/lists" view=ListsPage/>
+                    <Route path="/completed" view=CompletedTodoList/>
+                    <Route path="/tags" view=TagsPage/>
+                    <Route path="/trash" view=TrashList/>
+                    <Route path="/backup" view=BackupPage/>
+                    <Route path="/import" view=ImportPage/>
+                    <Route path="/calendar" view=CalendarPage/>
                     <Route path="/about" view=AboutPage/>
+                    <Route path="/login" view=LoginPage/>
+                    <Route path="/signup" view=SignupPage/>
                     <Route path="/*any" view=NotFound/>
                 </Routes>
             </main>
@@ -35,6 +55,11 @@ fn AboutPage() -> impl IntoView {
         <div class="container mx-auto text-center mt-2">
             <h1 class="font-bold text-xl">"About"</h1>
             <p class="fond-semibold text-lg">"A basic app made with Leptos, a Rust framework for building CSR and SSR web applications."</p>
+            <p class="mt-2">
+                "Scripts can manage todos through the "
+                <a href="/api/docs/" rel="external" class="text-blue-600 underline">"JSON API"</a>
+                "."
+            </p>
         </div>
     }
 }
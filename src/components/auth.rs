@@ -0,0 +1,139 @@
+use crate::components::todo::{error_message, server_field_errors, FORM_ERROR_STYLE};
+use crate::server::auth::{get_current_user, Login, Logout, Signup};
+use crate::server::error::TodoError;
+use crate::validation::todo::error_for;
+use crate::validation::user::{PASSWORD_MAX_CHARS, PASSWORD_MIN_CHARS, USERNAME_MAX_CHARS};
+use leptos::{
+    component, create_effect, create_resource, create_server_action, view, IntoView, RwSignal,
+    ServerFnError, SignalGet, Transition,
+};
+use leptos_router::ActionForm;
+
+const AUTH_FIELD_STYLE: &str = "pl-1 mt-1 block w-full border-gray-300 rounded-md shadow-sm focus:border-blue-500 focus:ring focus:ring-blue-200";
+const AUTH_LABEL_STYLE: &str = "block text-gray-700";
+const AUTH_SUBMIT_STYLE: &str =
+    "w-full bg-blue-400 text-white font-bold py-2 rounded-md hover:bg-blue-700";
+const LOGOUT_BUTTON_STYLE: &str =
+    "block w-full text-left py-2 px-4 text-gray-700 hover:bg-gray-200 rounded";
+
+/// Loads `path` from scratch once the action behind `value` succeeds, so nothing from
+/// the previous session stays on screen.
+fn load_on_success(
+    value: RwSignal<Option<Result<(), ServerFnError<TodoError>>>>,
+    path: &'static str,
+) {
+    create_effect(move |_| {
+        if let Some(Ok(())) = value.get() {
+            let _ = leptos::window().location().set_href(path);
+        }
+    });
+}
+
+/// The error for a failed submission that isn't about a single field.
+fn form_error(value: RwSignal<Option<Result<(), ServerFnError<TodoError>>>>) -> Option<String> {
+    value.get().and_then(|result| match result {
+        Err(ServerFnError::WrappedServerError(TodoError::Validation { .. })) | Ok(_) => None,
+        Err(e) => Some(error_message(&e)),
+    })
+}
+
+#[component]
+pub fn LoginPage() -> impl IntoView {
+    let action = create_server_action::<Login>();
+    load_on_success(action.value(), "/");
+
+    view! {
+        <div class="container mx-auto mt-6">
+            <div class="w-1/3 mx-auto space-y-4">
+                <h2 class="text-lg font-bold">"Log in"</h2>
+                <ActionForm action class="space-y-4">
+                    <label class=AUTH_LABEL_STYLE>
+                        "Username"
+                        <input type="text" name="username" required autocomplete="username" class=AUTH_FIELD_STYLE/>
+                    </label>
+                    <label class=AUTH_LABEL_STYLE>
+                        "Password"
+                        <input type="password" name="password" required autocomplete="current-password" class=AUTH_FIELD_STYLE/>
+                    </label>
+                    <p class=FORM_ERROR_STYLE>{move || form_error(action.value())}</p>
+                    <button type="submit" class=AUTH_SUBMIT_STYLE>"Log in"</button>
+                </ActionForm>
+                <p class="text-sm text-gray-700">
+                    "No account yet? "<a href="/signup" class="text-blue-600 hover:text-blue-800">"Sign up"</a>
+                </p>
+            </div>
+        </div>
+    }
+}
+
+#[component]
+pub fn SignupPage() -> impl IntoView {
+    let action = create_server_action::<Signup>();
+    load_on_success(action.value(), "/");
+    let errors = move || server_field_errors(action.value().get());
+
+    view! {
+        <div class="container mx-auto mt-6">
+            <div class="w-1/3 mx-auto space-y-4">
+                <h2 class="text-lg font-bold">"Sign up"</h2>
+                <ActionForm action class="space-y-4">
+                    <label class=AUTH_LABEL_STYLE>
+                        "Username"
+                        <input
+                            type="text"
+                            name="username"
+                            required
+                            maxlength=USERNAME_MAX_CHARS
+                            autocomplete="username"
+                            class=AUTH_FIELD_STYLE
+                        />
+                    </label>
+                    <p class=FORM_ERROR_STYLE>{move || error_for(&errors(), "username")}</p>
+                    <label class=AUTH_LABEL_STYLE>
+                        "Password"
+                        <input
+                            type="password"
+                            name="password"
+                            required
+                            minlength=PASSWORD_MIN_CHARS
+                            maxlength=PASSWORD_MAX_CHARS
+                            autocomplete="new-password"
+                            class=AUTH_FIELD_STYLE
+                        />
+                    </label>
+                    <p class=FORM_ERROR_STYLE>{move || error_for(&errors(), "password")}</p>
+                    <p class=FORM_ERROR_STYLE>{move || form_error(action.value())}</p>
+                    <button type="submit" class=AUTH_SUBMIT_STYLE>"Sign up"</button>
+                </ActionForm>
+                <p class="text-sm text-gray-700">
+                    "Already have an account? "<a href="/login" class="text-blue-600 hover:text-blue-800">"Log in"</a>
+                </p>
+            </div>
+        </div>
+    }
+}
+
+/// Who is logged in, with a button to log out. Shows nothing for visitors.
+#[component]
+pub fn AccountMenu() -> impl IntoView {
+    let user = create_resource(|| (), |_| async move { get_current_user().await });
+    let logout = create_server_action::<Logout>();
+    load_on_success(logout.value(), "/login");
+
+    view! {
+        <Transition fallback=|| ()>
+            {move || {
+                user.get().and_then(Result::ok).flatten().map(|user| view! {
+                    <li class="mb-2 py-2 px-4 text-sm text-gray-500">
+                        {format!("Logged in as {}", user.username)}
+                    </li>
+                    <li class="mb-2">
+                        <button class=LOGOUT_BUTTON_STYLE on:click=move |_| logout.dispatch(Logout {})>
+                            "Log out"
+                        </button>
+                    </li>
+                })
+            }}
+        </Transition>
+    }
+}
@@ -0,0 +1,142 @@
+use crate::components::list::refetch_lists_on;
+use crate::components::todo::{error_message, FORM_ERROR_STYLE};
+use crate::components::upload::{picked_file, read_text};
+use crate::server::backup::{ImportBackup, ImportReport, BACKUP_PATH};
+use crate::server::error::TodoError;
+use leptos::ev::Event;
+use leptos::{
+    component, create_effect, create_rw_signal, create_server_action, spawn_local, view, IntoView,
+    ServerFnError, SignalGet, SignalGetUntracked, SignalSet,
+};
+
+const BUTTON_STYLE: &str =
+    "bg-blue-400 text-white font-bold py-1 px-4 rounded-md hover:bg-blue-700 disabled:opacity-50";
+
+/// Downloads everything as a backup file, and restores backups: picking a file shows
+/// what restoring it would add, which the user then confirms.
+#[component]
+pub fn BackupPage() -> impl IntoView {
+    let import_action = create_server_action::<ImportBackup>();
+    refetch_lists_on(move || import_action.version().get());
+
+    // The picked backup, kept between the preview and restoring it for real.
+    let contents = create_rw_signal(None::<String>);
+    let read_error = create_rw_signal(None::<String>);
+    create_effect(move |_| {
+        if let Some(Ok(report)) = import_action.value().get() {
+            if !report.dry_run {
+                contents.set(None);
+            }
+        }
+    });
+
+    let on_pick = move |ev: Event| {
+        contents.set(None);
+        read_error.set(None);
+        let Some(file) = picked_file(&ev) else {
+            return;
+        };
+        spawn_local(async move {
+            match read_text(&file).await {
+                Ok(text) => {
+                    contents.set(Some(text.clone()));
+                    import_action.dispatch(ImportBackup {
+                        contents: text,
+                        dry_run: true,
+                    });
+                }
+                Err(e) => read_error.set(Some(e)),
+            }
+        });
+    };
+    let on_restore = move |_| {
+        if let Some(text) = contents.get_untracked() {
+            import_action.dispatch(ImportBackup {
+                contents: text,
+                dry_run: false,
+            });
+        }
+    };
+
+    let outcome = move || {
+        let result = import_action.value().get()?;
+        Some(match result {
+            Ok(report) if report.dry_run => view! {
+                <p class="font-semibold">"Restoring this backup adds:"</p>
+                <ReportSummary report/>
+                <button
+                    class=BUTTON_STYLE
+                    disabled=move || import_action.pending().get() || contents.get().is_none()
+                    on:click=on_restore
+                >
+                    "Restore"
+                </button>
+            }
+            .into_view(),
+            Ok(report) => view! {
+                <p class="font-semibold">"The backup was restored. It added:"</p>
+                <ReportSummary report/>
+            }
+            .into_view(),
+            Err(ServerFnError::WrappedServerError(TodoError::Validation { errors })) => view! {
+                <p class=FORM_ERROR_STYLE>"This backup can't be restored:"</p>
+                <ul class="list-disc pl-6 text-sm text-red-600">
+                    {errors
+                        .into_iter()
+                        .map(|e| view! { <li>{format!("{}: {}", e.field, e.message)}</li> })
+                        .collect::<Vec<_>>()}
+                </ul>
+            }
+            .into_view(),
+            Err(e) => view! { <p class=FORM_ERROR_STYLE>{error_message(&e)}</p> }.into_view(),
+        })
+    };
+
+    view! {
+        <div class="container mx-auto mt-6">
+            <div class="w-3/4 mx-auto space-y-4">
+                <h2 class="text-lg font-bold">"Backup"</h2>
+                <p>
+                    "A backup holds every list, tag and todo item, completed and trashed ones included. "
+                    <a href=BACKUP_PATH rel="external" download class="text-blue-600 underline">
+                        "Download a backup"
+                    </a>
+                </p>
+                <h2 class="text-lg font-bold">"Restore"</h2>
+                <p>
+                    "Restoring adds everything in a backup to what's already here. Lists and tags "
+                    "with the same name as one of yours are merged into it."
+                </p>
+                <input type="file" accept=".json,application/json" on:change=on_pick/>
+                <p class=FORM_ERROR_STYLE>{read_error}</p>
+                {outcome}
+            </div>
+        </div>
+    }
+}
+
+/// What a restore adds, one line per kind of thing.
+#[component]
+fn ReportSummary(report: ImportReport) -> impl IntoView {
+    let names = |label: &str, names: Vec<String>| {
+        (!names.is_empty()).then(|| view! { <li>{format!("{label}: {}", names.join(", "))}</li> })
+    };
+
+    view! {
+        <ul class="list-disc pl-6">
+            <li>
+                {format!(
+                    "{} todo items, {} of them completed and {} in the trash",
+                    report.todos,
+                    report.completed,
+                    report.trashed,
+                )}
+            </li>
+            <li>{format!("{} checklist items", report.checklist_items)}</li>
+            {names("New lists", report.new_lists)}
+            {names("Merged into your lists", report.merged_lists)}
+            {names("New tags", report.new_tags)}
+            {names("Merged into your tags", report.merged_tags)}
+        </ul>
+    }
+}
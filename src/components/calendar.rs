@@ -0,0 +1,94 @@
+use crate::components::todo::{error_message, FORM_ERROR_STYLE};
+use crate::server::calendar::{get_calendar_feed, ResetCalendarFeed, TurnOffCalendarFeed};
+use leptos::{
+    component, create_resource, create_server_action, view, IntoView, SignalGet, Transition,
+};
+
+const BUTTON_STYLE: &str =
+    "bg-blue-400 text-white font-bold py-1 px-4 rounded-md hover:bg-blue-700 disabled:opacity-50";
+
+/// Turns the calendar feed on and off, and shows its URL for subscribing to it.
+#[component]
+pub fn CalendarPage() -> impl IntoView {
+    let reset_action = create_server_action::<ResetCalendarFeed>();
+    let turn_off_action = create_server_action::<TurnOffCalendarFeed>();
+    let feed = create_resource(
+        move || {
+            (
+                reset_action.version().get(),
+                turn_off_action.version().get(),
+            )
+        },
+        |_| async move { get_calendar_feed().await },
+    );
+    let pending = move || reset_action.pending().get() || turn_off_action.pending().get();
+
+    let on_reset = move |_| reset_action.dispatch(ResetCalendarFeed {});
+    let on_turn_off = move |_| turn_off_action.dispatch(TurnOffCalendarFeed {});
+
+    let feed_view = move || {
+        feed.get().map(|result| match result {
+            Ok(Some(url)) => {
+                // Calendar apps open webcal links as subscriptions.
+                let webcal = url
+                    .split_once("://")
+                    .map(|(_, rest)| format!("webcal://{rest}"))
+                    .unwrap_or_else(|| url.clone());
+                view! {
+                    <p>
+                        "Subscribe to this URL in your calendar app, or "
+                        <a href=webcal rel="external" class="text-blue-600 underline">
+                            "open it there"
+                        </a>
+                        ". Anyone with the URL can see your todo items, so keep it to yourself."
+                    </p>
+                    <input
+                        type="text"
+                        readonly
+                        class="w-full text-sm border-gray-300 rounded-md"
+                        value=url
+                    />
+                    <div class="space-x-2">
+                        <button class=BUTTON_STYLE disabled=pending on:click=on_reset>
+                            "Replace the URL"
+                        </button>
+                        <button class=BUTTON_STYLE disabled=pending on:click=on_turn_off>
+                            "Turn off"
+                        </button>
+                    </div>
+                }
+                .into_view()
+            }
+            Ok(None) => view! {
+                <p>"The calendar feed is off."</p>
+                <button class=BUTTON_STYLE disabled=pending on:click=on_reset>
+                    "Turn on"
+                </button>
+            }
+            .into_view(),
+            Err(e) => view! { <p class=FORM_ERROR_STYLE>{error_message(&e)}</p> }.into_view(),
+        })
+    };
+    let action_error = move || {
+        let reset = reset_action.value().get().and_then(Result::err);
+        let turn_off = turn_off_action.value().get().and_then(Result::err);
+        reset
+            .or(turn_off)
+            .map(|e| view! { <p class=FORM_ERROR_STYLE>{error_message(&e)}</p> })
+    };
+
+    view! {
+        <div class="container mx-auto mt-6">
+            <div class="w-3/4 mx-auto space-y-4">
+                <h2 class="text-lg font-bold">"Calendar"</h2>
+                <p>
+                    "The calendar feed shows each todo item that isn't in the trash on its due "
+                    "date, marked done once it's completed. Calendar apps check it for changes "
+                    "every 15 minutes or so."
+                </p>
+                <Transition fallback=move || view! { <p>"Loading..."</p> }>{feed_view}</Transition>
+                {action_error}
+            </div>
+        </div>
+    }
+}
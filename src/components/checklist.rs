@@ -0,0 +1,132 @@
+use crate::components::todo::{TodoActions, FORM_ERROR_STYLE};
+use crate::server::checklist::{
+    AddChecklistItem, ChecklistItem, DeleteChecklistItem, MoveChecklistItem, SetChecklistItemDone,
+};
+use crate::validation::checklist::check_checklist_text;
+use leptos::{
+    component, create_rw_signal, event_target_checked, event_target_value, use_context, view,
+    IntoView, SignalGet, SignalGetUntracked, SignalSet,
+};
+
+const MOVE_BUTTON_STYLE: &str = "px-1 text-gray-500 hover:text-gray-800 disabled:opacity-30";
+
+/// How much of a checklist is done, e.g. "3/5". Shows nothing for an empty checklist.
+#[component]
+pub fn ChecklistProgress(items: Vec<ChecklistItem>) -> impl IntoView {
+    if items.is_empty() {
+        return None;
+    }
+    let done = items.iter().filter(|item| item.done).count();
+    let colour = if done == items.len() {
+        "bg-green-100 text-green-800"
+    } else {
+        "bg-gray-100 text-gray-700"
+    };
+    Some(view! {
+        <span class=format!("ml-2 px-2 py-0.5 rounded text-xs font-medium align-middle {colour}")>
+            {format!("{done}/{}", items.len())}
+        </span>
+    })
+}
+
+/// The checklist of a todo item, with inline controls to tick, reorder and delete its
+/// entries and a field to add new ones.
+#[component]
+pub fn Checklist(todo_id: i32, items: Vec<ChecklistItem>) -> impl IntoView {
+    let actions = use_context::<TodoActions>().expect("need TodoActions to edit checklists");
+
+    let text = create_rw_signal(String::new());
+    let error = create_rw_signal(None::<String>);
+
+    let on_keydown = move |ev: leptos::ev::KeyboardEvent| {
+        if ev.key() != "Enter" {
+            return;
+        }
+        ev.prevent_default();
+        let value = text.get_untracked();
+        error.set(check_checklist_text(&value));
+        if error.get_untracked().is_none() {
+            actions.add_checklist_item.dispatch(AddChecklistItem {
+                todo_id,
+                text: value,
+            });
+            text.set(String::new());
+        }
+    };
+
+    let last = items.len().saturating_sub(1);
+    let rows = items
+        .into_iter()
+        .enumerate()
+        .map(|(index, item)| {
+            let id = item.id;
+            let position = index as u32;
+            view! {
+                <li class="flex items-center text-sm text-gray-700">
+                    <input
+                        type="checkbox"
+                        checked=item.done
+                        class="mr-2 h-4 w-4 border-gray-300 rounded"
+                        on:change=move |ev| {
+                            actions.set_checklist_item_done.dispatch(SetChecklistItemDone {
+                                id,
+                                done: event_target_checked(&ev),
+                            })
+                        }
+                    />
+                    <span class="flex-grow" class=("line-through", item.done)>{item.text}</span>
+                    <button
+                        class=MOVE_BUTTON_STYLE
+                        title="Move up"
+                        disabled=index == 0
+                        on:click=move |_| {
+                            actions.move_checklist_item.dispatch(MoveChecklistItem {
+                                id,
+                                position: position.saturating_sub(1),
+                            })
+                        }
+                    >
+                        "↑"
+                    </button>
+                    <button
+                        class=MOVE_BUTTON_STYLE
+                        title="Move down"
+                        disabled=index == last
+                        on:click=move |_| {
+                            actions.move_checklist_item.dispatch(MoveChecklistItem {
+                                id,
+                                position: position + 1,
+                            })
+                        }
+                    >
+                        "↓"
+                    </button>
+                    <button
+                        class="px-1 text-red-600 hover:text-red-800"
+                        title="Delete"
+                        on:click=move |_| {
+                            actions.delete_checklist_item.dispatch(DeleteChecklistItem { id })
+                        }
+                    >
+                        "×"
+                    </button>
+                </li>
+            }
+        })
+        .collect::<Vec<_>>();
+
+    view! {
+        <div class="mt-2">
+            <ul class="space-y-1">{rows}</ul>
+            <input
+                type="text"
+                placeholder="Add a checklist item"
+                class="mt-1 pl-1 text-sm border-gray-300 rounded-md"
+                prop:value=text
+                on:input=move |ev| text.set(event_target_value(&ev))
+                on:keydown=on_keydown
+            />
+            <p class=FORM_ERROR_STYLE>{move || error.get()}</p>
+        </div>
+    }
+}
@@ -0,0 +1,48 @@
+use crate::components::tag::provide_tags;
+use crate::components::todo::{
+    provide_todo_actions, NotificationComponent, Pagination, TodoItems, TodosResource,
+};
+use crate::server::todo::{get_completed_todos, SortOrder};
+use leptos::{
+    component, create_resource, create_rw_signal, provide_context, view, IntoView, SignalGet,
+    Transition,
+};
+
+/// The todo items that have been completed, most recently completed first. Unticking an
+/// item moves it back to the main list.
+#[component]
+pub fn CompletedTodoList() -> impl IntoView {
+    let current_page = create_rw_signal(0u32);
+
+    let actions = provide_todo_actions();
+    provide_tags();
+
+    let refetch_resource: TodosResource = create_resource(
+        move || {
+            (
+                actions.version(),
+                current_page.get(),
+                Vec::new(),
+                SortOrder::default(),
+                None,
+            )
+        },
+        |(_, page, _, _, _)| async move { get_completed_todos(page).await },
+    );
+
+    provide_context(current_page);
+    provide_context(refetch_resource);
+
+    view! {
+        <NotificationComponent/>
+        <div class="container mx-auto mt-6">
+            <div class="w-3/4 mx-auto space-y-4">
+                <h2 class="text-lg font-bold">"Completed"</h2>
+                <Transition fallback=move || view! { <p>"Loading todos..."</p> }>
+                    <TodoItems empty_message="You haven't completed any todo items yet."/>
+                </Transition>
+                <Pagination/>
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,344 @@
+use crate::components::list::{refetch_lists_on, ListOptions};
+use crate::components::todo::{error_message, FORM_ERROR_STYLE};
+use crate::components::upload::{picked_file, read_text};
+use crate::server::calendar::{IcsImportReport, ImportIcs};
+use crate::server::csv::{CsvField, CsvImportReport, CsvPreview, ImportCsv, PreviewCsv};
+use leptos::ev::Event;
+use leptos::{
+    component, create_effect, create_rw_signal, create_server_action, event_target_value,
+    spawn_local, view, IntoView, RwSignal, SignalGet, SignalGetUntracked, SignalSet, SignalUpdate,
+    SignalWith,
+};
+
+const BUTTON_STYLE: &str =
+    "bg-blue-400 text-white font-bold py-1 px-4 rounded-md hover:bg-blue-700 disabled:opacity-50";
+
+/// Adds todo items from files made by other apps.
+#[component]
+pub fn ImportPage() -> impl IntoView {
+    view! {
+        <div class="container mx-auto mt-6">
+            <div class="w-3/4 mx-auto space-y-4">
+                <CsvImport/>
+                <IcsImport/>
+            </div>
+        </div>
+    }
+}
+
+/// Imports a CSV file: picking one shows its first rows, under which the user says
+/// which field each column holds before importing it.
+#[component]
+fn CsvImport() -> impl IntoView {
+    let preview_action = create_server_action::<PreviewCsv>();
+    let import_action = create_server_action::<ImportCsv>();
+    refetch_lists_on(move || import_action.version().get());
+
+    // The picked file and its mapping, kept between the preview and the import.
+    let contents = create_rw_signal(None::<String>);
+    let fields = create_rw_signal(Vec::<CsvField>::new());
+    let list_id = create_rw_signal(None::<i32>);
+    let read_error = create_rw_signal(None::<String>);
+    create_effect(move |_| {
+        if let Some(Ok(preview)) = preview_action.value().get() {
+            fields.set(preview.fields);
+        }
+    });
+
+    let on_pick = move |ev: Event| {
+        contents.set(None);
+        read_error.set(None);
+        preview_action.value().set(None);
+        import_action.value().set(None);
+        let Some(file) = picked_file(&ev) else {
+            return;
+        };
+        spawn_local(async move {
+            match read_text(&file).await {
+                Ok(text) => {
+                    contents.set(Some(text.clone()));
+                    preview_action.dispatch(PreviewCsv { contents: text });
+                }
+                Err(e) => read_error.set(Some(e)),
+            }
+        });
+    };
+    let on_import = move |_| {
+        if let Some(text) = contents.get_untracked() {
+            import_action.dispatch(ImportCsv {
+                contents: text,
+                fields: fields.get_untracked(),
+                list_id: list_id.get_untracked(),
+            });
+        }
+    };
+
+    let preview = move || {
+        let result = preview_action.value().get()?;
+        Some(match result {
+            Ok(preview) => view! {
+                <PreviewTable preview fields/>
+                <div class="flex items-center space-x-2">
+                    <label for="csv_list_id">"Add them to"</label>
+                    <select
+                        id="csv_list_id"
+                        class="text-sm border-gray-300 rounded-md"
+                        on:change=move |ev| list_id.set(event_target_value(&ev).parse().ok())
+                    >
+                        <ListOptions selected=list_id/>
+                    </select>
+                    <button
+                        class=BUTTON_STYLE
+                        disabled=move || import_action.pending().get() || contents.get().is_none()
+                        on:click=on_import
+                    >
+                        "Import"
+                    </button>
+                </div>
+            }
+            .into_view(),
+            Err(e) => view! { <p class=FORM_ERROR_STYLE>{error_message(&e)}</p> }.into_view(),
+        })
+    };
+    let outcome = move || {
+        let result = import_action.value().get()?;
+        Some(match result {
+            Ok(report) => view! { <ImportSummary report/> }.into_view(),
+            Err(e) => view! { <p class=FORM_ERROR_STYLE>{error_message(&e)}</p> }.into_view(),
+        })
+    };
+
+    view! {
+        <h2 class="text-lg font-bold">"CSV"</h2>
+        <p>
+            "The first row of the file names its columns. Each of the others becomes a todo "
+            "item, with the title and due date, as YYYY-MM-DD, taken from the columns you pick."
+        </p>
+        <input type="file" accept=".csv,text/csv" on:change=on_pick/>
+        <p class=FORM_ERROR_STYLE>{read_error}</p>
+        {preview}
+        {outcome}
+    }
+}
+
+/// The first rows of a CSV file, with a picker above each column for the field it holds.
+#[component]
+fn PreviewTable(preview: CsvPreview, fields: RwSignal<Vec<CsvField>>) -> impl IntoView {
+    let CsvPreview {
+        headers,
+        rows,
+        total_rows,
+        ..
+    } = preview;
+    let shown = rows.len();
+
+    let pickers = (0..headers.len())
+        .map(|column| {
+            let on_change = move |ev| {
+                let value = event_target_value(&ev);
+                if let Some(field) = CsvField::ALL.into_iter().find(|f| f.as_str() == value) {
+                    fields.update(|fields| {
+                        if let Some(picked) = fields.get_mut(column) {
+                            *picked = field;
+                        }
+                    });
+                }
+            };
+            view! {
+                <th class="p-1">
+                    <select class="text-sm border-gray-300 rounded-md" on:change=on_change>
+                        {CsvField::ALL
+                            .into_iter()
+                            .map(|field| {
+                                let selected = move || {
+                                    fields.with(|fields| fields.get(column) == Some(&field))
+                                };
+                                view! {
+                                    <option value=field.as_str() selected=selected>
+                                        {field.label()}
+                                    </option>
+                                }
+                            })
+                            .collect::<Vec<_>>()}
+                    </select>
+                </th>
+            }
+        })
+        .collect::<Vec<_>>();
+    let cell = |text: String| view! { <td class="p-1 border-t truncate max-w-xs">{text}</td> };
+
+    view! {
+        <table class="text-sm text-left">
+            <thead>
+                <tr>{pickers}</tr>
+                <tr>
+                    {headers
+                        .into_iter()
+                        .map(|header| view! { <th class="p-1">{header}</th> })
+                        .collect::<Vec<_>>()}
+                </tr>
+            </thead>
+            <tbody>
+                {rows
+                    .into_iter()
+                    .map(|row| view! { <tr>{row.into_iter().map(cell).collect::<Vec<_>>()}</tr> })
+                    .collect::<Vec<_>>()}
+            </tbody>
+        </table>
+        <p class="text-sm text-gray-600">
+            {format!("Showing {shown} of {total_rows} rows.")}
+        </p>
+    }
+}
+
+/// How many items an import added, and the rows it left out with why.
+#[component]
+fn ImportSummary(report: CsvImportReport) -> impl IntoView {
+    let rejected = (!report.rejected.is_empty()).then(|| {
+        view! {
+            <p class=FORM_ERROR_STYLE>"These rows weren't imported:"</p>
+            <ul class="list-disc pl-6 text-sm text-red-600">
+                {report
+                    .rejected
+                    .into_iter()
+                    .map(|row| {
+                        let messages = row
+                            .errors
+                            .into_iter()
+                            .map(|e| e.message)
+                            .collect::<Vec<_>>()
+                            .join("; ");
+                        view! { <li>{format!("Line {}: {messages}", row.line)}</li> }
+                    })
+                    .collect::<Vec<_>>()}
+            </ul>
+        }
+    });
+
+    view! {
+        <p class="font-semibold">{format!("Imported {} todo items.", report.imported)}</p>
+        {rejected}
+    }
+}
+
+/// Imports the VTODOs of an iCalendar file, exported by a calendar or task app.
+#[component]
+fn IcsImport() -> impl IntoView {
+    let import_action = create_server_action::<ImportIcs>();
+    refetch_lists_on(move || import_action.version().get());
+
+    let contents = create_rw_signal(None::<String>);
+    let list_id = create_rw_signal(None::<i32>);
+    let read_error = create_rw_signal(None::<String>);
+
+    let on_pick = move |ev: Event| {
+        contents.set(None);
+        read_error.set(None);
+        import_action.value().set(None);
+        let Some(file) = picked_file(&ev) else {
+            return;
+        };
+        spawn_local(async move {
+            match read_text(&file).await {
+                Ok(text) => contents.set(Some(text)),
+                Err(e) => read_error.set(Some(e)),
+            }
+        });
+    };
+    let on_import = move |_| {
+        if let Some(text) = contents.get_untracked() {
+            import_action.dispatch(ImportIcs {
+                contents: text,
+                list_id: list_id.get_untracked(),
+            });
+        }
+    };
+
+    let outcome = move || {
+        let result = import_action.value().get()?;
+        Some(match result {
+            Ok(report) => view! { <IcsSummary report/> }.into_view(),
+            Err(e) => view! { <p class=FORM_ERROR_STYLE>{error_message(&e)}</p> }.into_view(),
+        })
+    };
+
+    view! {
+        <h2 class="text-lg font-bold">"iCalendar"</h2>
+        <p>
+            "Each task (VTODO) in an .ics file becomes a todo item, keeping its title, "
+            "description, due date, priority, whether it's completed and how it repeats, "
+            "where todo items can repeat that way. Tasks imported before are skipped."
+        </p>
+        <input type="file" accept=".ics,text/calendar" on:change=on_pick/>
+        <p class=FORM_ERROR_STYLE>{read_error}</p>
+        <div class="flex items-center space-x-2">
+            <label for="ics_list_id">"Add them to"</label>
+            <select
+                id="ics_list_id"
+                class="text-sm border-gray-300 rounded-md"
+                on:change=move |ev| list_id.set(event_target_value(&ev).parse().ok())
+            >
+                <ListOptions selected=list_id/>
+            </select>
+            <button
+                class=BUTTON_STYLE
+                disabled=move || import_action.pending().get() || contents.get().is_none()
+                on:click=on_import
+            >
+                "Import"
+            </button>
+        </div>
+        {outcome}
+    }
+}
+
+/// How many items an iCalendar import added, what it skipped and left out, and the tasks
+/// it rejected with why.
+#[component]
+fn IcsSummary(report: IcsImportReport) -> impl IntoView {
+    let duplicates = (!report.duplicates.is_empty()).then(|| {
+        view! {
+            <p>
+                {format!(
+                    "Skipped because they were imported before: {}",
+                    report.duplicates.join(", "),
+                )}
+            </p>
+        }
+    });
+    let dropped = (!report.dropped.is_empty()).then(|| {
+        let names = report
+            .dropped
+            .into_iter()
+            .map(|dropped| format!("{} ({})", dropped.name, dropped.count))
+            .collect::<Vec<_>>();
+        view! { <p>{format!("Left out: {}", names.join(", "))}</p> }
+    });
+    let rejected = (!report.rejected.is_empty()).then(|| {
+        view! {
+            <p class=FORM_ERROR_STYLE>"These tasks weren't imported:"</p>
+            <ul class="list-disc pl-6 text-sm text-red-600">
+                {report
+                    .rejected
+                    .into_iter()
+                    .map(|todo| {
+                        let messages = todo
+                            .errors
+                            .into_iter()
+                            .map(|e| e.message)
+                            .collect::<Vec<_>>()
+                            .join("; ");
+                        view! { <li>{format!("Line {}: {messages}", todo.line)}</li> }
+                    })
+                    .collect::<Vec<_>>()}
+            </ul>
+        }
+    });
+
+    view! {
+        <p class="font-semibold">{format!("Imported {} todo items.", report.imported)}</p>
+        {duplicates}
+        {dropped}
+        {rejected}
+    }
+}
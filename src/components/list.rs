@@ -0,0 +1,254 @@
+use crate::components::todo::{error_message, server_field_errors, FORM_ERROR_STYLE};
+use crate::server::error::TodoError;
+use crate::server::list::{
+    get_lists, CreateList, DeleteList, List, MoveList, RenameList, SetListColour,
+};
+use crate::validation::todo::error_for;
+use leptos::html::Form;
+use leptos::{
+    component, create_effect, create_node_ref, create_resource, create_rw_signal,
+    create_server_action, event_target_value, provide_context, use_context, view, For, IntoView,
+    Resource, ServerFnError, Signal, SignalGet, SignalSet, Transition,
+};
+use leptos_router::ActionForm;
+
+const LIST_FIELD_STYLE: &str = "pl-1 border-gray-300 rounded-md";
+const MOVE_BUTTON_STYLE: &str = "px-1 text-gray-500 hover:text-gray-800 disabled:opacity-30";
+
+/// Every list with its open-item count, shared by the sidebar, the list pages and the
+/// forms. Refetch it after changing lists or todo items.
+pub type ListsResource = Resource<(), Result<Vec<List>, ServerFnError<TodoError>>>;
+
+/// Loads the lists and provides them as context for the whole app.
+pub fn provide_lists() -> ListsResource {
+    let lists: ListsResource = create_resource(|| (), |_| async move { get_lists().await });
+    provide_context(lists);
+    lists
+}
+
+/// Refetches the lists in context whenever `version` changes, so their counts stay
+/// current. Does nothing outside the app, where there are no lists to refresh.
+pub fn refetch_lists_on(version: impl Fn() -> usize + 'static) {
+    let Some(lists) = use_context::<ListsResource>() else {
+        return;
+    };
+    create_effect(move |previous: Option<usize>| {
+        let version = version();
+        if previous.is_some_and(|previous| previous != version) {
+            lists.refetch();
+        }
+        version
+    });
+}
+
+/// A small dot in the list's colour.
+#[component]
+pub fn ListDot(#[prop(into)] colour: String) -> impl IntoView {
+    view! {
+        <span
+            class="inline-block w-3 h-3 mr-2 rounded-full shrink-0"
+            style=format!("background-color: {colour}")
+        ></span>
+    }
+}
+
+/// The lists as the options of a `<select>`, with the one in `selected` picked.
+#[component]
+pub fn ListOptions(#[prop(into)] selected: Signal<Option<i32>>) -> impl IntoView {
+    let lists = use_context::<ListsResource>().expect("need lists to pick from");
+
+    view! {
+        <Transition fallback=|| ()>
+            {move || {
+                lists.get().and_then(Result::ok).map(|lists| {
+                    lists
+                        .into_iter()
+                        .map(|list| {
+                            let id = list.id;
+                            view! {
+                                <option value=id selected=move || selected.get() == Some(id)>
+                                    {list.name}
+                                </option>
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                })
+            }}
+        </Transition>
+    }
+}
+
+/// The name of the list being shown, with its colour.
+#[component]
+pub fn ListHeading(#[prop(into)] list: Signal<Option<i32>>) -> impl IntoView {
+    let lists = use_context::<ListsResource>().expect("need lists to name the current one");
+
+    view! {
+        <Transition fallback=|| ()>
+            {move || {
+                let id = list.get()?;
+                let list = lists
+                    .get()
+                    .and_then(Result::ok)?
+                    .into_iter()
+                    .find(|list| list.id == id)?;
+                Some(view! {
+                    <h2 class="flex items-center text-lg font-bold">
+                        <ListDot colour=list.colour/>
+                        {list.name}
+                    </h2>
+                })
+            }}
+        </Transition>
+    }
+}
+
+/// Creates, renames, recolours, reorders and deletes lists.
+#[component]
+pub fn ListsPage() -> impl IntoView {
+    let lists = use_context::<ListsResource>().expect("need lists to manage");
+
+    let create_action = create_server_action::<CreateList>();
+    let rename_action = create_server_action::<RenameList>();
+    let colour_action = create_server_action::<SetListColour>();
+    let move_action = create_server_action::<MoveList>();
+    let delete_action = create_server_action::<DeleteList>();
+    refetch_lists_on(move || {
+        create_action.version().get()
+            + rename_action.version().get()
+            + colour_action.version().get()
+            + move_action.version().get()
+            + delete_action.version().get()
+    });
+
+    let form_ref = create_node_ref::<Form>();
+    create_effect(move |_| {
+        if let Some(Ok(_)) = create_action.value().get() {
+            if let Some(form) = form_ref.get() {
+                form.reset();
+            }
+        }
+    });
+
+    let create_errors = move || server_field_errors(create_action.value().get());
+    let create_error = move || {
+        create_action.value().get().and_then(|result| match result {
+            Err(ServerFnError::WrappedServerError(TodoError::Validation { .. })) | Ok(_) => None,
+            Err(e) => Some(error_message(&e)),
+        })
+    };
+
+    // The last failure of a rename, recolour, move or delete, shown above the lists.
+    let edit_error = create_rw_signal(None::<String>);
+    let report = move |result: Option<Result<(), ServerFnError<TodoError>>>| match result {
+        Some(Err(e)) => edit_error.set(Some(error_message(&e))),
+        Some(Ok(_)) => edit_error.set(None),
+        None => {}
+    };
+    create_effect(move |_| report(rename_action.value().get()));
+    create_effect(move |_| report(colour_action.value().get()));
+    create_effect(move |_| report(move_action.value().get()));
+    create_effect(move |_| report(delete_action.value().get()));
+
+    let list_row = move |(index, last, list): (usize, usize, List)| {
+        let id = list.id;
+        let position = index as u32;
+        let on_delete = move |_| {
+            let confirmed = leptos::window()
+                .confirm_with_message("Delete this list? Every todo item in it is deleted too.")
+                .unwrap_or(false);
+            if confirmed {
+                delete_action.dispatch(DeleteList { id });
+            }
+        };
+
+        view! {
+            <li class="flex items-center space-x-2 border-b border-gray-300 pb-2">
+                <ListDot colour=list.colour.clone()/>
+                <input
+                    type="text"
+                    class=LIST_FIELD_STYLE
+                    value=list.name
+                    on:change=move |ev| {
+                        rename_action.dispatch(RenameList { id, name: event_target_value(&ev) })
+                    }
+                />
+                <input
+                    type="color"
+                    value=list.colour
+                    on:change=move |ev| {
+                        colour_action.dispatch(SetListColour { id, colour: event_target_value(&ev) })
+                    }
+                />
+                <span class="text-sm text-gray-500">{format!("{} open", list.open_count)}</span>
+                <button
+                    class=MOVE_BUTTON_STYLE
+                    title="Move up"
+                    disabled=index == 0
+                    on:click=move |_| {
+                        move_action.dispatch(MoveList { id, position: position.saturating_sub(1) })
+                    }
+                >
+                    "↑"
+                </button>
+                <button
+                    class=MOVE_BUTTON_STYLE
+                    title="Move down"
+                    disabled=index == last
+                    on:click=move |_| move_action.dispatch(MoveList { id, position: position + 1 })
+                >
+                    "↓"
+                </button>
+                <button class="text-red-600 hover:text-red-800" on:click=on_delete>"Delete"</button>
+            </li>
+        }
+    };
+
+    view! {
+        <div class="container mx-auto mt-6">
+            <div class="w-3/4 mx-auto space-y-4">
+                <h2 class="text-lg font-bold">"Lists"</h2>
+                <ActionForm action=create_action node_ref=form_ref class="flex items-start space-x-2">
+                    <div>
+                        <input type="text" name="name" placeholder="New list" required class=LIST_FIELD_STYLE/>
+                        <p class=FORM_ERROR_STYLE>{move || error_for(&create_errors(), "name")}</p>
+                    </div>
+                    <div>
+                        <input type="color" name="colour" value="#6b7280"/>
+                        <p class=FORM_ERROR_STYLE>{move || error_for(&create_errors(), "colour")}</p>
+                    </div>
+                    <button type="submit" class="text-blue-600 hover:text-blue-800">"Add list"</button>
+                </ActionForm>
+                <p class=FORM_ERROR_STYLE>{create_error}</p>
+                <p class=FORM_ERROR_STYLE>{edit_error}</p>
+                <Transition fallback=move || view! { <p>"Loading lists..."</p> }>
+                    {move || {
+                        lists.get().map(|result| match result {
+                            Ok(lists) => {
+                                let last = lists.len().saturating_sub(1);
+                                let rows = lists
+                                    .into_iter()
+                                    .enumerate()
+                                    .map(|(index, list)| (index, last, list))
+                                    .collect::<Vec<_>>();
+                                view! {
+                                    <ul class="space-y-2">
+                                        <For
+                                            each=move || rows.clone()
+                                            key=|(index, last, list)| {
+                                                (*index, *last, list.id, list.name.clone(), list.colour.clone(), list.open_count)
+                                            }
+                                            children=list_row
+                                        />
+                                    </ul>
+                                }
+                                .into_view()
+                            }
+                            Err(e) => view! { <p>"Error loading lists: "{error_message(&e)}</p> }.into_view(),
+                        })
+                    }}
+                </Transition>
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,28 @@
+use crate::server::live::EVENTS_PATH;
+use codee::string::FromToStringCodec;
+use leptos::{create_effect, create_rw_signal, Signal, SignalUpdate, SignalWith};
+use leptos_use::{
+    signal_debounced, use_event_source_with_options, ReconnectLimit, UseEventSourceOptions,
+    UseEventSourceReturn,
+};
+
+/// How long to wait for a burst of announcements to end before counting it.
+const SETTLE_MS: f64 = 300.0;
+
+/// Counts the changes the server has announced to the user's data since the page
+/// loaded, whether made in this tab or anywhere else. A burst counts once.
+pub fn use_todo_changes() -> Signal<usize> {
+    let UseEventSourceReturn { data, .. } =
+        use_event_source_with_options::<String, FromToStringCodec>(
+            EVENTS_PATH,
+            UseEventSourceOptions::default().reconnect_limit(ReconnectLimit::Infinite),
+        );
+
+    let changes = create_rw_signal(0);
+    create_effect(move |_| {
+        if data.with(Option::is_some) {
+            changes.update(|changes| *changes += 1);
+        }
+    });
+    signal_debounced(changes, SETTLE_MS)
+}
@@ -1,4 +1,16 @@
 pub mod app;
+pub mod auth;
+pub mod backup;
+pub mod calendar;
+pub mod checklist;
+pub mod completed;
+pub mod import;
+pub mod list;
+pub mod live;
+pub mod recurrence;
 pub mod sidebar;
+pub mod tag;
 pub mod todo;
+pub mod trash;
 pub mod types;
+pub mod upload;
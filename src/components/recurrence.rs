@@ -0,0 +1,103 @@
+use crate::components::todo::FORM_ERROR_STYLE;
+use crate::components::types::{RecurrenceForm, RepeatKind};
+use crate::server::recurrence::{weekdays, Recurrence, MAX_INTERVAL_DAYS};
+use crate::validation::recurrence::check_recurrence;
+use leptos::{
+    component, event_target_checked, event_target_value, view, IntoView, RwSignal, Show,
+    SignalUpdate, SignalWith,
+};
+
+const PICKER_FIELD_STYLE: &str = "pl-1 border-gray-300 rounded-md";
+
+/// Shows how a todo item repeats. Shows nothing for items that don't.
+#[component]
+pub fn RepeatBadge(recurrence: Option<Recurrence>) -> impl IntoView {
+    recurrence.map(|recurrence| {
+        view! {
+            <span
+                class="ml-2 px-2 py-0.5 rounded text-xs font-medium align-middle bg-purple-100 text-purple-800"
+                title=recurrence.to_string()
+            >
+                {format!("↻ {}", recurrence.label())}
+            </span>
+        }
+    })
+}
+
+/// Lets the user choose how a todo item repeats. The caller submits `form`'s
+/// [`RecurrenceForm::rule`].
+#[component]
+pub fn RecurrencePicker(form: RwSignal<RecurrenceForm>) -> impl IntoView {
+    let kind = move || form.with(|form| form.kind);
+    let error = move || form.with(|form| check_recurrence(&form.rule()));
+
+    let on_kind = move |ev| {
+        let value = event_target_value(&ev);
+        let kind = RepeatKind::ALL
+            .into_iter()
+            .find(|kind| kind.as_str() == value)
+            .unwrap_or_default();
+        form.update(|form| form.kind = kind);
+    };
+
+    view! {
+        <div class="space-y-1">
+            <select class=PICKER_FIELD_STYLE on:change=on_kind>
+                {RepeatKind::ALL
+                    .into_iter()
+                    .map(|option| view! {
+                        <option value=option.as_str() selected=move || kind() == option>
+                            {option.label()}
+                        </option>
+                    })
+                    .collect::<Vec<_>>()}
+            </select>
+            <Show when=move || kind() == RepeatKind::Weekly>
+                <div class="flex flex-wrap text-sm">
+                    {weekdays()
+                        .map(|(day, name)| view! {
+                            <label class="mr-2">
+                                <input
+                                    type="checkbox"
+                                    class="mr-1"
+                                    prop:checked=move || form.with(|form| form.weekdays.contains(&day))
+                                    on:change=move |ev| {
+                                        let checked = event_target_checked(&ev);
+                                        form.update(|form| {
+                                            form.weekdays.retain(|other| *other != day);
+                                            if checked {
+                                                form.weekdays.push(day);
+                                            }
+                                        })
+                                    }
+                                />
+                                {name}
+                            </label>
+                        })
+                        .collect::<Vec<_>>()}
+                </div>
+            </Show>
+            <Show when=move || kind() == RepeatKind::Monthly>
+                <input
+                    type="number"
+                    min=1
+                    max=31
+                    class=PICKER_FIELD_STYLE
+                    prop:value=move || form.with(|form| form.month_day.clone())
+                    on:input=move |ev| form.update(|form| form.month_day = event_target_value(&ev))
+                />
+            </Show>
+            <Show when=move || kind() == RepeatKind::AfterCompletion>
+                <input
+                    type="number"
+                    min=1
+                    max=MAX_INTERVAL_DAYS
+                    class=PICKER_FIELD_STYLE
+                    prop:value=move || form.with(|form| form.days.clone())
+                    on:input=move |ev| form.update(|form| form.days = event_target_value(&ev))
+                />
+            </Show>
+            <p class=FORM_ERROR_STYLE>{error}</p>
+        </div>
+    }
+}
@@ -1,12 +1,15 @@
+use crate::components::auth::AccountMenu;
+use crate::components::list::{ListDot, ListsResource};
 use leptos::{
-    component, create_signal, provide_context, view, IntoView, ReadSignal, SignalUpdate,
-    WriteSignal,
+    component, create_signal, provide_context, use_context, view, IntoView, ReadSignal, SignalGet,
+    SignalUpdate, Transition, WriteSignal,
 };
 
 const HEADER_CONTAINER_STYLE: &str =
     "bg-violet-300 p-2 mx-auto flex justify-center items-center text-center";
 const ANCHOR_STYLE: &str = "block py-2 px-4 text-gray-700 hover:bg-gray-200 rounded";
 const H1_STYLE: &str = "mx-auto font-bold text-xl text-center";
+const COUNT_STYLE: &str = "ml-4 px-2 rounded-full text-xs font-medium bg-gray-100 text-gray-700";
 
 /// The header of the page containing the expandable sidebar on the left and
 /// the centered title.
@@ -43,7 +46,6 @@ fn Header(set_open: WriteSignal<bool>) -> impl IntoView {
                 class="hover:text-violet-50 transition-colors duration-200"
                 on:click=move |_| {
                     set_open.update(|open| *open = !*open);
-                    ()
                 }
             >
                 <line x1="3" y1="6" x2="21" y2="6" />
@@ -65,9 +67,47 @@ fn Sidebar(open: ReadSignal<bool>) -> impl IntoView {
             <div>
                 <ul class="mb-2">
                     <li class="mb-2"><a href="/" class=ANCHOR_STYLE>"Home"</a></li>
+                    <ListLinks/>
+                    <li class="mb-2"><a href="/lists" class=ANCHOR_STYLE>"Manage lists"</a></li>
+                    <li class="mb-2"><a href="/completed" class=ANCHOR_STYLE>"Completed"</a></li>
+                    <li class="mb-2"><a href="/tags" class=ANCHOR_STYLE>"Tags"</a></li>
+                    <li class="mb-2"><a href="/trash" class=ANCHOR_STYLE>"Trash"</a></li>
+                    <li class="mb-2"><a href="/backup" class=ANCHOR_STYLE>"Backup"</a></li>
+                    <li class="mb-2"><a href="/import" class=ANCHOR_STYLE>"Import"</a></li>
+                    <li class="mb-2"><a href="/calendar" class=ANCHOR_STYLE>"Calendar"</a></li>
                     <li class="mb-2"><a href="/about" class=ANCHOR_STYLE>"About"</a></li>
+                    <AccountMenu/>
                 </ul>
             </div>
         </nav>
     }
 }
+
+/// A link to each list, with how many of its items are still open.
+#[component]
+fn ListLinks() -> impl IntoView {
+    let lists = use_context::<ListsResource>().expect("need lists for the sidebar");
+
+    view! {
+        <Transition fallback=|| ()>
+            {move || {
+                lists.get().and_then(Result::ok).map(|lists| {
+                    lists
+                        .into_iter()
+                        .map(|list| view! {
+                            <li class="mb-2">
+                                <a href=format!("/list/{}", list.id) class=ANCHOR_STYLE>
+                                    <span class="flex items-center">
+                                        <ListDot colour=list.colour/>
+                                        <span class="flex-grow">{list.name}</span>
+                                        <span class=COUNT_STYLE>{list.open_count}</span>
+                                    </span>
+                                </a>
+                            </li>
+                        })
+                        .collect::<Vec<_>>()
+                })
+            }}
+        </Transition>
+    }
+}
@@ -0,0 +1,244 @@
+use crate::components::todo::{error_message, server_field_errors, FORM_ERROR_STYLE};
+use crate::server::error::TodoError;
+use crate::server::tag::{get_tags, CreateTag, DeleteTag, RenameTag, SetTagColour, Tag};
+use crate::validation::todo::error_for;
+use leptos::html::Form;
+use leptos::{
+    component, create_effect, create_node_ref, create_resource, create_rw_signal,
+    create_server_action, event_target_checked, event_target_value, provide_context, use_context,
+    view, For, IntoView, Resource, RwSignal, ServerFnError, SignalGet, SignalSet, SignalUpdate,
+    SignalWith, Transition,
+};
+use leptos_router::ActionForm;
+
+const CHIP_STYLE: &str =
+    "inline-block px-2 py-0.5 mr-1 rounded-full text-xs font-medium text-white";
+const TAG_FIELD_STYLE: &str = "pl-1 border-gray-300 rounded-md";
+
+/// Every tag, for the filter bar and the tag pickers in the forms.
+pub type TagsResource = Resource<usize, Result<Vec<Tag>, ServerFnError<TodoError>>>;
+
+/// The tags picked in the filter bar. Only todo items carrying all of them are listed.
+#[derive(Clone, Copy)]
+pub struct TagFilter(pub RwSignal<Vec<i32>>);
+
+/// Loads the tags once and provides them as context.
+pub fn provide_tags() -> TagsResource {
+    let tags: TagsResource = create_resource(|| 0, |_| async move { get_tags().await });
+    provide_context(tags);
+    tags
+}
+
+/// Adds `id` to `ids` if it's missing, or removes it otherwise.
+fn toggle(ids: &mut Vec<i32>, id: i32) {
+    if let Some(index) = ids.iter().position(|selected| *selected == id) {
+        ids.remove(index);
+    } else {
+        ids.push(id);
+    }
+}
+
+#[component]
+pub fn TagChip(tag: Tag) -> impl IntoView {
+    view! {
+        <span class=CHIP_STYLE style=format!("background-color: {}", tag.colour)>{tag.name}</span>
+    }
+}
+
+/// The tags as toggle buttons that narrow the list down to items carrying all of them.
+#[component]
+pub fn TagFilterBar() -> impl IntoView {
+    let tags = use_context::<TagsResource>().expect("need tags to filter by");
+    let TagFilter(selected) = use_context::<TagFilter>().expect("need a TagFilter to update");
+    let current_page =
+        use_context::<RwSignal<u32>>().expect("need current_page to go back to the first page");
+
+    let tag_button = move |tag: Tag| {
+        let id = tag.id;
+        let is_selected = move || selected.with(|ids| ids.contains(&id));
+        view! {
+            <button
+                class="opacity-50"
+                class=("opacity-100", is_selected)
+                class=("ring-2", is_selected)
+                class="rounded-full ring-blue-500"
+                on:click=move |_| {
+                    selected.update(|ids| toggle(ids, id));
+                    current_page.set(0);
+                }
+            >
+                <TagChip tag/>
+            </button>
+        }
+    };
+
+    view! {
+        <Transition fallback=|| ()>
+            {move || {
+                tags.get()
+                    .and_then(Result::ok)
+                    .filter(|tags| !tags.is_empty())
+                    .map(|tags| view! {
+                        <div class="flex flex-wrap items-center gap-1">
+                            <span class="text-sm text-gray-500 mr-1">"Filter by tag:"</span>
+                            {tags.into_iter().map(tag_button).collect::<Vec<_>>()}
+                        </div>
+                    })
+            }}
+        </Transition>
+    }
+}
+
+/// Checkboxes for attaching tags to a todo item, bound to `selected`.
+#[component]
+pub fn TagPicker(selected: RwSignal<Vec<i32>>) -> impl IntoView {
+    let tags = use_context::<TagsResource>().expect("need tags to pick from");
+
+    let tag_checkbox = move |tag: Tag| {
+        let id = tag.id;
+        view! {
+            <label class="inline-flex items-center mr-2">
+                <input
+                    type="checkbox"
+                    class="mr-1"
+                    prop:checked=move || selected.with(|ids| ids.contains(&id))
+                    on:change=move |ev| {
+                        let checked = event_target_checked(&ev);
+                        selected.update(|ids| {
+                            if checked != ids.contains(&id) {
+                                toggle(ids, id);
+                            }
+                        });
+                    }
+                />
+                <TagChip tag/>
+            </label>
+        }
+    };
+
+    view! {
+        <Transition fallback=|| ()>
+            {move || {
+                tags.get()
+                    .and_then(Result::ok)
+                    .map(|tags| tags.into_iter().map(tag_checkbox).collect::<Vec<_>>())
+            }}
+        </Transition>
+    }
+}
+
+/// Creates, renames, recolours and deletes tags.
+#[component]
+pub fn TagsPage() -> impl IntoView {
+    let create_action = create_server_action::<CreateTag>();
+    let rename_action = create_server_action::<RenameTag>();
+    let colour_action = create_server_action::<SetTagColour>();
+    let delete_action = create_server_action::<DeleteTag>();
+
+    let tags: TagsResource = create_resource(
+        move || {
+            create_action.version().get()
+                + rename_action.version().get()
+                + colour_action.version().get()
+                + delete_action.version().get()
+        },
+        |_| async move { get_tags().await },
+    );
+
+    let form_ref = create_node_ref::<Form>();
+    create_effect(move |_| {
+        if let Some(Ok(_)) = create_action.value().get() {
+            if let Some(form) = form_ref.get() {
+                form.reset();
+            }
+        }
+    });
+
+    let create_errors = move || server_field_errors(create_action.value().get());
+    let create_error = move || {
+        create_action.value().get().and_then(|result| match result {
+            Err(ServerFnError::WrappedServerError(TodoError::Validation { .. })) | Ok(_) => None,
+            Err(e) => Some(error_message(&e)),
+        })
+    };
+
+    // The last failure of a rename, recolour or delete, shown above the list.
+    let edit_error = create_rw_signal(None::<String>);
+    let report = move |result: Option<Result<(), ServerFnError<TodoError>>>| match result {
+        Some(Err(e)) => edit_error.set(Some(error_message(&e))),
+        Some(Ok(_)) => edit_error.set(None),
+        None => {}
+    };
+    create_effect(move |_| report(rename_action.value().get()));
+    create_effect(move |_| report(colour_action.value().get()));
+    create_effect(move |_| report(delete_action.value().get()));
+
+    let tag_row = move |tag: Tag| {
+        let id = tag.id;
+        let on_delete = move |_| {
+            let confirmed = leptos::window()
+                .confirm_with_message("Delete this tag? It will be removed from every todo item.")
+                .unwrap_or(false);
+            if confirmed {
+                delete_action.dispatch(DeleteTag { id });
+            }
+        };
+
+        view! {
+            <li class="flex items-center space-x-2 border-b border-gray-300 pb-2">
+                <TagChip tag=tag.clone()/>
+                <input
+                    type="text"
+                    class=TAG_FIELD_STYLE
+                    value=tag.name
+                    on:change=move |ev| {
+                        rename_action.dispatch(RenameTag { id, name: event_target_value(&ev) })
+                    }
+                />
+                <input
+                    type="color"
+                    value=tag.colour
+                    on:change=move |ev| {
+                        colour_action.dispatch(SetTagColour { id, colour: event_target_value(&ev) })
+                    }
+                />
+                <button class="text-red-600 hover:text-red-800" on:click=on_delete>"Delete"</button>
+            </li>
+        }
+    };
+
+    view! {
+        <div class="container mx-auto mt-6">
+            <div class="w-3/4 mx-auto space-y-4">
+                <h2 class="text-lg font-bold">"Tags"</h2>
+                <ActionForm action=create_action node_ref=form_ref class="flex items-start space-x-2">
+                    <div>
+                        <input type="text" name="name" placeholder="New tag" required class=TAG_FIELD_STYLE/>
+                        <p class=FORM_ERROR_STYLE>{move || error_for(&create_errors(), "name")}</p>
+                    </div>
+                    <div>
+                        <input type="color" name="colour" value="#6b7280"/>
+                        <p class=FORM_ERROR_STYLE>{move || error_for(&create_errors(), "colour")}</p>
+                    </div>
+                    <button type="submit" class="text-blue-600 hover:text-blue-800">"Add tag"</button>
+                </ActionForm>
+                <p class=FORM_ERROR_STYLE>{create_error}</p>
+                <p class=FORM_ERROR_STYLE>{edit_error}</p>
+                <Transition fallback=move || view! { <p>"Loading tags..."</p> }>
+                    {move || {
+                        tags.get().map(|result| match result {
+                            Ok(tags) if tags.is_empty() => view! { <p>"No tags yet."</p> }.into_view(),
+                            Ok(tags) => view! {
+                                <ul class="space-y-2">
+                                    <For each=move || tags.clone() key=|tag| (tag.id, tag.name.clone(), tag.colour.clone()) children=tag_row/>
+                                </ul>
+                            }
+                            .into_view(),
+                            Err(e) => view! { <p>"Error loading tags: "{error_message(&e)}</p> }.into_view(),
+                        })
+                    }}
+                </Transition>
+            </div>
+        </div>
+    }
+}
@@ -1,17 +1,31 @@
-use crate::components::types::{NotificationType, UpdateForm};
+use crate::components::checklist::{Checklist, ChecklistProgress};
+use crate::components::list::{refetch_lists_on, ListHeading, ListOptions};
+use crate::components::live::use_todo_changes;
+use crate::components::recurrence::{RecurrencePicker, RepeatBadge};
+use crate::components::tag::{provide_tags, TagChip, TagFilter, TagFilterBar, TagPicker};
+use crate::components::types::{NotificationType, RecurrenceForm, UpdateForm};
+use crate::server::checklist::{
+    AddChecklistItem, DeleteChecklistItem, MoveChecklistItem, SetChecklistItemDone,
+};
+use crate::server::csv::CSV_EXPORT_PATH;
+use crate::server::error::TodoError;
 use crate::server::todo::{
-    get_paginated_todos, search_todo, AddTodo, CompleteTodo, DeleteTodo, PaginatedTodos, Todo,
-    UpdateTodo,
+    get_paginated_todos, search_todo, AddTodo, CompleteTodo, DeleteTodo, MoveTodo, MoveTodoToList,
+    PaginatedTodos, Priority, PurgeTodo, RestoreTodo, SortOrder, Todo, UncompleteTodo, UpdateTodo,
+};
+use crate::validation::recurrence::check_recurrence;
+use crate::validation::todo::{
+    check_description, check_due_date, check_title, error_for, validate_todo, FieldError,
 };
 use leptos::html::Form;
 use leptos::{
     component, create_effect, create_memo, create_node_ref, create_resource, create_rw_signal,
     create_server_action, create_signal, event_target_value, provide_context, set_timeout,
     use_context, view, Action, Callback, For, IntoView, NodeRef, ReadSignal, Resource, RwSignal,
-    ServerFnError, Signal, SignalGet, SignalGetUntracked, SignalSet, SignalUpdate, SignalWith,
-    Suspense, Transition,
+    ServerFnError, Show, Signal, SignalGet, SignalGetUntracked, SignalSet, SignalUpdate,
+    SignalWith, Suspense, Transition,
 };
-use leptos_router::ActionForm;
+use leptos_router::{use_params_map, ActionForm};
 use leptos_use::signal_debounced;
 use std::time::Duration;
 
@@ -21,6 +35,7 @@ const FALLBACK_STYLE: &str = "px-2 py-2 text-sm font-medium text-gray-700 bg-whi
 
 const FORM_FIELD_STYLE: &str = "pl-1 mt-1 block w-full border-gray-300 rounded-md shadow-sm focus:border-blue-500 focus:ring focus:ring-blue-200";
 const FORM_LABEL_STYLE: &str = "block text-gray-700";
+pub(crate) const FORM_ERROR_STYLE: &str = "mt-1 text-sm text-red-600";
 const FORM_SUBMIT_STYLE: &str =
     "w-full bg-blue-400 text-white font-bold py-2 rounded-md hover:bg-blue-700";
 
@@ -28,27 +43,83 @@ const EDIT_FIELD_STYLE: &str = "mb-2 border-gray-300 rounded-md";
 
 const NOTIFICATION_STYLE: &str = "hidden w-1/4 text-center fixed mx-auto top-4 inset-x-1.5 bg-green-500 text-white px-4 py-2 rounded-lg shadow-lg";
 
-#[component]
-pub fn TodoList() -> impl IntoView {
-    let current_page = create_rw_signal(0u32);
+/// The message shown to the user for a failed server function call.
+pub(crate) fn error_message(e: &ServerFnError<TodoError>) -> String {
+    match e {
+        ServerFnError::WrappedServerError(e) => e.message(),
+        ServerFnError::Request(_) => "Could not reach the server, please try again.".into(),
+        _ => TodoError::Internal.message(),
+    }
+}
 
-    let add_action = create_server_action::<AddTodo>();
-    let complete_action = create_server_action::<CompleteTodo>();
-    let update_action = create_server_action::<UpdateTodo>();
-    let delete_action = create_server_action::<DeleteTodo>();
+/// The page of todo items the pagination controls and list read from context.
+pub type TodosResource = Resource<
+    (usize, u32, Vec<i32>, SortOrder, Option<i32>),
+    Result<PaginatedTodos, ServerFnError<TodoError>>,
+>;
+
+/// The server actions shared by the todo pages, along with the changes the server
+/// announces. The actions are also provided as context one by one, so each list item can
+/// dispatch them.
+#[derive(Clone, Copy)]
+pub struct TodoActions {
+    pub add: Action<AddTodo, Result<(), ServerFnError<TodoError>>>,
+    pub complete: Action<CompleteTodo, Result<(), ServerFnError<TodoError>>>,
+    pub uncomplete: Action<UncompleteTodo, Result<(), ServerFnError<TodoError>>>,
+    pub update: Action<UpdateTodo, Result<(), ServerFnError<TodoError>>>,
+    pub delete: Action<DeleteTodo, Result<(), ServerFnError<TodoError>>>,
+    pub restore: Action<RestoreTodo, Result<(), ServerFnError<TodoError>>>,
+    pub purge: Action<PurgeTodo, Result<(), ServerFnError<TodoError>>>,
+    pub move_todo: Action<MoveTodo, Result<(), ServerFnError<TodoError>>>,
+    pub move_to_list: Action<MoveTodoToList, Result<(), ServerFnError<TodoError>>>,
+    pub add_checklist_item: Action<AddChecklistItem, Result<(), ServerFnError<TodoError>>>,
+    pub set_checklist_item_done: Action<SetChecklistItemDone, Result<(), ServerFnError<TodoError>>>,
+    pub move_checklist_item: Action<MoveChecklistItem, Result<(), ServerFnError<TodoError>>>,
+    pub delete_checklist_item: Action<DeleteChecklistItem, Result<(), ServerFnError<TodoError>>>,
+    /// Counts changes made anywhere, e.g. in another tab, as announced by the server.
+    pub changes: Signal<usize>,
+}
 
-    let refetch_resource = create_resource(
-        move || {
-            (
-                add_action.version().get(),
-                complete_action.version().get(),
-                update_action.version().get(),
-                delete_action.version().get(),
-                current_page.get(),
-            )
-        },
-        |(_, _, _, _, page)| async move { get_paginated_todos(page).await },
-    );
+impl TodoActions {
+    /// Changes whenever any of the actions finishes or the server announces a change, so
+    /// resources keyed on it refetch.
+    pub fn version(&self) -> usize {
+        self.add.version().get()
+            + self.complete.version().get()
+            + self.uncomplete.version().get()
+            + self.update.version().get()
+            + self.delete.version().get()
+            + self.restore.version().get()
+            + self.purge.version().get()
+            + self.move_todo.version().get()
+            + self.move_to_list.version().get()
+            + self.add_checklist_item.version().get()
+            + self.set_checklist_item_done.version().get()
+            + self.move_checklist_item.version().get()
+            + self.delete_checklist_item.version().get()
+            + self.changes.get()
+    }
+}
+
+/// Creates the todo actions and the notification shown when they finish, and provides
+/// both as context. The sidebar's list counts are refreshed after every action.
+pub fn provide_todo_actions() -> TodoActions {
+    let actions = TodoActions {
+        add: create_server_action::<AddTodo>(),
+        complete: create_server_action::<CompleteTodo>(),
+        uncomplete: create_server_action::<UncompleteTodo>(),
+        update: create_server_action::<UpdateTodo>(),
+        delete: create_server_action::<DeleteTodo>(),
+        restore: create_server_action::<RestoreTodo>(),
+        purge: create_server_action::<PurgeTodo>(),
+        move_todo: create_server_action::<MoveTodo>(),
+        move_to_list: create_server_action::<MoveTodoToList>(),
+        add_checklist_item: create_server_action::<AddChecklistItem>(),
+        set_checklist_item_done: create_server_action::<SetChecklistItemDone>(),
+        move_checklist_item: create_server_action::<MoveChecklistItem>(),
+        delete_checklist_item: create_server_action::<DeleteChecklistItem>(),
+        changes: use_todo_changes(),
+    };
 
     let form_ref = create_node_ref::<Form>();
 
@@ -62,65 +133,244 @@ pub fn TodoList() -> impl IntoView {
         set_notification_type.set(None);
     };
 
-    create_effect(move |_| match add_action.value().get() {
+    let notify = move |notification: NotificationType| {
+        let duration = match notification {
+            NotificationType::Error(_) => Duration::from_secs(2),
+            _ => Duration::from_secs(1),
+        };
+        set_show_notification.update(|show| *show = true);
+        set_notification_type.set(Some(notification));
+        set_timeout(clear_notification, duration);
+    };
+    let notify_error = move |e: ServerFnError<TodoError>| {
+        notify(NotificationType::Error(error_message(&e)));
+    };
+
+    create_effect(move |_| match actions.add.value().get() {
         Some(Ok(_)) => {
             if let Some(form) = form_ref.get() {
                 form.reset();
             }
-            set_show_notification.update(|show| *show = true);
-            set_notification_type.set(Some(NotificationType::SuccessAdd));
-            set_timeout(clear_notification, Duration::from_secs(1));
-        }
-        Some(Err(e)) => {
-            set_notification_type.set(Some(NotificationType::Error(e.to_string())));
-            set_timeout(clear_notification, Duration::from_secs(1));
+            notify(NotificationType::SuccessAdd);
         }
+        Some(Err(e)) => notify_error(e),
         None => {}
     });
 
-    create_effect(move |_| match update_action.value().get() {
-        Some(Ok(_)) => {
-            set_show_notification.update(|show| *show = true);
-            set_notification_type.set(Some(NotificationType::SuccessUpdate));
-            set_timeout(clear_notification, Duration::from_secs(1));
-        }
-        Some(Err(e)) => {
-            set_notification_type.set(Some(NotificationType::Error(e.to_string())));
-            set_timeout(clear_notification, Duration::from_secs(1));
+    create_effect(move |_| {
+        if let Some(Err(e)) = actions.complete.value().get() {
+            notify_error(e);
         }
+    });
+
+    create_effect(move |_| match actions.uncomplete.value().get() {
+        Some(Ok(_)) => notify(NotificationType::SuccessReopen),
+        Some(Err(e)) => notify_error(e),
         None => {}
     });
 
-    create_effect(move |_| match delete_action.value().get() {
-        Some(Ok(_)) => {
-            set_show_notification.update(|show| *show = true);
-            set_notification_type.set(Some(NotificationType::SuccessDelete));
-            set_timeout(clear_notification, Duration::from_secs(1));
-        }
-        Some(Err(e)) => {
-            set_notification_type.set(Some(NotificationType::Error(e.to_string())));
-            set_timeout(clear_notification, Duration::from_secs(1));
-        }
+    create_effect(move |_| match actions.update.value().get() {
+        Some(Ok(_)) => notify(NotificationType::SuccessUpdate),
+        Some(Err(e)) => notify_error(e),
         None => {}
     });
 
-    provide_context(current_page);
-    provide_context(refetch_resource);
+    create_effect(move |_| match actions.delete.value().get() {
+        Some(Ok(_)) => notify(NotificationType::SuccessDelete),
+        Some(Err(e)) => notify_error(e),
+        None => {}
+    });
+
+    create_effect(move |_| match actions.restore.value().get() {
+        Some(Ok(_)) => notify(NotificationType::SuccessRestore),
+        Some(Err(e)) => notify_error(e),
+        None => {}
+    });
 
-    provide_context(add_action);
-    provide_context(complete_action);
-    provide_context(update_action);
-    provide_context(delete_action);
+    create_effect(move |_| match actions.purge.value().get() {
+        Some(Ok(_)) => notify(NotificationType::SuccessPurge),
+        Some(Err(e)) => notify_error(e),
+        None => {}
+    });
+
+    for value in [actions.move_todo.value(), actions.move_to_list.value()] {
+        create_effect(move |_| match value.get() {
+            Some(Ok(_)) => notify(NotificationType::SuccessMove),
+            Some(Err(e)) => notify_error(e),
+            None => {}
+        });
+    }
+
+    // Checklist edits show up in place, so only their failures are announced.
+    for value in [
+        actions.add_checklist_item.value(),
+        actions.set_checklist_item_done.value(),
+        actions.move_checklist_item.value(),
+        actions.delete_checklist_item.value(),
+    ] {
+        create_effect(move |_| {
+            if let Some(Err(e)) = value.get() {
+                notify_error(e);
+            }
+        });
+    }
+
+    provide_context(actions);
+    provide_context(actions.add);
+    provide_context(actions.complete);
+    provide_context(actions.uncomplete);
+    provide_context(actions.update);
+    provide_context(actions.delete);
+    provide_context(actions.restore);
+    provide_context(actions.purge);
+    provide_context(actions.move_todo);
+    provide_context(actions.move_to_list);
+    refetch_lists_on(move || actions.version());
 
     provide_context(show_notification);
     provide_context(notification_type);
     provide_context(form_ref);
 
-    let todos = move || {
-        refetch_resource().map(|result| match result {
+    actions
+}
+
+/// The open todo items of the list in the `:id` route parameter, or of every list if
+/// there is none.
+#[component]
+pub fn TodoList() -> impl IntoView {
+    let current_page = create_rw_signal(0u32);
+
+    let params = use_params_map();
+    let list = create_memo(move |_| {
+        params.with(|params| params.get("id").and_then(|id| id.parse::<i32>().ok()))
+    });
+    create_effect(move |_| {
+        list.track();
+        current_page.set(0);
+    });
+
+    let actions = provide_todo_actions();
+    provide_tags();
+
+    let tag_filter = TagFilter(create_rw_signal(Vec::new()));
+    let TagFilter(selected_tags) = tag_filter;
+
+    let sort = create_rw_signal(SortOrder::default());
+
+    let refetch_resource: TodosResource = create_resource(
+        move || {
+            (
+                actions.version(),
+                current_page.get(),
+                selected_tags.get(),
+                sort.get(),
+                list.get(),
+            )
+        },
+        |(_, page, tags, sort, list)| async move { get_paginated_todos(page, tags, sort, list).await },
+    );
+
+    provide_context(current_page);
+    provide_context(refetch_resource);
+    provide_context(tag_filter);
+
+    // The same items as shown, every page of them, as a CSV file.
+    let export_href = move || {
+        let mut query = vec![format!("sort={}", sort.get().as_str())];
+        if let Some(list) = list.get() {
+            query.push(format!("list={list}"));
+        }
+        let tags = selected_tags.get();
+        if !tags.is_empty() {
+            let ids = tags.iter().map(ToString::to_string).collect::<Vec<_>>();
+            query.push(format!("tags={}", ids.join(",")));
+        }
+        format!("{CSV_EXPORT_PATH}?{}", query.join("&"))
+    };
+
+    view! {
+        <NotificationComponent/>
+        <div class="container mx-auto flex mt-6">
+            <FormAddTodo list/>
+
+            <div class="w-3/4">
+                <div class="space-y-4">
+                    <ListHeading list/>
+                    <div class="flex items-center justify-between">
+                        <TagFilterBar/>
+                        <SortSelect sort current_page/>
+                        <a
+                            href=export_href
+                            rel="external"
+                            download
+                            class="ml-4 text-sm text-blue-600 underline whitespace-nowrap"
+                        >
+                            "Export CSV"
+                        </a>
+                    </div>
+                    <Search list/>
+                    <Transition fallback=move || view! { <p>"Loading todos..."</p> }>
+                        <TodoItems empty_message="You finished all of your todo items!"/>
+                    </Transition>
+                    <Pagination/>
+                </div>
+            </div>
+        </div>
+    }
+}
+
+#[component]
+fn SortSelect(sort: RwSignal<SortOrder>, current_page: RwSignal<u32>) -> impl IntoView {
+    let on_change = move |ev| {
+        let order = match event_target_value(&ev).as_str() {
+            "priority" => SortOrder::Priority,
+            _ => SortOrder::Newest,
+        };
+        sort.set(order);
+        current_page.set(0);
+    };
+
+    view! {
+        <select class="ml-auto text-sm border-gray-300 rounded-md" on:change=on_change>
+            <option value="newest" selected=move || sort() == SortOrder::Newest>"Newest first"</option>
+            <option value="priority" selected=move || sort() == SortOrder::Priority>"Priority, then due date"</option>
+        </select>
+    }
+}
+
+/// A coloured label for the priority, or nothing for `Priority::None`.
+#[component]
+fn PriorityBadge(priority: Priority) -> impl IntoView {
+    let colour = match priority {
+        Priority::None => return None,
+        Priority::Low => "bg-sky-100 text-sky-800",
+        Priority::Medium => "bg-yellow-100 text-yellow-800",
+        Priority::High => "bg-orange-100 text-orange-800",
+        Priority::Urgent => "bg-red-100 text-red-800",
+    };
+    Some(view! {
+        <span class=format!("ml-2 px-2 py-0.5 rounded text-xs font-medium align-middle {colour}")>
+            {priority.label()}
+        </span>
+    })
+}
+
+/// The ids of the todo items whose sub-todos are folded away. Kept outside the items so
+/// it survives refetching the page.
+#[derive(Clone, Copy)]
+struct CollapsedTodos(RwSignal<Vec<i32>>);
+
+/// The items of the current page, read from the [`TodosResource`] in context.
+#[component]
+pub fn TodoItems(#[prop(into)] empty_message: String) -> impl IntoView {
+    let todos = use_context::<TodosResource>().expect("need a TodosResource to list todo items");
+    provide_context(CollapsedTodos(create_rw_signal(Vec::new())));
+
+    move || {
+        todos().map(|result| match result {
             Ok(todos) => {
                 if todos.items.is_empty() {
-                    view! { <p>"You finished all of your todo items!"</p> }.into_view()
+                    view! { <p>{empty_message.clone()}</p> }.into_view()
                 } else {
                     view! {
                         {
@@ -134,27 +384,10 @@ pub fn TodoList() -> impl IntoView {
                 }
             }
             Err(e) => view! {
-                <p>"Error loading todos: "{e.to_string()}</p>
+                <p>"Error loading todos: "{error_message(&e)}</p>
             }
             .into_view(),
         })
-    };
-
-    view! {
-        <NotificationComponent/>
-        <div class="container mx-auto flex mt-6">
-            <FormAddTodo/>
-
-            <div class="w-3/4">
-                <div class="space-y-4">
-                    <Search/>
-                    <Transition fallback=move || view! { <p>"Loading todos..."</p> }>
-                        {todos}
-                    </Transition>
-                    <Pagination/>
-                </div>
-            </div>
-        </div>
     }
 }
 
@@ -261,17 +494,14 @@ fn PaginationFallback() -> impl IntoView {
 }
 
 #[component]
-fn Pagination() -> impl IntoView {
+pub fn Pagination() -> impl IntoView {
     const PER_PAGE: u32 = 10;
     const VISIBLE_PAGES: u32 = 5;
 
     let current_page =
         use_context::<RwSignal<u32>>().expect("need current_page RwSignal for pagination");
 
-    let todos = use_context::<
-        Resource<(usize, usize, usize, usize, u32), Result<PaginatedTodos, ServerFnError>>,
-    >()
-    .expect("need refetch_resource for pagination");
+    let todos = use_context::<TodosResource>().expect("need refetch_resource for pagination");
 
     view! {
         <div class="w-full max-w-4xl mx-auto">
@@ -280,7 +510,7 @@ fn Pagination() -> impl IntoView {
                     todos.get().map(|data| {
                         match data {
                             Ok(response) => {
-                                let total_pages = (response.total + PER_PAGE - 1) / PER_PAGE;
+                                let total_pages = response.total.div_ceil(PER_PAGE);
 
                                 // Calculate visible pages
                                 let half_visible = VISIBLE_PAGES / 2;
@@ -316,17 +546,21 @@ fn Pagination() -> impl IntoView {
 }
 
 #[component]
-fn NotificationComponent() -> impl IntoView {
+pub fn NotificationComponent() -> impl IntoView {
     let show_notification = use_context::<ReadSignal<bool>>()
         .expect("need show_notification ReadSignal to show/hide notification");
 
     let notification_type = use_context::<ReadSignal<Option<NotificationType>>>()
         .expect("need notification type to display message");
 
-    let notification_message = move || match notification_type().take() {
+    let notification_message = move || match notification_type() {
         Some(NotificationType::SuccessAdd) => "Todo item added successfully!".to_string(),
         Some(NotificationType::SuccessUpdate) => "Todo item updated successfully!".to_string(),
-        Some(NotificationType::SuccessDelete) => "Todo item deleted successfully!".to_string(),
+        Some(NotificationType::SuccessDelete) => "Todo item moved to the trash!".to_string(),
+        Some(NotificationType::SuccessReopen) => "Todo item moved back to your list!".to_string(),
+        Some(NotificationType::SuccessRestore) => "Todo item restored from the trash!".to_string(),
+        Some(NotificationType::SuccessPurge) => "Todo item deleted for good!".to_string(),
+        Some(NotificationType::SuccessMove) => "Todo item moved!".to_string(),
         Some(NotificationType::Error(e)) => e,
         None => "".to_string(),
     };
@@ -338,9 +572,29 @@ fn NotificationComponent() -> impl IntoView {
     }
 }
 
+/// Shows an RFC 3339 timestamp from the server as `YYYY-MM-DD HH:MM UTC`, or as it came
+/// if it can't be parsed.
+pub(crate) fn format_timestamp(timestamp: &str) -> String {
+    chrono::DateTime::parse_from_rfc3339(timestamp)
+        .map(|at| at.naive_utc().format("%Y-%m-%d %H:%M UTC").to_string())
+        .unwrap_or_else(|_| timestamp.to_string())
+}
+
+/// The field errors from the last failed call of a server function, if it failed
+/// validation.
+pub(crate) fn server_field_errors(
+    result: Option<Result<(), ServerFnError<TodoError>>>,
+) -> Vec<FieldError> {
+    match result {
+        Some(Err(ServerFnError::WrappedServerError(TodoError::Validation { errors }))) => errors,
+        _ => Vec::new(),
+    }
+}
+
+/// The form for new todo items, which go in `list` unless another one is picked.
 #[component]
-fn FormAddTodo() -> impl IntoView {
-    let add_action = use_context::<Action<AddTodo, Result<(), ServerFnError>>>()
+fn FormAddTodo(#[prop(into)] list: Signal<Option<i32>>) -> impl IntoView {
+    let add_action = use_context::<Action<AddTodo, Result<(), ServerFnError<TodoError>>>>()
         .expect("need action for adding a todo item");
 
     let form_ref =
@@ -348,26 +602,112 @@ fn FormAddTodo() -> impl IntoView {
 
     let today = chrono::offset::Local::now().date_naive().to_string();
 
+    // Submitted as `tag_ids[0]`, `tag_ids[1]`, ... by the hidden inputs below.
+    let tag_ids = create_rw_signal(Vec::<i32>::new());
+    let recurrence = create_rw_signal(RecurrenceForm::default());
+    create_effect(move |_| {
+        if let Some(Ok(_)) = add_action.value().get() {
+            tag_ids.set(Vec::new());
+            recurrence.set(RecurrenceForm::default());
+        }
+    });
+
+    // Errors found while typing, checked with the same rules the server uses.
+    let (title_error, set_title_error) = create_signal::<Option<String>>(None);
+    let (description_error, set_description_error) = create_signal::<Option<String>>(None);
+    let (due_date_error, set_due_date_error) = create_signal::<Option<String>>(None);
+
+    let server_errors = move || server_field_errors(add_action.value().get());
+    let field_error = move |field: &'static str, client_error: ReadSignal<Option<String>>| {
+        move || client_error().or_else(|| error_for(&server_errors(), field))
+    };
+    let has_errors = move || {
+        title_error().is_some()
+            || description_error().is_some()
+            || due_date_error().is_some()
+            || recurrence
+                .with(|form| check_recurrence(&form.rule()))
+                .is_some()
+    };
+
     view! {
         <div class="w-1/4 bg-white p-4 rounded-lg shadow-md mr-6">
             <h2 class="text-lg font-bold mb-4">Add New To-Do</h2>
             <ActionForm action=add_action node_ref=form_ref>
                 <div class="mb-4">
                     <label for="title" class=FORM_LABEL_STYLE>Title</label>
-                    <input name="title" type="text" placeholder="Enter title" required class=FORM_FIELD_STYLE/>
+                    <input
+                        name="title"
+                        type="text"
+                        placeholder="Enter title"
+                        required
+                        class=FORM_FIELD_STYLE
+                        on:input=move |ev| set_title_error.set(check_title(&event_target_value(&ev)))
+                    />
+                    <p class=FORM_ERROR_STYLE>{field_error("title", title_error)}</p>
                 </div>
                 <div class="mb-4">
                     <label for="description" class=FORM_LABEL_STYLE>Description</label>
-                    <textarea name="description" rows="3" placeholder="Enter description" class=FORM_FIELD_STYLE></textarea>
+                    <textarea
+                        name="description"
+                        rows="3"
+                        placeholder="Enter description"
+                        class=FORM_FIELD_STYLE
+                        on:input=move |ev| {
+                            set_description_error.set(check_description(&event_target_value(&ev)))
+                        }
+                    ></textarea>
+                    <p class=FORM_ERROR_STYLE>{field_error("description", description_error)}</p>
                 </div>
                 <div class="mb-4">
                     <label for="due_date" class=FORM_LABEL_STYLE>Due Date</label>
-                    <input name="due_date" type="date" class=FORM_FIELD_STYLE value={today} required/>
+                    <input
+                        name="due_date"
+                        type="date"
+                        class=FORM_FIELD_STYLE
+                        value={today}
+                        required
+                        on:input=move |ev| set_due_date_error.set(check_due_date(&event_target_value(&ev)))
+                    />
+                    <p class=FORM_ERROR_STYLE>{field_error("due_date", due_date_error)}</p>
+                </div>
+                <div class="mb-4">
+                    <label for="priority" class=FORM_LABEL_STYLE>Priority</label>
+                    <select name="priority" class=FORM_FIELD_STYLE>
+                        {Priority::ALL
+                            .into_iter()
+                            .map(|priority| view! { <option value=priority.as_str()>{priority.label()}</option> })
+                            .collect::<Vec<_>>()}
+                    </select>
+                </div>
+                <div class="mb-4">
+                    <label for="list_id" class=FORM_LABEL_STYLE>List</label>
+                    <select name="list_id" class=FORM_FIELD_STYLE>
+                        <ListOptions selected=list/>
+                    </select>
+                </div>
+                <div class="mb-4">
+                    <span class=FORM_LABEL_STYLE>Tags</span>
+                    <TagPicker selected=tag_ids/>
+                    {move || {
+                        tag_ids()
+                            .into_iter()
+                            .enumerate()
+                            .map(|(i, id)| view! {
+                                <input type="hidden" name=format!("tag_ids[{i}]") value=id/>
+                            })
+                            .collect::<Vec<_>>()
+                    }}
+                </div>
+                <div class="mb-4">
+                    <span class=FORM_LABEL_STYLE>Repeat</span>
+                    <RecurrencePicker form=recurrence/>
+                    <input type="hidden" name="recurrence" prop:value=move || recurrence.with(RecurrenceForm::rule)/>
                 </div>
                 <button
                     type="submit"
                     class=FORM_SUBMIT_STYLE
-                    prop:disabled=move || add_action.pending().get()
+                    prop:disabled=move || add_action.pending().get() || has_errors()
                 >
                     {move || {
                         if add_action.pending().get() {
@@ -383,16 +723,24 @@ fn FormAddTodo() -> impl IntoView {
 }
 
 #[component]
-fn Search() -> impl IntoView {
-    let complete_action = use_context::<Action<CompleteTodo, Result<(), ServerFnError>>>()
-        .expect("need complete_action to update search results");
+fn Search(#[prop(into)] list: Signal<Option<i32>>) -> impl IntoView {
+    let actions = use_context::<TodoActions>().expect("need todo actions to update search results");
+    let TagFilter(selected_tags) =
+        use_context::<TagFilter>().expect("need the tag filter to narrow search results");
 
     let (query, set_query) = create_signal(String::new());
     let debounced: Signal<String> = signal_debounced(query, 500.0);
 
     let todos = create_resource(
-        move || (debounced(), complete_action.version().get()),
-        |(q, _)| async move { search_todo(q).await },
+        move || {
+            (
+                debounced(),
+                actions.version(),
+                selected_tags.get(),
+                list.get(),
+            )
+        },
+        |(q, _, tags, list)| async move { search_todo(q, tags, list).await },
     );
 
     let todos_result = move || match todos() {
@@ -416,7 +764,7 @@ fn Search() -> impl IntoView {
             }
         }
         Some(Err(e)) => view! {
-            <p>"Search error: "{e.to_string()}</p>
+            <p>"Search error: "{error_message(&e)}</p>
         }
         .into_view(),
     };
@@ -444,17 +792,60 @@ fn Search() -> impl IntoView {
 
 #[component]
 fn TodoItem(todo: Todo) -> impl IntoView {
-    let complete_action = use_context::<Action<CompleteTodo, Result<(), ServerFnError>>>()
-        .expect("need complete_action to trigger server function");
+    let complete_action =
+        use_context::<Action<CompleteTodo, Result<(), ServerFnError<TodoError>>>>()
+            .expect("need complete_action to trigger server function");
 
-    let delete_action = use_context::<Action<DeleteTodo, Result<(), ServerFnError>>>()
+    let uncomplete_action =
+        use_context::<Action<UncompleteTodo, Result<(), ServerFnError<TodoError>>>>()
+            .expect("need uncomplete_action to trigger server function");
+
+    let delete_action = use_context::<Action<DeleteTodo, Result<(), ServerFnError<TodoError>>>>()
         .expect("need delete_action to trigger server function");
 
     let hidden = create_rw_signal(true);
 
     provide_context(hidden);
 
-    let on_complete = move |_| complete_action.dispatch(CompleteTodo { id: todo.id });
+    let completed = todo.completed;
+    let has_open_items = todo.checklist.iter().any(|item| !item.done);
+    let on_complete = move |_| {
+        if completed {
+            uncomplete_action.dispatch(UncompleteTodo { id: todo.id });
+        } else {
+            let complete_checklist = has_open_items
+                && leptos::window()
+                    .confirm_with_message("Also tick off the open checklist items?")
+                    .unwrap_or(false);
+            complete_action.dispatch(CompleteTodo {
+                id: todo.id,
+                complete_checklist,
+            });
+        }
+    };
+    let completed_at = todo.completed_at.as_deref().map(format_timestamp);
+    let tags = todo.tags.clone();
+    let checklist = todo.checklist.clone();
+    let children = todo.children.clone();
+    let id = todo.id;
+    let due_date = todo.due_date.clone();
+
+    let collapsed = use_context::<CollapsedTodos>();
+    let expanded =
+        move || collapsed.is_none_or(|CollapsedTodos(ids)| !ids.with(|ids| ids.contains(&id)));
+    let on_toggle = move |_| {
+        if let Some(CollapsedTodos(ids)) = collapsed {
+            ids.update(|ids| {
+                if let Some(index) = ids.iter().position(|other| *other == id) {
+                    ids.remove(index);
+                } else {
+                    ids.push(id);
+                }
+            });
+        }
+    };
+    let show_sub_todo_form = create_rw_signal(false);
+    let child_count = children.len();
     let on_delete = move |_| delete_action.dispatch(DeleteTodo { id: todo.id });
     let on_edit = move |_| {
         hidden.update(|hidden| *hidden = false);
@@ -473,13 +864,53 @@ fn TodoItem(todo: Todo) -> impl IntoView {
             <div class="flex-grow">
                 // content
                 <div class="view" class:hidden=move || !hidden() on:click=on_edit>
-                    <h3 class="text-lg font-semibold text-gray-800">{todo.title.clone()}</h3>
+                    <h3 class="text-lg font-semibold text-gray-800">
+                        <span class=("line-through", completed)>{todo.title.clone()}</span>
+                        <PriorityBadge priority=todo.priority/>
+                        <RepeatBadge recurrence=todo.recurrence.clone()/>
+                        <ChecklistProgress items=checklist.clone()/>
+                    </h3>
                     <p class="text-gray-600 selection:text-sky-500">{todo.description.clone()}</p>
                     <p class="text-sm text-gray-500 mt-1">Due Date: <span class="font-medium">{todo.due_date.clone()}</span></p>
+                    <div class="mt-1">
+                        {tags.into_iter().map(|tag| view! { <TagChip tag/> }).collect::<Vec<_>>()}
+                    </div>
+                    {completed_at.map(|at| view! {
+                        <p class="text-sm text-gray-500">Completed: <span class="font-medium">{at}</span></p>
+                    })}
                 </div>
 
                 <FormUpdateTodo todo/>
 
+                <Checklist todo_id=id items=checklist/>
+
+                <div class="mt-2 text-sm space-x-2">
+                    {(child_count > 0).then(|| view! {
+                        <button class="text-gray-600 hover:text-gray-900" on:click=on_toggle>
+                            {move || if expanded() { "▾" } else { "▸" }}
+                            {format!(" {child_count} sub-todo{}", if child_count == 1 { "" } else { "s" })}
+                        </button>
+                    })}
+                    <button
+                        class="text-blue-600 hover:text-blue-800"
+                        on:click=move |_| show_sub_todo_form.update(|show| *show = !*show)
+                    >
+                        "+ Sub-todo"
+                    </button>
+                </div>
+                <Show when=show_sub_todo_form>
+                    <FormAddSubTodo parent_id=id due_date=due_date.clone()/>
+                </Show>
+
+                {(child_count > 0).then(move || view! {
+                    <div class="mt-4 ml-2 pl-4 border-l border-gray-200" class:hidden=move || !expanded()>
+                        {children
+                            .into_iter()
+                            .map(|child| view! { <TodoItem todo=child/> }.into_view())
+                            .collect::<Vec<_>>()}
+                    </div>
+                })}
+
             </div>
 
             // delete button
@@ -494,6 +925,74 @@ fn TodoItem(todo: Todo) -> impl IntoView {
     }
 }
 
+/// A small inline form for adding a sub-todo, starting with the parent's due date.
+#[component]
+fn FormAddSubTodo(parent_id: i32, due_date: String) -> impl IntoView {
+    let add_action = use_context::<Action<AddTodo, Result<(), ServerFnError<TodoError>>>>()
+        .expect("need add_action to trigger server function");
+
+    let title = create_rw_signal(String::new());
+    let due_date = create_rw_signal(due_date);
+    let error = move || check_title(&title()).or_else(|| check_due_date(&due_date()));
+
+    let on_add = move |_| {
+        if error().is_some() {
+            return;
+        }
+        add_action.dispatch(AddTodo {
+            title: title.get_untracked(),
+            description: String::new(),
+            due_date: due_date.get_untracked(),
+            priority: Priority::default(),
+            tag_ids: Vec::new(),
+            parent_id: Some(parent_id),
+            recurrence: String::new(),
+            list_id: None,
+        });
+        title.set(String::new());
+    };
+
+    view! {
+        <div class="mt-2 flex items-center space-x-2">
+            <input
+                type="text"
+                placeholder="Sub-todo title"
+                class=EDIT_FIELD_STYLE
+                prop:value=title
+                on:input=move |ev| title.set(event_target_value(&ev))
+            />
+            <input
+                type="date"
+                class=EDIT_FIELD_STYLE
+                prop:value=due_date
+                on:input=move |ev| due_date.set(event_target_value(&ev))
+            />
+            <button
+                class="mb-2 text-blue-600 hover:text-blue-800 disabled:opacity-50"
+                prop:disabled=move || error().is_some()
+                on:click=on_add
+            >
+                "Add"
+            </button>
+        </div>
+    }
+}
+
+/// The items todo item `id` could be moved under: every item on the current page except
+/// itself and its sub-todos, indented by depth.
+fn parent_choices(page: &[Todo], id: i32) -> Vec<(i32, String)> {
+    fn walk(todos: &[Todo], excluded: i32, depth: usize, choices: &mut Vec<(i32, String)>) {
+        for todo in todos.iter().filter(|todo| todo.id != excluded) {
+            choices.push((todo.id, format!("{}{}", "— ".repeat(depth), todo.title)));
+            walk(&todo.children, excluded, depth + 1, choices);
+        }
+    }
+
+    let mut choices = Vec::new();
+    walk(page, id, 0, &mut choices);
+    choices
+}
+
 /// Allow a user to edit a todo-item inline.
 /// When the user clicks on the div containing the title, description, and due_date,
 /// the original content is hidden and corresponding inputs and a save button are revealed.
@@ -502,42 +1001,116 @@ fn TodoItem(todo: Todo) -> impl IntoView {
 /// Then the inputs and save button are hidden and the item is re-rendered.
 #[component]
 fn FormUpdateTodo(todo: Todo) -> impl IntoView {
+    let tag_ids = create_rw_signal(todo.tags.iter().map(|tag| tag.id).collect::<Vec<_>>());
+    let recurrence = create_rw_signal(RecurrenceForm::new(todo.recurrence.as_ref()));
     let form_state = create_rw_signal(UpdateForm {
         title: todo.title,
         description: todo.description,
         due_date: todo.due_date,
+        priority: todo.priority,
+        list_id: todo.list_id,
+        parent_id: todo.parent_id,
     });
 
     let hidden = use_context::<RwSignal<bool>>().expect("need hidden to show edit inputs");
 
-    let update_action = use_context::<Action<UpdateTodo, Result<(), ServerFnError>>>()
+    let move_action = use_context::<Action<MoveTodo, Result<(), ServerFnError<TodoError>>>>()
+        .expect("need move_action to call server function");
+    let move_to_list_action =
+        use_context::<Action<MoveTodoToList, Result<(), ServerFnError<TodoError>>>>()
+            .expect("need move_to_list_action to call server function");
+    let page = use_context::<TodosResource>();
+    let choices = move || {
+        page.map(|page| {
+            page.with(|result| match result {
+                Some(Ok(page)) => parent_choices(&page.items, todo.id),
+                _ => Vec::new(),
+            })
+        })
+        .unwrap_or_default()
+    };
+
+    let update_action = use_context::<Action<UpdateTodo, Result<(), ServerFnError<TodoError>>>>()
         .expect("need update_action to call server function");
 
+    // The update action is shared by every item, so only pick up the server's field
+    // errors when this form was the one that submitted.
+    let submitted = create_rw_signal(false);
+    let server_errors = create_rw_signal(Vec::<FieldError>::new());
+
+    create_effect(move |_| {
+        let result = update_action.value().get();
+        if result.is_some() && submitted.get_untracked() {
+            submitted.set(false);
+            let errors = server_field_errors(result);
+            if !errors.is_empty() {
+                hidden.set(false);
+            }
+            server_errors.set(errors);
+        }
+    });
+
+    let field_error = move |field: &'static str| {
+        move || {
+            let state = form_state();
+            let client_error = match field {
+                "title" => check_title(&state.title),
+                "description" => check_description(&state.description),
+                _ => check_due_date(&state.due_date),
+            };
+            client_error.or_else(|| error_for(&server_errors(), field))
+        }
+    };
+    let has_errors = move || {
+        let state = form_state();
+        validate_todo(&state.title, &state.description, &state.due_date).is_err()
+            || recurrence
+                .with(|form| check_recurrence(&form.rule()))
+                .is_some()
+    };
+
     let on_submit = move |_| {
+        if has_errors() {
+            return;
+        }
+        submitted.set(true);
         update_action.dispatch(UpdateTodo {
             id: todo.id,
             title: form_state().title,
             description: form_state().description,
             due_date: form_state().due_date,
+            priority: form_state().priority,
+            tag_ids: tag_ids(),
+            recurrence: recurrence.with(RecurrenceForm::rule),
         });
+        // Changing the list takes the item out of a parent in the old one, so the parent
+        // choice only applies when staying in the same list.
+        if form_state().list_id != todo.list_id {
+            move_to_list_action.dispatch(MoveTodoToList {
+                id: todo.id,
+                list_id: form_state().list_id,
+            });
+        } else if form_state().parent_id != todo.parent_id {
+            move_action.dispatch(MoveTodo {
+                id: todo.id,
+                parent_id: form_state().parent_id,
+            });
+        }
         hidden.update(|hidden| *hidden = true);
     };
 
     view! {
-        <div class="flex flex-col" class:hidden=move || hidden()>
+        <div class="flex flex-col" class:hidden=hidden>
             <input
                 type="text"
                 required
                 class=EDIT_FIELD_STYLE
                 value=move || form_state().title
                 on:input=move |ev| {
-                    form_state.update(|state| {
-                        if event_target_value(&ev) != "" {
-                            state.title = event_target_value(&ev)
-                        }
-                    })
+                    form_state.update(|state| state.title = event_target_value(&ev))
                 }
             />
+            <p class=FORM_ERROR_STYLE>{field_error("title")}</p>
             <textarea
                 rows=3
                 class=EDIT_FIELD_STYLE
@@ -548,22 +1121,76 @@ fn FormUpdateTodo(todo: Todo) -> impl IntoView {
             >
                 {form_state.get_untracked().description}
             </textarea>
+            <p class=FORM_ERROR_STYLE>{field_error("description")}</p>
             <input
                 type="date"
                 required
                 class=EDIT_FIELD_STYLE
                 value=move || form_state().due_date
                 on:input=move |ev| {
-                    form_state.update(|state| {
-                        if event_target_value(&ev) != "" {
-                            state.due_date = event_target_value(&ev)
-                        }
-                    })
+                    form_state.update(|state| state.due_date = event_target_value(&ev))
                 }
             />
+            <p class=FORM_ERROR_STYLE>{field_error("due_date")}</p>
+            <select
+                class=EDIT_FIELD_STYLE
+                on:change=move |ev| {
+                    let priority = event_target_value(&ev).parse().unwrap_or_default();
+                    form_state.update(|state| state.priority = priority)
+                }
+            >
+                {Priority::ALL
+                    .into_iter()
+                    .map(|priority| view! {
+                        <option value=priority.as_str() selected=move || form_state().priority == priority>
+                            {priority.label()}
+                        </option>
+                    })
+                    .collect::<Vec<_>>()}
+            </select>
+            <div class="mb-2">
+                <TagPicker selected=tag_ids/>
+            </div>
+            <div class="mb-2">
+                <RecurrencePicker form=recurrence/>
+            </div>
+            <select
+                class=EDIT_FIELD_STYLE
+                on:change=move |ev| {
+                    if let Ok(list_id) = event_target_value(&ev).parse() {
+                        form_state.update(|state| state.list_id = list_id)
+                    }
+                }
+            >
+                <ListOptions selected=Signal::derive(move || Some(form_state().list_id))/>
+            </select>
+            <select
+                class=EDIT_FIELD_STYLE
+                on:change=move |ev| {
+                    let parent_id = event_target_value(&ev).parse().ok();
+                    form_state.update(|state| state.parent_id = parent_id)
+                }
+            >
+                <option value="" selected=move || form_state().parent_id.is_none()>"No parent"</option>
+                {move || {
+                    choices()
+                        .into_iter()
+                        .map(|(id, label)| view! {
+                            <option value=id selected=move || form_state().parent_id == Some(id)>
+                                {label}
+                            </option>
+                        })
+                        .collect::<Vec<_>>()
+                }}
+            </select>
         </div>
 
-        <button class="text-green-600 hover:text-green-800" class:hidden=move|| hidden() on:click=on_submit>
+        <button
+            class="text-green-600 hover:text-green-800 disabled:opacity-50"
+            class:hidden=hidden
+            prop:disabled=has_errors
+            on:click=on_submit
+        >
             <span class="flex items-center">
                 <p>Save</p>
                 <svg class="w-5 h-5" fill="none" stroke="currentColor" viewBox="0 0 24 24">
@@ -0,0 +1,99 @@
+use crate::components::todo::{
+    format_timestamp, provide_todo_actions, NotificationComponent, Pagination, TodosResource,
+};
+use crate::server::error::TodoError;
+use crate::server::todo::{get_trashed_todos, PurgeTodo, RestoreTodo, SortOrder, Todo};
+use leptos::{
+    component, create_resource, create_rw_signal, provide_context, use_context, view, Action,
+    IntoView, ServerFnError, SignalGet, Transition,
+};
+
+/// The todo items in the trash. They can be restored or deleted for good; the server
+/// also purges them once they are older than the configured retention period.
+#[component]
+pub fn TrashList() -> impl IntoView {
+    let current_page = create_rw_signal(0u32);
+
+    let actions = provide_todo_actions();
+
+    let refetch_resource: TodosResource = create_resource(
+        move || {
+            (
+                actions.version(),
+                current_page.get(),
+                Vec::new(),
+                SortOrder::default(),
+                None,
+            )
+        },
+        |(_, page, _, _, _)| async move { get_trashed_todos(page).await },
+    );
+
+    provide_context(current_page);
+    provide_context(refetch_resource);
+
+    let todos = move || {
+        refetch_resource().map(|result| match result {
+            Ok(todos) if todos.items.is_empty() => {
+                view! { <p>"The trash is empty."</p> }.into_view()
+            }
+            Ok(todos) => todos
+                .items
+                .into_iter()
+                .map(|todo| view! { <TrashedTodoItem todo/> })
+                .collect::<Vec<_>>()
+                .into_view(),
+            Err(_) => view! { <p>"Error loading the trash."</p> }.into_view(),
+        })
+    };
+
+    view! {
+        <NotificationComponent/>
+        <div class="container mx-auto mt-6">
+            <div class="w-3/4 mx-auto space-y-4">
+                <h2 class="text-lg font-bold">"Trash"</h2>
+                <Transition fallback=move || view! { <p>"Loading todos..."</p> }>
+                    {todos}
+                </Transition>
+                <Pagination/>
+            </div>
+        </div>
+    }
+}
+
+#[component]
+fn TrashedTodoItem(todo: Todo) -> impl IntoView {
+    let restore_action = use_context::<Action<RestoreTodo, Result<(), ServerFnError<TodoError>>>>()
+        .expect("need restore_action to trigger server function");
+
+    let purge_action = use_context::<Action<PurgeTodo, Result<(), ServerFnError<TodoError>>>>()
+        .expect("need purge_action to trigger server function");
+
+    let id = todo.id;
+    let on_restore = move |_| restore_action.dispatch(RestoreTodo { id });
+    let on_purge = move |_| {
+        let confirmed = leptos::window()
+            .confirm_with_message("Delete this todo item for good? This can't be undone.")
+            .unwrap_or(false);
+        if confirmed {
+            purge_action.dispatch(PurgeTodo { id });
+        }
+    };
+
+    view! {
+        <div class="flex items-start border-b border-gray-300 pb-4 mb-4">
+            <div class="flex-grow">
+                <h3 class="text-lg font-semibold text-gray-500">{todo.title}</h3>
+                <p class="text-gray-500">{todo.description}</p>
+                {todo.deleted_at.as_deref().map(format_timestamp).map(|at| view! {
+                    <p class="text-sm text-gray-500 mt-1">Trashed: <span class="font-medium">{at}</span></p>
+                })}
+            </div>
+
+            <div class="flex space-x-4 ml-4">
+                <button class="text-blue-600 hover:text-blue-800" on:click=on_restore>"Restore"</button>
+                <button class="text-red-600 hover:text-red-800" on:click=on_purge>"Delete forever"</button>
+            </div>
+        </div>
+    }
+}
@@ -1,8 +1,16 @@
+use crate::server::recurrence::Recurrence;
+use crate::server::todo::Priority;
+use chrono::Weekday;
+
 #[derive(Clone, PartialEq)]
 pub enum NotificationType {
     SuccessAdd,
     SuccessUpdate,
     SuccessDelete,
+    SuccessReopen,
+    SuccessRestore,
+    SuccessPurge,
+    SuccessMove,
     Error(String),
 }
 
@@ -11,4 +19,111 @@ pub struct UpdateForm {
     pub title: String,
     pub description: String,
     pub due_date: String,
+    pub priority: Priority,
+    pub list_id: i32,
+    pub parent_id: Option<i32>,
+}
+
+/// The kinds of schedule the repeat picker offers.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum RepeatKind {
+    #[default]
+    Never,
+    Daily,
+    Weekly,
+    Monthly,
+    AfterCompletion,
+}
+
+impl RepeatKind {
+    pub const ALL: [RepeatKind; 5] = [
+        RepeatKind::Never,
+        RepeatKind::Daily,
+        RepeatKind::Weekly,
+        RepeatKind::Monthly,
+        RepeatKind::AfterCompletion,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RepeatKind::Never => "never",
+            RepeatKind::Daily => "daily",
+            RepeatKind::Weekly => "weekly",
+            RepeatKind::Monthly => "monthly",
+            RepeatKind::AfterCompletion => "after_completion",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            RepeatKind::Never => "Does not repeat",
+            RepeatKind::Daily => "Daily",
+            RepeatKind::Weekly => "Weekly on...",
+            RepeatKind::Monthly => "Monthly on day...",
+            RepeatKind::AfterCompletion => "Days after completion...",
+        }
+    }
+}
+
+/// The fields of the repeat picker. The number fields keep what was typed, so a bad
+/// value can be reported instead of silently replaced.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecurrenceForm {
+    pub kind: RepeatKind,
+    pub weekdays: Vec<Weekday>,
+    pub month_day: String,
+    pub days: String,
+}
+
+impl Default for RecurrenceForm {
+    fn default() -> Self {
+        Self {
+            kind: RepeatKind::Never,
+            weekdays: Vec::new(),
+            month_day: "1".into(),
+            days: "7".into(),
+        }
+    }
+}
+
+impl RecurrenceForm {
+    pub fn new(recurrence: Option<&Recurrence>) -> Self {
+        let defaults = Self::default();
+        match recurrence {
+            None => defaults,
+            Some(Recurrence::Daily) => Self {
+                kind: RepeatKind::Daily,
+                ..defaults
+            },
+            Some(Recurrence::Weekly(weekdays)) => Self {
+                kind: RepeatKind::Weekly,
+                weekdays: weekdays.clone(),
+                ..defaults
+            },
+            Some(Recurrence::Monthly(day)) => Self {
+                kind: RepeatKind::Monthly,
+                month_day: day.to_string(),
+                ..defaults
+            },
+            Some(Recurrence::AfterCompletion(days)) => Self {
+                kind: RepeatKind::AfterCompletion,
+                days: days.to_string(),
+                ..defaults
+            },
+        }
+    }
+
+    /// The rule to submit, or an empty string if the item doesn't repeat. Bad choices,
+    /// such as a weekly rule without days, give a rule that fails validation.
+    pub fn rule(&self) -> String {
+        let number = |value: &str| value.trim().parse().unwrap_or(0);
+        let recurrence = match self.kind {
+            RepeatKind::Never => return String::new(),
+            RepeatKind::Daily => Recurrence::Daily,
+            RepeatKind::Weekly => Recurrence::Weekly(self.weekdays.clone()),
+            RepeatKind::Monthly => Recurrence::Monthly(number(&self.month_day)),
+            RepeatKind::AfterCompletion => Recurrence::AfterCompletion(number(&self.days)),
+        };
+        recurrence.to_string()
+    }
 }
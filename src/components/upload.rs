@@ -0,0 +1,19 @@
+//! Reading files the user picks, so their contents can be sent to a server function.
+
+use leptos::ev::Event;
+use leptos::event_target;
+use web_sys::{File, HtmlInputElement};
+
+/// The file picked in the `<input type="file">` that fired `ev`, if any.
+pub fn picked_file(ev: &Event) -> Option<File> {
+    event_target::<HtmlInputElement>(ev).files()?.get(0)
+}
+
+/// Reads the whole of `file` as UTF-8 text.
+pub async fn read_text(file: &File) -> Result<String, String> {
+    wasm_bindgen_futures::JsFuture::from(file.text())
+        .await
+        .ok()
+        .and_then(|text| text.as_string())
+        .ok_or_else(|| format!("{} could not be read.", file.name()))
+}
@@ -1,5 +1,6 @@
 pub mod components;
 pub mod server;
+pub mod validation;
 
 #[cfg(feature = "hydrate")]
 #[wasm_bindgen::prelude::wasm_bindgen]
@@ -1,20 +1,77 @@
-pub mod components;
-pub mod server;
-
 #[cfg(feature = "ssr")]
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     use actix_files::Files;
+    use actix_web::middleware::from_fn;
     use actix_web::{web, App, HttpServer};
     use leptos::get_configuration;
     use leptos_actix::{generate_route_list, LeptosRoutes};
     use leptos_todo_new::components::app::App;
+    use leptos_todo_new::server::api;
+    use leptos_todo_new::server::backup::ssr::download_backup;
+    use leptos_todo_new::server::backup::BACKUP_PATH;
+    use leptos_todo_new::server::calendar::ssr::calendar_feed;
+    use leptos_todo_new::server::calendar::CALENDAR_PATH;
+    use leptos_todo_new::server::csv::ssr::download_csv;
+    use leptos_todo_new::server::csv::CSV_EXPORT_PATH;
+    use leptos_todo_new::server::live::ssr::{todo_events, TodoChanges};
+    use leptos_todo_new::server::live::EVENTS_PATH;
+    use leptos_todo_new::server::session::redirect_to_login;
+    use leptos_todo_new::server::settings::{DatabaseBackend, Settings};
+    use leptos_todo_new::server::trash::spawn_purge_task;
+
+    let settings =
+        Settings::load().map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+
+    env_logger::Builder::new()
+        .filter_level(settings.log_level)
+        .init();
 
     let conf = get_configuration(None).await.unwrap();
-    let addr = conf.leptos_options.site_addr;
+    let addr = settings
+        .server
+        .bind_address
+        .unwrap_or(conf.leptos_options.site_addr);
+
+    let command = Command::from_args()?;
+    // Only Postgres announces changes; with SQLite the event streams stay quiet.
+    let changes = TodoChanges::new();
+    let repository = match settings.database.backend {
+        DatabaseBackend::Postgres => postgres_repository(&settings, &command, &changes).await?,
+        #[cfg(feature = "sqlite")]
+        DatabaseBackend::Sqlite => sqlite_repository(&settings, &command)?,
+        #[cfg(not(feature = "sqlite"))]
+        DatabaseBackend::Sqlite => unreachable!("rejected by Settings::load"),
+    };
+    let Some(repository) = repository else {
+        return Ok(());
+    };
+    if let Command::Claim(username) = &command {
+        return claim_unowned(repository.as_ref(), username).await;
+    }
+    match repository.count_unowned().await {
+        Ok(0) => {}
+        Ok(unowned) => log::warn!(
+            "{unowned} todo items from before accounts existed belong to nobody and are hidden; \
+             run `leptos-todo-new claim <username>` to give them to that user"
+        ),
+        Err(e) => log::warn!("failed to count todo items without an owner: {e:?}"),
+    }
+
+    if let Some(retention) = settings.trash.retention() {
+        spawn_purge_task(
+            repository.clone(),
+            retention,
+            settings.trash.purge_interval(),
+        );
+    }
+
+    // Every worker has to encrypt and decrypt cookies with the same key.
+    let session_key = settings.session.key();
+
     // Generate the list of routes in your Leptos App
     let routes = generate_route_list(App);
-    println!("listening on http://{}", &addr);
+    log::info!("listening on http://{}", addr);
 
     HttpServer::new(move || {
         let leptos_options = &conf.leptos_options;
@@ -27,11 +84,35 @@ async fn main() -> std::io::Result<()> {
             .service(Files::new("/assets", site_root))
             // serve the favicon from /favicon.ico
             .service(favicon)
-            .leptos_routes(leptos_options.to_owned(), routes.to_owned(), App)
+            // registered ahead of the server functions, which take the rest of /api
+            .route(EVENTS_PATH, web::get().to(todo_events))
+            .route(BACKUP_PATH, web::get().to(download_backup))
+            .route(CSV_EXPORT_PATH, web::get().to(download_csv))
+            .route(
+                &format!("{CALENDAR_PATH}/{{file}}"),
+                web::get().to(calendar_feed),
+            )
+            .service(api::scope())
+            .service(api::docs())
+            .leptos_routes_with_context(
+                leptos_options.to_owned(),
+                routes.to_owned(),
+                {
+                    let repository = repository.clone();
+                    move || leptos::provide_context(repository.clone())
+                },
+                App,
+            )
             .app_data(web::Data::new(leptos_options.to_owned()))
+            .app_data(web::Data::new(settings.clone()))
+            .app_data(web::Data::new(changes.clone()))
+            .app_data(web::Data::new(repository.clone()))
+            // The session has to wrap the redirect, which reads it.
+            .wrap(from_fn(redirect_to_login))
+            .wrap(settings.session.middleware(session_key.clone()))
         //.wrap(middleware::Compress::default())
     })
-    .bind(&addr)?
+    .bind(addr)?
     .run()
     .await
 }
@@ -47,3 +128,147 @@ async fn favicon(
         "{site_root}/favicon.ico"
     ))?)
 }
+
+/// What the binary was asked to do. `migrate up` applies pending migrations,
+/// `migrate down [steps]` reverts the latest ones and `claim <username>` gives that user
+/// whatever was stored before accounts existed; each exits afterwards. Without arguments
+/// the server migrates on boot and then serves.
+#[cfg(feature = "ssr")]
+enum Command {
+    Serve,
+    MigrateUp,
+    MigrateDown(usize),
+    Claim(String),
+}
+
+#[cfg(feature = "ssr")]
+impl Command {
+    fn from_args() -> std::io::Result<Self> {
+        let usage = || {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "usage: leptos-todo-new [migrate up | migrate down [steps] | claim <username>]",
+            )
+        };
+
+        let args = std::env::args().skip(1).collect::<Vec<_>>();
+        match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+            [] => Ok(Command::Serve),
+            ["migrate", "up"] => Ok(Command::MigrateUp),
+            ["migrate", "down"] => Ok(Command::MigrateDown(1)),
+            ["migrate", "down", steps] => {
+                steps.parse().map(Command::MigrateDown).map_err(|_| usage())
+            }
+            ["claim", username] => Ok(Command::Claim(username.into())),
+            _ => Err(usage()),
+        }
+    }
+}
+
+/// Gives `username` the todo items, tags and lists stored before accounts existed.
+#[cfg(feature = "ssr")]
+async fn claim_unowned(
+    repository: &dyn leptos_todo_new::server::repository::TodoRepository,
+    username: &str,
+) -> std::io::Result<()> {
+    use leptos_todo_new::validation::user::normalize_username;
+
+    let repository_error = |e| std::io::Error::other(format!("{e:?}"));
+    let user = repository
+        .find_user(&normalize_username(username))
+        .await
+        .map_err(repository_error)?
+        .ok_or_else(|| {
+            let message = format!("there is no user named {username}; sign up first");
+            std::io::Error::new(std::io::ErrorKind::NotFound, message)
+        })?;
+    let claimed = repository
+        .claim_unowned(user.user.id)
+        .await
+        .map_err(repository_error)?;
+    log::info!("gave {claimed} todo items to {}", user.user.username);
+    Ok(())
+}
+
+/// Connects to Postgres and runs `command`. Returns the repository to serve from, or
+/// `None` if the command was a one-off migration. When serving, announces changes to
+/// `changes`.
+#[cfg(feature = "ssr")]
+async fn postgres_repository(
+    settings: &leptos_todo_new::server::settings::Settings,
+    command: &Command,
+    changes: &leptos_todo_new::server::live::ssr::TodoChanges,
+) -> std::io::Result<Option<leptos_todo_new::server::repository::SharedTodoRepository>> {
+    use leptos_todo_new::server::db::{create_pool, spawn_idle_reaper};
+    use leptos_todo_new::server::live::ssr::spawn_change_listener;
+    use leptos_todo_new::server::migrate;
+    use leptos_todo_new::server::repository::PgTodoRepository;
+    use std::sync::Arc;
+
+    let pool_settings = &settings.database.pool;
+    let pg_config = settings
+        .database
+        .pg_config()
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+    let pool = create_pool(pg_config.clone(), pool_settings).map_err(std::io::Error::other)?;
+
+    match command {
+        Command::MigrateDown(steps) => {
+            let reverted = migrate::rollback(&pool, *steps)
+                .await
+                .map_err(std::io::Error::other)?;
+            log::info!("reverted migrations {reverted:?}");
+            return Ok(None);
+        }
+        Command::MigrateUp | Command::Serve | Command::Claim(_) => {
+            let applied = migrate::run_pending(&pool)
+                .await
+                .map_err(std::io::Error::other)?;
+            log::info!("applied migrations {applied:?}");
+            if let Command::MigrateUp = command {
+                return Ok(None);
+            }
+        }
+    }
+
+    if let Some(idle_timeout) = pool_settings.idle_timeout() {
+        spawn_idle_reaper(pool.clone(), idle_timeout);
+    }
+    spawn_change_listener(pg_config, changes.clone());
+
+    Ok(Some(Arc::new(PgTodoRepository::new(pool))))
+}
+
+/// Opens the SQLite file and runs `command`, like [`postgres_repository`].
+#[cfg(feature = "sqlite")]
+fn sqlite_repository(
+    settings: &leptos_todo_new::server::settings::Settings,
+    command: &Command,
+) -> std::io::Result<Option<leptos_todo_new::server::repository::SharedTodoRepository>> {
+    use leptos_todo_new::server::migrate;
+    use leptos_todo_new::server::repository::SqliteTodoRepository;
+    use std::sync::Arc;
+
+    let mut conn = rusqlite::Connection::open(&settings.database.sqlite_path)
+        .map_err(std::io::Error::other)?;
+    conn.pragma_update(None, "foreign_keys", true)
+        .map_err(std::io::Error::other)?;
+
+    match command {
+        Command::MigrateDown(steps) => {
+            let reverted =
+                migrate::sqlite::rollback(&mut conn, *steps).map_err(std::io::Error::other)?;
+            log::info!("reverted migrations {reverted:?}");
+            return Ok(None);
+        }
+        Command::MigrateUp | Command::Serve | Command::Claim(_) => {
+            let applied = migrate::sqlite::run_pending(&mut conn).map_err(std::io::Error::other)?;
+            log::info!("applied migrations {applied:?}");
+            if let Command::MigrateUp = command {
+                return Ok(None);
+            }
+        }
+    }
+
+    Ok(Some(Arc::new(SqliteTodoRepository::new(conn))))
+}
@@ -0,0 +1,767 @@
+//! The versioned JSON API under `/api/v1`, for scripts and other services. The server
+//! functions change along with the UI; the paths and JSON shapes here only change in a
+//! new version.
+//!
+//! Requests log in with HTTP Basic credentials, or use the session of a logged-in
+//! browser. Every failure has a body like
+//! `{"error": {"code": "not_found", "message": "That todo item no longer exists."}}`,
+//! with a `fields` array of `{"field", "message"}` for validation failures.
+//!
+//! [`ApiDoc`] describes the API as OpenAPI 3, generated from the types and handlers
+//! below. It's served at [`OPENAPI_PATH`] and can be browsed at [`DOCS_PATH`].
+
+use crate::server::auth::ssr::{check_access, check_credentials, session_user};
+use crate::server::checklist::ChecklistItem;
+use crate::server::error::TodoError;
+use crate::server::repository::{RepositoryError, Resource, SharedTodoRepository, TodoChanges};
+use crate::server::session::SESSION_COOKIE;
+use crate::server::tag::Tag;
+use crate::server::todo::ssr::{tag_set, todo_input};
+use crate::server::todo::{PaginatedTodos, Priority, SortOrder, Todo};
+use crate::validation::date::parse_due_date;
+use crate::validation::recurrence::parse_recurrence;
+use crate::validation::todo::{
+    check_all, check_description, check_due_date, check_title, FieldError,
+};
+use actix_session::SessionExt;
+use actix_web::http::{header, Method, StatusCode};
+use actix_web::{web, HttpRequest, HttpResponse, ResponseError, Route};
+use base64::prelude::{Engine, BASE64_STANDARD};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::fmt;
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::openapi::Server;
+use utoipa::{IntoParams, Modify, OpenApi, ToSchema};
+use utoipa_swagger_ui::SwaggerUi;
+
+/// Where version 1 of the API lives.
+pub const V1_PATH: &str = "/api/v1";
+
+/// Where the OpenAPI document is served.
+pub const OPENAPI_PATH: &str = "/api/openapi.json";
+
+/// Where the API docs are served.
+pub const DOCS_PATH: &str = "/api/docs/";
+
+/// How many items a page holds unless the request says otherwise.
+const DEFAULT_PER_PAGE: u32 = 10;
+
+/// The most items a page may hold.
+pub const MAX_PER_PAGE: u32 = 100;
+
+/// Every endpoint: its method, its path under [`V1_PATH`] and its handler. Each handler
+/// must also be listed in [`ApiDoc`].
+fn endpoints() -> [(Method, &'static str, Route); 6] {
+    [
+        (Method::GET, "/todos", web::route().to(list_todos)),
+        (Method::POST, "/todos", web::route().to(create_todo)),
+        (Method::GET, "/todos/{id}", web::route().to(get_todo)),
+        (Method::PATCH, "/todos/{id}", web::route().to(update_todo)),
+        (Method::DELETE, "/todos/{id}", web::route().to(delete_todo)),
+        (
+            Method::POST,
+            "/todos/{id}/complete",
+            web::route().to(complete_todo),
+        ),
+    ]
+}
+
+/// The API's routes. Register them ahead of the server functions, which take the rest of
+/// `/api`.
+pub fn scope() -> actix_web::Scope {
+    let json = web::JsonConfig::default().error_handler(|e, _| ApiError::bad_request(e).into());
+    let query = web::QueryConfig::default().error_handler(|e, _| ApiError::bad_request(e).into());
+    let path = web::PathConfig::default().error_handler(|e, _| ApiError::bad_request(e).into());
+    let scope = web::scope(V1_PATH)
+        .app_data(json)
+        .app_data(query)
+        .app_data(path);
+
+    endpoints()
+        .into_iter()
+        .fold(scope, |scope, (method, path, route)| {
+            scope.route(path, route.method(method))
+        })
+        .default_service(web::to(|| async {
+            ApiError::unknown_endpoint().error_response()
+        }))
+}
+
+/// The method and full path of every endpoint [`scope`] serves, with path parameters in
+/// braces as in the OpenAPI document.
+pub fn routes() -> Vec<(Method, String)> {
+    endpoints()
+        .into_iter()
+        .map(|(method, path, _)| (method, format!("{V1_PATH}{path}")))
+        .collect()
+}
+
+/// The OpenAPI document and the docs page browsing it. The page is bundled into the
+/// binary, so it works offline.
+pub fn docs() -> SwaggerUi {
+    SwaggerUi::new(format!("{DOCS_PATH}{{_:.*}}")).url(OPENAPI_PATH, ApiDoc::openapi())
+}
+
+/// The OpenAPI 3 description of the API.
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Todos",
+        version = "1",
+        description = "Todo items of the logged-in user. Failures answer with an `Error` body.",
+        license(name = "Unlicense", url = "https://unlicense.org"),
+    ),
+    paths(list_todos, create_todo, get_todo, update_todo, delete_todo, complete_todo),
+    // schemas the query parameters use, which aren't collected from the handlers
+    components(schemas(Status, SortOrder)),
+    modifiers(&Contract),
+    security(("basic" = []), ("session" = [])),
+)]
+pub struct ApiDoc;
+
+/// Fills in what the derive can't take from constants: the base path and the ways to log
+/// in.
+struct Contract;
+
+impl Modify for Contract {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        openapi.servers = Some(vec![Server::new(V1_PATH)]);
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "basic",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Basic).build()),
+        );
+        components.add_security_scheme(
+            "session",
+            SecurityScheme::ApiKey(ApiKey::Cookie(ApiKeyValue::new(SESSION_COOKIE))),
+        );
+    }
+}
+
+/// A todo item as the API shows it.
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug, PartialEq)]
+#[schema(as = Todo)]
+pub struct ApiTodo {
+    pub id: i32,
+    pub title: String,
+    pub description: String,
+    pub completed: bool,
+    /// When the item was completed, as an RFC 3339 UTC timestamp.
+    pub completed_at: Option<String>,
+    /// When the item was moved to the trash, as an RFC 3339 UTC timestamp.
+    pub deleted_at: Option<String>,
+    /// When the item was created, as an RFC 3339 UTC timestamp.
+    pub created: Option<String>,
+    /// `YYYY-MM-DD`.
+    pub due_date: String,
+    pub priority: Priority,
+    /// Ordered by name.
+    pub tags: Vec<ApiTag>,
+    /// In the user's order.
+    pub checklist: Vec<ApiChecklistItem>,
+    pub list_id: i32,
+    pub parent_id: Option<i32>,
+    /// An RRULE-style rule such as `FREQ=WEEKLY;BYDAY=MO,FR`, if the item repeats.
+    pub recurrence: Option<String>,
+    /// The sub-todos outside the trash, oldest first.
+    #[schema(no_recursion)]
+    pub subtodos: Vec<ApiTodo>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug, PartialEq)]
+#[schema(as = Tag)]
+pub struct ApiTag {
+    pub id: i32,
+    pub name: String,
+    /// `#rrggbb`.
+    pub colour: String,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug, PartialEq)]
+#[schema(as = ChecklistItem)]
+pub struct ApiChecklistItem {
+    pub id: i32,
+    pub text: String,
+    pub done: bool,
+}
+
+/// One page of todo items. Pages start at 0.
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug, PartialEq)]
+pub struct TodoPage {
+    pub items: Vec<ApiTodo>,
+    pub page: u32,
+    pub per_page: u32,
+    pub total: u32,
+    pub total_pages: u32,
+}
+
+impl From<Todo> for ApiTodo {
+    fn from(todo: Todo) -> Self {
+        Self {
+            id: todo.id,
+            title: todo.title,
+            description: todo.description,
+            completed: todo.completed,
+            completed_at: todo.completed_at,
+            deleted_at: todo.deleted_at,
+            created: todo.created,
+            due_date: todo.due_date,
+            priority: todo.priority,
+            tags: todo.tags.into_iter().map(ApiTag::from).collect(),
+            checklist: todo
+                .checklist
+                .into_iter()
+                .map(ApiChecklistItem::from)
+                .collect(),
+            list_id: todo.list_id,
+            parent_id: todo.parent_id,
+            recurrence: todo.recurrence.map(String::from),
+            subtodos: todo.children.into_iter().map(ApiTodo::from).collect(),
+        }
+    }
+}
+
+impl From<Tag> for ApiTag {
+    fn from(tag: Tag) -> Self {
+        Self {
+            id: tag.id,
+            name: tag.name,
+            colour: tag.colour,
+        }
+    }
+}
+
+impl From<ChecklistItem> for ApiChecklistItem {
+    fn from(item: ChecklistItem) -> Self {
+        Self {
+            id: item.id,
+            text: item.text,
+            done: item.done,
+        }
+    }
+}
+
+impl TodoPage {
+    fn new(page: PaginatedTodos, per_page: u32) -> Self {
+        Self {
+            items: page.items.into_iter().map(ApiTodo::from).collect(),
+            page: page.page,
+            per_page,
+            total: page.total,
+            total_pages: page.total_pages,
+        }
+    }
+}
+
+/// Which todo items `GET /todos` lists.
+#[derive(Deserialize, ToSchema, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    #[default]
+    Open,
+    Completed,
+    Trashed,
+}
+
+/// The query of `GET /todos`.
+#[derive(Deserialize, IntoParams, Debug)]
+#[into_params(parameter_in = Query)]
+pub struct ListQuery {
+    /// Which items to list.
+    #[serde(default)]
+    pub status: Status,
+    /// The page to show, starting at 0.
+    #[serde(default)]
+    pub page: u32,
+    /// How many items a page holds; 10 if missing.
+    #[param(minimum = 1, maximum = 100)]
+    pub per_page: Option<u32>,
+    /// Only items in this list. Open items only.
+    pub list: Option<i32>,
+    /// Comma-separated tag ids; only items carrying all of them. Open items only.
+    pub tags: Option<String>,
+    /// How open items are ordered.
+    #[serde(default)]
+    pub sort: SortOrder,
+}
+
+/// The body of `POST /todos`.
+#[derive(Deserialize, ToSchema, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct NewTodo {
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    /// `YYYY-MM-DD`.
+    pub due_date: String,
+    #[serde(default)]
+    pub priority: Priority,
+    #[serde(default)]
+    pub tag_ids: Vec<i32>,
+    /// An RRULE-style rule, or `null` if the item doesn't repeat.
+    #[serde(default)]
+    pub recurrence: Option<String>,
+    /// The first list if missing.
+    pub list_id: Option<i32>,
+    /// Makes the item a sub-todo, in its parent's list.
+    pub parent_id: Option<i32>,
+}
+
+/// The body of `PATCH /todos/{id}`. Missing fields are left alone.
+#[derive(Deserialize, ToSchema, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct TodoPatch {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub due_date: Option<String>,
+    pub priority: Option<Priority>,
+    /// Replaces every attached tag.
+    pub tag_ids: Option<Vec<i32>>,
+    /// A new rule, or `null` to stop the item repeating.
+    #[serde(default, deserialize_with = "present")]
+    #[schema(value_type = Option<String>)]
+    pub recurrence: Option<Option<String>>,
+    /// Moves the item and its sub-todos to another list.
+    pub list_id: Option<i32>,
+}
+
+/// The query of `POST /todos/{id}/complete`.
+#[derive(Deserialize, IntoParams, Debug)]
+#[into_params(parameter_in = Query)]
+pub struct CompleteQuery {
+    /// Also ticks off every item of the checklist.
+    #[serde(default)]
+    pub complete_checklist: bool,
+}
+
+/// Tells a field that's `null` apart from one that's missing, which `default` leaves
+/// as `None`.
+fn present<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+/// A failed request, sent as the JSON body described in the module docs.
+#[derive(Debug)]
+pub struct ApiError {
+    status: StatusCode,
+    code: &'static str,
+    message: String,
+    fields: Vec<FieldError>,
+}
+
+/// The body of every failure.
+#[derive(Serialize, Deserialize, ToSchema, Debug)]
+#[schema(as = Error)]
+pub struct ErrorBody {
+    pub error: ErrorDetail,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Debug)]
+pub struct ErrorDetail {
+    /// What went wrong, for programs: `not_found`, `validation_failed`, `forbidden`...
+    pub code: String,
+    /// What went wrong, for people.
+    pub message: String,
+    /// The rejected fields, for `validation_failed`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<FieldError>,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            code,
+            message: message.into(),
+            fields: Vec::new(),
+        }
+    }
+
+    fn bad_request(e: impl fmt::Display) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, "bad_request", e.to_string())
+    }
+
+    pub(crate) fn unknown_endpoint() -> Self {
+        Self::new(
+            StatusCode::NOT_FOUND,
+            "unknown_endpoint",
+            "No such endpoint.",
+        )
+    }
+
+    fn invalid(field: &str, message: &str) -> Self {
+        TodoError::from(vec![FieldError::new(field, message)]).into()
+    }
+}
+
+impl From<TodoError> for ApiError {
+    fn from(e: TodoError) -> Self {
+        let message = e.message();
+        let (status, code) = match &e {
+            TodoError::NotFound { .. } => (StatusCode::NOT_FOUND, "not_found"),
+            TodoError::TagNotFound { .. } => (StatusCode::NOT_FOUND, "tag_not_found"),
+            TodoError::ChecklistItemNotFound { .. } => {
+                (StatusCode::NOT_FOUND, "checklist_item_not_found")
+            }
+            TodoError::ListNotFound { .. } => (StatusCode::NOT_FOUND, "list_not_found"),
+            TodoError::Validation { .. } => (StatusCode::UNPROCESSABLE_ENTITY, "validation_failed"),
+            TodoError::Conflict { .. } => (StatusCode::CONFLICT, "conflict"),
+            TodoError::Unauthorized => (StatusCode::UNAUTHORIZED, "unauthorized"),
+            TodoError::InvalidCredentials => (StatusCode::UNAUTHORIZED, "invalid_credentials"),
+            TodoError::Forbidden => (StatusCode::FORBIDDEN, "forbidden"),
+            TodoError::Unavailable => (StatusCode::SERVICE_UNAVAILABLE, "unavailable"),
+            TodoError::Internal => (StatusCode::INTERNAL_SERVER_ERROR, "internal"),
+        };
+        let fields = match e {
+            TodoError::Validation { errors } => errors,
+            _ => Vec::new(),
+        };
+        Self {
+            status,
+            code,
+            message,
+            fields,
+        }
+    }
+}
+
+impl From<RepositoryError> for ApiError {
+    fn from(e: RepositoryError) -> Self {
+        TodoError::from(e).into()
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.code, self.message)
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        self.status
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status);
+        if self.status == StatusCode::UNAUTHORIZED {
+            response.insert_header((header::WWW_AUTHENTICATE, "Basic realm=\"todos\""));
+        }
+        response.json(ErrorBody {
+            error: ErrorDetail {
+                code: self.code.into(),
+                message: self.message.clone(),
+                fields: self.fields.clone(),
+            },
+        })
+    }
+}
+
+/// The user making the request, from HTTP Basic credentials if there are any and the
+/// session otherwise.
+pub(crate) async fn caller(
+    req: &HttpRequest,
+    repository: &SharedTodoRepository,
+) -> Result<i32, ApiError> {
+    let Some(authorization) = req.headers().get(header::AUTHORIZATION) else {
+        return session_user(&req.get_session()).ok_or(TodoError::Unauthorized.into());
+    };
+
+    let credentials = authorization
+        .to_str()
+        .ok()
+        .and_then(|value| value.strip_prefix("Basic "))
+        .and_then(|encoded| BASE64_STANDARD.decode(encoded.trim()).ok())
+        .and_then(|decoded| String::from_utf8(decoded).ok());
+    let Some((username, password)) = credentials
+        .as_deref()
+        .and_then(|credentials| credentials.split_once(':'))
+    else {
+        return Err(TodoError::Unauthorized.into());
+    };
+    let user = check_credentials(&**repository, username, password.to_string()).await?;
+    Ok(user.id)
+}
+
+/// The comma-separated tag ids of a filter.
+pub(crate) fn parse_tags(tags: Option<&str>) -> Result<Vec<i32>, ApiError> {
+    tags.unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .map(|id| {
+            id.parse()
+                .map_err(|_| ApiError::invalid("tags", "tags must be comma-separated tag ids"))
+        })
+        .collect()
+}
+
+pub(crate) fn tag_resources(tag_ids: &[i32]) -> impl Iterator<Item = Resource> + '_ {
+    tag_ids.iter().copied().map(Resource::Tag)
+}
+
+/// Lists a page of open, completed or trashed top-level items.
+#[utoipa::path(
+    get,
+    path = "/todos",
+    params(ListQuery),
+    responses(
+        (status = 200, description = "The page", body = TodoPage),
+        (status = 400, description = "The query can't be read", body = ErrorBody),
+        (status = 401, description = "Not logged in", body = ErrorBody),
+        (status = 403, description = "A filter names another user's list or tag", body = ErrorBody),
+        (status = 404, description = "A filter names a missing list or tag", body = ErrorBody),
+        (status = 422, description = "The query is invalid", body = ErrorBody),
+    )
+)]
+async fn list_todos(
+    req: HttpRequest,
+    repository: web::Data<SharedTodoRepository>,
+    query: web::Query<ListQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let owner = caller(&req, &repository).await?;
+    let query = query.into_inner();
+    let tags = tag_set(parse_tags(query.tags.as_deref())?);
+    let per_page = query.per_page.unwrap_or(DEFAULT_PER_PAGE);
+    if !(1..=MAX_PER_PAGE).contains(&per_page) {
+        let message = format!("per_page must be between 1 and {MAX_PER_PAGE}");
+        return Err(ApiError::invalid("per_page", &message));
+    }
+    let filtered = query.list.is_some() || !tags.is_empty();
+    if filtered && query.status != Status::Open {
+        let message = "list and tags only filter open items";
+        return Err(ApiError::invalid("status", message));
+    }
+    check_access(
+        repository.get_ref().as_ref(),
+        owner,
+        tag_resources(&tags).chain(query.list.map(Resource::List)),
+    )
+    .await?;
+
+    let page = match query.status {
+        Status::Open => {
+            repository
+                .paginate(owner, query.page, per_page, query.list, &tags, query.sort)
+                .await?
+        }
+        Status::Completed => {
+            repository
+                .paginate_completed(owner, query.page, per_page)
+                .await?
+        }
+        Status::Trashed => {
+            repository
+                .paginate_trash(owner, query.page, per_page)
+                .await?
+        }
+    };
+    Ok(HttpResponse::Ok().json(TodoPage::new(page, per_page)))
+}
+
+/// Creates an item.
+#[utoipa::path(
+    post,
+    path = "/todos",
+    request_body = NewTodo,
+    responses(
+        (status = 201, description = "The new item", body = ApiTodo,
+            headers(("Location" = String, description = "Where the item lives"))),
+        (status = 400, description = "The body isn't a JSON `NewTodo`", body = ErrorBody),
+        (status = 401, description = "Not logged in", body = ErrorBody),
+        (status = 403, description = "The item names another user's list, tag or parent", body = ErrorBody),
+        (status = 404, description = "The item names a missing list, tag or parent", body = ErrorBody),
+        (status = 422, description = "The item is invalid", body = ErrorBody),
+    )
+)]
+async fn create_todo(
+    req: HttpRequest,
+    repository: web::Data<SharedTodoRepository>,
+    body: web::Json<NewTodo>,
+) -> Result<HttpResponse, ApiError> {
+    let owner = caller(&req, &repository).await?;
+    let new = body.into_inner();
+    let input = todo_input(
+        &new.title,
+        &new.description,
+        &new.due_date,
+        new.priority,
+        new.tag_ids,
+        new.recurrence.as_deref().unwrap_or_default(),
+    )?;
+    check_access(
+        repository.get_ref().as_ref(),
+        owner,
+        tag_resources(&input.tag_ids)
+            .chain(new.parent_id.map(Resource::Todo))
+            .chain(new.list_id.map(Resource::List)),
+    )
+    .await?;
+
+    let todo = repository
+        .create(owner, input, new.list_id, new.parent_id)
+        .await?;
+    Ok(HttpResponse::Created()
+        .insert_header((header::LOCATION, format!("{V1_PATH}/todos/{}", todo.id)))
+        .json(ApiTodo::from(todo)))
+}
+
+/// Shows an item outside the trash, with its sub-todos.
+#[utoipa::path(
+    get,
+    path = "/todos/{id}",
+    params(("id" = i32, Path, description = "The item's id")),
+    responses(
+        (status = 200, description = "The item", body = ApiTodo),
+        (status = 401, description = "Not logged in", body = ErrorBody),
+        (status = 403, description = "The item belongs to another user", body = ErrorBody),
+        (status = 404, description = "No such item outside the trash", body = ErrorBody),
+    )
+)]
+async fn get_todo(
+    req: HttpRequest,
+    repository: web::Data<SharedTodoRepository>,
+    id: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
+    let owner = caller(&req, &repository).await?;
+    let id = id.into_inner();
+    check_access(repository.get_ref().as_ref(), owner, [Resource::Todo(id)]).await?;
+
+    let todo = repository.get(owner, id).await?;
+    Ok(HttpResponse::Ok().json(ApiTodo::from(todo)))
+}
+
+/// Changes the given fields of an item.
+#[utoipa::path(
+    patch,
+    path = "/todos/{id}",
+    params(("id" = i32, Path, description = "The item's id")),
+    request_body = TodoPatch,
+    responses(
+        (status = 200, description = "The updated item", body = ApiTodo),
+        (status = 400, description = "The body isn't a JSON `TodoPatch`", body = ErrorBody),
+        (status = 401, description = "Not logged in", body = ErrorBody),
+        (status = 403, description = "The item, or a list or tag it names, belongs to another user", body = ErrorBody),
+        (status = 404, description = "No such item outside the trash, or a missing list or tag", body = ErrorBody),
+        (status = 422, description = "The changes are invalid", body = ErrorBody),
+    )
+)]
+async fn update_todo(
+    req: HttpRequest,
+    repository: web::Data<SharedTodoRepository>,
+    id: web::Path<i32>,
+    body: web::Json<TodoPatch>,
+) -> Result<HttpResponse, ApiError> {
+    let owner = caller(&req, &repository).await?;
+    let id = id.into_inner();
+    let patch = body.into_inner();
+    check_access(repository.get_ref().as_ref(), owner, [Resource::Todo(id)]).await?;
+
+    let changes = todo_changes(patch)?;
+    check_access(
+        repository.get_ref().as_ref(),
+        owner,
+        tag_resources(changes.tag_ids.as_deref().unwrap_or_default())
+            .chain(changes.list_id.map(Resource::List)),
+    )
+    .await?;
+
+    repository.patch(owner, id, changes).await?;
+    let todo = repository.get(owner, id).await?;
+    Ok(HttpResponse::Ok().json(ApiTodo::from(todo)))
+}
+
+/// Validates the fields a patch gives, leaving out the rest.
+fn todo_changes(patch: TodoPatch) -> Result<TodoChanges, TodoError> {
+    let recurrence = patch
+        .recurrence
+        .map(|rule| parse_recurrence(rule.as_deref().unwrap_or_default()));
+    check_all([
+        ("title", patch.title.as_deref().and_then(check_title)),
+        (
+            "description",
+            patch.description.as_deref().and_then(check_description),
+        ),
+        (
+            "due_date",
+            patch.due_date.as_deref().and_then(check_due_date),
+        ),
+        (
+            "recurrence",
+            recurrence
+                .as_ref()
+                .and_then(|parsed| parsed.as_ref().err())
+                .map(ToString::to_string),
+        ),
+    ])?;
+
+    Ok(TodoChanges {
+        title: patch.title.map(|title| title.trim().to_string()),
+        description: patch.description,
+        due_date: patch
+            .due_date
+            .and_then(|due_date| parse_due_date(&due_date).ok()),
+        priority: patch.priority,
+        tag_ids: patch.tag_ids.map(tag_set),
+        recurrence: recurrence.and_then(Result::ok),
+        list_id: patch.list_id,
+    })
+}
+
+/// Moves an item and its sub-todos to the trash.
+#[utoipa::path(
+    delete,
+    path = "/todos/{id}",
+    params(("id" = i32, Path, description = "The item's id")),
+    responses(
+        (status = 204, description = "The item is in the trash"),
+        (status = 401, description = "Not logged in", body = ErrorBody),
+        (status = 403, description = "The item belongs to another user", body = ErrorBody),
+        (status = 404, description = "No such item", body = ErrorBody),
+    )
+)]
+async fn delete_todo(
+    req: HttpRequest,
+    repository: web::Data<SharedTodoRepository>,
+    id: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
+    let owner = caller(&req, &repository).await?;
+    let id = id.into_inner();
+    check_access(repository.get_ref().as_ref(), owner, [Resource::Todo(id)]).await?;
+
+    repository.delete(owner, id).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Completes an item like the UI does, sub-todos and repeats included.
+#[utoipa::path(
+    post,
+    path = "/todos/{id}/complete",
+    params(("id" = i32, Path, description = "The item's id"), CompleteQuery),
+    responses(
+        (status = 200, description = "The completed item", body = ApiTodo),
+        (status = 401, description = "Not logged in", body = ErrorBody),
+        (status = 403, description = "The item belongs to another user", body = ErrorBody),
+        (status = 404, description = "No such item outside the trash", body = ErrorBody),
+    )
+)]
+async fn complete_todo(
+    req: HttpRequest,
+    repository: web::Data<SharedTodoRepository>,
+    id: web::Path<i32>,
+    query: web::Query<CompleteQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let owner = caller(&req, &repository).await?;
+    let id = id.into_inner();
+    check_access(repository.get_ref().as_ref(), owner, [Resource::Todo(id)]).await?;
+
+    repository
+        .complete(owner, id, query.complete_checklist)
+        .await?;
+    let todo = repository.get(owner, id).await?;
+    Ok(HttpResponse::Ok().json(ApiTodo::from(todo)))
+}
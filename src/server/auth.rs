@@ -0,0 +1,183 @@
+use crate::server::error::TodoError;
+use leptos::{server, ServerFnError};
+use serde::{Deserialize, Serialize};
+
+/// Someone who can log in. Every todo item, tag and list belongs to exactly one user.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct User {
+    pub id: i32,
+    pub username: String,
+}
+
+#[cfg(feature = "ssr")]
+pub mod ssr {
+    use super::User;
+    pub use crate::server::error::TodoError;
+    pub use crate::server::repository::{
+        use_repository, Resource, SharedTodoRepository, TodoRepository,
+    };
+    pub use crate::server::session::{log_in, log_out, session_user};
+    pub use crate::validation::user::{normalize_username, validate_signup};
+    use actix_session::Session;
+    use argon2::password_hash::rand_core::OsRng;
+    use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+    use argon2::Argon2;
+    use std::sync::OnceLock;
+
+    /// The session of the request being handled.
+    pub async fn session() -> Result<Session, TodoError> {
+        leptos_actix::extract::<Session>().await.map_err(|e| {
+            log::error!("failed to read the session: {e}");
+            TodoError::Internal
+        })
+    }
+
+    /// The id of the logged-in user, or `Unauthorized` if nobody is.
+    pub async fn current_user_id() -> Result<i32, TodoError> {
+        session_user(&session().await?).ok_or(TodoError::Unauthorized)
+    }
+
+    /// What a server function may use once [`authorize`] has let its request through.
+    pub struct Authorized {
+        /// The logged-in user, whom every repository call acts for.
+        pub owner: i32,
+        pub repository: SharedTodoRepository,
+    }
+
+    /// The check every server function that touches todo items, tags, lists or checklists
+    /// makes first, naming each row its arguments refer to. Fails with `Unauthorized` if
+    /// nobody is logged in, with the row's not-found error if it doesn't exist, and with
+    /// `Forbidden` if it belongs to someone else.
+    pub async fn authorize(
+        resources: impl IntoIterator<Item = Resource>,
+    ) -> Result<Authorized, TodoError> {
+        let owner = current_user_id().await?;
+        let repository = use_repository()?;
+
+        check_access(&*repository, owner, resources).await?;
+        Ok(Authorized { owner, repository })
+    }
+
+    /// The ownership half of [`authorize`], for callers that know the user some other way.
+    pub async fn check_access(
+        repository: &dyn TodoRepository,
+        owner: i32,
+        resources: impl IntoIterator<Item = Resource>,
+    ) -> Result<(), TodoError> {
+        for resource in resources {
+            if repository.owner_of(resource).await? != Some(owner) {
+                log::warn!("user {owner} was refused access to {resource:?}");
+                return Err(TodoError::Forbidden);
+            }
+        }
+        Ok(())
+    }
+
+    /// The user `username` and `password` belong to, or `InvalidCredentials`.
+    pub async fn check_credentials(
+        repository: &dyn TodoRepository,
+        username: &str,
+        password: String,
+    ) -> Result<User, TodoError> {
+        let credentials = repository.find_user(&normalize_username(username)).await?;
+
+        let hash = credentials.as_ref().map(|c| c.password_hash.clone());
+        let verified = verify_password(hash, password).await;
+        credentials
+            .filter(|_| verified)
+            .map(|credentials| credentials.user)
+            .ok_or(TodoError::InvalidCredentials)
+    }
+
+    /// Hashes `password` with Argon2 and a fresh salt, off the async runtime since it's
+    /// deliberately slow.
+    pub async fn hash_password(password: String) -> Result<String, TodoError> {
+        tokio::task::spawn_blocking(move || {
+            let salt = SaltString::generate(&mut OsRng);
+            Argon2::default()
+                .hash_password(password.as_bytes(), &salt)
+                .map(|hash| hash.to_string())
+        })
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|hash| hash.map_err(|e| e.to_string()))
+        .map_err(|e| {
+            log::error!("failed to hash a password: {e}");
+            TodoError::Internal
+        })
+    }
+
+    /// Checks `password` against a stored hash. Without a hash, checks it against a
+    /// throwaway one instead, so unknown usernames take as long to reject as wrong
+    /// passwords.
+    pub async fn verify_password(hash: Option<String>, password: String) -> bool {
+        static DUMMY_HASH: OnceLock<String> = OnceLock::new();
+
+        tokio::task::spawn_blocking(move || {
+            let known = hash.is_some();
+            let hash = hash.unwrap_or_else(|| {
+                DUMMY_HASH
+                    .get_or_init(|| {
+                        let salt = SaltString::generate(&mut OsRng);
+                        Argon2::default()
+                            .hash_password(b"not a password", &salt)
+                            .map(|hash| hash.to_string())
+                            .unwrap_or_default()
+                    })
+                    .clone()
+            });
+            let matches = PasswordHash::new(&hash).is_ok_and(|hash| {
+                Argon2::default()
+                    .verify_password(password.as_bytes(), &hash)
+                    .is_ok()
+            });
+            known && matches
+        })
+        .await
+        .unwrap_or(false)
+    }
+}
+
+/// Creates an account and logs into it.
+#[server]
+pub async fn signup(username: String, password: String) -> Result<(), ServerFnError<TodoError>> {
+    use self::ssr::*;
+
+    let username = validate_signup(&username, &password).map_err(TodoError::from)?;
+    let password_hash = hash_password(password).await?;
+    let repository = use_repository()?;
+
+    let user = repository.create_user(username, password_hash).await?;
+    log_in(&session().await?, user.id)?;
+    Ok(())
+}
+
+#[server]
+pub async fn login(username: String, password: String) -> Result<(), ServerFnError<TodoError>> {
+    use self::ssr::*;
+
+    let repository = use_repository()?;
+    let user = check_credentials(&*repository, &username, password).await?;
+    log_in(&session().await?, user.id)?;
+    Ok(())
+}
+
+#[server]
+pub async fn logout() -> Result<(), ServerFnError<TodoError>> {
+    use self::ssr::*;
+
+    log_out(&session().await?);
+    Ok(())
+}
+
+/// The logged-in user, or `None` for visitors.
+#[server]
+pub async fn get_current_user() -> Result<Option<User>, ServerFnError<TodoError>> {
+    use self::ssr::*;
+
+    let Some(id) = session_user(&session().await?) else {
+        return Ok(None);
+    };
+    let repository = use_repository()?;
+    Ok(repository.get_user(id).await?)
+}
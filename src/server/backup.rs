@@ -0,0 +1,817 @@
+//! Backups of everything a user has, as versioned JSON. Restoring one adds its contents
+//! alongside what's already there, so it also moves todo items between accounts and
+//! servers.
+
+use crate::server::error::TodoError;
+use crate::server::todo::Priority;
+use leptos::{server, ServerFnError};
+use serde::{Deserialize, Serialize};
+
+/// The version of the backup format written by [`export_backup`]. Bump it whenever the
+/// format changes in a way older servers can't read.
+pub const BACKUP_VERSION: u32 = 1;
+
+/// Where a logged-in user downloads a backup as a file.
+pub const BACKUP_PATH: &str = "/api/backup";
+
+/// Every list, tag and todo item of a user, trashed and completed items included.
+/// Lists, tags and items refer to each other by the ids they had when exported.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Backup {
+    pub version: u32,
+    /// When the backup was made, as an RFC 3339 UTC timestamp.
+    pub exported_at: String,
+    /// In the user's order.
+    #[serde(default)]
+    pub lists: Vec<BackupList>,
+    #[serde(default)]
+    pub tags: Vec<BackupTag>,
+    #[serde(default)]
+    pub todos: Vec<BackupTodo>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BackupList {
+    pub id: i32,
+    pub name: String,
+    pub colour: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BackupTag {
+    pub id: i32,
+    pub name: String,
+    pub colour: String,
+}
+
+/// A todo item, with the same fields as [`crate::server::todo::Todo`] except that tags
+/// are named by id and sub-todos are listed separately.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BackupTodo {
+    pub id: i32,
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    pub due_date: String,
+    #[serde(default)]
+    pub priority: Priority,
+    #[serde(default)]
+    pub completed: bool,
+    #[serde(default)]
+    pub completed_at: Option<String>,
+    #[serde(default)]
+    pub deleted_at: Option<String>,
+    /// When the item was created. Restored items without one are created now.
+    #[serde(default)]
+    pub created: Option<String>,
+    pub list_id: i32,
+    #[serde(default)]
+    pub parent_id: Option<i32>,
+    /// The repeat rule, as an RRULE-style string.
+    #[serde(default)]
+    pub recurrence: Option<String>,
+    #[serde(default)]
+    pub tag_ids: Vec<i32>,
+    /// In the user's order.
+    #[serde(default)]
+    pub checklist: Vec<BackupChecklistItem>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BackupChecklistItem {
+    pub text: String,
+    #[serde(default)]
+    pub done: bool,
+}
+
+/// What restoring a backup changed, or would change with a dry run.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ImportReport {
+    /// Nothing was kept.
+    pub dry_run: bool,
+    /// The names of the lists added.
+    pub new_lists: Vec<String>,
+    /// The names of the lists whose items went into the user's list of the same name.
+    pub merged_lists: Vec<String>,
+    /// The names of the tags added.
+    pub new_tags: Vec<String>,
+    /// The names of the tags that were already there and got reused.
+    pub merged_tags: Vec<String>,
+    /// How many todo items were added, sub-todos included.
+    pub todos: u32,
+    /// How many of them are completed.
+    pub completed: u32,
+    /// How many of them are in the trash.
+    pub trashed: u32,
+    pub checklist_items: u32,
+}
+
+#[cfg(feature = "ssr")]
+pub mod ssr {
+    use super::{Backup, BackupChecklistItem, BackupList, BackupTag, BackupTodo, BACKUP_VERSION};
+    use crate::server::api::{caller, ApiError};
+    use crate::server::error::TodoError;
+    use crate::server::repository::{BackupImport, ImportTodo, SharedTodoRepository, Snapshot};
+    use crate::server::todo::ssr::todo_input;
+    use crate::validation::checklist::check_checklist_text;
+    use crate::validation::list::validate_list;
+    use crate::validation::tag::validate_tag;
+    use crate::validation::todo::FieldError;
+    use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
+    use actix_web::web::Data;
+    use actix_web::{HttpRequest, HttpResponse};
+    use chrono::{DateTime, Utc};
+    use serde::Deserialize;
+    use std::collections::{HashMap, HashSet};
+
+    /// The field that problems with the file as a whole are reported on.
+    const CONTENTS_FIELD: &str = "contents";
+
+    /// Formats `at` like the timestamps of todo items.
+    fn timestamp(at: DateTime<Utc>) -> String {
+        at.format("%Y-%m-%dT%H:%M:%SZ").to_string()
+    }
+
+    /// Writes out what the repository exported as a backup.
+    pub fn backup(snapshot: Snapshot) -> Backup {
+        Backup {
+            version: BACKUP_VERSION,
+            exported_at: timestamp(Utc::now()),
+            lists: snapshot
+                .lists
+                .into_iter()
+                .map(|list| BackupList {
+                    id: list.id,
+                    name: list.name,
+                    colour: list.colour,
+                })
+                .collect(),
+            tags: snapshot
+                .tags
+                .into_iter()
+                .map(|tag| BackupTag {
+                    id: tag.id,
+                    name: tag.name,
+                    colour: tag.colour,
+                })
+                .collect(),
+            todos: snapshot
+                .todos
+                .into_iter()
+                .map(|todo| BackupTodo {
+                    id: todo.id,
+                    title: todo.title,
+                    description: todo.description,
+                    due_date: todo.due_date,
+                    priority: todo.priority,
+                    completed: todo.completed,
+                    completed_at: todo.completed_at,
+                    deleted_at: todo.deleted_at,
+                    created: todo.created,
+                    list_id: todo.list_id,
+                    parent_id: todo.parent_id,
+                    recurrence: todo.recurrence.map(|rule| rule.to_string()),
+                    tag_ids: todo.tags.into_iter().map(|tag| tag.id).collect(),
+                    checklist: todo
+                        .checklist
+                        .into_iter()
+                        .map(|item| BackupChecklistItem {
+                            text: item.text,
+                            done: item.done,
+                        })
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Parses and checks an uploaded backup, collecting an error for each bad field.
+    /// Fields are named by their path in the file, like `todos[3].title`.
+    pub fn parse_backup(contents: &str) -> Result<BackupImport, TodoError> {
+        /// Just enough of a backup to tell which format the rest is in.
+        #[derive(Deserialize)]
+        struct Version {
+            version: u32,
+        }
+
+        let file_error =
+            |message: String| TodoError::from(vec![FieldError::new(CONTENTS_FIELD, message)]);
+        let Version { version } = serde_json::from_str(contents)
+            .map_err(|e| file_error(format!("this isn't a backup file: {e}")))?;
+        if version != BACKUP_VERSION {
+            return Err(file_error(format!(
+                "backups in version {version} can't be restored, only version {BACKUP_VERSION}"
+            )));
+        }
+        let backup = serde_json::from_str(contents)
+            .map_err(|e| file_error(format!("the backup is damaged: {e}")))?;
+        check_backup(backup)
+    }
+
+    /// Names the fields of `errors` as fields of the entry at `at`.
+    fn nested(at: &str, errors: Vec<FieldError>) -> impl Iterator<Item = FieldError> + '_ {
+        errors
+            .into_iter()
+            .map(move |e| FieldError::new(&format!("{at}.{}", e.field), e.message))
+    }
+
+    /// Parses the optional timestamp `name` of the entry at `at`, adding an error if it's
+    /// malformed.
+    fn check_timestamp(
+        at: &str,
+        name: &str,
+        value: Option<&str>,
+        errors: &mut Vec<FieldError>,
+    ) -> Option<DateTime<Utc>> {
+        match DateTime::parse_from_rfc3339(value?) {
+            Ok(at) => Some(at.with_timezone(&Utc)),
+            Err(_) => {
+                errors.push(FieldError::new(
+                    &format!("{at}.{name}"),
+                    format!("{name} must be a timestamp like 2024-01-31T09:30:00Z"),
+                ));
+                None
+            }
+        }
+    }
+
+    fn check_backup(backup: Backup) -> Result<BackupImport, TodoError> {
+        let mut errors = Vec::new();
+
+        let mut lists = Vec::new();
+        let mut list_ids = HashSet::new();
+        let mut list_names = HashSet::new();
+        for (i, list) in backup.lists.iter().enumerate() {
+            let at = format!("lists[{i}]");
+            if !list_ids.insert(list.id) {
+                errors.push(FieldError::new(
+                    &format!("{at}.id"),
+                    "another list has this id",
+                ));
+            }
+            match validate_list(&list.name, &list.colour) {
+                Ok(valid) if !list_names.insert(valid.name.clone()) => errors.push(
+                    FieldError::new(&format!("{at}.name"), "another list has this name"),
+                ),
+                Ok(valid) => lists.push((list.id, valid)),
+                Err(rejected) => errors.extend(nested(&at, rejected)),
+            }
+        }
+
+        let mut tags = Vec::new();
+        let mut tag_ids = HashSet::new();
+        let mut tag_names = HashSet::new();
+        for (i, tag) in backup.tags.iter().enumerate() {
+            let at = format!("tags[{i}]");
+            if !tag_ids.insert(tag.id) {
+                errors.push(FieldError::new(
+                    &format!("{at}.id"),
+                    "another tag has this id",
+                ));
+            }
+            match validate_tag(&tag.name, &tag.colour) {
+                Ok(valid) if !tag_names.insert(valid.name.clone()) => errors.push(FieldError::new(
+                    &format!("{at}.name"),
+                    "another tag has this name",
+                )),
+                Ok(valid) => tags.push((tag.id, valid)),
+                Err(rejected) => errors.extend(nested(&at, rejected)),
+            }
+        }
+
+        // Which list each item is in, to check sub-todos are in their parent's.
+        let mut todo_lists = HashMap::new();
+        for (i, todo) in backup.todos.iter().enumerate() {
+            if todo_lists.insert(todo.id, todo.list_id).is_some() {
+                errors.push(FieldError::new(
+                    &format!("todos[{i}].id"),
+                    "another todo item has this id",
+                ));
+            }
+        }
+
+        let mut todos = Vec::new();
+        for (i, todo) in backup.todos.into_iter().enumerate() {
+            let at = format!("todos[{i}]");
+            let input = match todo_input(
+                &todo.title,
+                &todo.description,
+                &todo.due_date,
+                todo.priority,
+                todo.tag_ids.clone(),
+                todo.recurrence.as_deref().unwrap_or_default(),
+            ) {
+                Ok(input) => Some(input),
+                Err(TodoError::Validation { errors: rejected }) => {
+                    errors.extend(nested(&at, rejected));
+                    None
+                }
+                Err(e) => return Err(e),
+            };
+
+            if !list_ids.contains(&todo.list_id) {
+                errors.push(FieldError::new(
+                    &format!("{at}.list_id"),
+                    "no list in the backup has this id",
+                ));
+            }
+            if let Some(id) = todo.tag_ids.iter().find(|id| !tag_ids.contains(id)) {
+                errors.push(FieldError::new(
+                    &format!("{at}.tag_ids"),
+                    format!("no tag in the backup has the id {id}"),
+                ));
+            }
+            match todo.parent_id.map(|id| todo_lists.get(&id)) {
+                Some(None) => errors.push(FieldError::new(
+                    &format!("{at}.parent_id"),
+                    "no todo item in the backup has this id",
+                )),
+                Some(Some(list_id)) if *list_id != todo.list_id => errors.push(FieldError::new(
+                    &format!("{at}.list_id"),
+                    "a sub-todo must be in its parent's list",
+                )),
+                _ => {}
+            }
+
+            let completed_at = check_timestamp(
+                &at,
+                "completed_at",
+                todo.completed_at.as_deref().filter(|_| todo.completed),
+                &mut errors,
+            );
+            let deleted_at =
+                check_timestamp(&at, "deleted_at", todo.deleted_at.as_deref(), &mut errors);
+            let created = check_timestamp(&at, "created", todo.created.as_deref(), &mut errors);
+
+            let mut checklist = Vec::new();
+            for (j, item) in todo.checklist.into_iter().enumerate() {
+                match check_checklist_text(&item.text) {
+                    Some(message) => errors.push(FieldError::new(
+                        &format!("{at}.checklist[{j}].text"),
+                        message,
+                    )),
+                    None => checklist.push(BackupChecklistItem {
+                        text: item.text.trim().to_string(),
+                        done: item.done,
+                    }),
+                }
+            }
+
+            if let Some(input) = input {
+                todos.push((
+                    i,
+                    ImportTodo {
+                        id: todo.id,
+                        input,
+                        list_id: todo.list_id,
+                        parent_id: todo.parent_id,
+                        completed: todo.completed,
+                        completed_at,
+                        deleted_at,
+                        created,
+                        checklist,
+                    },
+                ));
+            }
+        }
+        if !errors.is_empty() {
+            return Err(errors.into());
+        }
+
+        // Parents go first, so every sub-todo's parent has its new id by the time the
+        // sub-todo is added. Whatever is left over is part of a cycle.
+        let mut ordered = Vec::with_capacity(todos.len());
+        let mut placed = HashSet::new();
+        loop {
+            let (ready, waiting): (Vec<_>, Vec<_>) = todos.into_iter().partition(|(_, todo)| {
+                todo.parent_id.is_none_or(|parent| placed.contains(&parent))
+            });
+            todos = waiting;
+            if ready.is_empty() {
+                break;
+            }
+            placed.extend(ready.iter().map(|(_, todo)| todo.id));
+            ordered.extend(ready.into_iter().map(|(_, todo)| todo));
+        }
+        if !todos.is_empty() {
+            let errors = todos
+                .into_iter()
+                .map(|(i, _)| {
+                    FieldError::new(
+                        &format!("todos[{i}].parent_id"),
+                        "the item ends up as a sub-todo of itself",
+                    )
+                })
+                .collect::<Vec<_>>();
+            return Err(errors.into());
+        }
+
+        Ok(BackupImport {
+            lists,
+            tags,
+            todos: ordered,
+        })
+    }
+
+    /// Sends a backup of everything the caller has, as a file to save. Like the JSON API,
+    /// it takes HTTP Basic credentials as well as the session.
+    pub async fn download_backup(
+        req: HttpRequest,
+        repository: Data<SharedTodoRepository>,
+    ) -> Result<HttpResponse, ApiError> {
+        let owner = caller(&req, &repository).await?;
+        let backup = backup(repository.export(owner).await?);
+
+        let filename = format!("todos-{}.json", Utc::now().format("%Y-%m-%d"));
+        Ok(HttpResponse::Ok()
+            .insert_header(ContentDisposition {
+                disposition: DispositionType::Attachment,
+                parameters: vec![DispositionParam::Filename(filename)],
+            })
+            .json(backup))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::server::backup::ImportReport;
+        use crate::server::repository::{InMemoryTodoRepository, TodoInput, TodoRepository};
+        use crate::server::todo::Priority;
+        use chrono::NaiveDate;
+
+        fn list(id: i32, name: &str) -> BackupList {
+            BackupList {
+                id,
+                name: name.to_string(),
+                colour: "#3b82f6".to_string(),
+            }
+        }
+
+        fn tag(id: i32, name: &str) -> BackupTag {
+            BackupTag {
+                id,
+                name: name.to_string(),
+                colour: "#ef4444".to_string(),
+            }
+        }
+
+        fn todo(id: i32, list_id: i32, parent_id: Option<i32>) -> BackupTodo {
+            BackupTodo {
+                id,
+                title: format!("item {id}"),
+                description: String::new(),
+                due_date: "2030-01-01".to_string(),
+                priority: Priority::None,
+                completed: false,
+                completed_at: None,
+                deleted_at: None,
+                created: None,
+                list_id,
+                parent_id,
+                recurrence: None,
+                tag_ids: Vec::new(),
+                checklist: Vec::new(),
+            }
+        }
+
+        /// A backup file holding `lists`, `tags` and `todos`.
+        fn file(lists: Vec<BackupList>, tags: Vec<BackupTag>, todos: Vec<BackupTodo>) -> String {
+            serde_json::to_string(&Backup {
+                version: BACKUP_VERSION,
+                exported_at: "2030-01-01T00:00:00Z".to_string(),
+                lists,
+                tags,
+                todos,
+            })
+            .unwrap()
+        }
+
+        /// The fields `contents` is rejected for, with their messages.
+        fn rejected(contents: &str) -> Vec<(String, String)> {
+            match parse_backup(contents) {
+                Err(TodoError::Validation { errors }) => {
+                    errors.into_iter().map(|e| (e.field, e.message)).collect()
+                }
+                other => panic!("expected validation errors, got {other:?}"),
+            }
+        }
+
+        fn fields(rejected: &[(String, String)]) -> Vec<&str> {
+            rejected.iter().map(|(field, _)| field.as_str()).collect()
+        }
+
+        #[test]
+        fn other_versions_are_refused() {
+            let newer = file(vec![], vec![], vec![]).replace(
+                &format!("\"version\":{BACKUP_VERSION}"),
+                &format!("\"version\":{}", BACKUP_VERSION + 1),
+            );
+            let errors = rejected(&newer);
+            assert_eq!(fields(&errors), [CONTENTS_FIELD]);
+            assert!(errors[0]
+                .1
+                .starts_with("backups in version 2 can't be restored"));
+
+            for broken in ["", "[]", "{\"todos\": []}"] {
+                assert_eq!(fields(&rejected(broken)), [CONTENTS_FIELD], "{broken:?}");
+            }
+            assert!(parse_backup(&file(vec![], vec![], vec![])).is_ok());
+        }
+
+        #[test]
+        fn ids_and_names_must_be_unique() {
+            let contents = file(
+                vec![list(1, "Work"), list(1, "Home"), list(2, " Work ")],
+                vec![tag(1, "urgent"), tag(2, "urgent")],
+                vec![todo(1, 1, None), todo(1, 1, None)],
+            );
+            assert_eq!(
+                fields(&rejected(&contents)),
+                [
+                    "lists[1].id",
+                    "lists[2].name",
+                    "tags[1].name",
+                    "todos[1].id"
+                ]
+            );
+        }
+
+        #[test]
+        fn bad_todo_fields_are_named_by_their_path() {
+            let mut bad = todo(2, 1, None);
+            bad.title = String::new();
+            bad.due_date = "2030-02-30".to_string();
+            bad.tag_ids = vec![9];
+            bad.completed = true;
+            bad.completed_at = Some("yesterday".to_string());
+            bad.checklist = vec![BackupChecklistItem {
+                text: " ".to_string(),
+                done: false,
+            }];
+            let contents = file(vec![list(1, "Work")], vec![], vec![todo(1, 1, None), bad]);
+            assert_eq!(
+                fields(&rejected(&contents)),
+                [
+                    "todos[1].title",
+                    "todos[1].due_date",
+                    "todos[1].tag_ids",
+                    "todos[1].completed_at",
+                    "todos[1].checklist[0].text",
+                ]
+            );
+        }
+
+        #[test]
+        fn sub_todos_must_be_in_their_parents_list() {
+            let contents = file(
+                vec![list(1, "Work"), list(2, "Home")],
+                vec![],
+                vec![todo(1, 1, None), todo(2, 2, Some(1)), todo(3, 1, Some(9))],
+            );
+            assert_eq!(
+                rejected(&contents),
+                [
+                    (
+                        "todos[1].list_id".to_string(),
+                        "a sub-todo must be in its parent's list".to_string()
+                    ),
+                    (
+                        "todos[2].parent_id".to_string(),
+                        "no todo item in the backup has this id".to_string()
+                    ),
+                ]
+            );
+        }
+
+        #[test]
+        fn parents_are_added_before_their_sub_todos() {
+            let contents = file(
+                vec![list(1, "Work")],
+                vec![],
+                vec![
+                    todo(4, 1, Some(3)),
+                    todo(3, 1, Some(2)),
+                    todo(1, 1, None),
+                    todo(2, 1, None),
+                ],
+            );
+            let parsed = parse_backup(&contents).unwrap();
+            let ids = parsed.todos.iter().map(|todo| todo.id);
+            assert_eq!(ids.collect::<Vec<_>>(), [1, 2, 3, 4]);
+        }
+
+        #[test]
+        fn parent_cycles_are_rejected() {
+            let contents = file(
+                vec![list(1, "Work")],
+                vec![],
+                vec![
+                    todo(1, 1, Some(2)),
+                    todo(2, 1, Some(1)),
+                    todo(3, 1, None),
+                    todo(4, 1, Some(4)),
+                    todo(5, 1, Some(1)),
+                ],
+            );
+            let errors = rejected(&contents);
+            assert_eq!(
+                fields(&errors),
+                [
+                    "todos[0].parent_id",
+                    "todos[1].parent_id",
+                    "todos[3].parent_id",
+                    "todos[4].parent_id"
+                ]
+            );
+            assert!(errors
+                .iter()
+                .all(|(_, message)| message == "the item ends up as a sub-todo of itself"));
+        }
+
+        fn input(title: &str, tag_ids: Vec<i32>) -> TodoInput {
+            TodoInput {
+                title: title.to_string(),
+                description: String::new(),
+                due_date: NaiveDate::from_ymd_opt(2030, 1, 1).unwrap(),
+                priority: Priority::None,
+                tag_ids,
+                recurrence: None,
+            }
+        }
+
+        fn names<T>(items: &[T], name: impl Fn(&T) -> &str) -> Vec<String> {
+            let mut names = items
+                .iter()
+                .map(|item| name(item).to_string())
+                .collect::<Vec<_>>();
+            names.sort();
+            names
+        }
+
+        #[actix_web::test]
+        async fn backups_are_restored_with_new_ids_into_namesake_lists_and_tags() {
+            let repository = InMemoryTodoRepository::new();
+            let alice = repository
+                .create_user("alice".into(), "hash".into())
+                .await
+                .unwrap();
+            let work = repository
+                .create_list(alice.id, "Work".into(), "#3b82f6".into())
+                .await
+                .unwrap();
+            let home = repository
+                .create_tag(alice.id, "home".into(), "#ef4444".into())
+                .await
+                .unwrap();
+            let urgent = repository
+                .create_tag(alice.id, "urgent".into(), "#f59e0b".into())
+                .await
+                .unwrap();
+            let parent = repository
+                .create(
+                    alice.id,
+                    input("report", vec![urgent.id]),
+                    Some(work.id),
+                    None,
+                )
+                .await
+                .unwrap();
+            let child = repository
+                .create(alice.id, input("charts", vec![]), None, Some(parent.id))
+                .await
+                .unwrap();
+            repository
+                .add_checklist_item(alice.id, child.id, "bar chart".into())
+                .await
+                .unwrap();
+            let chore = repository
+                .create(alice.id, input("laundry", vec![home.id]), None, None)
+                .await
+                .unwrap();
+            repository
+                .complete(alice.id, chore.id, false)
+                .await
+                .unwrap();
+            let old = repository
+                .create(alice.id, input("old plan", vec![]), None, None)
+                .await
+                .unwrap();
+            repository.delete(alice.id, old.id).await.unwrap();
+            let contents =
+                serde_json::to_string(&backup(repository.export(alice.id).await.unwrap())).unwrap();
+
+            // Bob already has a Work list and a home tag.
+            let bob = repository
+                .create_user("bob".into(), "hash".into())
+                .await
+                .unwrap();
+            let bobs_work = repository
+                .create_list(bob.id, "Work".into(), "#10b981".into())
+                .await
+                .unwrap();
+            let bobs_home = repository
+                .create_tag(bob.id, "home".into(), "#10b981".into())
+                .await
+                .unwrap();
+            let before = repository.export(bob.id).await.unwrap();
+
+            let dry_run = repository
+                .import(bob.id, parse_backup(&contents).unwrap(), true)
+                .await
+                .unwrap();
+            let after_dry_run = repository.export(bob.id).await.unwrap();
+            assert_eq!(after_dry_run.lists, before.lists);
+            assert_eq!(after_dry_run.tags, before.tags);
+            assert_eq!(after_dry_run.todos, before.todos);
+
+            let report = repository
+                .import(bob.id, parse_backup(&contents).unwrap(), false)
+                .await
+                .unwrap();
+            assert_eq!(
+                report,
+                ImportReport {
+                    dry_run: false,
+                    ..dry_run.clone()
+                }
+            );
+            assert!(dry_run.dry_run);
+            assert_eq!(
+                (
+                    report.todos,
+                    report.completed,
+                    report.trashed,
+                    report.checklist_items
+                ),
+                (4, 1, 1, 1)
+            );
+            assert_eq!(names(&report.merged_lists, |name| name), ["Inbox", "Work"]);
+            assert!(report.new_lists.is_empty());
+            assert_eq!(
+                (report.merged_tags, report.new_tags),
+                (vec!["home".into()], vec!["urgent".into()])
+            );
+
+            let restored = repository.export(bob.id).await.unwrap();
+            assert_eq!(
+                names(&restored.lists, |list| &list.name),
+                names(&before.lists, |list| &list.name)
+            );
+            assert_eq!(names(&restored.tags, |tag| &tag.name), ["home", "urgent"]);
+            let find = |title: &str| {
+                let found = restored.todos.iter().find(|todo| todo.title == title);
+                found
+                    .unwrap_or_else(|| panic!("{title} was restored"))
+                    .clone()
+            };
+            let (report_todo, charts, laundry) = (find("report"), find("charts"), find("laundry"));
+            for (restored, original) in [
+                (&report_todo, parent.id),
+                (&charts, child.id),
+                (&laundry, chore.id),
+            ] {
+                assert_ne!(restored.id, original);
+            }
+            assert_eq!(report_todo.list_id, bobs_work.id);
+            assert_eq!(charts.list_id, bobs_work.id);
+            assert_eq!(charts.parent_id, Some(report_todo.id));
+            assert_eq!(charts.checklist[0].text, "bar chart");
+            let bobs_inbox = before.lists.iter().find(|list| list.name == "Inbox");
+            assert_eq!(Some(laundry.list_id), bobs_inbox.map(|list| list.id));
+            assert_eq!(laundry.tags[0].id, bobs_home.id);
+            assert!(laundry.completed);
+            assert_eq!(report_todo.tags[0].name, "urgent");
+            assert_ne!(report_todo.tags[0].id, urgent.id);
+            assert!(find("old plan").deleted_at.is_some());
+
+            // Alice's items are untouched.
+            assert_eq!(repository.export(alice.id).await.unwrap().todos.len(), 4);
+        }
+    }
+}
+
+/// Every list, tag and todo item of the logged-in user.
+#[server]
+pub async fn export_backup() -> Result<Backup, ServerFnError<TodoError>> {
+    use crate::server::auth::ssr::{authorize, Authorized};
+
+    let Authorized { owner, repository } = authorize([]).await?;
+    Ok(self::ssr::backup(repository.export(owner).await?))
+}
+
+/// Restores a backup read from a file, adding everything in it with new ids. Lists and
+/// tags are merged into the user's own ones with the same name. Either everything is
+/// added or, if anything fails, nothing is. With `dry_run`, nothing is kept either way
+/// and the report says what would have been added.
+#[server]
+pub async fn import_backup(
+    contents: String,
+    #[server(default)] dry_run: bool,
+) -> Result<ImportReport, ServerFnError<TodoError>> {
+    use crate::server::auth::ssr::{authorize, Authorized};
+
+    // Checks the caller before parsing the upload.
+    let Authorized { owner, repository } = authorize([]).await?;
+    let backup = self::ssr::parse_backup(&contents)?;
+    Ok(repository.import(owner, backup, dry_run).await?)
+}
@@ -0,0 +1,1035 @@
+//! Todo items as iCalendar: a feed of each user's items for calendar apps to subscribe
+//! to, and VTODOs imported from `.ics` files. The feed URL holds a secret rather than
+//! asking for a login, since calendar apps can't log in.
+
+use crate::server::error::TodoError;
+use crate::validation::todo::FieldError;
+use leptos::{server, ServerFnError};
+use serde::{Deserialize, Serialize};
+
+/// Where calendar feeds live; each is at `{CALENDAR_PATH}/{token}.ics`.
+pub const CALENDAR_PATH: &str = "/api/calendar";
+
+/// What importing an iCalendar file did.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct IcsImportReport {
+    /// How many todo items were added.
+    pub imported: u32,
+    /// The titles of the VTODOs skipped because their UID was imported before.
+    pub duplicates: Vec<String>,
+    /// What was left out, by name: properties and components nested in VTODOs, counted
+    /// once per VTODO, and the other components of the file, such as VEVENT.
+    pub dropped: Vec<DroppedProperty>,
+    /// The VTODOs that weren't added, in file order.
+    pub rejected: Vec<RejectedTodo>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DroppedProperty {
+    pub name: String,
+    pub count: u32,
+}
+
+/// A VTODO that couldn't be imported, and why.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RejectedTodo {
+    /// The line of the file its `BEGIN:VTODO` is on, counting from 1.
+    pub line: u64,
+    /// Named by the todo item field the problem is with.
+    pub errors: Vec<FieldError>,
+}
+
+#[cfg(feature = "ssr")]
+pub mod ssr {
+    use super::{DroppedProperty, RejectedTodo, CALENDAR_PATH};
+    use crate::server::api::ApiError;
+    use crate::server::error::TodoError;
+    use crate::server::repository::{CalendarTodo, SharedTodoRepository};
+    use crate::server::todo::ssr::todo_input;
+    use crate::server::todo::{Priority, Todo};
+    use crate::validation::recurrence::parse_recurrence;
+    use crate::validation::todo::FieldError;
+    use actix_web::http::header::{
+        CacheControl, CacheDirective, EntityTag, Header, IfNoneMatch, ETAG,
+    };
+    use actix_web::web::{Data, Path};
+    use actix_web::{HttpRequest, HttpResponse};
+    use argon2::password_hash::rand_core::{OsRng, RngCore};
+    use base64::prelude::{Engine, BASE64_URL_SAFE_NO_PAD};
+    use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, Utc};
+    use std::collections::{BTreeMap, BTreeSet};
+
+    /// How long calendar apps may keep a feed before asking again, in seconds.
+    const MAX_AGE: u32 = 15 * 60;
+
+    /// The longest a line of the feed may be, in bytes, before it's folded.
+    const LINE_LIMIT: usize = 75;
+
+    /// A fresh secret for a calendar feed URL.
+    pub fn new_token() -> String {
+        let mut bytes = [0u8; 32];
+        OsRng.fill_bytes(&mut bytes);
+        BASE64_URL_SAFE_NO_PAD.encode(bytes)
+    }
+
+    /// The full URL of the feed with `token`, on the host the request came in on.
+    pub async fn feed_url(token: &str) -> Result<String, TodoError> {
+        let req = leptos_actix::extract::<HttpRequest>().await.map_err(|e| {
+            log::error!("failed to read the request: {e}");
+            TodoError::Internal
+        })?;
+        let info = req.connection_info();
+        Ok(format!(
+            "{}://{}{CALENDAR_PATH}/{token}.ics",
+            info.scheme(),
+            info.host()
+        ))
+    }
+
+    /// The UID of the VTODO for the item `id`, which stays the same across feeds.
+    pub fn uid(id: i32) -> String {
+        format!("todo-{id}@leptos-todo")
+    }
+
+    /// Escapes `text` as an iCalendar TEXT value.
+    fn escape(text: &str) -> String {
+        let mut escaped = String::with_capacity(text.len());
+        for c in text.chars() {
+            match c {
+                '\\' | ';' | ',' => {
+                    escaped.push('\\');
+                    escaped.push(c);
+                }
+                '\n' => escaped.push_str("\\n"),
+                '\r' => {}
+                c => escaped.push(c),
+            }
+        }
+        escaped
+    }
+
+    /// Writes `line` to `out`, folded into lines of at most [`LINE_LIMIT`] bytes without
+    /// splitting a character, each ending in CRLF.
+    fn push_line(out: &mut String, line: &str) {
+        let mut limit = LINE_LIMIT;
+        let mut start = 0;
+        for (i, c) in line.char_indices() {
+            if i + c.len_utf8() - start > limit {
+                out.push_str(&line[start..i]);
+                out.push_str("\r\n ");
+                start = i;
+                // The space starting a continuation line counts towards its length.
+                limit = LINE_LIMIT - 1;
+            }
+        }
+        out.push_str(&line[start..]);
+        out.push_str("\r\n");
+    }
+
+    /// An RFC 3339 timestamp of a todo item as an iCalendar UTC DATE-TIME.
+    fn date_time(timestamp: &str) -> Option<String> {
+        let at = DateTime::parse_from_rfc3339(timestamp).ok()?;
+        Some(at.with_timezone(&Utc).format("%Y%m%dT%H%M%SZ").to_string())
+    }
+
+    /// The iCalendar PRIORITY of `priority`, where 1 is the most urgent and 9 the least.
+    /// `None` for items without a priority.
+    pub fn ical_priority(priority: Priority) -> Option<u8> {
+        match priority {
+            Priority::None => None,
+            Priority::Low => Some(7),
+            Priority::Medium => Some(5),
+            Priority::High => Some(3),
+            Priority::Urgent => Some(1),
+        }
+    }
+
+    fn push_todo(out: &mut String, todo: &Todo, stamp: &str) {
+        let Ok(due) = NaiveDate::parse_from_str(&todo.due_date, "%Y-%m-%d") else {
+            return;
+        };
+
+        push_line(out, "BEGIN:VTODO");
+        push_line(out, &format!("UID:{}", uid(todo.id)));
+        push_line(out, &format!("DTSTAMP:{stamp}"));
+        if let Some(created) = todo.created.as_deref().and_then(date_time) {
+            push_line(out, &format!("CREATED:{created}"));
+        }
+        push_line(out, &format!("SUMMARY:{}", escape(&todo.title)));
+        if !todo.description.is_empty() {
+            push_line(out, &format!("DESCRIPTION:{}", escape(&todo.description)));
+        }
+        push_line(out, &format!("DUE;VALUE=DATE:{}", due.format("%Y%m%d")));
+        if let Some(priority) = ical_priority(todo.priority) {
+            push_line(out, &format!("PRIORITY:{priority}"));
+        }
+        if todo.completed {
+            push_line(out, "STATUS:COMPLETED");
+            push_line(out, "PERCENT-COMPLETE:100");
+            if let Some(completed) = todo.completed_at.as_deref().and_then(date_time) {
+                push_line(out, &format!("COMPLETED:{completed}"));
+            }
+        } else {
+            push_line(out, "STATUS:NEEDS-ACTION");
+        }
+        if let Some(parent_id) = todo.parent_id {
+            push_line(out, &format!("RELATED-TO:{}", uid(parent_id)));
+        }
+        push_line(out, "END:VTODO");
+    }
+
+    /// A VCALENDAR with an all-day VTODO due on the due date of each of `todos`, stamped
+    /// as made at `now`.
+    pub fn calendar(name: &str, todos: &[Todo], now: DateTime<Utc>) -> String {
+        let stamp = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let mut out = String::new();
+        push_line(&mut out, "BEGIN:VCALENDAR");
+        push_line(&mut out, "VERSION:2.0");
+        push_line(&mut out, "PRODID:-//leptos-todo//Todos//EN");
+        push_line(&mut out, "CALSCALE:GREGORIAN");
+        push_line(&mut out, &format!("X-WR-CALNAME:{}", escape(name)));
+        // Hints for how often to check for changes, in the standard and the common form.
+        let refresh = format!("PT{}M", MAX_AGE / 60);
+        push_line(
+            &mut out,
+            &format!("REFRESH-INTERVAL;VALUE=DURATION:{refresh}"),
+        );
+        push_line(&mut out, &format!("X-PUBLISHED-TTL:{refresh}"));
+        for todo in todos {
+            push_todo(&mut out, todo, &stamp);
+        }
+        push_line(&mut out, "END:VCALENDAR");
+        out
+    }
+
+    /// The ETag of a feed: a 64-bit FNV-1a hash of `body` without its DTSTAMPs, so it
+    /// stays the same until the items change.
+    fn etag(body: &str) -> EntityTag {
+        let hash = body
+            .split_inclusive("\r\n")
+            .filter(|line| !line.starts_with("DTSTAMP:"))
+            .flat_map(str::bytes)
+            .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+                (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+            });
+        EntityTag::new_strong(format!("{hash:016x}"))
+    }
+
+    /// Sends the calendar feed with the secret in the path, holding the owner's todo items
+    /// outside the trash. Unchanged feeds are answered with `304 Not Modified`.
+    pub async fn calendar_feed(
+        req: HttpRequest,
+        repository: Data<SharedTodoRepository>,
+        file: Path<String>,
+    ) -> Result<HttpResponse, ApiError> {
+        let token = file.strip_suffix(".ics").unwrap_or(&file);
+        let Some(owner) = repository.calendar_owner(token).await? else {
+            return Err(ApiError::unknown_endpoint());
+        };
+        let username = repository
+            .get_user(owner)
+            .await?
+            .map(|user| user.username)
+            .unwrap_or_default();
+        let mut todos = repository.export(owner).await?.todos;
+        todos.retain(|todo| todo.deleted_at.is_none());
+        let body = calendar(&format!("Todos of {username}"), &todos, Utc::now());
+
+        let etag = etag(&body);
+        let cache_control = CacheControl(vec![
+            CacheDirective::Private,
+            CacheDirective::MaxAge(MAX_AGE),
+        ]);
+        let unchanged = match IfNoneMatch::parse(&req) {
+            Ok(IfNoneMatch::Any) => true,
+            Ok(IfNoneMatch::Items(tags)) => tags.iter().any(|tag| tag.weak_eq(&etag)),
+            Err(_) => false,
+        };
+        if unchanged {
+            return Ok(HttpResponse::NotModified()
+                .insert_header(cache_control)
+                .insert_header((ETAG, etag.to_string()))
+                .finish());
+        }
+        Ok(HttpResponse::Ok()
+            .content_type("text/calendar; charset=utf-8")
+            .insert_header(cache_control)
+            .insert_header((ETAG, etag.to_string()))
+            .body(body))
+    }
+
+    /// The field that problems with the file as a whole are reported on.
+    const CONTENTS_FIELD: &str = "contents";
+
+    /// VTODO properties that only describe the VTODO itself, so leaving them out loses
+    /// nothing worth reporting.
+    const BOOKKEEPING: [&str; 5] = [
+        "DTSTAMP",
+        "CREATED",
+        "LAST-MODIFIED",
+        "SEQUENCE",
+        "PERCENT-COMPLETE",
+    ];
+
+    /// A content line, unfolded.
+    struct Property {
+        /// Upper case.
+        name: String,
+        value: String,
+    }
+
+    /// A VTODO, with the names of the components nested in it.
+    struct Component {
+        line: u64,
+        properties: Vec<Property>,
+        nested: Vec<String>,
+    }
+
+    /// The VTODOs of an iCalendar file, ready to be added.
+    pub struct ParsedCalendar {
+        pub todos: Vec<CalendarTodo>,
+        pub rejected: Vec<RejectedTodo>,
+        pub dropped: Vec<DroppedProperty>,
+    }
+
+    fn malformed(message: impl Into<String>) -> TodoError {
+        vec![FieldError::new(CONTENTS_FIELD, message)].into()
+    }
+
+    /// Joins folded lines back together, dropping empty ones.
+    fn unfold(contents: &str) -> Vec<(u64, String)> {
+        let mut lines: Vec<(u64, String)> = Vec::new();
+        for (i, line) in contents.lines().enumerate() {
+            match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+                (Some(rest), Some((_, previous))) => previous.push_str(rest),
+                _ if line.trim().is_empty() => {}
+                _ => lines.push((i as u64 + 1, line.to_string())),
+            }
+        }
+        lines
+    }
+
+    /// Splits a content line into its name and value, leaving out the parameters in
+    /// between. `None` if it has no value.
+    fn property(text: &str) -> Option<Property> {
+        let mut quoted = false;
+        let mut name_end = None;
+        for (i, c) in text.char_indices() {
+            match c {
+                '"' => quoted = !quoted,
+                ';' if !quoted => name_end = name_end.or(Some(i)),
+                ':' if !quoted => {
+                    let name = &text[..name_end.unwrap_or(i)];
+                    return Some(Property {
+                        name: name.trim().to_uppercase(),
+                        value: text[i + 1..].to_string(),
+                    });
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+
+    /// The VTODOs of `contents`, and how many of each other top-level component there are.
+    fn components(contents: &str) -> Result<(Vec<Component>, BTreeMap<String, u32>), TodoError> {
+        let mut todos = Vec::new();
+        let mut others = BTreeMap::new();
+        let mut open: Vec<String> = Vec::new();
+        let mut seen_calendar = false;
+        for (line, text) in unfold(contents) {
+            let Some(property) = property(&text) else {
+                return Err(malformed(format!(
+                    "line {line} isn't an iCalendar property"
+                )));
+            };
+            match property.name.as_str() {
+                "BEGIN" => {
+                    let name = property.value.trim().to_uppercase();
+                    match open.len() {
+                        0 if name != "VCALENDAR" => {
+                            return Err(malformed("the file isn't an iCalendar file"));
+                        }
+                        0 => seen_calendar = true,
+                        1 if name == "VTODO" => todos.push(Component {
+                            line,
+                            properties: Vec::new(),
+                            nested: Vec::new(),
+                        }),
+                        // Time zones only support the components that use them.
+                        1 if name == "VTIMEZONE" => {}
+                        1 => *others.entry(name.clone()).or_default() += 1,
+                        _ if open[1] == "VTODO" => {
+                            let todo = todos.last_mut().expect("an open VTODO was pushed");
+                            if !todo.nested.contains(&name) {
+                                todo.nested.push(name.clone());
+                            }
+                        }
+                        _ => {}
+                    }
+                    open.push(name);
+                }
+                "END" => {
+                    let name = property.value.trim().to_uppercase();
+                    let message = match open.pop() {
+                        Some(begun) if begun == name => continue,
+                        Some(begun) => format!("line {line} ends the {name} before its {begun}"),
+                        None => format!("line {line} ends a {name} that wasn't begun"),
+                    };
+                    return Err(malformed(message));
+                }
+                _ if open.len() == 2 && open[1] == "VTODO" => {
+                    let todo = todos.last_mut().expect("an open VTODO was pushed");
+                    todo.properties.push(property);
+                }
+                _ if open.is_empty() => {
+                    return Err(malformed("the file isn't an iCalendar file"));
+                }
+                _ => {}
+            }
+        }
+        if !seen_calendar {
+            return Err(malformed("the file isn't an iCalendar file"));
+        }
+        if let Some(name) = open.last() {
+            return Err(malformed(format!(
+                "the file ends before the end of its {name}"
+            )));
+        }
+        Ok((todos, others))
+    }
+
+    /// Undoes the escaping of an iCalendar TEXT value.
+    fn unescape(value: &str) -> String {
+        let mut text = String::with_capacity(value.len());
+        let mut chars = value.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                text.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('n' | 'N') => text.push('\n'),
+                Some(escaped) => text.push(escaped),
+                None => {}
+            }
+        }
+        text
+    }
+
+    /// The day a DATE or DATE-TIME value falls on, as written, ignoring any time zone.
+    fn date(value: &str) -> Option<NaiveDate> {
+        NaiveDate::parse_from_str(value.trim().get(..8)?, "%Y%m%d").ok()
+    }
+
+    /// A UTC DATE-TIME value.
+    fn utc_date_time(value: &str) -> Option<DateTime<Utc>> {
+        NaiveDateTime::parse_from_str(value.trim(), "%Y%m%dT%H%M%SZ")
+            .ok()
+            .map(|at| at.and_utc())
+    }
+
+    /// The priority of an iCalendar PRIORITY, where 1 is the most urgent, 9 the least and
+    /// 0 means none. `None` if it isn't one.
+    fn priority(value: &str) -> Option<Priority> {
+        match value.trim().parse::<u8>().ok()? {
+            0 => Some(Priority::None),
+            1 => Some(Priority::Urgent),
+            2..=4 => Some(Priority::High),
+            5 => Some(Priority::Medium),
+            6..=9 => Some(Priority::Low),
+            _ => None,
+        }
+    }
+
+    /// `rrule` as a repeat rule of a todo item due on `due`, if it's one they support. A
+    /// weekly or monthly rule without its days repeats on the day of `due`, as in
+    /// iCalendar.
+    fn recurrence(rrule: &str, due: Option<NaiveDate>) -> Option<String> {
+        let mut parts = rrule
+            .trim()
+            .split(';')
+            .filter(|part| !part.is_empty())
+            .map(|part| part.split_once('='))
+            .collect::<Option<Vec<_>>>()?;
+        parts.retain(|part| *part != ("INTERVAL", "1") && part.0 != "WKST");
+        let missing = |freq: &str, by: &str| {
+            parts.contains(&("FREQ", freq)) && !parts.iter().any(|(name, _)| *name == by)
+        };
+        let weekday = if missing("WEEKLY", "BYDAY") {
+            Some(due?.weekday().to_string()[..2].to_uppercase())
+        } else {
+            None
+        };
+        let day = if missing("MONTHLY", "BYMONTHDAY") {
+            Some(due?.day().to_string())
+        } else {
+            None
+        };
+        parts.extend(weekday.as_deref().map(|weekday| ("BYDAY", weekday)));
+        parts.extend(day.as_deref().map(|day| ("BYMONTHDAY", day)));
+        let rule = parts
+            .iter()
+            .map(|(name, value)| format!("{name}={value}"))
+            .collect::<Vec<_>>()
+            .join(";");
+        parse_recurrence(&rule).ok().flatten().map(|_| rule)
+    }
+
+    /// Reads a VTODO the way `add_todo` reads its form. Adds the properties and nested
+    /// components it leaves out to `dropped`.
+    fn todo(
+        component: &Component,
+        dropped: &mut BTreeSet<String>,
+    ) -> Result<CalendarTodo, Vec<FieldError>> {
+        let value = |name: &str| {
+            component
+                .properties
+                .iter()
+                .find(|property| property.name == name)
+                .map(|property| property.value.as_str())
+        };
+        let due = value("DUE").and_then(date);
+
+        let mut priority_level = Priority::None;
+        let mut rule = String::new();
+        let mut completed_at = None;
+        let mut errors = Vec::new();
+        for property in &component.properties {
+            match property.name.as_str() {
+                "SUMMARY" | "DESCRIPTION" | "DUE" | "UID" | "COMPLETED" => {}
+                "PRIORITY" => match priority(&property.value) {
+                    Some(priority) => priority_level = priority,
+                    None => {
+                        dropped.insert(property.name.clone());
+                    }
+                },
+                "RRULE" => match recurrence(&property.value, due) {
+                    Some(recurrence) => rule = recurrence,
+                    None => {
+                        dropped.insert(property.name.clone());
+                    }
+                },
+                "STATUS" => match property.value.trim().to_uppercase().as_str() {
+                    "COMPLETED" => {
+                        completed_at = Some(
+                            value("COMPLETED")
+                                .and_then(utc_date_time)
+                                .unwrap_or_else(Utc::now),
+                        );
+                    }
+                    "CANCELLED" => {
+                        let message = "cancelled items aren't imported";
+                        errors.push(FieldError::new("status", message));
+                    }
+                    _ => {}
+                },
+                name if BOOKKEEPING.contains(&name) => {}
+                name => {
+                    dropped.insert(name.to_string());
+                }
+            }
+        }
+        dropped.extend(component.nested.iter().cloned());
+
+        let due_date = match (value("DUE"), due) {
+            (_, Some(due)) => due.to_string(),
+            (Some(raw), None) => raw.to_string(),
+            (None, None) => String::new(),
+        };
+        let input = todo_input(
+            &unescape(value("SUMMARY").unwrap_or_default()),
+            &unescape(value("DESCRIPTION").unwrap_or_default()),
+            &due_date,
+            priority_level,
+            Vec::new(),
+            &rule,
+        );
+        match input {
+            Ok(input) if errors.is_empty() => Ok(CalendarTodo {
+                uid: value("UID")
+                    .map(str::trim)
+                    .filter(|uid| !uid.is_empty())
+                    .map(str::to_string),
+                input,
+                completed_at,
+            }),
+            Ok(_) => Err(errors),
+            Err(e) => {
+                if let TodoError::Validation { errors: invalid } = e {
+                    errors.splice(0..0, invalid);
+                }
+                Err(errors)
+            }
+        }
+    }
+
+    /// Reads the VTODOs of an iCalendar file. Those that can't become todo items are
+    /// reported rather than failing the whole file.
+    pub fn parse_calendar(contents: &str) -> Result<ParsedCalendar, TodoError> {
+        let (components, others) = components(contents)?;
+
+        let mut todos = Vec::new();
+        let mut rejected = Vec::new();
+        let mut dropped = BTreeMap::<String, u32>::new();
+        for component in &components {
+            // Changes to one occurrence of a repeating task, which todo items can't have.
+            if let Some(property) = component
+                .properties
+                .iter()
+                .find(|property| property.name == "RECURRENCE-ID")
+            {
+                *dropped.entry(property.name.clone()).or_default() += 1;
+                continue;
+            }
+            let mut left_out = BTreeSet::new();
+            match todo(component, &mut left_out) {
+                Ok(todo) => todos.push(todo),
+                Err(errors) => rejected.push(RejectedTodo {
+                    line: component.line,
+                    errors,
+                }),
+            }
+            for name in left_out {
+                *dropped.entry(name).or_default() += 1;
+            }
+        }
+        dropped.extend(others);
+
+        Ok(ParsedCalendar {
+            todos,
+            rejected,
+            dropped: dropped
+                .into_iter()
+                .map(|(name, count)| DroppedProperty { name, count })
+                .collect(),
+        })
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::server::recurrence::Recurrence;
+        use chrono::Weekday;
+
+        /// `line` as [`push_line`] writes it, split into its physical lines.
+        fn folded(line: &str) -> Vec<String> {
+            let mut out = String::new();
+            push_line(&mut out, line);
+            let body = out.strip_suffix("\r\n").expect("lines end in CRLF");
+            body.split("\r\n").map(str::to_string).collect()
+        }
+
+        fn todo(id: i32, title: &str) -> Todo {
+            Todo {
+                id,
+                title: title.to_string(),
+                description: String::new(),
+                completed: false,
+                completed_at: None,
+                deleted_at: None,
+                created: Some("2030-01-01T08:30:00+00:00".to_string()),
+                due_date: "2030-01-02".to_string(),
+                priority: Priority::None,
+                tags: Vec::new(),
+                checklist: Vec::new(),
+                list_id: 1,
+                parent_id: None,
+                recurrence: None,
+                children: Vec::new(),
+            }
+        }
+
+        fn at(timestamp: &str) -> DateTime<Utc> {
+            timestamp.parse().unwrap()
+        }
+
+        #[test]
+        fn text_values_are_escaped() {
+            assert_eq!(escape("buy milk"), "buy milk");
+            assert_eq!(
+                escape("milk, eggs; bread\\butter"),
+                "milk\\, eggs\\; bread\\\\butter"
+            );
+            assert_eq!(escape("one\r\ntwo\nthree"), "one\\ntwo\\nthree");
+        }
+
+        #[test]
+        fn short_lines_are_not_folded() {
+            assert_eq!(folded("SUMMARY:buy milk"), ["SUMMARY:buy milk"]);
+            let full = format!("SUMMARY:{}", "a".repeat(LINE_LIMIT - 8));
+            assert_eq!(folded(&full), [full]);
+        }
+
+        #[test]
+        fn long_lines_are_folded_at_75_octets() {
+            let line = format!("DESCRIPTION:{}", "a".repeat(200));
+            let lines = folded(&line);
+
+            assert_eq!(lines.len(), 3);
+            assert_eq!(lines[0].len(), LINE_LIMIT);
+            assert_eq!(lines[1].len(), LINE_LIMIT);
+            assert!(lines[1..].iter().all(|line| line.starts_with(' ')));
+            let unfolded = lines.concat().replace(" a", "a");
+            assert_eq!(unfolded, line);
+        }
+
+        #[test]
+        fn folding_does_not_split_characters() {
+            for c in ['é', '€', '🦀'] {
+                let line = format!("SUMMARY:{}", c.to_string().repeat(60));
+                let lines = folded(&line);
+
+                assert!(lines.len() > 1, "{c} lines are folded");
+                for (i, physical) in lines.iter().enumerate() {
+                    assert!(physical.len() <= LINE_LIMIT, "line {i} of {c} is too long");
+                    // A whole number of characters, and as many as fit.
+                    let text = if i == 0 {
+                        &physical["SUMMARY:".len()..]
+                    } else {
+                        &physical[1..]
+                    };
+                    assert!(text.chars().all(|text_c| text_c == c));
+                    if i + 1 < lines.len() {
+                        assert!(physical.len() + c.len_utf8() > LINE_LIMIT);
+                    }
+                }
+                let unfolded: String = lines
+                    .iter()
+                    .enumerate()
+                    .map(|(i, line)| if i == 0 { line.as_str() } else { &line[1..] })
+                    .collect();
+                assert_eq!(unfolded, line);
+            }
+        }
+
+        #[test]
+        fn items_are_stamped_with_the_feed_time() {
+            let body = calendar("Todos", &[todo(7, "buy milk")], at("2031-05-06T07:08:09Z"));
+
+            assert!(body.contains("\r\nUID:todo-7@leptos-todo\r\n"));
+            assert!(body.contains("\r\nDTSTAMP:20310506T070809Z\r\n"));
+            assert!(body.contains("\r\nCREATED:20300101T083000Z\r\n"));
+            assert!(body.contains("\r\nDUE;VALUE=DATE:20300102\r\n"));
+        }
+
+        #[test]
+        fn the_etag_changes_with_the_items_only() {
+            let todos = [todo(7, "buy milk")];
+            let earlier = calendar("Todos", &todos, at("2031-05-06T07:08:09Z"));
+            let later = calendar("Todos", &todos, at("2032-01-01T00:00:00Z"));
+            assert_ne!(earlier, later);
+            assert_eq!(etag(&earlier), etag(&later));
+
+            let renamed = calendar(
+                "Todos",
+                &[todo(7, "buy oat milk")],
+                at("2031-05-06T07:08:09Z"),
+            );
+            assert_ne!(etag(&earlier), etag(&renamed));
+        }
+
+        /// A VCALENDAR holding `lines`, with CRLF line endings.
+        fn ics(lines: &[&str]) -> String {
+            let mut contents = "BEGIN:VCALENDAR\r\nVERSION:2.0\r\n".to_string();
+            for line in lines {
+                contents.push_str(line);
+                contents.push_str("\r\n");
+            }
+            contents.push_str("END:VCALENDAR\r\n");
+            contents
+        }
+
+        fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+            NaiveDate::from_ymd_opt(year, month, day).unwrap()
+        }
+
+        fn dropped(parsed: &ParsedCalendar) -> Vec<(&str, u32)> {
+            let dropped = parsed.dropped.iter();
+            dropped.map(|d| (d.name.as_str(), d.count)).collect()
+        }
+
+        #[test]
+        fn folded_lines_are_joined() {
+            let contents = "SUMMARY:buy\r\n  milk\r\n\tand eggs\r\n\r\nDUE:20300102\nUID:1\n";
+            assert_eq!(
+                unfold(contents),
+                [
+                    (1, "SUMMARY:buy milkand eggs".to_string()),
+                    (5, "DUE:20300102".to_string()),
+                    (6, "UID:1".to_string()),
+                ]
+            );
+        }
+
+        #[test]
+        fn properties_are_split_around_their_parameters() {
+            let due = property("due;VALUE=DATE:20300102").unwrap();
+            assert_eq!((due.name.as_str(), due.value.as_str()), ("DUE", "20300102"));
+            let quoted = property("DESCRIPTION;ALTREP=\"cid:a;b\":see: here").unwrap();
+            assert_eq!(
+                (quoted.name.as_str(), quoted.value.as_str()),
+                ("DESCRIPTION", "see: here")
+            );
+            assert!(property("SUMMARY").is_none());
+        }
+
+        #[test]
+        fn text_values_are_unescaped() {
+            assert_eq!(
+                unescape("milk\\, eggs\\; bread\\nbutter\\Nand \\\\jam"),
+                "milk, eggs; bread\nbutter\nand \\jam"
+            );
+            let text = "a, b; c\\d\ne";
+            assert_eq!(unescape(&escape(text)), text);
+        }
+
+        #[test]
+        fn due_dates_are_read_as_written() {
+            let parsed = parse_calendar(&ics(&[
+                "BEGIN:VTODO",
+                "SUMMARY:all day",
+                "DUE;VALUE=DATE:20300102",
+                "END:VTODO",
+                "BEGIN:VTODO",
+                "SUMMARY:in Berlin",
+                "DUE;TZID=Europe/Berlin:20300103T233000",
+                "END:VTODO",
+                "BEGIN:VTODO",
+                "SUMMARY:in UTC",
+                "DUE:20300104T090000Z",
+                "END:VTODO",
+            ]))
+            .unwrap();
+
+            let dues = parsed.todos.iter().map(|todo| todo.input.due_date);
+            assert_eq!(
+                dues.collect::<Vec<_>>(),
+                [date(2030, 1, 2), date(2030, 1, 3), date(2030, 1, 4)]
+            );
+            assert!(parsed.rejected.is_empty());
+            assert!(parsed.dropped.is_empty());
+        }
+
+        #[test]
+        fn rules_todo_items_support_are_kept() {
+            let wednesday = Some(date(2030, 1, 2));
+            for (rrule, rule) in [
+                ("FREQ=DAILY", "FREQ=DAILY"),
+                ("FREQ=DAILY;INTERVAL=1", "FREQ=DAILY"),
+                ("FREQ=WEEKLY", "FREQ=WEEKLY;BYDAY=WE"),
+                ("FREQ=WEEKLY;WKST=SU;BYDAY=MO,FR", "FREQ=WEEKLY;BYDAY=MO,FR"),
+                ("FREQ=MONTHLY", "FREQ=MONTHLY;BYMONTHDAY=2"),
+                ("FREQ=MONTHLY;BYMONTHDAY=15", "FREQ=MONTHLY;BYMONTHDAY=15"),
+            ] {
+                assert_eq!(recurrence(rrule, wednesday).as_deref(), Some(rule));
+            }
+            for rrule in [
+                "FREQ=DAILY;INTERVAL=2",
+                "FREQ=WEEKLY;COUNT=5",
+                "FREQ=MONTHLY;BYDAY=1MO",
+                "FREQ=YEARLY",
+                "FREQ",
+            ] {
+                assert_eq!(recurrence(rrule, wednesday), None, "{rrule}");
+            }
+            // Without a due date there's no day to repeat on.
+            assert_eq!(recurrence("FREQ=WEEKLY", None), None);
+        }
+
+        #[test]
+        fn other_rules_are_reported_as_dropped() {
+            let parsed = parse_calendar(&ics(&[
+                "BEGIN:VTODO",
+                "SUMMARY:water plants",
+                "DUE;VALUE=DATE:20300102",
+                "RRULE:FREQ=WEEKLY",
+                "END:VTODO",
+                "BEGIN:VTODO",
+                "SUMMARY:every other day",
+                "DUE;VALUE=DATE:20300102",
+                "RRULE:FREQ=DAILY;INTERVAL=2",
+                "END:VTODO",
+                "BEGIN:VTODO",
+                "SUMMARY:five times",
+                "DUE;VALUE=DATE:20300102",
+                "RRULE:FREQ=DAILY;COUNT=5",
+                "END:VTODO",
+            ]))
+            .unwrap();
+
+            let rules = parsed
+                .todos
+                .iter()
+                .map(|todo| todo.input.recurrence.clone());
+            assert_eq!(
+                rules.collect::<Vec<_>>(),
+                [Some(Recurrence::Weekly(vec![Weekday::Wed])), None, None]
+            );
+            assert_eq!(dropped(&parsed), [("RRULE", 2)]);
+        }
+
+        #[test]
+        fn only_vtodos_are_imported() {
+            let parsed = parse_calendar(&ics(&[
+                "BEGIN:VEVENT",
+                "SUMMARY:meeting",
+                "DTSTART:20300102T090000Z",
+                "BEGIN:VALARM",
+                "ACTION:DISPLAY",
+                "END:VALARM",
+                "END:VEVENT",
+                "BEGIN:VTODO",
+                "UID:report@example.com",
+                "DTSTAMP:20300101T000000Z",
+                "SUMMARY:write report",
+                "DESCRIPTION:before the meeting\\, please",
+                "DUE;VALUE=DATE:20300102",
+                "PRIORITY:1",
+                "STATUS:COMPLETED",
+                "COMPLETED:20300101T120000Z",
+                "CATEGORIES:work",
+                "BEGIN:VALARM",
+                "ACTION:DISPLAY",
+                "END:VALARM",
+                "END:VTODO",
+                "BEGIN:VEVENT",
+                "SUMMARY:lunch",
+                "END:VEVENT",
+            ]))
+            .unwrap();
+
+            assert_eq!(parsed.todos.len(), 1);
+            let todo = &parsed.todos[0];
+            assert_eq!(todo.uid.as_deref(), Some("report@example.com"));
+            assert_eq!(todo.input.title, "write report");
+            assert_eq!(todo.input.description, "before the meeting, please");
+            assert_eq!(todo.input.priority, Priority::Urgent);
+            assert_eq!(todo.completed_at, Some(at("2030-01-01T12:00:00Z")));
+            // The VTODO's own VALARM and CATEGORIES, and the VEVENTs with their VALARM.
+            assert_eq!(
+                dropped(&parsed),
+                [("CATEGORIES", 1), ("VALARM", 1), ("VEVENT", 2)]
+            );
+        }
+
+        #[test]
+        fn invalid_vtodos_are_rejected_with_their_line() {
+            let parsed = parse_calendar(&ics(&[
+                "BEGIN:VTODO",
+                "SUMMARY:no due date",
+                "END:VTODO",
+                "BEGIN:VTODO",
+                "SUMMARY:called off",
+                "DUE;VALUE=DATE:20300102",
+                "STATUS:CANCELLED",
+                "END:VTODO",
+            ]))
+            .unwrap();
+
+            assert!(parsed.todos.is_empty());
+            let rejected = parsed.rejected.iter().map(|todo| {
+                let fields = todo.errors.iter().map(|e| e.field.as_str());
+                (todo.line, fields.collect::<Vec<_>>())
+            });
+            assert_eq!(
+                rejected.collect::<Vec<_>>(),
+                [(3, vec!["due_date"]), (6, vec!["status"])]
+            );
+        }
+
+        #[test]
+        fn files_that_are_not_calendars_fail() {
+            for contents in [
+                "",
+                "SUMMARY:buy milk\r\n",
+                "BEGIN:VTODO\r\nEND:VTODO\r\n",
+                "BEGIN:VCALENDAR\r\nBEGIN:VTODO\r\nEND:VCALENDAR\r\n",
+                "BEGIN:VCALENDAR\r\nBEGIN:VTODO\r\n",
+                "BEGIN:VCALENDAR\r\nnot a property\r\nEND:VCALENDAR\r\n",
+            ] {
+                let Err(TodoError::Validation { errors }) = parse_calendar(contents) else {
+                    panic!("{contents:?} isn't a calendar");
+                };
+                assert_eq!(errors[0].field, CONTENTS_FIELD);
+            }
+        }
+    }
+}
+
+/// The URL of the logged-in user's calendar feed, or `None` if it's turned off.
+#[server]
+pub async fn get_calendar_feed() -> Result<Option<String>, ServerFnError<TodoError>> {
+    use crate::server::auth::ssr::{authorize, Authorized};
+
+    let Authorized { owner, repository } = authorize([]).await?;
+    match repository.calendar_token(owner).await? {
+        Some(token) => Ok(Some(self::ssr::feed_url(&token).await?)),
+        None => Ok(None),
+    }
+}
+
+/// Turns the logged-in user's calendar feed on with a new URL, which stops the old one
+/// from working. Returns the new URL.
+#[server]
+pub async fn reset_calendar_feed() -> Result<String, ServerFnError<TodoError>> {
+    use crate::server::auth::ssr::{authorize, Authorized};
+
+    let Authorized { owner, repository } = authorize([]).await?;
+    let token = self::ssr::new_token();
+    repository
+        .set_calendar_token(owner, Some(token.clone()))
+        .await?;
+    Ok(self::ssr::feed_url(&token).await?)
+}
+
+/// Turns the logged-in user's calendar feed off.
+#[server]
+pub async fn turn_off_calendar_feed() -> Result<(), ServerFnError<TodoError>> {
+    use crate::server::auth::ssr::{authorize, Authorized};
+
+    let Authorized { owner, repository } = authorize([]).await?;
+    repository.set_calendar_token(owner, None).await?;
+    Ok(())
+}
+
+/// Adds a todo item to `list_id`, or to the first list if it's missing, for each VTODO
+/// of an iCalendar file. VTODOs that don't pass the checks `add_todo` makes are
+/// reported rather than added, and so are those whose UID was imported before; the
+/// others are added together. SUMMARY, DESCRIPTION, DUE, PRIORITY, STATUS and the RRULEs
+/// todo items can repeat by are kept, and the report names what else was left out.
+#[server]
+pub async fn import_ics(
+    contents: String,
+    #[server(default)] list_id: Option<i32>,
+) -> Result<IcsImportReport, ServerFnError<TodoError>> {
+    use crate::server::auth::ssr::{authorize, Authorized};
+    use crate::server::repository::Resource;
+    use std::collections::HashMap;
+
+    let Authorized { owner, repository } = authorize(list_id.map(Resource::List)).await?;
+    let parsed = self::ssr::parse_calendar(&contents)?;
+    let titles = parsed
+        .todos
+        .iter()
+        .rev()
+        .filter_map(|todo| Some((todo.uid.clone()?, todo.input.title.clone())))
+        .collect::<HashMap<_, _>>();
+    let total = parsed.todos.len();
+    let skipped = if parsed.todos.is_empty() {
+        Vec::new()
+    } else {
+        repository
+            .create_from_calendar(owner, parsed.todos, list_id)
+            .await?
+    };
+    Ok(IcsImportReport {
+        imported: (total - skipped.len()) as u32,
+        duplicates: skipped
+            .into_iter()
+            .filter_map(|uid| titles.get(&uid).cloned())
+            .collect(),
+        dropped: parsed.dropped,
+        rejected: parsed.rejected,
+    })
+}
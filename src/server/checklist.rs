@@ -0,0 +1,64 @@
+use crate::server::error::TodoError;
+use leptos::{server, ServerFnError};
+use serde::{Deserialize, Serialize};
+
+/// One entry of a todo item's checklist.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ChecklistItem {
+    pub id: i32,
+    pub text: String,
+    pub done: bool,
+}
+
+#[cfg(feature = "ssr")]
+pub mod ssr {
+    pub use crate::server::auth::ssr::{authorize, Authorized};
+    pub use crate::server::error::{check_field, TodoError};
+    pub use crate::server::repository::Resource;
+    pub use crate::validation::checklist::check_checklist_text;
+}
+
+#[server]
+pub async fn add_checklist_item(
+    todo_id: i32,
+    text: String,
+) -> Result<(), ServerFnError<TodoError>> {
+    use self::ssr::*;
+
+    check_field("text", &text, check_checklist_text)?;
+    let Authorized { owner, repository } = authorize([Resource::Todo(todo_id)]).await?;
+
+    repository
+        .add_checklist_item(owner, todo_id, text.trim().to_string())
+        .await?;
+    Ok(())
+}
+
+#[server]
+pub async fn set_checklist_item_done(id: i32, done: bool) -> Result<(), ServerFnError<TodoError>> {
+    use self::ssr::*;
+
+    let Authorized { owner, repository } = authorize([Resource::ChecklistItem(id)]).await?;
+    repository.set_checklist_item_done(owner, id, done).await?;
+    Ok(())
+}
+
+/// Moves the item to `position` in its checklist, counting from 0. Positions past the
+/// end move it to the end.
+#[server]
+pub async fn move_checklist_item(id: i32, position: u32) -> Result<(), ServerFnError<TodoError>> {
+    use self::ssr::*;
+
+    let Authorized { owner, repository } = authorize([Resource::ChecklistItem(id)]).await?;
+    repository.move_checklist_item(owner, id, position).await?;
+    Ok(())
+}
+
+#[server]
+pub async fn delete_checklist_item(id: i32) -> Result<(), ServerFnError<TodoError>> {
+    use self::ssr::*;
+
+    let Authorized { owner, repository } = authorize([Resource::ChecklistItem(id)]).await?;
+    repository.delete_checklist_item(owner, id).await?;
+    Ok(())
+}
@@ -0,0 +1,581 @@
+//! Todo items as CSV: the filtered list downloads as a file, and files from elsewhere
+//! are imported after the user says which column holds which field.
+
+use crate::server::error::TodoError;
+use crate::validation::todo::FieldError;
+use leptos::{server, ServerFnError};
+use serde::{Deserialize, Serialize};
+
+/// Where a logged-in user downloads the open items as CSV. Takes the `list`, `tags` and
+/// `sort` query parameters of the JSON API's `GET /todos`.
+pub const CSV_EXPORT_PATH: &str = "/api/todos.csv";
+
+/// How many rows [`preview_csv`] shows.
+pub const PREVIEW_ROWS: usize = 5;
+
+/// The field of a todo item a CSV column is imported as.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum CsvField {
+    /// The column isn't imported.
+    #[default]
+    Ignore,
+    Title,
+    Description,
+    /// `YYYY-MM-DD`.
+    DueDate,
+    /// `none`, `low`, `medium`, `high` or `urgent`; `none` if empty.
+    Priority,
+    /// An RRULE-style rule; the item doesn't repeat if empty.
+    Recurrence,
+}
+
+impl CsvField {
+    /// Every field, in the order they're offered to the user.
+    pub const ALL: [CsvField; 6] = [
+        CsvField::Ignore,
+        CsvField::Title,
+        CsvField::Description,
+        CsvField::DueDate,
+        CsvField::Priority,
+        CsvField::Recurrence,
+    ];
+
+    /// The serialized name, as used in form fields.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CsvField::Ignore => "ignore",
+            CsvField::Title => "title",
+            CsvField::Description => "description",
+            CsvField::DueDate => "due_date",
+            CsvField::Priority => "priority",
+            CsvField::Recurrence => "recurrence",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            CsvField::Ignore => "Don't import",
+            CsvField::Title => "Title",
+            CsvField::Description => "Description",
+            CsvField::DueDate => "Due date",
+            CsvField::Priority => "Priority",
+            CsvField::Recurrence => "Repeats",
+        }
+    }
+
+    /// The field a column with this header most likely holds, by the names this app
+    /// and common todo apps export.
+    pub fn guess(header: &str) -> Self {
+        match header
+            .trim()
+            .to_lowercase()
+            .replace([' ', '-'], "_")
+            .as_str()
+        {
+            "title" | "name" | "summary" | "task" => CsvField::Title,
+            "description" | "notes" | "note" | "details" => CsvField::Description,
+            "due_date" | "due" | "date" | "deadline" => CsvField::DueDate,
+            "priority" => CsvField::Priority,
+            "recurrence" | "repeat" | "repeats" | "rrule" => CsvField::Recurrence,
+            _ => CsvField::Ignore,
+        }
+    }
+}
+
+/// The start of a CSV file, for the user to map its columns to fields.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CsvPreview {
+    /// The first row of the file.
+    pub headers: Vec<String>,
+    /// The next [`PREVIEW_ROWS`] rows at most.
+    pub rows: Vec<Vec<String>>,
+    /// How many rows follow the headers.
+    pub total_rows: u32,
+    /// A field for each column, guessed from its header. No field is guessed twice.
+    pub fields: Vec<CsvField>,
+}
+
+/// What importing a CSV file did.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct CsvImportReport {
+    /// How many todo items were added.
+    pub imported: u32,
+    /// The rows that weren't, in file order.
+    pub rejected: Vec<RejectedRow>,
+}
+
+/// A row that couldn't be imported, and why.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RejectedRow {
+    /// The line of the file the row starts on, counting from 1.
+    pub line: u64,
+    /// Named by the field the column was mapped to.
+    pub errors: Vec<FieldError>,
+}
+
+#[cfg(feature = "ssr")]
+pub mod ssr {
+    use super::{CsvField, CsvPreview, RejectedRow, PREVIEW_ROWS};
+    use crate::server::api::{caller, parse_tags, tag_resources, ApiError, MAX_PER_PAGE};
+    use crate::server::auth::ssr::check_access;
+    use crate::server::error::TodoError;
+    use crate::server::repository::{Resource, SharedTodoRepository, TodoInput};
+    use crate::server::todo::ssr::{tag_set, todo_input};
+    use crate::server::todo::{Priority, SortOrder, Todo};
+    use crate::validation::todo::FieldError;
+    use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
+    use actix_web::web::{Data, Query};
+    use actix_web::{HttpRequest, HttpResponse};
+    use chrono::Utc;
+    use csv::{Reader, ReaderBuilder, StringRecord, Writer};
+    use serde::Deserialize;
+
+    /// The field that problems with the file as a whole are reported on.
+    const CONTENTS_FIELD: &str = "contents";
+
+    /// The field that problems with the column mapping are reported on.
+    const FIELDS_FIELD: &str = "fields";
+
+    /// The columns of an exported file.
+    const COLUMNS: [&str; 5] = ["title", "description", "due_date", "completed", "created"];
+
+    fn reader(contents: &str) -> Reader<&[u8]> {
+        ReaderBuilder::new()
+            .flexible(true)
+            .from_reader(contents.as_bytes())
+    }
+
+    fn unreadable(e: csv::Error) -> TodoError {
+        let message = match e.position() {
+            Some(position) => format!("line {} isn't valid CSV", position.line()),
+            None => "the file isn't valid CSV".to_string(),
+        };
+        vec![FieldError::new(CONTENTS_FIELD, message)].into()
+    }
+
+    fn headers(reader: &mut Reader<&[u8]>) -> Result<Vec<String>, TodoError> {
+        let headers = reader.headers().map_err(unreadable)?;
+        if headers.iter().all(|header| header.trim().is_empty()) {
+            let message = "the file needs a first row naming its columns";
+            return Err(vec![FieldError::new(CONTENTS_FIELD, message)].into());
+        }
+        Ok(headers.iter().map(str::to_string).collect())
+    }
+
+    /// Reads the headers and first rows of `contents`.
+    pub fn preview(contents: &str) -> Result<CsvPreview, TodoError> {
+        let mut reader = reader(contents);
+        let headers = headers(&mut reader)?;
+
+        let mut rows = Vec::new();
+        let mut total_rows = 0;
+        for record in reader.records() {
+            let record = record.map_err(unreadable)?;
+            if rows.len() < PREVIEW_ROWS {
+                rows.push(record.iter().map(str::to_string).collect());
+            }
+            total_rows += 1;
+        }
+
+        let mut fields = Vec::with_capacity(headers.len());
+        for header in &headers {
+            let field = CsvField::guess(header);
+            let taken = field != CsvField::Ignore && fields.contains(&field);
+            fields.push(if taken { CsvField::Ignore } else { field });
+        }
+
+        Ok(CsvPreview {
+            headers,
+            rows,
+            total_rows,
+            fields,
+        })
+    }
+
+    /// Checks that `fields` maps a column to the title and due date, and no field to two
+    /// columns.
+    fn check_fields(fields: &[CsvField]) -> Result<(), TodoError> {
+        let mut errors = Vec::new();
+        for required in [CsvField::Title, CsvField::DueDate] {
+            if !fields.contains(&required) {
+                let message = format!("{} must be picked for a column", required.label());
+                errors.push(FieldError::new(FIELDS_FIELD, message));
+            }
+        }
+        for (i, field) in fields.iter().enumerate() {
+            // Reported once, on the second column it's picked for.
+            let earlier = fields[..i]
+                .iter()
+                .filter(|earlier| *earlier == field)
+                .count();
+            if *field != CsvField::Ignore && earlier == 1 {
+                let message = format!("{} is picked for more than one column", field.label());
+                errors.push(FieldError::new(FIELDS_FIELD, message));
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.into())
+        }
+    }
+
+    /// Validates a row the way `add_todo` validates its form.
+    fn row_input(record: &StringRecord, fields: &[CsvField]) -> Result<TodoInput, Vec<FieldError>> {
+        let cell = |wanted: CsvField| {
+            fields
+                .iter()
+                .position(|field| *field == wanted)
+                .and_then(|column| record.get(column))
+                .unwrap_or_default()
+        };
+
+        let priority = match cell(CsvField::Priority).trim().to_lowercase().as_str() {
+            "" => Ok(Priority::None),
+            priority => priority.parse::<Priority>().map_err(|_| {
+                let message = "priority must be one of none, low, medium, high or urgent";
+                FieldError::new(CsvField::Priority.as_str(), message)
+            }),
+        };
+        let input = todo_input(
+            cell(CsvField::Title),
+            cell(CsvField::Description),
+            cell(CsvField::DueDate).trim(),
+            priority.clone().unwrap_or_default(),
+            Vec::new(),
+            cell(CsvField::Recurrence).trim(),
+        );
+
+        match (input, priority) {
+            (Ok(input), Ok(_)) => Ok(input),
+            (input, priority) => {
+                let mut errors = match input {
+                    Err(TodoError::Validation { errors }) => errors,
+                    _ => Vec::new(),
+                };
+                errors.extend(priority.err());
+                Err(errors)
+            }
+        }
+    }
+
+    /// Reads the rows of `contents` after the headers, taking each column as the field
+    /// `fields` maps it to. Returns the valid rows, and the others with their problems.
+    pub fn parse_rows(
+        contents: &str,
+        fields: &[CsvField],
+    ) -> Result<(Vec<TodoInput>, Vec<RejectedRow>), TodoError> {
+        check_fields(fields)?;
+        let mut reader = reader(contents);
+        headers(&mut reader)?;
+
+        let mut inputs = Vec::new();
+        let mut rejected = Vec::new();
+        for record in reader.records() {
+            let record = record.map_err(unreadable)?;
+            match row_input(&record, fields) {
+                Ok(input) => inputs.push(input),
+                Err(errors) => rejected.push(RejectedRow {
+                    line: record.position().map_or(0, |position| position.line()),
+                    errors,
+                }),
+            }
+        }
+        Ok((inputs, rejected))
+    }
+
+    /// The query of [`super::CSV_EXPORT_PATH`].
+    #[derive(Deserialize, Debug)]
+    pub struct ExportQuery {
+        list: Option<i32>,
+        tags: Option<String>,
+        #[serde(default)]
+        sort: SortOrder,
+    }
+
+    /// `text` as a cell spreadsheet apps show as text: they run cells starting with `=`,
+    /// `+`, `-`, `@`, a tab or a carriage return as formulas, but not after a quote.
+    fn cell(text: &str) -> String {
+        if text.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+            format!("'{text}")
+        } else {
+            text.to_string()
+        }
+    }
+
+    /// Writes `todo` and then, depth first, its sub-todos.
+    fn write_rows(writer: &mut Writer<Vec<u8>>, todo: &Todo) -> csv::Result<()> {
+        writer.write_record([
+            cell(&todo.title),
+            cell(&todo.description),
+            cell(&todo.due_date),
+            todo.completed.to_string(),
+            cell(todo.created.as_deref().unwrap_or_default()),
+        ])?;
+        for child in &todo.children {
+            write_rows(writer, child)?;
+        }
+        Ok(())
+    }
+
+    fn internal(e: csv::Error) -> ApiError {
+        log::error!("failed to write CSV: {e}");
+        TodoError::Internal.into()
+    }
+
+    /// Sends the open items the todo list shows with the same filters and order as a CSV
+    /// file, each followed by its sub-todos at every depth. Like the JSON API, it takes
+    /// HTTP Basic credentials as well as the session.
+    pub async fn download_csv(
+        req: HttpRequest,
+        repository: Data<SharedTodoRepository>,
+        query: Query<ExportQuery>,
+    ) -> Result<HttpResponse, ApiError> {
+        let owner = caller(&req, &repository).await?;
+        let query = query.into_inner();
+        let tags = tag_set(parse_tags(query.tags.as_deref())?);
+        check_access(
+            repository.get_ref().as_ref(),
+            owner,
+            tag_resources(&tags).chain(query.list.map(Resource::List)),
+        )
+        .await?;
+
+        let mut writer = Writer::from_writer(Vec::new());
+        writer.write_record(COLUMNS).map_err(internal)?;
+        let mut page = 0;
+        loop {
+            let todos = repository
+                .paginate(owner, page, MAX_PER_PAGE, query.list, &tags, query.sort)
+                .await?;
+            for todo in &todos.items {
+                write_rows(&mut writer, todo).map_err(internal)?;
+            }
+            page += 1;
+            if page >= todos.total_pages {
+                break;
+            }
+        }
+        let body = writer
+            .into_inner()
+            .map_err(|e| internal(e.into_error().into()))?;
+
+        let filename = format!("todos-{}.csv", Utc::now().format("%Y-%m-%d"));
+        Ok(HttpResponse::Ok()
+            .content_type("text/csv; charset=utf-8")
+            .insert_header(ContentDisposition {
+                disposition: DispositionType::Attachment,
+                parameters: vec![DispositionParam::Filename(filename)],
+            })
+            .body(body))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use chrono::NaiveDate;
+
+        const TITLE_AND_DUE: [CsvField; 2] = [CsvField::Title, CsvField::DueDate];
+
+        fn messages(result: Result<(), TodoError>) -> Vec<String> {
+            match result {
+                Err(TodoError::Validation { errors }) => {
+                    errors.into_iter().map(|e| e.message).collect()
+                }
+                other => panic!("expected validation errors, got {other:?}"),
+            }
+        }
+
+        fn rejected_fields(row: &RejectedRow) -> Vec<&str> {
+            row.errors.iter().map(|e| e.field.as_str()).collect()
+        }
+
+        fn todo(title: &str, children: Vec<Todo>) -> Todo {
+            Todo {
+                id: 0,
+                title: title.to_string(),
+                description: String::new(),
+                completed: false,
+                completed_at: None,
+                deleted_at: None,
+                created: None,
+                due_date: "2030-01-01".to_string(),
+                priority: Priority::None,
+                tags: Vec::new(),
+                checklist: Vec::new(),
+                list_id: 1,
+                parent_id: None,
+                recurrence: None,
+                children,
+            }
+        }
+
+        #[test]
+        fn columns_are_read_by_their_mapping_in_any_order() {
+            let contents = "Due,Notes,Level,Name\n2030-01-02,for the party,HIGH,buy cake\n";
+            let fields = [
+                CsvField::DueDate,
+                CsvField::Description,
+                CsvField::Priority,
+                CsvField::Title,
+            ];
+            let (inputs, rejected) = parse_rows(contents, &fields).unwrap();
+
+            assert!(rejected.is_empty());
+            assert_eq!(inputs.len(), 1);
+            assert_eq!(inputs[0].title, "buy cake");
+            assert_eq!(inputs[0].description, "for the party");
+            assert_eq!(
+                inputs[0].due_date,
+                NaiveDate::from_ymd_opt(2030, 1, 2).unwrap()
+            );
+            assert_eq!(inputs[0].priority, Priority::High);
+        }
+
+        #[test]
+        fn the_title_and_due_date_must_be_mapped_once() {
+            assert_eq!(check_fields(&TITLE_AND_DUE), Ok(()));
+            assert_eq!(
+                messages(check_fields(&[CsvField::Description, CsvField::Ignore])),
+                [
+                    "Title must be picked for a column",
+                    "Due date must be picked for a column"
+                ]
+            );
+            let twice = [
+                CsvField::Title,
+                CsvField::Title,
+                CsvField::DueDate,
+                CsvField::Title,
+                CsvField::Ignore,
+                CsvField::Ignore,
+            ];
+            assert_eq!(
+                messages(check_fields(&twice)),
+                ["Title is picked for more than one column"]
+            );
+            // The mapping is checked before the rows are read.
+            let missing = parse_rows("title\nbuy milk\n", &[CsvField::Title]);
+            assert!(matches!(missing, Err(TodoError::Validation { .. })));
+        }
+
+        #[test]
+        fn rows_with_a_bad_date_are_rejected_with_their_line() {
+            let contents = "title,due\n\
+                            buy milk,2030-01-01\n\
+                            \"call\nmom\",2030-02-30\n\
+                            pay rent,2030-03-01\n";
+            let (inputs, rejected) = parse_rows(contents, &TITLE_AND_DUE).unwrap();
+
+            let titles = inputs.iter().map(|input| input.title.as_str());
+            assert_eq!(titles.collect::<Vec<_>>(), ["buy milk", "pay rent"]);
+            assert_eq!(rejected.len(), 1);
+            // The line the row starts on, though its title runs onto the next.
+            assert_eq!(rejected[0].line, 3);
+            assert_eq!(rejected_fields(&rejected[0]), ["due_date"]);
+        }
+
+        #[test]
+        fn short_rows_have_empty_cells() {
+            let contents = "title,due,notes\nbuy milk,2030-01-01\nbuy bread\n";
+            let fields = [CsvField::Title, CsvField::DueDate, CsvField::Description];
+            let (inputs, rejected) = parse_rows(contents, &fields).unwrap();
+
+            assert_eq!(inputs.len(), 1);
+            assert_eq!(inputs[0].description, "");
+            assert_eq!(rejected.len(), 1);
+            assert_eq!(rejected[0].line, 3);
+            assert_eq!(rejected_fields(&rejected[0]), ["due_date"]);
+        }
+
+        #[test]
+        fn a_file_of_headers_has_no_rows() {
+            let (inputs, rejected) = parse_rows("title,due\n", &TITLE_AND_DUE).unwrap();
+            assert!(inputs.is_empty());
+            assert!(rejected.is_empty());
+            assert_eq!(preview("title,due").unwrap().total_rows, 0);
+
+            for blank in ["", "\n", ",\n"] {
+                let Err(TodoError::Validation { errors }) = parse_rows(blank, &TITLE_AND_DUE)
+                else {
+                    panic!("{blank:?} has no headers");
+                };
+                assert_eq!(errors[0].field, CONTENTS_FIELD);
+            }
+        }
+
+        #[test]
+        fn sub_todos_are_exported_at_every_depth() {
+            let tree = todo(
+                "party",
+                vec![
+                    todo("food", vec![todo("cake", vec![todo("candles", vec![])])]),
+                    todo("music", vec![]),
+                ],
+            );
+            let mut writer = Writer::from_writer(Vec::new());
+            write_rows(&mut writer, &tree).unwrap();
+            let body = String::from_utf8(writer.into_inner().unwrap()).unwrap();
+
+            let titles = body.lines().map(|line| line.split(',').next().unwrap());
+            assert_eq!(
+                titles.collect::<Vec<_>>(),
+                ["party", "food", "cake", "candles", "music"]
+            );
+        }
+
+        #[test]
+        fn formulas_are_exported_as_text() {
+            for formula in ["=1+1", "+1", "-1", "@SUM(A1)"] {
+                assert_eq!(cell(formula), format!("'{formula}"));
+            }
+            assert_eq!(cell("buy milk"), "buy milk");
+            assert_eq!(cell("a=b"), "a=b");
+            assert_eq!(cell(""), "");
+
+            let mut todo = todo("=HYPERLINK(\"http://example.com\")", vec![]);
+            todo.description = "-2".to_string();
+            let mut writer = Writer::from_writer(Vec::new());
+            write_rows(&mut writer, &todo).unwrap();
+            let body = String::from_utf8(writer.into_inner().unwrap()).unwrap();
+            assert_eq!(
+                body,
+                "\"'=HYPERLINK(\"\"http://example.com\"\")\",'-2,2030-01-01,false,\n"
+            );
+        }
+    }
+}
+
+/// The headers and first rows of a CSV file, with a guess at which column holds which
+/// field.
+#[server]
+pub async fn preview_csv(contents: String) -> Result<CsvPreview, ServerFnError<TodoError>> {
+    use crate::server::auth::ssr::authorize;
+
+    authorize([]).await?;
+    Ok(self::ssr::preview(&contents)?)
+}
+
+/// Adds a todo item to `list_id`, or to the first list if it's missing, for each row of
+/// a CSV file after the headers. `fields` says which field each column holds. Rows that
+/// don't pass the checks `add_todo` makes are reported rather than added; the others are
+/// added together.
+#[server]
+pub async fn import_csv(
+    contents: String,
+    #[server(default)] fields: Vec<CsvField>,
+    #[server(default)] list_id: Option<i32>,
+) -> Result<CsvImportReport, ServerFnError<TodoError>> {
+    use crate::server::auth::ssr::{authorize, Authorized};
+    use crate::server::repository::Resource;
+
+    let Authorized { owner, repository } = authorize(list_id.map(Resource::List)).await?;
+    let (inputs, rejected) = self::ssr::parse_rows(&contents, &fields)?;
+    let imported = if inputs.is_empty() {
+        0
+    } else {
+        repository.create_many(owner, inputs, list_id).await?
+    };
+    Ok(CsvImportReport { imported, rejected })
+}
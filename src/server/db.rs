@@ -0,0 +1,73 @@
+use deadpool_postgres::{
+    BuildError, Manager, ManagerConfig, Pool, RecyclingMethod, Runtime, Timeouts,
+};
+use serde::Deserialize;
+use std::time::Duration;
+use tokio_postgres::NoTls;
+
+/// Sizing and timeout settings for the shared Postgres connection pool.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct PoolSettings {
+    /// The maximum number of open connections.
+    pub max_size: usize,
+    /// How long a server function waits for a free connection before giving up.
+    pub acquire_timeout_secs: u64,
+    /// Connections unused for longer than this are closed. 0 keeps them open.
+    pub idle_timeout_secs: u64,
+}
+
+impl Default for PoolSettings {
+    fn default() -> Self {
+        Self {
+            max_size: 16,
+            acquire_timeout_secs: 5,
+            idle_timeout_secs: 300,
+        }
+    }
+}
+
+impl PoolSettings {
+    pub fn acquire_timeout(&self) -> Duration {
+        Duration::from_secs(self.acquire_timeout_secs)
+    }
+
+    pub fn idle_timeout(&self) -> Option<Duration> {
+        (self.idle_timeout_secs > 0).then(|| Duration::from_secs(self.idle_timeout_secs))
+    }
+}
+
+/// Builds the connection pool. Connections are opened lazily on first checkout.
+pub fn create_pool(
+    pg_config: tokio_postgres::Config,
+    settings: &PoolSettings,
+) -> Result<Pool, BuildError> {
+    let manager = Manager::from_config(
+        pg_config,
+        NoTls,
+        ManagerConfig {
+            recycling_method: RecyclingMethod::Fast,
+        },
+    );
+
+    Pool::builder(manager)
+        .max_size(settings.max_size)
+        .timeouts(Timeouts {
+            wait: Some(settings.acquire_timeout()),
+            create: Some(settings.acquire_timeout()),
+            recycle: None,
+        })
+        .runtime(Runtime::Tokio1)
+        .build()
+}
+
+/// Periodically closes connections that have sat unused for longer than `idle_timeout`.
+pub fn spawn_idle_reaper(pool: Pool, idle_timeout: Duration) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(idle_timeout / 2);
+        loop {
+            interval.tick().await;
+            pool.retain(|_, metrics| metrics.last_used() < idle_timeout);
+        }
+    });
+}
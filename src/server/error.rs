@@ -0,0 +1,96 @@
+use crate::validation::todo::FieldError;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// The ways a todo server function can fail, as seen by the browser.
+///
+/// It travels inside `ServerFnError::WrappedServerError`, which serializes it with
+/// `Display` and parses it back with `FromStr`, so both use JSON. Show users
+/// [`TodoError::message`] rather than the `Display` output.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TodoError {
+    /// The todo item doesn't exist, e.g. it was deleted in another tab.
+    NotFound { id: i32 },
+    /// The tag doesn't exist.
+    TagNotFound { id: i32 },
+    /// The checklist item doesn't exist.
+    ChecklistItemNotFound { id: i32 },
+    /// The list doesn't exist.
+    ListNotFound { id: i32 },
+    /// One or more submitted fields were rejected.
+    Validation { errors: Vec<FieldError> },
+    /// The change clashes with the current state of the data.
+    Conflict { message: String },
+    /// Nobody is logged in, e.g. the session ended in another tab.
+    Unauthorized,
+    /// The username and password don't match an account.
+    InvalidCredentials,
+    /// What was asked for belongs to another user.
+    Forbidden,
+    /// The database can't be reached right now; retrying later may work.
+    Unavailable,
+    /// Anything else. The details are only logged on the server.
+    Internal,
+}
+
+impl TodoError {
+    /// A message suitable for showing to the user.
+    pub fn message(&self) -> String {
+        match self {
+            TodoError::NotFound { .. } => "That todo item no longer exists.".into(),
+            TodoError::TagNotFound { .. } => "That tag no longer exists.".into(),
+            TodoError::ChecklistItemNotFound { .. } => {
+                "That checklist item no longer exists.".into()
+            }
+            TodoError::ListNotFound { .. } => "That list no longer exists.".into(),
+            TodoError::Validation { errors } => errors
+                .iter()
+                .map(|e| e.message.as_str())
+                .collect::<Vec<_>>()
+                .join("; "),
+            TodoError::Conflict { message } => message.clone(),
+            TodoError::Unauthorized => "Please log in to continue.".into(),
+            TodoError::InvalidCredentials => "The username or password is incorrect.".into(),
+            TodoError::Forbidden => "You don't have access to that.".into(),
+            TodoError::Unavailable => {
+                "The server is unavailable right now, please try again later.".into()
+            }
+            TodoError::Internal => "Something went wrong, please try again.".into(),
+        }
+    }
+}
+
+/// Rejects `value` as `field` if `check` finds a problem with it.
+pub fn check_field(
+    field: &str,
+    value: &str,
+    check: fn(&str) -> Option<String>,
+) -> Result<(), TodoError> {
+    match check(value) {
+        Some(message) => Err(vec![FieldError::new(field, message)].into()),
+        None => Ok(()),
+    }
+}
+
+impl From<Vec<FieldError>> for TodoError {
+    fn from(errors: Vec<FieldError>) -> Self {
+        TodoError::Validation { errors }
+    }
+}
+
+impl fmt::Display for TodoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let json = serde_json::to_string(self).map_err(|_| fmt::Error)?;
+        f.write_str(&json)
+    }
+}
+
+impl FromStr for TodoError {
+    type Err = serde_json::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_json::from_str(s)
+    }
+}
@@ -0,0 +1,93 @@
+use crate::server::error::TodoError;
+use leptos::{server, ServerFnError};
+use serde::{Deserialize, Serialize};
+
+/// A user-created list (or project) that todo items are filed under. Every item belongs
+/// to exactly one list, and sub-todos always share their parent's.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct List {
+    pub id: i32,
+    pub name: String,
+    /// A `#rrggbb` colour for the list's marker.
+    pub colour: String,
+    /// How many items in the list are still open, sub-todos included and the trash left
+    /// out.
+    pub open_count: u32,
+}
+
+#[cfg(feature = "ssr")]
+pub mod ssr {
+    pub use crate::server::auth::ssr::{authorize, Authorized};
+    pub use crate::server::error::{check_field, TodoError};
+    pub use crate::server::repository::Resource;
+    pub use crate::validation::list::{check_list_name, validate_list};
+    pub use crate::validation::tag::check_colour;
+}
+
+/// Every list, in the user's order.
+#[server]
+pub async fn get_lists() -> Result<Vec<List>, ServerFnError<TodoError>> {
+    use self::ssr::*;
+
+    let Authorized { owner, repository } = authorize([]).await?;
+    Ok(repository.list_lists(owner).await?)
+}
+
+#[server]
+pub async fn create_list(name: String, colour: String) -> Result<(), ServerFnError<TodoError>> {
+    use self::ssr::*;
+
+    let valid = validate_list(&name, &colour).map_err(TodoError::from)?;
+    let Authorized { owner, repository } = authorize([]).await?;
+
+    repository
+        .create_list(owner, valid.name, valid.colour)
+        .await?;
+    Ok(())
+}
+
+#[server]
+pub async fn rename_list(id: i32, name: String) -> Result<(), ServerFnError<TodoError>> {
+    use self::ssr::*;
+
+    check_field("name", &name, check_list_name)?;
+    let Authorized { owner, repository } = authorize([Resource::List(id)]).await?;
+
+    repository
+        .rename_list(owner, id, name.trim().to_string())
+        .await?;
+    Ok(())
+}
+
+#[server]
+pub async fn set_list_colour(id: i32, colour: String) -> Result<(), ServerFnError<TodoError>> {
+    use self::ssr::*;
+
+    check_field("colour", &colour, check_colour)?;
+    let Authorized { owner, repository } = authorize([Resource::List(id)]).await?;
+
+    repository
+        .set_list_colour(owner, id, colour.trim().to_ascii_lowercase())
+        .await?;
+    Ok(())
+}
+
+/// Moves the list to `position` in the sidebar, counting from 0.
+#[server]
+pub async fn move_list(id: i32, position: u32) -> Result<(), ServerFnError<TodoError>> {
+    use self::ssr::*;
+
+    let Authorized { owner, repository } = authorize([Resource::List(id)]).await?;
+    repository.move_list(owner, id, position).await?;
+    Ok(())
+}
+
+/// Deletes the list along with every todo item in it. The last list can't be deleted.
+#[server]
+pub async fn delete_list(id: i32) -> Result<(), ServerFnError<TodoError>> {
+    use self::ssr::*;
+
+    let Authorized { owner, repository } = authorize([Resource::List(id)]).await?;
+    repository.delete_list(owner, id).await?;
+    Ok(())
+}
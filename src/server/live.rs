@@ -0,0 +1,140 @@
+//! Live updates. Postgres announces changes to a user's data with `NOTIFY`, and the
+//! server passes them on to that user's open pages as server-sent events.
+
+/// Where pages subscribe to changes to their user's data.
+pub const EVENTS_PATH: &str = "/api/events";
+
+#[cfg(feature = "ssr")]
+pub mod ssr {
+    use crate::server::session::session_user;
+    use actix_session::Session;
+    use actix_web::http::header;
+    use actix_web::web::{Bytes, Data};
+    use actix_web::HttpResponse;
+    use futures::stream;
+    use std::convert::Infallible;
+    use std::future::poll_fn;
+    use std::time::Duration;
+    use tokio::sync::broadcast::{self, error::RecvError};
+    use tokio_postgres::{AsyncMessage, NoTls};
+
+    /// The channel the triggers from the migrations notify on.
+    const CHANNEL: &str = "todo_changes";
+
+    /// How many announcements a slow stream may fall behind by before it skips ahead.
+    const CAPACITY: usize = 256;
+
+    /// How often an idle stream sends a comment, so proxies keep it open and streams to
+    /// closed pages are noticed.
+    const KEEP_ALIVE: Duration = Duration::from_secs(30);
+
+    /// How long to wait before listening again after the connection is lost.
+    const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+    /// Hands announcements to every open event stream. Each one is the id of the user
+    /// whose data changed, or `None` if anyone's may have changed without an
+    /// announcement, e.g. while the listener was reconnecting.
+    #[derive(Clone)]
+    pub struct TodoChanges(broadcast::Sender<Option<i32>>);
+
+    impl Default for TodoChanges {
+        fn default() -> Self {
+            Self(broadcast::channel(CAPACITY).0)
+        }
+    }
+
+    impl TodoChanges {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        fn publish(&self, owner: Option<i32>) {
+            // Sending only fails when no page is listening.
+            let _ = self.0.send(owner);
+        }
+    }
+
+    /// Listens for announcements on a connection of its own, outside the pool, for as
+    /// long as the server runs.
+    pub fn spawn_change_listener(pg_config: tokio_postgres::Config, changes: TodoChanges) {
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = listen(&pg_config, &changes).await {
+                    log::warn!("todo change listener disconnected: {e}");
+                }
+                tokio::time::sleep(RECONNECT_DELAY).await;
+            }
+        });
+    }
+
+    /// Passes announcements on until the connection is lost.
+    async fn listen(
+        pg_config: &tokio_postgres::Config,
+        changes: &TodoChanges,
+    ) -> Result<(), tokio_postgres::Error> {
+        let (client, mut connection) = pg_config.connect(NoTls).await?;
+
+        // Notifications only arrive while the connection is polled, which LISTEN itself
+        // needs too, so it's polled by a task of its own.
+        let forward = changes.clone();
+        let messages = tokio::spawn(async move {
+            while let Some(message) = poll_fn(|cx| connection.poll_message(cx)).await {
+                if let AsyncMessage::Notification(notification) = message? {
+                    forward.publish(notification.payload().parse().ok());
+                }
+            }
+            Ok(())
+        });
+
+        client.batch_execute(&format!("LISTEN {CHANNEL}")).await?;
+        log::info!("listening for todo changes");
+        // Anything that changed while nobody was listening went unannounced.
+        changes.publish(None);
+
+        messages.await.unwrap_or_else(|e| {
+            log::error!("todo change listener failed: {e}");
+            Ok(())
+        })
+    }
+
+    /// Streams an event to the logged-in user whenever their data changes. The event
+    /// data counts the events sent so far, so that no two are alike.
+    pub async fn todo_events(session: Session, changes: Data<TodoChanges>) -> HttpResponse {
+        let Some(user) = session_user(&session) else {
+            return HttpResponse::Unauthorized().finish();
+        };
+
+        let state = (
+            changes.0.subscribe(),
+            tokio::time::interval(KEEP_ALIVE),
+            0_u64,
+        );
+        let events = stream::unfold(
+            state,
+            move |(mut receiver, mut keep_alive, mut sent)| async move {
+                let chunk = loop {
+                    tokio::select! {
+                        change = receiver.recv() => match change {
+                            Ok(Some(owner)) if owner != user => continue,
+                            Ok(_) | Err(RecvError::Lagged(_)) => {
+                                sent += 1;
+                                break format!("data: {sent}\n\n");
+                            }
+                            Err(RecvError::Closed) => return None,
+                        },
+                        _ = keep_alive.tick() => break ":\n\n".to_string(),
+                    }
+                };
+                Some((
+                    Ok::<_, Infallible>(Bytes::from(chunk)),
+                    (receiver, keep_alive, sent),
+                ))
+            },
+        );
+
+        HttpResponse::Ok()
+            .content_type("text/event-stream")
+            .insert_header((header::CACHE_CONTROL, "no-cache"))
+            .streaming(events)
+    }
+}
@@ -0,0 +1,342 @@
+use deadpool_postgres::{Pool, PoolError};
+use include_dir::{include_dir, Dir};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// The Postgres migrations in `migrations/`, compiled into the binary.
+static MIGRATIONS: Dir = include_dir!("$CARGO_MANIFEST_DIR/migrations");
+
+/// Arbitrary key for the advisory lock held while migrating, so two servers booting
+/// against the same database don't race each other.
+const MIGRATION_LOCK_KEY: i64 = 0x6c65_7074_6f73;
+
+/// A pair of `{version}_{name}.up.sql` / `{version}_{name}.down.sql` scripts.
+#[derive(Clone, Debug)]
+pub struct Migration {
+    pub version: i64,
+    pub name: String,
+    pub up: &'static str,
+    pub down: Option<&'static str>,
+}
+
+#[derive(Debug)]
+pub enum MigrateError {
+    Pool(PoolError),
+    Postgres(tokio_postgres::Error),
+    #[cfg(feature = "sqlite")]
+    Sqlite(rusqlite::Error),
+    /// A file in `migrations/` doesn't follow the naming scheme.
+    BadFile(String),
+    /// The database has versions applied that this binary doesn't embed.
+    Unknown(Vec<i64>),
+    /// A migration being rolled back has no `down` script.
+    Irreversible(i64),
+    /// A SQLite migration left rows referring to rows that don't exist.
+    #[cfg(feature = "sqlite")]
+    ForeignKeys,
+}
+
+impl fmt::Display for MigrateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrateError::Pool(e) => write!(f, "could not connect to the database: {e}"),
+            MigrateError::Postgres(e) => write!(f, "migration failed: {e}"),
+            #[cfg(feature = "sqlite")]
+            MigrateError::Sqlite(e) => write!(f, "migration failed: {e}"),
+            MigrateError::BadFile(name) => write!(f, "unexpected migration file: {name}"),
+            MigrateError::Unknown(versions) => write!(
+                f,
+                "the database has migrations this binary doesn't know about: {versions:?}"
+            ),
+            MigrateError::Irreversible(version) => {
+                write!(f, "migration {version} has no down script")
+            }
+            #[cfg(feature = "sqlite")]
+            MigrateError::ForeignKeys => {
+                write!(f, "migration left rows referring to missing rows")
+            }
+        }
+    }
+}
+
+impl std::error::Error for MigrateError {}
+
+impl From<PoolError> for MigrateError {
+    fn from(e: PoolError) -> Self {
+        MigrateError::Pool(e)
+    }
+}
+
+impl From<tokio_postgres::Error> for MigrateError {
+    fn from(e: tokio_postgres::Error) -> Self {
+        MigrateError::Postgres(e)
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl From<rusqlite::Error> for MigrateError {
+    fn from(e: rusqlite::Error) -> Self {
+        MigrateError::Sqlite(e)
+    }
+}
+
+/// All embedded Postgres migrations, ordered by version.
+pub fn embedded() -> Result<Vec<Migration>, MigrateError> {
+    parse(&MIGRATIONS)
+}
+
+/// Pairs up the `up`/`down` scripts at the top level of `dir`.
+fn parse(dir: &Dir<'static>) -> Result<Vec<Migration>, MigrateError> {
+    let mut migrations = BTreeMap::<i64, Migration>::new();
+
+    for file in dir.files() {
+        let file_name = file
+            .path()
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy();
+        let bad_file = || MigrateError::BadFile(file_name.to_string());
+
+        let (stem, is_up) = if let Some(stem) = file_name.strip_suffix(".up.sql") {
+            (stem, true)
+        } else if let Some(stem) = file_name.strip_suffix(".down.sql") {
+            (stem, false)
+        } else {
+            return Err(bad_file());
+        };
+        let (version, name) = stem.split_once('_').ok_or_else(bad_file)?;
+        let version = version.parse::<i64>().map_err(|_| bad_file())?;
+        let sql = file.contents_utf8().ok_or_else(bad_file)?;
+
+        let migration = migrations.entry(version).or_insert_with(|| Migration {
+            version,
+            name: name.to_string(),
+            up: "",
+            down: None,
+        });
+        if migration.name != name {
+            return Err(bad_file());
+        }
+        if is_up {
+            migration.up = sql;
+        } else {
+            migration.down = Some(sql);
+        }
+    }
+
+    if let Some(m) = migrations.values().find(|m| m.up.is_empty()) {
+        return Err(MigrateError::BadFile(format!(
+            "{:06}_{}.up.sql",
+            m.version, m.name
+        )));
+    }
+
+    Ok(migrations.into_values().collect())
+}
+
+/// Fails if `applied` has versions that aren't in `migrations`.
+fn check_known(migrations: &[Migration], applied: &[i64]) -> Result<(), MigrateError> {
+    let unknown = applied
+        .iter()
+        .copied()
+        .filter(|v| !migrations.iter().any(|m| m.version == *v))
+        .collect::<Vec<_>>();
+    if !unknown.is_empty() {
+        return Err(MigrateError::Unknown(unknown));
+    }
+    Ok(())
+}
+
+async fn lock_and_list_applied(
+    tx: &tokio_postgres::Transaction<'_>,
+) -> Result<Vec<i64>, MigrateError> {
+    tx.execute("SELECT pg_advisory_xact_lock($1)", &[&MIGRATION_LOCK_KEY])
+        .await?;
+    tx.batch_execute(
+        "CREATE TABLE IF NOT EXISTS schema_migrations(
+            version bigint primary key,
+            name text NOT NULL,
+            applied_at timestamp with time zone NOT NULL default now()
+        )",
+    )
+    .await?;
+
+    let rows = tx
+        .query(
+            "SELECT version FROM schema_migrations ORDER BY version",
+            &[],
+        )
+        .await?;
+    Ok(rows.into_iter().map(|row| row.get(0)).collect())
+}
+
+/// Applies every pending `up` script in a single transaction and returns the versions
+/// applied. Refuses to run if the database is ahead of the binary.
+pub async fn run_pending(pool: &Pool) -> Result<Vec<i64>, MigrateError> {
+    let migrations = embedded()?;
+    let mut client = pool.get().await?;
+    let tx = client.transaction().await?;
+
+    let applied = lock_and_list_applied(&tx).await?;
+    check_known(&migrations, &applied)?;
+
+    let mut ran = Vec::new();
+    for migration in migrations.iter().filter(|m| !applied.contains(&m.version)) {
+        log::info!(
+            "applying migration {} {}",
+            migration.version,
+            migration.name
+        );
+        tx.batch_execute(migration.up).await?;
+        tx.execute(
+            "INSERT INTO schema_migrations(version, name) VALUES($1, $2)",
+            &[&migration.version, &migration.name],
+        )
+        .await?;
+        ran.push(migration.version);
+    }
+
+    tx.commit().await?;
+    Ok(ran)
+}
+
+/// Rolls back the latest `steps` applied migrations with their `down` scripts in a
+/// single transaction and returns the versions reverted.
+pub async fn rollback(pool: &Pool, steps: usize) -> Result<Vec<i64>, MigrateError> {
+    let migrations = embedded()?;
+    let mut client = pool.get().await?;
+    let tx = client.transaction().await?;
+
+    let applied = lock_and_list_applied(&tx).await?;
+
+    let mut reverted = Vec::new();
+    for version in applied.into_iter().rev().take(steps) {
+        let migration = migrations
+            .iter()
+            .find(|m| m.version == version)
+            .ok_or_else(|| MigrateError::Unknown(vec![version]))?;
+        let down = migration.down.ok_or(MigrateError::Irreversible(version))?;
+
+        log::info!(
+            "reverting migration {} {}",
+            migration.version,
+            migration.name
+        );
+        tx.batch_execute(down).await?;
+        tx.execute(
+            "DELETE FROM schema_migrations WHERE version = $1",
+            &[&version],
+        )
+        .await?;
+        reverted.push(version);
+    }
+
+    tx.commit().await?;
+    Ok(reverted)
+}
+
+/// The same runner for the SQLite backend, over the scripts in `migrations/sqlite/`.
+#[cfg(feature = "sqlite")]
+pub mod sqlite {
+    use super::{check_known, parse, MigrateError};
+    use include_dir::{include_dir, Dir};
+    use rusqlite::{Connection, Transaction};
+
+    static MIGRATIONS: Dir = include_dir!("$CARGO_MANIFEST_DIR/migrations/sqlite");
+
+    fn list_applied(tx: &Transaction<'_>) -> Result<Vec<i64>, MigrateError> {
+        tx.execute_batch(
+            "CREATE TABLE IF NOT EXISTS schema_migrations(
+                version integer primary key,
+                name text NOT NULL,
+                applied_at text NOT NULL default (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+            )",
+        )?;
+
+        let mut stmt = tx.prepare("SELECT version FROM schema_migrations ORDER BY version")?;
+        let versions = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<Vec<i64>, _>>()?;
+        Ok(versions)
+    }
+
+    /// Runs `migrate` in a single transaction with foreign key enforcement off, so scripts
+    /// can rebuild a table by copying it and dropping the original without cascading
+    /// deletes. Every reference is checked again before committing.
+    fn with_foreign_keys_off<T>(
+        conn: &mut Connection,
+        migrate: impl FnOnce(&Transaction<'_>) -> Result<T, MigrateError>,
+    ) -> Result<T, MigrateError> {
+        // The pragma is a no-op inside a transaction, so it's set around it.
+        conn.pragma_update(None, "foreign_keys", false)?;
+        let result = (|| {
+            // IMMEDIATE takes the write lock up front, like the advisory lock on Postgres.
+            let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+            let value = migrate(&tx)?;
+            if tx.prepare("PRAGMA foreign_key_check")?.exists([])? {
+                return Err(MigrateError::ForeignKeys);
+            }
+            tx.commit()?;
+            Ok(value)
+        })();
+        conn.pragma_update(None, "foreign_keys", true)?;
+        result
+    }
+
+    /// Applies every pending `up` script in a single transaction and returns the
+    /// versions applied. Refuses to run if the database is ahead of the binary.
+    pub fn run_pending(conn: &mut Connection) -> Result<Vec<i64>, MigrateError> {
+        let migrations = parse(&MIGRATIONS)?;
+        with_foreign_keys_off(conn, |tx| {
+            let applied = list_applied(tx)?;
+            check_known(&migrations, &applied)?;
+
+            let mut ran = Vec::new();
+            for migration in migrations.iter().filter(|m| !applied.contains(&m.version)) {
+                log::info!(
+                    "applying migration {} {}",
+                    migration.version,
+                    migration.name
+                );
+                tx.execute_batch(migration.up)?;
+                tx.execute(
+                    "INSERT INTO schema_migrations(version, name) VALUES(?1, ?2)",
+                    (migration.version, &migration.name),
+                )?;
+                ran.push(migration.version);
+            }
+            Ok(ran)
+        })
+    }
+
+    /// Rolls back the latest `steps` applied migrations in a single transaction and
+    /// returns the versions reverted.
+    pub fn rollback(conn: &mut Connection, steps: usize) -> Result<Vec<i64>, MigrateError> {
+        let migrations = parse(&MIGRATIONS)?;
+        with_foreign_keys_off(conn, |tx| {
+            let applied = list_applied(tx)?;
+
+            let mut reverted = Vec::new();
+            for version in applied.into_iter().rev().take(steps) {
+                let migration = migrations
+                    .iter()
+                    .find(|m| m.version == version)
+                    .ok_or_else(|| MigrateError::Unknown(vec![version]))?;
+                let down = migration.down.ok_or(MigrateError::Irreversible(version))?;
+
+                log::info!(
+                    "reverting migration {} {}",
+                    migration.version,
+                    migration.name
+                );
+                tx.execute_batch(down)?;
+                tx.execute(
+                    "DELETE FROM schema_migrations WHERE version = ?1",
+                    [version],
+                )?;
+                reverted.push(version);
+            }
+            Ok(reverted)
+        })
+    }
+}
@@ -1 +1,25 @@
+#[cfg(feature = "ssr")]
+pub mod api;
+pub mod auth;
+pub mod backup;
+pub mod calendar;
+pub mod checklist;
+pub mod csv;
+#[cfg(feature = "ssr")]
+pub mod db;
+pub mod error;
+pub mod list;
+pub mod live;
+#[cfg(feature = "ssr")]
+pub mod migrate;
+pub mod recurrence;
+#[cfg(feature = "ssr")]
+pub mod repository;
+#[cfg(feature = "ssr")]
+pub mod session;
+#[cfg(feature = "ssr")]
+pub mod settings;
+pub mod tag;
 pub mod todo;
+#[cfg(feature = "ssr")]
+pub mod trash;
@@ -0,0 +1,343 @@
+use chrono::{Datelike, Days, Months, NaiveDate, Weekday};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// The longest gap allowed by [`Recurrence::AfterCompletion`].
+pub const MAX_INTERVAL_DAYS: u32 = 365;
+
+/// When a recurring todo item comes back after it's completed.
+///
+/// Stored, submitted and serialized as an RRULE-style string:
+///
+/// - `FREQ=DAILY`
+/// - `FREQ=WEEKLY;BYDAY=MO,FR`
+/// - `FREQ=MONTHLY;BYMONTHDAY=15`
+/// - `FREQ=DAILY;INTERVAL=3;FROM=COMPLETION`, which is not part of RFC 5545
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(try_from = "String", into = "String")]
+pub enum Recurrence {
+    /// Every day.
+    Daily,
+    /// Every week on the given days.
+    Weekly(Vec<Weekday>),
+    /// Every month on the given day, or on the last day of shorter months.
+    Monthly(u32),
+    /// The given number of days after the item is actually completed.
+    AfterCompletion(u32),
+}
+
+const WEEKDAYS: [(Weekday, &str, &str); 7] = [
+    (Weekday::Mon, "MO", "Mon"),
+    (Weekday::Tue, "TU", "Tue"),
+    (Weekday::Wed, "WE", "Wed"),
+    (Weekday::Thu, "TH", "Thu"),
+    (Weekday::Fri, "FR", "Fri"),
+    (Weekday::Sat, "SA", "Sat"),
+    (Weekday::Sun, "SU", "Sun"),
+];
+
+/// Every weekday, from Monday, with its short name.
+pub fn weekdays() -> impl Iterator<Item = (Weekday, &'static str)> {
+    WEEKDAYS.iter().map(|(day, _, name)| (*day, *name))
+}
+
+impl Recurrence {
+    /// The due date of the occurrence after one due on `due` and completed on
+    /// `completed_on`. Schedules never produce a date before either of them, so an item
+    /// completed late doesn't come back already overdue.
+    pub fn next_due(&self, due: NaiveDate, completed_on: NaiveDate) -> NaiveDate {
+        let after = due.max(completed_on);
+        match self {
+            Recurrence::Daily => after + Days::new(1),
+            Recurrence::Weekly(days) => (1..=7)
+                .map(|n| after + Days::new(n))
+                .find(|date| days.contains(&date.weekday()))
+                .unwrap_or(after + Days::new(7)),
+            Recurrence::Monthly(day) => (0..=12)
+                .filter_map(|n| after.with_day(1)?.checked_add_months(Months::new(n)))
+                .map(|month| on_day_or_last(month, *day))
+                .find(|date| *date > after)
+                .unwrap_or(after + Months::new(1)),
+            Recurrence::AfterCompletion(days) => completed_on + Days::new(u64::from(*days)),
+        }
+    }
+
+    /// A short description for the repeat badge, e.g. "Weekly on Mon, Fri".
+    pub fn label(&self) -> String {
+        match self {
+            Recurrence::Daily => "Daily".into(),
+            Recurrence::Weekly(days) => {
+                let names = weekdays()
+                    .filter(|(day, _)| days.contains(day))
+                    .map(|(_, name)| name)
+                    .collect::<Vec<_>>();
+                format!("Weekly on {}", names.join(", "))
+            }
+            Recurrence::Monthly(day) => format!("Monthly on day {day}"),
+            Recurrence::AfterCompletion(1) => "1 day after completion".into(),
+            Recurrence::AfterCompletion(days) => format!("{days} days after completion"),
+        }
+    }
+}
+
+/// `day` in the month starting at `first`, or the month's last day if it's shorter.
+fn on_day_or_last(first: NaiveDate, day: u32) -> NaiveDate {
+    (1..=day)
+        .rev()
+        .find_map(|day| first.with_day(day))
+        .unwrap_or(first)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RecurrenceError {
+    /// Not one of the supported rule shapes.
+    Malformed,
+    /// A weekly rule without any days.
+    NoWeekdays,
+    /// A monthly day outside 1 to 31.
+    DayOutOfRange,
+    /// A gap outside 1 to [`MAX_INTERVAL_DAYS`] days.
+    IntervalOutOfRange,
+}
+
+impl fmt::Display for RecurrenceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecurrenceError::Malformed => write!(f, "repeat rule is not recognised"),
+            RecurrenceError::NoWeekdays => write!(f, "pick at least one day of the week"),
+            RecurrenceError::DayOutOfRange => {
+                write!(f, "day of the month must be between 1 and 31")
+            }
+            RecurrenceError::IntervalOutOfRange => write!(
+                f,
+                "number of days must be between 1 and {MAX_INTERVAL_DAYS}"
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Recurrence::Daily => write!(f, "FREQ=DAILY"),
+            Recurrence::Weekly(days) => {
+                let codes = WEEKDAYS
+                    .iter()
+                    .filter(|(day, _, _)| days.contains(day))
+                    .map(|(_, code, _)| *code)
+                    .collect::<Vec<_>>();
+                write!(f, "FREQ=WEEKLY;BYDAY={}", codes.join(","))
+            }
+            Recurrence::Monthly(day) => write!(f, "FREQ=MONTHLY;BYMONTHDAY={day}"),
+            Recurrence::AfterCompletion(days) => {
+                write!(f, "FREQ=DAILY;INTERVAL={days};FROM=COMPLETION")
+            }
+        }
+    }
+}
+
+impl FromStr for Recurrence {
+    type Err = RecurrenceError;
+
+    /// Parses a rule written by `Display`. The parts may come in any order.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s
+            .trim()
+            .split(';')
+            .map(|part| part.split_once('=').ok_or(RecurrenceError::Malformed))
+            .collect::<Result<Vec<_>, _>>()?;
+        parts.sort_unstable();
+
+        let number = |value: &str, max: u32, error: RecurrenceError| {
+            value
+                .parse::<u32>()
+                .ok()
+                .filter(|n| (1..=max).contains(n))
+                .ok_or(error)
+        };
+        match parts[..] {
+            [("FREQ", "DAILY")] => Ok(Recurrence::Daily),
+            [("BYDAY", days), ("FREQ", "WEEKLY")] => {
+                let days = days
+                    .split(',')
+                    .filter(|code| !code.is_empty())
+                    .map(|code| {
+                        WEEKDAYS
+                            .iter()
+                            .find(|(_, other, _)| *other == code)
+                            .map(|(day, _, _)| *day)
+                            .ok_or(RecurrenceError::Malformed)
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                if days.is_empty() {
+                    return Err(RecurrenceError::NoWeekdays);
+                }
+                Ok(Recurrence::Weekly(days))
+            }
+            [("BYMONTHDAY", day), ("FREQ", "MONTHLY")] => Ok(Recurrence::Monthly(number(
+                day,
+                31,
+                RecurrenceError::DayOutOfRange,
+            )?)),
+            [("FREQ", "DAILY"), ("FROM", "COMPLETION"), ("INTERVAL", days)] => {
+                Ok(Recurrence::AfterCompletion(number(
+                    days,
+                    MAX_INTERVAL_DAYS,
+                    RecurrenceError::IntervalOutOfRange,
+                )?))
+            }
+            _ => Err(RecurrenceError::Malformed),
+        }
+    }
+}
+
+impl TryFrom<String> for Recurrence {
+    type Error = RecurrenceError;
+
+    fn try_from(rule: String) -> Result<Self, Self::Error> {
+        rule.parse()
+    }
+}
+
+impl From<Recurrence> for String {
+    fn from(recurrence: Recurrence) -> Self {
+        recurrence.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    /// The next due date of an item completed on the day it was due.
+    fn next_on_time(recurrence: &Recurrence, due: NaiveDate) -> NaiveDate {
+        recurrence.next_due(due, due)
+    }
+
+    #[test]
+    fn day_31_falls_back_to_the_end_of_shorter_months() {
+        let monthly = Recurrence::Monthly(31);
+        assert_eq!(next_on_time(&monthly, date(2024, 1, 31)), date(2024, 2, 29));
+        assert_eq!(next_on_time(&monthly, date(2023, 1, 31)), date(2023, 2, 28));
+        // Back on the 31st once the month has one.
+        assert_eq!(next_on_time(&monthly, date(2023, 2, 28)), date(2023, 3, 31));
+        assert_eq!(next_on_time(&monthly, date(2024, 3, 31)), date(2024, 4, 30));
+        assert_eq!(next_on_time(&monthly, date(2024, 4, 30)), date(2024, 5, 31));
+        assert_eq!(
+            next_on_time(&monthly, date(2024, 12, 31)),
+            date(2025, 1, 31)
+        );
+    }
+
+    #[test]
+    fn day_29_lands_on_february_29_only_in_leap_years() {
+        let monthly = Recurrence::Monthly(29);
+        assert_eq!(next_on_time(&monthly, date(2024, 1, 29)), date(2024, 2, 29));
+        assert_eq!(next_on_time(&monthly, date(2025, 1, 29)), date(2025, 2, 28));
+        assert_eq!(next_on_time(&monthly, date(2024, 2, 29)), date(2024, 3, 29));
+    }
+
+    #[test]
+    fn a_year_after_february_29_is_february_28() {
+        let yearly = Recurrence::AfterCompletion(MAX_INTERVAL_DAYS);
+        assert_eq!(next_on_time(&yearly, date(2024, 2, 29)), date(2025, 2, 28));
+        assert_eq!(next_on_time(&yearly, date(2023, 3, 1)), date(2024, 2, 29));
+    }
+
+    #[test]
+    fn weekly_days_wrap_past_sunday() {
+        let weekly = Recurrence::Weekly(vec![Weekday::Mon, Weekday::Fri]);
+        // 2024-06-07 is a Friday.
+        assert_eq!(next_on_time(&weekly, date(2024, 6, 7)), date(2024, 6, 10));
+        assert_eq!(next_on_time(&weekly, date(2024, 6, 9)), date(2024, 6, 10));
+        assert_eq!(next_on_time(&weekly, date(2024, 6, 10)), date(2024, 6, 14));
+
+        let sundays = Recurrence::Weekly(vec![Weekday::Sun]);
+        assert_eq!(next_on_time(&sundays, date(2024, 6, 9)), date(2024, 6, 16));
+        assert_eq!(next_on_time(&sundays, date(2024, 12, 29)), date(2025, 1, 5));
+    }
+
+    #[test]
+    fn late_completion_moves_the_next_date_past_it() {
+        let due = date(2024, 6, 3);
+        let completed_on = date(2024, 6, 20);
+        assert_eq!(
+            Recurrence::Daily.next_due(due, completed_on),
+            date(2024, 6, 21)
+        );
+        assert_eq!(
+            Recurrence::Monthly(5).next_due(due, completed_on),
+            date(2024, 7, 5)
+        );
+        assert_eq!(
+            Recurrence::AfterCompletion(3).next_due(due, completed_on),
+            date(2024, 6, 23)
+        );
+        // Completing early counts from the due date instead.
+        assert_eq!(
+            Recurrence::Daily.next_due(due, date(2024, 6, 1)),
+            date(2024, 6, 4)
+        );
+    }
+
+    #[test]
+    fn rules_round_trip_through_display() {
+        for recurrence in [
+            Recurrence::Daily,
+            Recurrence::Weekly(vec![Weekday::Mon, Weekday::Sun]),
+            Recurrence::Monthly(31),
+            Recurrence::AfterCompletion(MAX_INTERVAL_DAYS),
+        ] {
+            assert_eq!(recurrence.to_string().parse(), Ok(recurrence));
+        }
+        assert_eq!(
+            "BYMONTHDAY=15;FREQ=MONTHLY".parse(),
+            Ok(Recurrence::Monthly(15))
+        );
+    }
+
+    #[test]
+    fn unknown_parts_are_rejected() {
+        for rule in [
+            "",
+            "FREQ",
+            "FREQ=HOURLY",
+            "FREQ=YEARLY;BYMONTH=2;BYMONTHDAY=29",
+            "FREQ=DAILY;COUNT=3",
+            "FREQ=DAILY;INTERVAL=2",
+            "FREQ=WEEKLY;BYDAY=MO;UNTIL=20250101",
+            "FREQ=WEEKLY;BYDAY=MO,XX",
+            "FREQ=WEEKLY;BYDAY=1MO",
+            "freq=daily",
+        ] {
+            assert_eq!(
+                rule.parse::<Recurrence>(),
+                Err(RecurrenceError::Malformed),
+                "{rule}"
+            );
+        }
+    }
+
+    #[test]
+    fn numbers_out_of_range_are_rejected() {
+        assert_eq!(
+            "FREQ=WEEKLY;BYDAY=".parse::<Recurrence>(),
+            Err(RecurrenceError::NoWeekdays)
+        );
+        for day in ["0", "32", "-1", "x"] {
+            assert_eq!(
+                format!("FREQ=MONTHLY;BYMONTHDAY={day}").parse::<Recurrence>(),
+                Err(RecurrenceError::DayOutOfRange)
+            );
+        }
+        assert_eq!(
+            "FREQ=DAILY;INTERVAL=366;FROM=COMPLETION".parse::<Recurrence>(),
+            Err(RecurrenceError::IntervalOutOfRange)
+        );
+    }
+}
@@ -0,0 +1,1444 @@
+use super::{
+    move_to, page_offset, remap, BackupImport, CalendarTodo, RepositoryError, Resource, Snapshot,
+    TodoChanges, TodoInput, TodoRepository, UserCredentials, DEFAULT_LIST_NAME, LAST_LIST_MESSAGE,
+    MOVE_CYCLE_MESSAGE, USERNAME_TAKEN_MESSAGE,
+};
+use crate::server::auth::User;
+use crate::server::backup::ImportReport;
+use crate::server::checklist::ChecklistItem;
+use crate::server::list::List;
+use crate::server::recurrence::Recurrence;
+use crate::server::tag::Tag;
+use crate::server::todo::{PaginatedTodos, Priority, SortOrder, Todo};
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+struct StoredTodo {
+    id: i32,
+    /// `None` for rows from before accounts existed, until someone claims them.
+    owner: Option<i32>,
+    title: String,
+    description: String,
+    completed: bool,
+    completed_at: Option<DateTime<Utc>>,
+    deleted_at: Option<DateTime<Utc>>,
+    created: DateTime<Utc>,
+    due_date: NaiveDate,
+    priority: Priority,
+    tag_ids: Vec<i32>,
+    /// Kept in display order.
+    checklist: Vec<ChecklistItem>,
+    list_id: i32,
+    parent_id: Option<i32>,
+    recurrence: Option<Recurrence>,
+    /// The UID of the iCalendar VTODO it was imported from, if it was.
+    ical_uid: Option<String>,
+}
+
+impl StoredTodo {
+    fn to_todo(&self, tags: &[StoredTag]) -> Todo {
+        let mut tags = tags
+            .iter()
+            .filter(|stored| self.tag_ids.contains(&stored.tag.id))
+            .map(|stored| stored.tag.clone())
+            .collect::<Vec<_>>();
+        tags.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Todo {
+            id: self.id,
+            title: self.title.clone(),
+            description: self.description.clone(),
+            completed: self.completed,
+            completed_at: self
+                .completed_at
+                .map(|at| at.format("%Y-%m-%dT%H:%M:%SZ").to_string()),
+            deleted_at: self
+                .deleted_at
+                .map(|at| at.format("%Y-%m-%dT%H:%M:%SZ").to_string()),
+            created: Some(self.created.format("%Y-%m-%dT%H:%M:%SZ").to_string()),
+            due_date: self.due_date.format("%Y-%m-%d").to_string(),
+            priority: self.priority,
+            tags,
+            checklist: self.checklist.clone(),
+            list_id: self.list_id,
+            parent_id: self.parent_id,
+            recurrence: self.recurrence.clone(),
+            children: Vec::new(),
+        }
+    }
+
+    fn has_tags(&self, tags: &[i32]) -> bool {
+        tags.iter().all(|tag| self.tag_ids.contains(tag))
+    }
+
+    fn in_list(&self, list: Option<i32>) -> bool {
+        list.is_none_or(|list| self.list_id == list)
+    }
+}
+
+struct StoredTag {
+    owner: Option<i32>,
+    tag: Tag,
+}
+
+/// A list without its open-item count, which is worked out when listing.
+struct StoredList {
+    id: i32,
+    owner: Option<i32>,
+    name: String,
+    colour: String,
+}
+
+struct State {
+    next_user_id: i32,
+    users: Vec<UserCredentials>,
+    /// The secret of each user's calendar feed, for those who turned it on.
+    calendar_tokens: HashMap<i32, String>,
+    next_id: i32,
+    todos: Vec<StoredTodo>,
+    next_tag_id: i32,
+    tags: Vec<StoredTag>,
+    next_checklist_id: i32,
+    next_list_id: i32,
+    /// Kept in the user's order.
+    lists: Vec<StoredList>,
+}
+
+impl Default for State {
+    /// An empty store with the default list, like a freshly migrated database.
+    fn default() -> Self {
+        Self {
+            next_user_id: 0,
+            users: Vec::new(),
+            calendar_tokens: HashMap::new(),
+            next_id: 0,
+            todos: Vec::new(),
+            next_tag_id: 0,
+            tags: Vec::new(),
+            next_checklist_id: 0,
+            next_list_id: 1,
+            lists: vec![StoredList {
+                id: 1,
+                owner: None,
+                name: DEFAULT_LIST_NAME.into(),
+                colour: "#6b7280".into(),
+            }],
+        }
+    }
+}
+
+impl State {
+    /// Finds an item of `owner` that isn't in the trash.
+    fn find_mut(&mut self, owner: i32, id: i32) -> Result<&mut StoredTodo, RepositoryError> {
+        self.todos
+            .iter_mut()
+            .find(|todo| todo.id == id && todo.owner == Some(owner) && todo.deleted_at.is_none())
+            .ok_or(RepositoryError::NotFound(id))
+    }
+
+    fn find_tag_mut(&mut self, owner: i32, id: i32) -> Result<&mut Tag, RepositoryError> {
+        self.tags
+            .iter_mut()
+            .find(|stored| stored.tag.id == id && stored.owner == Some(owner))
+            .map(|stored| &mut stored.tag)
+            .ok_or(RepositoryError::TagNotFound(id))
+    }
+
+    fn find_list_mut(&mut self, owner: i32, id: i32) -> Result<&mut StoredList, RepositoryError> {
+        self.lists
+            .iter_mut()
+            .find(|list| list.id == id && list.owner == Some(owner))
+            .ok_or(RepositoryError::ListNotFound(id))
+    }
+
+    /// The lists of `owner`, in the user's order.
+    fn lists_of(&self, owner: i32) -> impl Iterator<Item = &StoredList> {
+        self.lists
+            .iter()
+            .filter(move |list| list.owner == Some(owner))
+    }
+
+    /// The lists of `owner` with their open-item counts, in the user's order.
+    fn lists_with_counts(&self, owner: i32) -> Vec<List> {
+        self.lists_of(owner)
+            .map(|list| List {
+                id: list.id,
+                name: list.name.clone(),
+                colour: list.colour.clone(),
+                open_count: self
+                    .todos
+                    .iter()
+                    .filter(|todo| {
+                        todo.list_id == list.id && !todo.completed && todo.deleted_at.is_none()
+                    })
+                    .count() as u32,
+            })
+            .collect()
+    }
+
+    /// The tags of `owner`, ordered by name.
+    fn tags_of(&self, owner: i32) -> Vec<Tag> {
+        let mut tags = self
+            .tags
+            .iter()
+            .filter(|stored| stored.owner == Some(owner))
+            .map(|stored| stored.tag.clone())
+            .collect::<Vec<_>>();
+        tags.sort_by(|a, b| a.name.cmp(&b.name));
+        tags
+    }
+
+    /// Fails if another list of `owner` already has `name`.
+    fn check_unique_list(
+        &self,
+        owner: i32,
+        id: Option<i32>,
+        name: &str,
+    ) -> Result<(), RepositoryError> {
+        if self
+            .lists_of(owner)
+            .any(|list| Some(list.id) != id && list.name == name)
+        {
+            return Err(RepositoryError::Conflict(
+                "a matching item already exists".into(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// The list a new item goes in: its parent's for a sub-todo of `parent_id`, otherwise
+    /// `list_id`, or the owner's first list if that's `None`.
+    fn target_list(
+        &mut self,
+        owner: i32,
+        list_id: Option<i32>,
+        parent_id: Option<i32>,
+    ) -> Result<i32, RepositoryError> {
+        match (parent_id, list_id) {
+            (Some(parent_id), _) => Ok(self.find_mut(owner, parent_id)?.list_id),
+            (None, Some(list_id)) => Ok(self.find_list_mut(owner, list_id)?.id),
+            (None, None) => self
+                .lists_of(owner)
+                .next()
+                .map(|list| list.id)
+                .ok_or(RepositoryError::ListNotFound(0)),
+        }
+    }
+
+    /// Files item `id` and its sub-todos under `list_id`, first making it a top-level
+    /// item if its parent is in another list. Both must belong to the owner.
+    fn file_under(&mut self, id: i32, list_id: i32) {
+        let parent_id = self
+            .todos
+            .iter()
+            .find(|todo| todo.id == id)
+            .and_then(|todo| todo.parent_id);
+        let parent_elsewhere = self
+            .todos
+            .iter()
+            .any(|todo| Some(todo.id) == parent_id && todo.list_id != list_id);
+        if let Some(todo) = self.todos.iter_mut().find(|todo| todo.id == id) {
+            if parent_elsewhere {
+                todo.parent_id = None;
+            }
+        }
+        self.move_subtree_to_list(id, list_id);
+    }
+
+    /// Files item `id` and all of its sub-todos, trashed ones included, under `list_id`.
+    fn move_subtree_to_list(&mut self, id: i32, list_id: i32) {
+        let mut ids = vec![id];
+        let mut next = 0;
+        while let Some(&parent) = ids.get(next) {
+            ids.extend(
+                self.todos
+                    .iter()
+                    .filter(|todo| todo.parent_id == Some(parent))
+                    .map(|todo| todo.id),
+            );
+            next += 1;
+        }
+        for todo in self.todos.iter_mut() {
+            if ids.contains(&todo.id) {
+                todo.list_id = list_id;
+            }
+        }
+    }
+
+    /// Drops the ids of tags that `owner` doesn't have.
+    fn known_tags(&self, owner: i32, tag_ids: Vec<i32>) -> Vec<i32> {
+        tag_ids
+            .into_iter()
+            .filter(|id| {
+                self.tags
+                    .iter()
+                    .any(|stored| stored.tag.id == *id && stored.owner == Some(owner))
+            })
+            .collect()
+    }
+
+    /// Stores a new open item of `owner` in `list_id`, which must be theirs.
+    fn insert(
+        &mut self,
+        owner: i32,
+        input: TodoInput,
+        list_id: i32,
+        parent_id: Option<i32>,
+    ) -> &mut StoredTodo {
+        self.next_id += 1;
+        let tag_ids = self.known_tags(owner, input.tag_ids);
+        self.todos.push(StoredTodo {
+            id: self.next_id,
+            owner: Some(owner),
+            title: input.title,
+            description: input.description,
+            completed: false,
+            completed_at: None,
+            deleted_at: None,
+            created: Utc::now(),
+            due_date: input.due_date,
+            priority: input.priority,
+            tag_ids,
+            checklist: Vec::new(),
+            list_id,
+            parent_id,
+            recurrence: input.recurrence,
+            ical_uid: None,
+        });
+        self.todos.last_mut().expect("an item was just pushed")
+    }
+
+    /// Fails if another tag of `owner` already has `name`.
+    fn check_unique_tag(
+        &self,
+        owner: i32,
+        id: Option<i32>,
+        name: &str,
+    ) -> Result<(), RepositoryError> {
+        if self.tags.iter().any(|stored| {
+            stored.owner == Some(owner) && Some(stored.tag.id) != id && stored.tag.name == name
+        }) {
+            return Err(RepositoryError::Conflict(
+                "a matching item already exists".into(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// The ids of item `id` and its sub-todos outside the trash, parents first.
+    fn subtree(&self, id: i32) -> Vec<i32> {
+        let mut ids = vec![id];
+        let mut next = 0;
+        while let Some(&parent) = ids.get(next) {
+            ids.extend(
+                self.todos
+                    .iter()
+                    .filter(|todo| todo.parent_id == Some(parent) && todo.deleted_at.is_none())
+                    .map(|todo| todo.id),
+            );
+            next += 1;
+        }
+        ids
+    }
+
+    /// Reopens the completed parents of item `id`, unless the item itself is completed.
+    fn reopen_parents(&mut self, id: i32) {
+        let Some(todo) = self.todos.iter().find(|todo| todo.id == id) else {
+            return;
+        };
+        if todo.completed {
+            return;
+        }
+        let mut parent_id = todo.parent_id;
+        while let Some(parent) =
+            parent_id.and_then(|id| self.todos.iter_mut().find(|todo| todo.id == id))
+        {
+            parent.completed = false;
+            parent.completed_at = None;
+            parent_id = parent.parent_id;
+        }
+    }
+
+    /// Permanently deletes the items in `ids` and everything under them.
+    fn remove(&mut self, mut ids: Vec<i32>) {
+        let mut next = 0;
+        while let Some(&parent) = ids.get(next) {
+            ids.extend(
+                self.todos
+                    .iter()
+                    .filter(|todo| todo.parent_id == Some(parent))
+                    .map(|todo| todo.id),
+            );
+            next += 1;
+        }
+        self.todos.retain(|todo| !ids.contains(&todo.id));
+    }
+
+    /// Copies item `id` as its next occurrence, due on `due_date`, and moves the repeat
+    /// rule to the copy.
+    fn schedule_next(
+        &mut self,
+        owner: i32,
+        id: i32,
+        due_date: NaiveDate,
+    ) -> Result<(), RepositoryError> {
+        let todo = self.find_mut(owner, id)?;
+        let recurrence = todo.recurrence.take();
+        let (title, description, priority, tag_ids, list_id, parent_id) = (
+            todo.title.clone(),
+            todo.description.clone(),
+            todo.priority,
+            todo.tag_ids.clone(),
+            todo.list_id,
+            todo.parent_id,
+        );
+        let texts = todo
+            .checklist
+            .iter()
+            .map(|item| item.text.clone())
+            .collect::<Vec<_>>();
+
+        let checklist = texts
+            .into_iter()
+            .map(|text| {
+                self.next_checklist_id += 1;
+                ChecklistItem {
+                    id: self.next_checklist_id,
+                    text,
+                    done: false,
+                }
+            })
+            .collect();
+        self.next_id += 1;
+        self.todos.push(StoredTodo {
+            id: self.next_id,
+            owner: Some(owner),
+            title,
+            description,
+            completed: false,
+            completed_at: None,
+            deleted_at: None,
+            created: Utc::now(),
+            due_date,
+            priority,
+            tag_ids,
+            checklist,
+            list_id,
+            parent_id,
+            recurrence,
+            ical_uid: None,
+        });
+        Ok(())
+    }
+
+    /// Converts an item, with its sub-todos outside the trash, oldest first.
+    fn to_tree(&self, todo: &StoredTodo) -> Todo {
+        let mut children = self
+            .todos
+            .iter()
+            .filter(|child| child.parent_id == Some(todo.id) && child.deleted_at.is_none())
+            .collect::<Vec<_>>();
+        children.sort_by(|a, b| a.created.cmp(&b.created).then(a.id.cmp(&b.id)));
+
+        let mut tree = todo.to_todo(&self.tags);
+        tree.children = children
+            .into_iter()
+            .map(|child| self.to_tree(child))
+            .collect();
+        tree
+    }
+
+    /// Finds the checklist that holds an item, among those of `owner`'s todo items.
+    fn find_checklist_mut(
+        &mut self,
+        owner: i32,
+        id: i32,
+    ) -> Result<&mut Vec<ChecklistItem>, RepositoryError> {
+        self.todos
+            .iter_mut()
+            .filter(|todo| todo.owner == Some(owner))
+            .map(|todo| &mut todo.checklist)
+            .find(|checklist| checklist.iter().any(|item| item.id == id))
+            .ok_or(RepositoryError::ChecklistItemNotFound(id))
+    }
+
+    fn find_trashed_mut(
+        &mut self,
+        owner: i32,
+        id: i32,
+    ) -> Result<&mut StoredTodo, RepositoryError> {
+        self.todos
+            .iter_mut()
+            .find(|todo| todo.id == id && todo.owner == Some(owner) && todo.deleted_at.is_some())
+            .ok_or(RepositoryError::NotFound(id))
+    }
+}
+
+/// A repository that keeps everything in memory, for tests and demos. Data is lost when
+/// it is dropped.
+#[derive(Default)]
+pub struct InMemoryTodoRepository {
+    state: Mutex<State>,
+}
+
+impl InMemoryTodoRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+fn paginate(todos: Vec<&StoredTodo>, state: &State, page: u32, per_page: u32) -> PaginatedTodos {
+    let total = todos.len() as u32;
+    let items = todos
+        .into_iter()
+        .skip(usize::try_from(page_offset(page, per_page)).unwrap_or(usize::MAX))
+        .take(per_page as usize)
+        .map(|todo| state.to_tree(todo))
+        .collect();
+
+    PaginatedTodos {
+        items,
+        total,
+        page,
+        total_pages: total.div_ceil(per_page),
+    }
+}
+
+#[async_trait]
+impl TodoRepository for InMemoryTodoRepository {
+    async fn create_user(
+        &self,
+        username: String,
+        password_hash: String,
+    ) -> Result<User, RepositoryError> {
+        let mut state = self.state.lock().unwrap();
+
+        if state
+            .users
+            .iter()
+            .any(|credentials| credentials.user.username == username)
+        {
+            return Err(RepositoryError::Conflict(USERNAME_TAKEN_MESSAGE.into()));
+        }
+        state.next_user_id += 1;
+        let user = User {
+            id: state.next_user_id,
+            username,
+        };
+        state.users.push(UserCredentials {
+            user: user.clone(),
+            password_hash,
+        });
+
+        if state.lists_of(user.id).next().is_none() {
+            state.next_list_id += 1;
+            let list = StoredList {
+                id: state.next_list_id,
+                owner: Some(user.id),
+                name: DEFAULT_LIST_NAME.into(),
+                colour: "#6b7280".into(),
+            };
+            state.lists.push(list);
+        }
+        Ok(user)
+    }
+
+    async fn find_user(&self, username: &str) -> Result<Option<UserCredentials>, RepositoryError> {
+        let state = self.state.lock().unwrap();
+
+        Ok(state
+            .users
+            .iter()
+            .find(|credentials| credentials.user.username == username)
+            .cloned())
+    }
+
+    async fn get_user(&self, id: i32) -> Result<Option<User>, RepositoryError> {
+        let state = self.state.lock().unwrap();
+
+        Ok(state
+            .users
+            .iter()
+            .map(|credentials| &credentials.user)
+            .find(|user| user.id == id)
+            .cloned())
+    }
+
+    async fn calendar_token(&self, owner: i32) -> Result<Option<String>, RepositoryError> {
+        let state = self.state.lock().unwrap();
+
+        Ok(state.calendar_tokens.get(&owner).cloned())
+    }
+
+    async fn set_calendar_token(
+        &self,
+        owner: i32,
+        token: Option<String>,
+    ) -> Result<(), RepositoryError> {
+        let mut state = self.state.lock().unwrap();
+
+        match token {
+            Some(token) => state.calendar_tokens.insert(owner, token),
+            None => state.calendar_tokens.remove(&owner),
+        };
+        Ok(())
+    }
+
+    async fn calendar_owner(&self, token: &str) -> Result<Option<i32>, RepositoryError> {
+        let state = self.state.lock().unwrap();
+
+        Ok(state
+            .calendar_tokens
+            .iter()
+            .find(|(_, secret)| *secret == token)
+            .map(|(owner, _)| *owner))
+    }
+
+    async fn owner_of(&self, resource: Resource) -> Result<Option<i32>, RepositoryError> {
+        let state = self.state.lock().unwrap();
+
+        let owner = match resource {
+            Resource::Todo(id) => state
+                .todos
+                .iter()
+                .find(|todo| todo.id == id)
+                .map(|todo| todo.owner),
+            Resource::Tag(id) => state
+                .tags
+                .iter()
+                .find(|stored| stored.tag.id == id)
+                .map(|stored| stored.owner),
+            Resource::List(id) => state
+                .lists
+                .iter()
+                .find(|list| list.id == id)
+                .map(|list| list.owner),
+            Resource::ChecklistItem(id) => state
+                .todos
+                .iter()
+                .find(|todo| todo.checklist.iter().any(|item| item.id == id))
+                .map(|todo| todo.owner),
+        };
+        owner.ok_or(resource.not_found())
+    }
+
+    async fn count_unowned(&self) -> Result<u64, RepositoryError> {
+        let state = self.state.lock().unwrap();
+        Ok(state
+            .todos
+            .iter()
+            .filter(|todo| todo.owner.is_none())
+            .count() as u64)
+    }
+
+    async fn claim_unowned(&self, owner: i32) -> Result<u64, RepositoryError> {
+        let mut state = self.state.lock().unwrap();
+
+        // Names are unique for each user, so namesakes of the owner's tags and lists hand
+        // their items over and go.
+        let merged_tags = state
+            .tags
+            .iter()
+            .filter(|unowned| unowned.owner.is_none())
+            .filter_map(|unowned| {
+                let mine = state
+                    .tags
+                    .iter()
+                    .find(|mine| mine.owner == Some(owner) && mine.tag.name == unowned.tag.name)?;
+                Some((unowned.tag.id, mine.tag.id))
+            })
+            .collect::<HashMap<_, _>>();
+        let merged_lists = state
+            .lists
+            .iter()
+            .filter(|unowned| unowned.owner.is_none())
+            .filter_map(|unowned| {
+                let mine = state
+                    .lists_of(owner)
+                    .find(|mine| mine.name == unowned.name)?;
+                Some((unowned.id, mine.id))
+            })
+            .collect::<HashMap<_, _>>();
+        for todo in &mut state.todos {
+            for tag_id in &mut todo.tag_ids {
+                *tag_id = merged_tags.get(tag_id).copied().unwrap_or(*tag_id);
+            }
+            todo.list_id = merged_lists
+                .get(&todo.list_id)
+                .copied()
+                .unwrap_or(todo.list_id);
+        }
+        state
+            .tags
+            .retain(|stored| !merged_tags.contains_key(&stored.tag.id));
+        state
+            .lists
+            .retain(|list| !merged_lists.contains_key(&list.id));
+
+        // The claimed lists go after the owner's own.
+        let (mut unowned, mut lists): (Vec<_>, Vec<_>) = std::mem::take(&mut state.lists)
+            .into_iter()
+            .partition(|list| list.owner.is_none());
+        unowned.iter_mut().for_each(|list| list.owner = Some(owner));
+        lists.append(&mut unowned);
+        state.lists = lists;
+        state
+            .tags
+            .iter_mut()
+            .filter(|stored| stored.owner.is_none())
+            .for_each(|stored| stored.owner = Some(owner));
+
+        let mut claimed = 0;
+        for todo in state.todos.iter_mut().filter(|todo| todo.owner.is_none()) {
+            todo.owner = Some(owner);
+            claimed += 1;
+        }
+        Ok(claimed)
+    }
+
+    async fn paginate(
+        &self,
+        owner: i32,
+        page: u32,
+        per_page: u32,
+        list: Option<i32>,
+        tags: &[i32],
+        sort: SortOrder,
+    ) -> Result<PaginatedTodos, RepositoryError> {
+        let state = self.state.lock().unwrap();
+
+        let mut open = state
+            .todos
+            .iter()
+            .filter(|todo| {
+                todo.owner == Some(owner)
+                    && !todo.completed
+                    && todo.deleted_at.is_none()
+                    && todo.parent_id.is_none()
+                    && todo.in_list(list)
+                    && todo.has_tags(tags)
+            })
+            .collect::<Vec<_>>();
+        let newest =
+            |a: &&StoredTodo, b: &&StoredTodo| b.created.cmp(&a.created).then(b.id.cmp(&a.id));
+        match sort {
+            SortOrder::Newest => open.sort_by(newest),
+            SortOrder::Priority => open.sort_by(|a, b| {
+                b.priority
+                    .cmp(&a.priority)
+                    .then(a.due_date.cmp(&b.due_date))
+                    .then(newest(a, b))
+            }),
+        }
+
+        Ok(paginate(open, &state, page, per_page))
+    }
+
+    async fn paginate_completed(
+        &self,
+        owner: i32,
+        page: u32,
+        per_page: u32,
+    ) -> Result<PaginatedTodos, RepositoryError> {
+        let state = self.state.lock().unwrap();
+
+        let mut done = state
+            .todos
+            .iter()
+            .filter(|todo| {
+                todo.owner == Some(owner)
+                    && todo.completed
+                    && todo.deleted_at.is_none()
+                    && todo.parent_id.is_none()
+            })
+            .collect::<Vec<_>>();
+        done.sort_by(|a, b| b.completed_at.cmp(&a.completed_at).then(b.id.cmp(&a.id)));
+
+        Ok(paginate(done, &state, page, per_page))
+    }
+
+    async fn create(
+        &self,
+        owner: i32,
+        input: TodoInput,
+        list_id: Option<i32>,
+        parent_id: Option<i32>,
+    ) -> Result<Todo, RepositoryError> {
+        let mut state = self.state.lock().unwrap();
+
+        let list_id = state.target_list(owner, list_id, parent_id)?;
+        let id = state.insert(owner, input, list_id, parent_id).id;
+        let created = state.todos.last().expect("an item was just pushed");
+        let created = created.to_todo(&state.tags);
+        state.reopen_parents(id);
+        Ok(created)
+    }
+
+    async fn create_many(
+        &self,
+        owner: i32,
+        inputs: Vec<TodoInput>,
+        list_id: Option<i32>,
+    ) -> Result<u32, RepositoryError> {
+        let mut state = self.state.lock().unwrap();
+
+        let list_id = state.target_list(owner, list_id, None)?;
+        let created = inputs.len() as u32;
+        for input in inputs {
+            state.insert(owner, input, list_id, None);
+        }
+        Ok(created)
+    }
+
+    async fn create_from_calendar(
+        &self,
+        owner: i32,
+        todos: Vec<CalendarTodo>,
+        list_id: Option<i32>,
+    ) -> Result<Vec<String>, RepositoryError> {
+        let mut state = self.state.lock().unwrap();
+
+        let list_id = state.target_list(owner, list_id, None)?;
+        let mut skipped = Vec::new();
+        for todo in todos {
+            if let Some(uid) = todo.uid.as_ref().filter(|uid| {
+                state
+                    .todos
+                    .iter()
+                    .any(|other| other.owner == Some(owner) && other.ical_uid.as_ref() == Some(uid))
+            }) {
+                skipped.push(uid.clone());
+                continue;
+            }
+            let stored = state.insert(owner, todo.input, list_id, None);
+            stored.completed = todo.completed_at.is_some();
+            stored.completed_at = todo.completed_at;
+            stored.ical_uid = todo.uid;
+        }
+        Ok(skipped)
+    }
+
+    async fn get(&self, owner: i32, id: i32) -> Result<Todo, RepositoryError> {
+        let state = self.state.lock().unwrap();
+
+        let todo = state
+            .todos
+            .iter()
+            .find(|todo| todo.id == id && todo.owner == Some(owner) && todo.deleted_at.is_none())
+            .ok_or(RepositoryError::NotFound(id))?;
+        Ok(state.to_tree(todo))
+    }
+
+    async fn update(&self, owner: i32, id: i32, input: TodoInput) -> Result<(), RepositoryError> {
+        let mut state = self.state.lock().unwrap();
+
+        let tag_ids = state.known_tags(owner, input.tag_ids);
+        let todo = state.find_mut(owner, id)?;
+        todo.title = input.title;
+        todo.description = input.description;
+        todo.due_date = input.due_date;
+        todo.priority = input.priority;
+        todo.tag_ids = tag_ids;
+        todo.recurrence = input.recurrence;
+        Ok(())
+    }
+
+    async fn patch(
+        &self,
+        owner: i32,
+        id: i32,
+        changes: TodoChanges,
+    ) -> Result<(), RepositoryError> {
+        let mut state = self.state.lock().unwrap();
+
+        // Checked before anything changes, as the other backends roll back.
+        state.find_mut(owner, id)?;
+        if let Some(list_id) = changes.list_id {
+            state.find_list_mut(owner, list_id)?;
+        }
+        let tag_ids = changes
+            .tag_ids
+            .map(|tag_ids| state.known_tags(owner, tag_ids));
+
+        let todo = state.find_mut(owner, id)?;
+        if let Some(title) = changes.title {
+            todo.title = title;
+        }
+        if let Some(description) = changes.description {
+            todo.description = description;
+        }
+        if let Some(due_date) = changes.due_date {
+            todo.due_date = due_date;
+        }
+        if let Some(priority) = changes.priority {
+            todo.priority = priority;
+        }
+        if let Some(tag_ids) = tag_ids {
+            todo.tag_ids = tag_ids;
+        }
+        if let Some(recurrence) = changes.recurrence {
+            todo.recurrence = recurrence;
+        }
+        if let Some(list_id) = changes.list_id {
+            state.file_under(id, list_id);
+        }
+        Ok(())
+    }
+
+    async fn complete(
+        &self,
+        owner: i32,
+        id: i32,
+        include_checklist: bool,
+    ) -> Result<(), RepositoryError> {
+        let mut state = self.state.lock().unwrap();
+
+        let now = Utc::now();
+        let todo = state.find_mut(owner, id)?;
+        let next_due = match &todo.recurrence {
+            Some(recurrence) if !todo.completed => {
+                Some(recurrence.next_due(todo.due_date, now.date_naive()))
+            }
+            _ => None,
+        };
+        todo.completed = true;
+        todo.completed_at = Some(now);
+        if include_checklist {
+            todo.checklist.iter_mut().for_each(|item| item.done = true);
+        }
+
+        let subtree = state.subtree(id);
+        for todo in state.todos.iter_mut() {
+            if subtree.contains(&todo.id) && !todo.completed && todo.deleted_at.is_none() {
+                todo.completed = true;
+                todo.completed_at = Some(now);
+            }
+        }
+
+        if let Some(due_date) = next_due {
+            state.schedule_next(owner, id, due_date)?;
+        }
+        Ok(())
+    }
+
+    async fn uncomplete(&self, owner: i32, id: i32) -> Result<(), RepositoryError> {
+        let mut state = self.state.lock().unwrap();
+
+        let todo = state.find_mut(owner, id)?;
+        todo.completed = false;
+        todo.completed_at = None;
+        state.reopen_parents(id);
+        Ok(())
+    }
+
+    async fn delete(&self, owner: i32, id: i32) -> Result<(), RepositoryError> {
+        let mut state = self.state.lock().unwrap();
+
+        state.find_mut(owner, id)?;
+        let now = Utc::now();
+        let subtree = state.subtree(id);
+        for todo in state.todos.iter_mut() {
+            if subtree.contains(&todo.id) && todo.deleted_at.is_none() {
+                todo.deleted_at = Some(now);
+            }
+        }
+        Ok(())
+    }
+
+    async fn paginate_trash(
+        &self,
+        owner: i32,
+        page: u32,
+        per_page: u32,
+    ) -> Result<PaginatedTodos, RepositoryError> {
+        let state = self.state.lock().unwrap();
+
+        let mut trashed = state
+            .todos
+            .iter()
+            .filter(|todo| todo.owner == Some(owner) && todo.deleted_at.is_some())
+            .collect::<Vec<_>>();
+        trashed.sort_by(|a, b| b.deleted_at.cmp(&a.deleted_at).then(b.id.cmp(&a.id)));
+
+        Ok(paginate(trashed, &state, page, per_page))
+    }
+
+    async fn restore(&self, owner: i32, id: i32) -> Result<(), RepositoryError> {
+        let mut state = self.state.lock().unwrap();
+
+        let todo = state.find_trashed_mut(owner, id)?;
+        let deleted_at = todo.deleted_at;
+        let parent_id = todo.parent_id;
+        if state
+            .todos
+            .iter()
+            .any(|todo| Some(todo.id) == parent_id && todo.deleted_at.is_some())
+        {
+            state.find_trashed_mut(owner, id)?.parent_id = None;
+        }
+
+        // Sub-todos trashed along with the item share its deletion time.
+        let mut restored = vec![id];
+        let mut next = 0;
+        while let Some(&parent) = restored.get(next) {
+            restored.extend(
+                state
+                    .todos
+                    .iter()
+                    .filter(|todo| todo.parent_id == Some(parent) && todo.deleted_at == deleted_at)
+                    .map(|todo| todo.id),
+            );
+            next += 1;
+        }
+        for todo in state.todos.iter_mut() {
+            if restored.contains(&todo.id) {
+                todo.deleted_at = None;
+            }
+        }
+        Ok(())
+    }
+
+    async fn purge(&self, owner: i32, id: i32) -> Result<(), RepositoryError> {
+        let mut state = self.state.lock().unwrap();
+
+        state.find_trashed_mut(owner, id)?;
+        state.remove(vec![id]);
+        Ok(())
+    }
+
+    async fn purge_trashed_before(&self, cutoff: DateTime<Utc>) -> Result<u64, RepositoryError> {
+        let mut state = self.state.lock().unwrap();
+
+        let before = state.todos.len();
+        let expired = state
+            .todos
+            .iter()
+            .filter(|todo| todo.deleted_at.is_some_and(|at| at < cutoff))
+            .map(|todo| todo.id)
+            .collect();
+        state.remove(expired);
+        Ok((before - state.todos.len()) as u64)
+    }
+
+    async fn search(
+        &self,
+        owner: i32,
+        query: &str,
+        list: Option<i32>,
+        tags: &[i32],
+    ) -> Result<Vec<Todo>, RepositoryError> {
+        let state = self.state.lock().unwrap();
+
+        // Approximates `plainto_tsquery`: every word of the query must appear in the title.
+        let words = query
+            .split_whitespace()
+            .map(str::to_lowercase)
+            .collect::<Vec<_>>();
+        if words.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let todos = state
+            .todos
+            .iter()
+            .filter(|todo| {
+                todo.owner == Some(owner)
+                    && !todo.completed
+                    && todo.deleted_at.is_none()
+                    && todo.in_list(list)
+                    && todo.has_tags(tags)
+            })
+            .filter(|todo| {
+                let title = todo
+                    .title
+                    .split_whitespace()
+                    .map(str::to_lowercase)
+                    .collect::<Vec<_>>();
+                words.iter().all(|word| title.contains(word))
+            })
+            .map(|todo| todo.to_todo(&state.tags))
+            .collect();
+        Ok(todos)
+    }
+
+    async fn move_todo(
+        &self,
+        owner: i32,
+        id: i32,
+        parent_id: Option<i32>,
+    ) -> Result<(), RepositoryError> {
+        let mut state = self.state.lock().unwrap();
+
+        state.find_mut(owner, id)?;
+        if let Some(parent_id) = parent_id {
+            state.find_mut(owner, parent_id)?;
+            if state.subtree(id).contains(&parent_id) {
+                return Err(RepositoryError::Conflict(MOVE_CYCLE_MESSAGE.into()));
+            }
+
+            let list_id = state.find_mut(owner, parent_id)?.list_id;
+            state.move_subtree_to_list(id, list_id);
+        }
+
+        state.find_mut(owner, id)?.parent_id = parent_id;
+        state.reopen_parents(id);
+        Ok(())
+    }
+
+    async fn move_to_list(&self, owner: i32, id: i32, list_id: i32) -> Result<(), RepositoryError> {
+        let mut state = self.state.lock().unwrap();
+
+        state.find_mut(owner, id)?;
+        state.find_list_mut(owner, list_id)?;
+        state.file_under(id, list_id);
+        Ok(())
+    }
+
+    async fn list_tags(&self, owner: i32) -> Result<Vec<Tag>, RepositoryError> {
+        let state = self.state.lock().unwrap();
+
+        Ok(state.tags_of(owner))
+    }
+
+    async fn create_tag(
+        &self,
+        owner: i32,
+        name: String,
+        colour: String,
+    ) -> Result<Tag, RepositoryError> {
+        let mut state = self.state.lock().unwrap();
+
+        state.check_unique_tag(owner, None, &name)?;
+        state.next_tag_id += 1;
+        let tag = Tag {
+            id: state.next_tag_id,
+            name,
+            colour,
+        };
+        state.tags.push(StoredTag {
+            owner: Some(owner),
+            tag: tag.clone(),
+        });
+        Ok(tag)
+    }
+
+    async fn rename_tag(&self, owner: i32, id: i32, name: String) -> Result<(), RepositoryError> {
+        let mut state = self.state.lock().unwrap();
+
+        state.check_unique_tag(owner, Some(id), &name)?;
+        state.find_tag_mut(owner, id)?.name = name;
+        Ok(())
+    }
+
+    async fn set_tag_colour(
+        &self,
+        owner: i32,
+        id: i32,
+        colour: String,
+    ) -> Result<(), RepositoryError> {
+        let mut state = self.state.lock().unwrap();
+
+        state.find_tag_mut(owner, id)?.colour = colour;
+        Ok(())
+    }
+
+    async fn delete_tag(&self, owner: i32, id: i32) -> Result<(), RepositoryError> {
+        let mut state = self.state.lock().unwrap();
+
+        state.find_tag_mut(owner, id)?;
+        state.tags.retain(|stored| stored.tag.id != id);
+        for todo in &mut state.todos {
+            todo.tag_ids.retain(|tag| *tag != id);
+        }
+        Ok(())
+    }
+
+    async fn list_lists(&self, owner: i32) -> Result<Vec<List>, RepositoryError> {
+        let state = self.state.lock().unwrap();
+
+        Ok(state.lists_with_counts(owner))
+    }
+
+    async fn create_list(
+        &self,
+        owner: i32,
+        name: String,
+        colour: String,
+    ) -> Result<List, RepositoryError> {
+        let mut state = self.state.lock().unwrap();
+
+        state.check_unique_list(owner, None, &name)?;
+        state.next_list_id += 1;
+        let list = StoredList {
+            id: state.next_list_id,
+            owner: Some(owner),
+            name,
+            colour,
+        };
+        let created = List {
+            id: list.id,
+            name: list.name.clone(),
+            colour: list.colour.clone(),
+            open_count: 0,
+        };
+        state.lists.push(list);
+        Ok(created)
+    }
+
+    async fn rename_list(&self, owner: i32, id: i32, name: String) -> Result<(), RepositoryError> {
+        let mut state = self.state.lock().unwrap();
+
+        state.check_unique_list(owner, Some(id), &name)?;
+        state.find_list_mut(owner, id)?.name = name;
+        Ok(())
+    }
+
+    async fn set_list_colour(
+        &self,
+        owner: i32,
+        id: i32,
+        colour: String,
+    ) -> Result<(), RepositoryError> {
+        let mut state = self.state.lock().unwrap();
+
+        state.find_list_mut(owner, id)?.colour = colour;
+        Ok(())
+    }
+
+    async fn move_list(&self, owner: i32, id: i32, position: u32) -> Result<(), RepositoryError> {
+        let mut state = self.state.lock().unwrap();
+
+        state.find_list_mut(owner, id)?;
+        let mut ids = state
+            .lists_of(owner)
+            .map(|list| list.id)
+            .collect::<Vec<_>>();
+        move_to(&mut ids, id, position);
+        state
+            .lists
+            .sort_by_key(|list| ids.iter().position(|id| *id == list.id));
+        Ok(())
+    }
+
+    async fn delete_list(&self, owner: i32, id: i32) -> Result<(), RepositoryError> {
+        let mut state = self.state.lock().unwrap();
+
+        state.find_list_mut(owner, id)?;
+        if state.lists_of(owner).count() == 1 {
+            return Err(RepositoryError::Conflict(LAST_LIST_MESSAGE.into()));
+        }
+        state.lists.retain(|list| list.id != id);
+        let removed = state
+            .todos
+            .iter()
+            .filter(|todo| todo.list_id == id)
+            .map(|todo| todo.id)
+            .collect();
+        state.remove(removed);
+        Ok(())
+    }
+
+    async fn add_checklist_item(
+        &self,
+        owner: i32,
+        todo_id: i32,
+        text: String,
+    ) -> Result<ChecklistItem, RepositoryError> {
+        let mut state = self.state.lock().unwrap();
+
+        state.find_mut(owner, todo_id)?;
+        state.next_checklist_id += 1;
+        let item = ChecklistItem {
+            id: state.next_checklist_id,
+            text,
+            done: false,
+        };
+        state.find_mut(owner, todo_id)?.checklist.push(item.clone());
+        Ok(item)
+    }
+
+    async fn set_checklist_item_done(
+        &self,
+        owner: i32,
+        id: i32,
+        done: bool,
+    ) -> Result<(), RepositoryError> {
+        let mut state = self.state.lock().unwrap();
+
+        let checklist = state.find_checklist_mut(owner, id)?;
+        if let Some(item) = checklist.iter_mut().find(|item| item.id == id) {
+            item.done = done;
+        }
+        Ok(())
+    }
+
+    async fn move_checklist_item(
+        &self,
+        owner: i32,
+        id: i32,
+        position: u32,
+    ) -> Result<(), RepositoryError> {
+        let mut state = self.state.lock().unwrap();
+
+        let checklist = state.find_checklist_mut(owner, id)?;
+        let mut ids = checklist.iter().map(|item| item.id).collect::<Vec<_>>();
+        move_to(&mut ids, id, position);
+        checklist.sort_by_key(|item| ids.iter().position(|id| *id == item.id));
+        Ok(())
+    }
+
+    async fn delete_checklist_item(&self, owner: i32, id: i32) -> Result<(), RepositoryError> {
+        let mut state = self.state.lock().unwrap();
+
+        state
+            .find_checklist_mut(owner, id)?
+            .retain(|item| item.id != id);
+        Ok(())
+    }
+
+    async fn export(&self, owner: i32) -> Result<Snapshot, RepositoryError> {
+        let state = self.state.lock().unwrap();
+
+        let mut todos = state
+            .todos
+            .iter()
+            .filter(|todo| todo.owner == Some(owner))
+            .collect::<Vec<_>>();
+        todos.sort_by(|a, b| a.created.cmp(&b.created).then(a.id.cmp(&b.id)));
+        Ok(Snapshot {
+            lists: state.lists_with_counts(owner),
+            tags: state.tags_of(owner),
+            todos: todos
+                .into_iter()
+                .map(|todo| todo.to_todo(&state.tags))
+                .collect(),
+        })
+    }
+
+    async fn import(
+        &self,
+        owner: i32,
+        backup: BackupImport,
+        dry_run: bool,
+    ) -> Result<ImportReport, RepositoryError> {
+        let mut state = self.state.lock().unwrap();
+        let mut report = backup.report(dry_run);
+
+        // Everything is added at the end, so a failure part way leaves the store as it was.
+        let mut next_list_id = state.next_list_id;
+        let mut new_lists = Vec::new();
+        let mut lists = HashMap::new();
+        for (id, list) in backup.lists {
+            let existing = state
+                .lists_of(owner)
+                .find(|existing| existing.name == list.name)
+                .map(|existing| existing.id);
+            let new_id = match existing {
+                Some(existing) => {
+                    report.merged_lists.push(list.name);
+                    existing
+                }
+                None => {
+                    report.new_lists.push(list.name.clone());
+                    next_list_id += 1;
+                    new_lists.push(StoredList {
+                        id: next_list_id,
+                        owner: Some(owner),
+                        name: list.name,
+                        colour: list.colour,
+                    });
+                    next_list_id
+                }
+            };
+            lists.insert(id, new_id);
+        }
+
+        let mut next_tag_id = state.next_tag_id;
+        let mut new_tags = Vec::new();
+        let mut tags = HashMap::new();
+        for (id, tag) in backup.tags {
+            let existing = state
+                .tags
+                .iter()
+                .find(|stored| stored.owner == Some(owner) && stored.tag.name == tag.name)
+                .map(|stored| stored.tag.id);
+            let new_id = match existing {
+                Some(existing) => {
+                    report.merged_tags.push(tag.name);
+                    existing
+                }
+                None => {
+                    report.new_tags.push(tag.name.clone());
+                    next_tag_id += 1;
+                    new_tags.push(StoredTag {
+                        owner: Some(owner),
+                        tag: Tag {
+                            id: next_tag_id,
+                            name: tag.name,
+                            colour: tag.colour,
+                        },
+                    });
+                    next_tag_id
+                }
+            };
+            tags.insert(id, new_id);
+        }
+
+        let mut next_id = state.next_id;
+        let mut next_checklist_id = state.next_checklist_id;
+        let mut new_todos = Vec::new();
+        let mut todos = HashMap::new();
+        for todo in backup.todos {
+            next_id += 1;
+            todos.insert(todo.id, next_id);
+            let checklist = todo
+                .checklist
+                .into_iter()
+                .map(|item| {
+                    next_checklist_id += 1;
+                    ChecklistItem {
+                        id: next_checklist_id,
+                        text: item.text,
+                        done: item.done,
+                    }
+                })
+                .collect();
+            new_todos.push(StoredTodo {
+                id: next_id,
+                owner: Some(owner),
+                title: todo.input.title,
+                description: todo.input.description,
+                completed: todo.completed,
+                completed_at: todo.completed_at,
+                deleted_at: todo.deleted_at,
+                created: todo.created.unwrap_or_else(Utc::now),
+                due_date: todo.input.due_date,
+                priority: todo.input.priority,
+                tag_ids: todo
+                    .input
+                    .tag_ids
+                    .iter()
+                    .map(|id| remap(&tags, *id))
+                    .collect::<Result<_, _>>()?,
+                checklist,
+                list_id: remap(&lists, todo.list_id)?,
+                parent_id: todo.parent_id.map(|id| remap(&todos, id)).transpose()?,
+                recurrence: todo.input.recurrence,
+                ical_uid: None,
+            });
+        }
+
+        if !dry_run {
+            state.next_list_id = next_list_id;
+            state.lists.extend(new_lists);
+            state.next_tag_id = next_tag_id;
+            state.tags.extend(new_tags);
+            state.next_id = next_id;
+            state.next_checklist_id = next_checklist_id;
+            state.todos.extend(new_todos);
+        }
+        Ok(report)
+    }
+}
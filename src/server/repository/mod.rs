@@ -0,0 +1,530 @@
+//! Storage for todo items, kept behind a trait so the server functions can run against
+//! Postgres, SQLite (with the `sqlite` feature) or an in-memory store.
+
+mod memory;
+mod postgres;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+pub use memory::InMemoryTodoRepository;
+pub use postgres::PgTodoRepository;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteTodoRepository;
+
+use crate::server::auth::User;
+use crate::server::backup::{BackupChecklistItem, ImportReport};
+use crate::server::checklist::ChecklistItem;
+use crate::server::error::TodoError;
+use crate::server::list::List;
+use crate::server::recurrence::Recurrence;
+use crate::server::tag::Tag;
+use crate::server::todo::{PaginatedTodos, Priority, SortOrder, Todo};
+use crate::validation::list::ValidList;
+use crate::validation::tag::ValidTag;
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use leptos::ServerFnError;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// The fields a user provides when creating or editing a todo item.
+#[derive(Clone, Debug)]
+pub struct TodoInput {
+    pub title: String,
+    pub description: String,
+    pub due_date: NaiveDate,
+    pub priority: Priority,
+    /// The tags to attach, replacing any already attached. Unknown ids are ignored.
+    pub tag_ids: Vec<i32>,
+    pub recurrence: Option<Recurrence>,
+}
+
+/// The fields a partial edit changes, each already validated. `None` leaves a field as
+/// it is.
+#[derive(Clone, Debug, Default)]
+pub struct TodoChanges {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub due_date: Option<NaiveDate>,
+    pub priority: Option<Priority>,
+    /// Replaces every attached tag. Unknown ids are ignored.
+    pub tag_ids: Option<Vec<i32>>,
+    /// `Some(None)` stops the item repeating.
+    pub recurrence: Option<Option<Recurrence>>,
+    /// Moves the item and its sub-todos, like [`TodoRepository::move_to_list`].
+    pub list_id: Option<i32>,
+}
+
+/// Everything a user has, as read for a backup.
+#[derive(Clone, Debug)]
+pub struct Snapshot {
+    /// In the user's order.
+    pub lists: Vec<List>,
+    /// Ordered by name.
+    pub tags: Vec<Tag>,
+    /// Every item, completed and trashed ones included, oldest first and without their
+    /// sub-todos filled in.
+    pub todos: Vec<Todo>,
+}
+
+/// A backup that passed validation. Lists, tags and todo items are still named by their
+/// ids in the file, which importing replaces with new ones.
+#[derive(Clone, Debug)]
+pub struct BackupImport {
+    pub lists: Vec<(i32, ValidList)>,
+    pub tags: Vec<(i32, ValidTag)>,
+    /// Parents before their sub-todos.
+    pub todos: Vec<ImportTodo>,
+}
+
+/// A todo item from a backup, with the list, parent and tags it had in the file.
+#[derive(Clone, Debug)]
+pub struct ImportTodo {
+    pub id: i32,
+    pub input: TodoInput,
+    pub list_id: i32,
+    pub parent_id: Option<i32>,
+    pub completed: bool,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    /// `None` to use the time of the import.
+    pub created: Option<DateTime<Utc>>,
+    pub checklist: Vec<BackupChecklistItem>,
+}
+
+/// A todo item read from an iCalendar VTODO.
+#[derive(Clone, Debug)]
+pub struct CalendarTodo {
+    /// The VTODO's UID; items without one are always added.
+    pub uid: Option<String>,
+    pub input: TodoInput,
+    /// When it was completed, if it was.
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+impl BackupImport {
+    /// A report counting the todo items, for the backend to add the lists and tags to.
+    fn report(&self, dry_run: bool) -> ImportReport {
+        ImportReport {
+            dry_run,
+            todos: self.todos.len() as u32,
+            completed: self.todos.iter().filter(|todo| todo.completed).count() as u32,
+            trashed: self
+                .todos
+                .iter()
+                .filter(|todo| todo.deleted_at.is_some())
+                .count() as u32,
+            checklist_items: self
+                .todos
+                .iter()
+                .map(|todo| todo.checklist.len() as u32)
+                .sum(),
+            ..ImportReport::default()
+        }
+    }
+}
+
+/// The new id of whatever had `id` in the backup.
+fn remap(ids: &HashMap<i32, i32>, id: i32) -> Result<i32, RepositoryError> {
+    ids.get(&id).copied().ok_or_else(|| {
+        RepositoryError::Conflict(format!(
+            "the backup refers to {id}, which it doesn't contain"
+        ))
+    })
+}
+
+/// A user along with the hash their password is checked against.
+#[derive(Clone, Debug)]
+pub struct UserCredentials {
+    pub user: User,
+    pub password_hash: String,
+}
+
+/// A row that belongs to a user, named by its id.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Resource {
+    Todo(i32),
+    Tag(i32),
+    List(i32),
+    ChecklistItem(i32),
+}
+
+impl Resource {
+    pub fn id(self) -> i32 {
+        match self {
+            Resource::Todo(id)
+            | Resource::Tag(id)
+            | Resource::List(id)
+            | Resource::ChecklistItem(id) => id,
+        }
+    }
+
+    /// The error for a resource that doesn't exist.
+    pub fn not_found(self) -> RepositoryError {
+        match self {
+            Resource::Todo(id) => RepositoryError::NotFound(id),
+            Resource::Tag(id) => RepositoryError::TagNotFound(id),
+            Resource::List(id) => RepositoryError::ListNotFound(id),
+            Resource::ChecklistItem(id) => RepositoryError::ChecklistItemNotFound(id),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum RepositoryError {
+    /// No todo item has this id.
+    NotFound(i32),
+    /// No tag has this id.
+    TagNotFound(i32),
+    /// No checklist item has this id.
+    ChecklistItemNotFound(i32),
+    /// No list has this id.
+    ListNotFound(i32),
+    /// The store could not be reached.
+    Unavailable,
+    /// The change clashes with existing data, e.g. a uniqueness constraint.
+    Conflict(String),
+    /// The store rejected the operation. The message is for the server log only.
+    Database(String),
+}
+
+impl From<RepositoryError> for TodoError {
+    fn from(e: RepositoryError) -> Self {
+        match e {
+            RepositoryError::NotFound(id) => TodoError::NotFound { id },
+            RepositoryError::TagNotFound(id) => TodoError::TagNotFound { id },
+            RepositoryError::ChecklistItemNotFound(id) => TodoError::ChecklistItemNotFound { id },
+            RepositoryError::ListNotFound(id) => TodoError::ListNotFound { id },
+            RepositoryError::Unavailable => TodoError::Unavailable,
+            RepositoryError::Conflict(message) => TodoError::Conflict { message },
+            RepositoryError::Database(_) => TodoError::Internal,
+        }
+    }
+}
+
+impl From<RepositoryError> for ServerFnError<TodoError> {
+    fn from(e: RepositoryError) -> Self {
+        ServerFnError::WrappedServerError(e.into())
+    }
+}
+
+/// Every method but the user ones, [`TodoRepository::owner_of`],
+/// [`TodoRepository::count_unowned`] and [`TodoRepository::purge_trashed_before`] acts
+/// on behalf of the user `owner`. Todo items, tags, lists and checklist items belonging to
+/// anyone else are treated as if they didn't exist.
+#[async_trait]
+pub trait TodoRepository: Send + Sync {
+    /// Adds a user with their own empty list. Fails with `Conflict` if the username is
+    /// taken.
+    async fn create_user(
+        &self,
+        username: String,
+        password_hash: String,
+    ) -> Result<User, RepositoryError>;
+
+    async fn find_user(&self, username: &str) -> Result<Option<UserCredentials>, RepositoryError>;
+
+    async fn get_user(&self, id: i32) -> Result<Option<User>, RepositoryError>;
+
+    /// The secret in the URL of the user's calendar feed, or `None` if it's turned off.
+    async fn calendar_token(&self, owner: i32) -> Result<Option<String>, RepositoryError>;
+
+    /// Replaces the secret of the user's calendar feed, so the old URL stops working.
+    /// `None` turns the feed off.
+    async fn set_calendar_token(
+        &self,
+        owner: i32,
+        token: Option<String>,
+    ) -> Result<(), RepositoryError>;
+
+    /// The user whose calendar feed has the secret `token`, if anyone's does.
+    async fn calendar_owner(&self, token: &str) -> Result<Option<i32>, RepositoryError>;
+
+    /// The user `resource` belongs to, whether or not it's completed or trashed. `None`
+    /// for rows stored before accounts existed that nobody has taken over yet. Fails with
+    /// the resource's not-found error if it doesn't exist.
+    async fn owner_of(&self, resource: Resource) -> Result<Option<i32>, RepositoryError>;
+
+    /// How many todo items were stored before accounts existed and still belong to nobody.
+    async fn count_unowned(&self) -> Result<u64, RepositoryError>;
+
+    /// Gives `owner` the todo items, tags and lists stored before accounts existed,
+    /// returning how many todo items it took. A tag or list named like one `owner`
+    /// already has is merged into theirs.
+    async fn claim_unowned(&self, owner: i32) -> Result<u64, RepositoryError>;
+
+    /// Returns one page of open top-level todo items in `sort` order, each with its
+    /// sub-todos. Pages start at 0. With `list`, only that list's items are included. If
+    /// `tags` isn't empty, only items carrying every one of them are included.
+    async fn paginate(
+        &self,
+        owner: i32,
+        page: u32,
+        per_page: u32,
+        list: Option<i32>,
+        tags: &[i32],
+        sort: SortOrder,
+    ) -> Result<PaginatedTodos, RepositoryError>;
+
+    /// Creates an item in `list_id`, or in the first list if `None`. As a sub-todo of
+    /// `parent_id` it goes in its parent's list instead. Adding an open sub-todo reopens a
+    /// completed parent.
+    async fn create(
+        &self,
+        owner: i32,
+        input: TodoInput,
+        list_id: Option<i32>,
+        parent_id: Option<i32>,
+    ) -> Result<Todo, RepositoryError>;
+
+    /// Creates a top-level item for each of `inputs` in `list_id`, or in the first list if
+    /// `None`, in one transaction. Returns how many were created.
+    async fn create_many(
+        &self,
+        owner: i32,
+        inputs: Vec<TodoInput>,
+        list_id: Option<i32>,
+    ) -> Result<u32, RepositoryError>;
+
+    /// Creates a top-level item for each of `todos` in `list_id`, or in the first list if
+    /// `None`, in one transaction. Skips those whose UID an item of the user already has,
+    /// trashed ones included, or an earlier one of `todos` has, and returns their UIDs.
+    async fn create_from_calendar(
+        &self,
+        owner: i32,
+        todos: Vec<CalendarTodo>,
+        list_id: Option<i32>,
+    ) -> Result<Vec<String>, RepositoryError>;
+
+    /// Returns an item outside the trash, with its sub-todos.
+    async fn get(&self, owner: i32, id: i32) -> Result<Todo, RepositoryError>;
+
+    async fn update(&self, owner: i32, id: i32, input: TodoInput) -> Result<(), RepositoryError>;
+
+    /// Applies `changes` to an item outside the trash in one transaction, so the fields
+    /// it leaves alone keep whatever they hold when it runs, and a failed move changes
+    /// nothing.
+    async fn patch(&self, owner: i32, id: i32, changes: TodoChanges)
+        -> Result<(), RepositoryError>;
+
+    /// Returns one page of completed top-level todo items, most recently completed first.
+    async fn paginate_completed(
+        &self,
+        owner: i32,
+        page: u32,
+        per_page: u32,
+    ) -> Result<PaginatedTodos, RepositoryError>;
+
+    /// Marks the item and all of its open sub-todos completed and records when. With
+    /// `include_checklist`, every item of its own checklist is ticked off too.
+    ///
+    /// Completing an open item that repeats creates its next occurrence: a copy with the
+    /// next due date, its tags and a fresh checklist. The rule moves to the copy, so
+    /// reopening and completing the old item again doesn't create another.
+    async fn complete(
+        &self,
+        owner: i32,
+        id: i32,
+        include_checklist: bool,
+    ) -> Result<(), RepositoryError>;
+
+    /// Reopens a completed item, clearing its completion time. Completed parents are
+    /// reopened with it.
+    async fn uncomplete(&self, owner: i32, id: i32) -> Result<(), RepositoryError>;
+
+    /// Moves the item and its sub-todos to the trash. Trashed items are left out of every
+    /// other query until they are restored.
+    async fn delete(&self, owner: i32, id: i32) -> Result<(), RepositoryError>;
+
+    /// Returns one page of trashed todo items, most recently trashed first.
+    async fn paginate_trash(
+        &self,
+        owner: i32,
+        page: u32,
+        per_page: u32,
+    ) -> Result<PaginatedTodos, RepositoryError>;
+
+    /// Takes a trashed item back out of the trash, along with the sub-todos trashed with
+    /// it. If its parent is still in the trash it becomes a top-level item.
+    async fn restore(&self, owner: i32, id: i32) -> Result<(), RepositoryError>;
+
+    /// Permanently deletes a trashed item and its sub-todos.
+    async fn purge(&self, owner: i32, id: i32) -> Result<(), RepositoryError>;
+
+    /// Permanently deletes everything trashed before `cutoff`, whoever it belongs to,
+    /// returning how many items were removed.
+    async fn purge_trashed_before(&self, cutoff: DateTime<Utc>) -> Result<u64, RepositoryError>;
+
+    /// Full-text search over the titles of open todo items, filtered by `list` and `tags`
+    /// like [`TodoRepository::paginate`].
+    async fn search(
+        &self,
+        owner: i32,
+        query: &str,
+        list: Option<i32>,
+        tags: &[i32],
+    ) -> Result<Vec<Todo>, RepositoryError>;
+
+    /// Makes the item a sub-todo of `parent_id`, or a top-level item if `None`. Fails
+    /// with `Conflict` if `parent_id` is the item itself or one of its sub-todos. Moving
+    /// an open item reopens its new parents, and the item and its sub-todos join the
+    /// parent's list.
+    async fn move_todo(
+        &self,
+        owner: i32,
+        id: i32,
+        parent_id: Option<i32>,
+    ) -> Result<(), RepositoryError>;
+
+    /// Moves the item and its sub-todos, trashed ones included, to another list. A sub-todo
+    /// whose parent is in a different list becomes a top-level item.
+    async fn move_to_list(&self, owner: i32, id: i32, list_id: i32) -> Result<(), RepositoryError>;
+
+    /// Every tag, ordered by name.
+    async fn list_tags(&self, owner: i32) -> Result<Vec<Tag>, RepositoryError>;
+
+    async fn create_tag(
+        &self,
+        owner: i32,
+        name: String,
+        colour: String,
+    ) -> Result<Tag, RepositoryError>;
+
+    async fn rename_tag(&self, owner: i32, id: i32, name: String) -> Result<(), RepositoryError>;
+
+    async fn set_tag_colour(
+        &self,
+        owner: i32,
+        id: i32,
+        colour: String,
+    ) -> Result<(), RepositoryError>;
+
+    /// Deletes the tag and detaches it from every todo item.
+    async fn delete_tag(&self, owner: i32, id: i32) -> Result<(), RepositoryError>;
+
+    /// Every list with its open-item count, in the user's order.
+    async fn list_lists(&self, owner: i32) -> Result<Vec<List>, RepositoryError>;
+
+    /// Appends a list after the existing ones.
+    async fn create_list(
+        &self,
+        owner: i32,
+        name: String,
+        colour: String,
+    ) -> Result<List, RepositoryError>;
+
+    async fn rename_list(&self, owner: i32, id: i32, name: String) -> Result<(), RepositoryError>;
+
+    async fn set_list_colour(
+        &self,
+        owner: i32,
+        id: i32,
+        colour: String,
+    ) -> Result<(), RepositoryError>;
+
+    /// Moves the list to `position` among the others, counting from 0.
+    async fn move_list(&self, owner: i32, id: i32, position: u32) -> Result<(), RepositoryError>;
+
+    /// Permanently deletes the list and every todo item in it. Fails with `Conflict` if
+    /// it's the only list, since every item needs one.
+    async fn delete_list(&self, owner: i32, id: i32) -> Result<(), RepositoryError>;
+
+    /// Appends an item to the checklist of todo item `todo_id`.
+    async fn add_checklist_item(
+        &self,
+        owner: i32,
+        todo_id: i32,
+        text: String,
+    ) -> Result<ChecklistItem, RepositoryError>;
+
+    async fn set_checklist_item_done(
+        &self,
+        owner: i32,
+        id: i32,
+        done: bool,
+    ) -> Result<(), RepositoryError>;
+
+    /// Moves the checklist item to `position` among its siblings, counting from 0.
+    async fn move_checklist_item(
+        &self,
+        owner: i32,
+        id: i32,
+        position: u32,
+    ) -> Result<(), RepositoryError>;
+
+    async fn delete_checklist_item(&self, owner: i32, id: i32) -> Result<(), RepositoryError>;
+
+    /// Reads everything the user has in one consistent go.
+    async fn export(&self, owner: i32) -> Result<Snapshot, RepositoryError>;
+
+    /// Adds everything in `backup` in one transaction. Its lists and tags are merged into
+    /// the user's own ones with the same name, and everything else gets new ids. With
+    /// `dry_run` the transaction is rolled back, and the report says what would have been
+    /// added.
+    async fn import(
+        &self,
+        owner: i32,
+        backup: BackupImport,
+        dry_run: bool,
+    ) -> Result<ImportReport, RepositoryError>;
+}
+
+/// The number of items before `page`. The product is taken in 64 bits, since it can
+/// overflow `u32`, and saturates at `i64::MAX`, which is past the end of any page.
+fn page_offset(page: u32, per_page: u32) -> i64 {
+    i64::try_from(u64::from(page) * u64::from(per_page)).unwrap_or(i64::MAX)
+}
+
+/// Moves `id` to `position` in `ids`, or to the end if `position` is past it.
+fn move_to(ids: &mut Vec<i32>, id: i32, position: u32) {
+    ids.retain(|other| *other != id);
+    let position = (position as usize).min(ids.len());
+    ids.insert(position, id);
+}
+
+/// Hangs each of `descendants` under its parent, which is either one of `roots` or
+/// another descendant. Keeps the order of `descendants` among siblings.
+fn nest(roots: &mut [Todo], descendants: Vec<Todo>) {
+    let mut by_parent = HashMap::<i32, Vec<Todo>>::new();
+    for todo in descendants {
+        if let Some(parent_id) = todo.parent_id {
+            by_parent.entry(parent_id).or_default().push(todo);
+        }
+    }
+
+    fn fill(todo: &mut Todo, by_parent: &mut HashMap<i32, Vec<Todo>>) {
+        if let Some(children) = by_parent.remove(&todo.id) {
+            todo.children = children;
+            for child in &mut todo.children {
+                fill(child, by_parent);
+            }
+        }
+    }
+    for root in roots {
+        fill(root, &mut by_parent);
+    }
+}
+
+/// The message for moving an item under itself or one of its sub-todos.
+const MOVE_CYCLE_MESSAGE: &str = "a todo item can't be moved under itself or its sub-todos";
+
+/// The message for deleting the only list.
+const LAST_LIST_MESSAGE: &str = "the last list can't be deleted";
+
+/// The list the in-memory store starts with, like the one the migrations create, and
+/// the one every new user gets.
+const DEFAULT_LIST_NAME: &str = "Inbox";
+
+/// The message for signing up with a username someone already has.
+const USERNAME_TAKEN_MESSAGE: &str = "that username is taken";
+
+/// The repository handed to server functions through context.
+pub type SharedTodoRepository = Arc<dyn TodoRepository>;
+
+/// Fetches the repository provided with `leptos_routes_with_context`.
+pub fn use_repository() -> Result<SharedTodoRepository, TodoError> {
+    leptos::use_context::<SharedTodoRepository>().ok_or_else(|| {
+        log::error!("todo repository missing from context");
+        TodoError::Internal
+    })
+}
@@ -0,0 +1,1459 @@
+use super::{
+    move_to, nest, page_offset, remap, BackupImport, CalendarTodo, RepositoryError, Resource,
+    Snapshot, TodoChanges, TodoInput, TodoRepository, UserCredentials, DEFAULT_LIST_NAME,
+    LAST_LIST_MESSAGE, MOVE_CYCLE_MESSAGE, USERNAME_TAKEN_MESSAGE,
+};
+use crate::server::auth::User;
+use crate::server::backup::ImportReport;
+use crate::server::checklist::ChecklistItem;
+use crate::server::list::List;
+use crate::server::recurrence::Recurrence;
+use crate::server::tag::Tag;
+use crate::server::todo::{PaginatedTodos, Priority, SortOrder, Todo};
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use deadpool_postgres::{Object, Pool};
+use std::collections::HashMap;
+use tokio_postgres::error::SqlState;
+use tokio_postgres::{GenericClient, IsolationLevel, Row};
+
+const TODO_COLUMNS: &str = "id, title, description, to_char(due_date, 'YYYY-MM-DD'), completed, \
+    to_char(completed_at AT TIME ZONE 'UTC', 'YYYY-MM-DD\"T\"HH24:MI:SS\"Z\"'), \
+    to_char(deleted_at AT TIME ZONE 'UTC', 'YYYY-MM-DD\"T\"HH24:MI:SS\"Z\"'), priority, parent_id, recurrence, list_id, \
+    to_char(created AT TIME ZONE 'UTC', 'YYYY-MM-DD\"T\"HH24:MI:SS\"Z\"')";
+
+/// Looks up who `resource` belongs to, given its id as `$1`.
+fn owner_statement(resource: Resource) -> &'static str {
+    match resource {
+        Resource::Todo(_) => "SELECT owner_id FROM todos WHERE id = $1",
+        Resource::Tag(_) => "SELECT owner_id FROM tags WHERE id = $1",
+        Resource::List(_) => "SELECT owner_id FROM lists WHERE id = $1",
+        Resource::ChecklistItem(_) => {
+            "SELECT todos.owner_id FROM checklist_items \
+                JOIN todos ON todos.id = checklist_items.todo_id WHERE checklist_items.id = $1"
+        }
+    }
+}
+
+/// Starts a statement with `subtree`, the ids of item `$1` and its sub-todos outside the
+/// trash.
+const SUBTREE: &str = "WITH RECURSIVE subtree(id) AS (SELECT $1::int \
+    UNION SELECT todos.id FROM todos JOIN subtree ON todos.parent_id = subtree.id \
+    WHERE todos.deleted_at IS NULL)";
+
+/// Reopens the completed parents of item `$1`, unless the item itself is completed.
+const REOPEN_PARENTS: &str = "WITH RECURSIVE parents(id) AS (\
+        SELECT parent_id FROM todos WHERE id = $1 \
+        UNION SELECT todos.parent_id FROM todos JOIN parents ON todos.id = parents.id) \
+    UPDATE todos SET completed = false, completed_at = NULL \
+    WHERE id IN (SELECT id FROM parents) AND completed \
+    AND NOT (SELECT completed FROM todos WHERE id = $1)";
+
+/// Files item `$1` and all of its sub-todos, trashed ones included, under list `$2`.
+const MOVE_SUBTREE_TO_LIST: &str = "WITH RECURSIVE subtree(id) AS (SELECT $1::int \
+    UNION SELECT todos.id FROM todos JOIN subtree ON todos.parent_id = subtree.id) \
+    UPDATE todos SET list_id = $2 WHERE id IN (SELECT id FROM subtree)";
+
+const TAG_COLUMNS: &str = "tags.id, tags.name, tags.colour";
+
+/// Every list of user `$1` with its open-item count, in the user's order.
+const LISTS: &str = "SELECT lists.id, lists.name, lists.colour, count(todos.id) FROM lists \
+    LEFT JOIN todos ON todos.list_id = lists.id AND NOT todos.completed AND todos.deleted_at IS NULL \
+    WHERE lists.owner_id = $1 GROUP BY lists.id ORDER BY lists.position, lists.id";
+
+pub struct PgTodoRepository {
+    pool: Pool,
+}
+
+impl PgTodoRepository {
+    pub fn new(pool: Pool) -> Self {
+        Self { pool }
+    }
+
+    async fn client(&self) -> Result<Object, RepositoryError> {
+        self.pool.get().await.map_err(|e| {
+            log::error!("failed to check out a database connection: {e}");
+            RepositoryError::Unavailable
+        })
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn paginate_where(
+        &self,
+        owner: i32,
+        filter: &str,
+        order: &str,
+        page: u32,
+        per_page: u32,
+        list: Option<i32>,
+        tags: &[i32],
+    ) -> Result<PaginatedTodos, RepositoryError> {
+        let client = self.client().await?;
+
+        let limit = per_page as i64;
+        let offset = page_offset(page, per_page);
+        let stmt = format!(
+            "SELECT {TODO_COLUMNS} FROM todos WHERE owner_id = $5 AND {filter} AND {} AND {} ORDER BY {order} LIMIT $1 OFFSET $2",
+            list_filter(4),
+            tag_filter(3)
+        );
+        let mut items = client
+            .query(&stmt, &[&limit, &offset, &tags, &list, &owner])
+            .await
+            .map_err(db_error)?
+            .iter()
+            .map(todo_from_row)
+            .collect::<Vec<_>>();
+        attach_details(&**client, &mut items).await?;
+        attach_children(&**client, &mut items).await?;
+
+        let stmt = format!(
+            "SELECT count(1) FROM todos WHERE owner_id = $3 AND {filter} AND {} AND {}",
+            list_filter(2),
+            tag_filter(1)
+        );
+        let total = client
+            .query_one(&stmt, &[&tags, &list, &owner])
+            .await
+            .map_err(db_error)?
+            .get::<usize, i64>(0) as u32;
+
+        Ok(PaginatedTodos {
+            items,
+            total,
+            page,
+            total_pages: total.div_ceil(per_page),
+        })
+    }
+}
+
+fn db_error(e: tokio_postgres::Error) -> RepositoryError {
+    if e.code() == Some(&SqlState::UNIQUE_VIOLATION) {
+        return RepositoryError::Conflict("a matching item already exists".into());
+    }
+    log::error!("database error: {e}");
+    RepositoryError::Database(e.to_string())
+}
+
+fn todo_from_row(row: &Row) -> Todo {
+    Todo {
+        id: row.get(0),
+        title: row.get(1),
+        description: row.get(2),
+        completed: row.get(4),
+        completed_at: row.get(5),
+        deleted_at: row.get(6),
+        created: row.get(11),
+        due_date: row.get(3),
+        priority: Priority::from_level(row.get(7)),
+        tags: Vec::new(),
+        checklist: Vec::new(),
+        parent_id: row.get(8),
+        recurrence: row
+            .get::<_, Option<String>>(9)
+            .and_then(|rule| rule.parse().ok()),
+        list_id: row.get(10),
+        children: Vec::new(),
+    }
+}
+
+fn list_from_row(row: &Row) -> List {
+    List {
+        id: row.get(0),
+        name: row.get(1),
+        colour: row.get(2),
+        open_count: row.get::<_, i64>(3) as u32,
+    }
+}
+
+fn user_from_row(row: &Row) -> User {
+    User {
+        id: row.get(0),
+        username: row.get(1),
+    }
+}
+
+fn tag_from_row(row: &Row) -> Tag {
+    Tag {
+        id: row.get(0),
+        name: row.get(1),
+        colour: row.get(2),
+    }
+}
+
+/// Maps "no rows affected" to `NotFound`.
+fn expect_one(id: i32, affected: u64) -> Result<(), RepositoryError> {
+    if affected == 0 {
+        Err(RepositoryError::NotFound(id))
+    } else {
+        Ok(())
+    }
+}
+
+/// Matches todo items in the list given by the `int` parameter `$param`, or every item if
+/// it's null.
+fn list_filter(param: usize) -> String {
+    format!("(${param}::int IS NULL OR list_id = ${param})")
+}
+
+/// Matches todo items carrying every tag in the `int[]` parameter `$param`, or every
+/// item if the array is empty. The array must not contain duplicates.
+fn tag_filter(param: usize) -> String {
+    format!(
+        "(cardinality(${param}::int[]) = 0 OR id IN (\
+            SELECT todo_id FROM todo_tags WHERE tag_id = ANY(${param}) \
+            GROUP BY todo_id HAVING count(*) = cardinality(${param})))"
+    )
+}
+
+/// Fills in the tags of each todo item with one query.
+async fn attach_tags(
+    client: &impl GenericClient,
+    todos: &mut [Todo],
+) -> Result<(), RepositoryError> {
+    if todos.is_empty() {
+        return Ok(());
+    }
+
+    let ids = todos.iter().map(|todo| todo.id).collect::<Vec<_>>();
+    let stmt = format!(
+        "SELECT {TAG_COLUMNS}, todo_tags.todo_id FROM todo_tags JOIN tags ON tags.id = todo_tags.tag_id \
+         WHERE todo_tags.todo_id = ANY($1) ORDER BY tags.name"
+    );
+    for row in client.query(&stmt, &[&ids]).await.map_err(db_error)? {
+        let todo_id: i32 = row.get(3);
+        if let Some(todo) = todos.iter_mut().find(|todo| todo.id == todo_id) {
+            todo.tags.push(tag_from_row(&row));
+        }
+    }
+    Ok(())
+}
+
+/// Fills in the checklist of each todo item with one query.
+async fn attach_checklists(
+    client: &impl GenericClient,
+    todos: &mut [Todo],
+) -> Result<(), RepositoryError> {
+    if todos.is_empty() {
+        return Ok(());
+    }
+
+    let ids = todos.iter().map(|todo| todo.id).collect::<Vec<_>>();
+    let stmt = "SELECT id, text, done, todo_id FROM checklist_items WHERE todo_id = ANY($1) ORDER BY position, id";
+    for row in client.query(stmt, &[&ids]).await.map_err(db_error)? {
+        let todo_id: i32 = row.get(3);
+        if let Some(todo) = todos.iter_mut().find(|todo| todo.id == todo_id) {
+            todo.checklist.push(checklist_item_from_row(&row));
+        }
+    }
+    Ok(())
+}
+
+/// Fills in everything stored alongside each todo item.
+async fn attach_details(
+    client: &impl GenericClient,
+    todos: &mut [Todo],
+) -> Result<(), RepositoryError> {
+    attach_tags(client, todos).await?;
+    attach_checklists(client, todos).await
+}
+
+fn checklist_item_from_row(row: &Row) -> ChecklistItem {
+    ChecklistItem {
+        id: row.get(0),
+        text: row.get(1),
+        done: row.get(2),
+    }
+}
+
+/// Maps "no rows affected" to `ChecklistItemNotFound`.
+fn expect_checklist_item(id: i32, affected: u64) -> Result<(), RepositoryError> {
+    if affected == 0 {
+        Err(RepositoryError::ChecklistItemNotFound(id))
+    } else {
+        Ok(())
+    }
+}
+
+/// Fills in the sub-todos of each todo item, all the way down.
+async fn attach_children(
+    client: &impl GenericClient,
+    todos: &mut [Todo],
+) -> Result<(), RepositoryError> {
+    if todos.is_empty() {
+        return Ok(());
+    }
+
+    let ids = todos.iter().map(|todo| todo.id).collect::<Vec<_>>();
+    let stmt = format!(
+        "WITH RECURSIVE subtree(id) AS (\
+            SELECT id FROM todos WHERE parent_id = ANY($1) AND deleted_at IS NULL \
+            UNION SELECT todos.id FROM todos JOIN subtree ON todos.parent_id = subtree.id \
+            WHERE todos.deleted_at IS NULL) \
+         SELECT {TODO_COLUMNS} FROM todos WHERE id IN (SELECT id FROM subtree) ORDER BY created, id"
+    );
+    let mut descendants = client
+        .query(&stmt, &[&ids])
+        .await
+        .map_err(db_error)?
+        .iter()
+        .map(todo_from_row)
+        .collect::<Vec<_>>();
+    attach_details(client, &mut descendants).await?;
+    nest(todos, descendants);
+    Ok(())
+}
+
+/// Copies item `id` as its next occurrence, due on `due_date`, and moves the repeat rule
+/// to the copy.
+async fn schedule_next(
+    client: &impl GenericClient,
+    id: i32,
+    due_date: NaiveDate,
+) -> Result<(), RepositoryError> {
+    let stmt = "INSERT INTO todos(title, description, due_date, priority, parent_id, recurrence, list_id, owner_id) \
+        SELECT title, description, $2, priority, parent_id, recurrence, list_id, owner_id FROM todos WHERE id = $1 \
+        RETURNING id";
+    let next: i32 = client
+        .query_one(stmt, &[&id, &due_date])
+        .await
+        .map_err(db_error)?
+        .get(0);
+
+    let stmt = "INSERT INTO todo_tags(todo_id, tag_id) SELECT $2, tag_id FROM todo_tags WHERE todo_id = $1";
+    client
+        .execute(stmt, &[&id, &next])
+        .await
+        .map_err(db_error)?;
+    let stmt = "INSERT INTO checklist_items(todo_id, position, text) \
+        SELECT $2, position, text FROM checklist_items WHERE todo_id = $1";
+    client
+        .execute(stmt, &[&id, &next])
+        .await
+        .map_err(db_error)?;
+    client
+        .execute("UPDATE todos SET recurrence = NULL WHERE id = $1", &[&id])
+        .await
+        .map_err(db_error)?;
+    Ok(())
+}
+
+/// Fails with `NotFound` unless `owner` has item `id` outside the trash.
+async fn expect_todo(
+    client: &impl GenericClient,
+    owner: i32,
+    id: i32,
+) -> Result<(), RepositoryError> {
+    let stmt = "SELECT 1 FROM todos WHERE id = $1 AND owner_id = $2 AND deleted_at IS NULL";
+    match client
+        .query_opt(stmt, &[&id, &owner])
+        .await
+        .map_err(db_error)?
+    {
+        Some(_) => Ok(()),
+        None => Err(RepositoryError::NotFound(id)),
+    }
+}
+
+/// The list a new item goes in: its parent's for a sub-todo of `parent_id`, otherwise
+/// `list_id`, or the owner's first list if that's `None`.
+async fn target_list(
+    client: &impl GenericClient,
+    owner: i32,
+    list_id: Option<i32>,
+    parent_id: Option<i32>,
+) -> Result<i32, RepositoryError> {
+    if let Some(parent_id) = parent_id {
+        let stmt =
+            "SELECT list_id FROM todos WHERE id = $1 AND owner_id = $2 AND deleted_at IS NULL";
+        return client
+            .query_opt(stmt, &[&parent_id, &owner])
+            .await
+            .map_err(db_error)?
+            .map(|row| row.get(0))
+            .ok_or(RepositoryError::NotFound(parent_id));
+    }
+
+    let stmt = "SELECT id FROM lists WHERE owner_id = $2 AND ($1::int IS NULL OR id = $1) \
+        ORDER BY position, id LIMIT 1";
+    client
+        .query_opt(stmt, &[&list_id, &owner])
+        .await
+        .map_err(db_error)?
+        .map(|row| row.get(0))
+        .ok_or(RepositoryError::ListNotFound(list_id.unwrap_or_default()))
+}
+
+/// Locks every list of `owner` and returns their ids in the user's order, failing with
+/// `ListNotFound` unless `id` is one of them.
+async fn lock_lists(
+    client: &impl GenericClient,
+    owner: i32,
+    id: i32,
+) -> Result<Vec<i32>, RepositoryError> {
+    let stmt = "SELECT id FROM lists WHERE owner_id = $1 ORDER BY position, id FOR UPDATE";
+    let ids = client
+        .query(stmt, &[&owner])
+        .await
+        .map_err(db_error)?
+        .iter()
+        .map(|row| row.get::<usize, i32>(0))
+        .collect::<Vec<_>>();
+    if !ids.contains(&id) {
+        return Err(RepositoryError::ListNotFound(id));
+    }
+    Ok(ids)
+}
+
+/// Maps "no rows affected" to `ListNotFound`.
+fn expect_list(id: i32, affected: u64) -> Result<(), RepositoryError> {
+    if affected == 0 {
+        Err(RepositoryError::ListNotFound(id))
+    } else {
+        Ok(())
+    }
+}
+
+/// Moves an item of `owner` and its sub-todos to `list_id`, detaching it from a parent
+/// in another list.
+async fn move_subtree_to_list(
+    client: &impl GenericClient,
+    owner: i32,
+    id: i32,
+    list_id: i32,
+) -> Result<(), RepositoryError> {
+    expect_todo(client, owner, id).await?;
+    let stmt = "SELECT 1 FROM lists WHERE id = $1 AND owner_id = $2";
+    if client
+        .query_opt(stmt, &[&list_id, &owner])
+        .await
+        .map_err(db_error)?
+        .is_none()
+    {
+        return Err(RepositoryError::ListNotFound(list_id));
+    }
+
+    let stmt = "UPDATE todos SET parent_id = NULL WHERE id = $1 \
+        AND parent_id IN (SELECT id FROM todos WHERE list_id <> $2)";
+    client
+        .execute(stmt, &[&id, &list_id])
+        .await
+        .map_err(db_error)?;
+    client
+        .execute(MOVE_SUBTREE_TO_LIST, &[&id, &list_id])
+        .await
+        .map_err(db_error)?;
+    Ok(())
+}
+
+/// Replaces the tags attached to a todo item with those of `tag_ids` that `owner` has.
+async fn set_tags(
+    client: &impl GenericClient,
+    owner: i32,
+    id: i32,
+    tag_ids: &[i32],
+) -> Result<(), RepositoryError> {
+    client
+        .execute("DELETE FROM todo_tags WHERE todo_id = $1", &[&id])
+        .await
+        .map_err(db_error)?;
+    client
+        .execute(
+            "INSERT INTO todo_tags(todo_id, tag_id) SELECT $1, id FROM tags WHERE id = ANY($2) AND owner_id = $3",
+            &[&id, &tag_ids, &owner],
+        )
+        .await
+        .map_err(db_error)?;
+    Ok(())
+}
+
+/// Inserts an open item of `owner` in `list_id`, which must be theirs, with its tags.
+async fn insert_todo(
+    client: &impl GenericClient,
+    owner: i32,
+    input: &TodoInput,
+    list_id: i32,
+    parent_id: Option<i32>,
+) -> Result<Todo, RepositoryError> {
+    let stmt = format!(
+        "INSERT INTO todos(title, description, due_date, priority, parent_id, recurrence, list_id, owner_id) VALUES($1, $2, $3, $4, $5, $6, $7, $8) RETURNING {TODO_COLUMNS}"
+    );
+    let row = client
+        .query_one(
+            &stmt,
+            &[
+                &input.title,
+                &input.description,
+                &input.due_date,
+                &input.priority.level(),
+                &parent_id,
+                &input.recurrence.as_ref().map(ToString::to_string),
+                &list_id,
+                &owner,
+            ],
+        )
+        .await
+        .map_err(db_error)?;
+    let todo = todo_from_row(&row);
+    set_tags(client, owner, todo.id, &input.tag_ids).await?;
+    Ok(todo)
+}
+
+/// Maps "no rows affected" to `TagNotFound`.
+fn expect_tag(id: i32, affected: u64) -> Result<(), RepositoryError> {
+    if affected == 0 {
+        Err(RepositoryError::TagNotFound(id))
+    } else {
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl TodoRepository for PgTodoRepository {
+    async fn create_user(
+        &self,
+        username: String,
+        password_hash: String,
+    ) -> Result<User, RepositoryError> {
+        let mut client = self.client().await?;
+        let tx = client.transaction().await.map_err(db_error)?;
+
+        let stmt =
+            "INSERT INTO users(username, password_hash) VALUES($1, $2) RETURNING id, username";
+        let row = tx
+            .query_one(stmt, &[&username, &password_hash])
+            .await
+            .map_err(|e| match e.code() {
+                Some(&SqlState::UNIQUE_VIOLATION) => {
+                    RepositoryError::Conflict(USERNAME_TAKEN_MESSAGE.into())
+                }
+                _ => db_error(e),
+            })?;
+        let user = user_from_row(&row);
+
+        let stmt = "INSERT INTO lists(name, position, owner_id) SELECT $1, 0, $2 \
+            WHERE NOT EXISTS (SELECT 1 FROM lists WHERE owner_id = $2)";
+        tx.execute(stmt, &[&DEFAULT_LIST_NAME, &user.id])
+            .await
+            .map_err(db_error)?;
+        tx.commit().await.map_err(db_error)?;
+        Ok(user)
+    }
+
+    async fn find_user(&self, username: &str) -> Result<Option<UserCredentials>, RepositoryError> {
+        let client = self.client().await?;
+
+        let stmt = "SELECT id, username, password_hash FROM users WHERE username = $1";
+        let row = client
+            .query_opt(stmt, &[&username])
+            .await
+            .map_err(db_error)?;
+        Ok(row.map(|row| UserCredentials {
+            user: user_from_row(&row),
+            password_hash: row.get(2),
+        }))
+    }
+
+    async fn get_user(&self, id: i32) -> Result<Option<User>, RepositoryError> {
+        let client = self.client().await?;
+
+        let stmt = "SELECT id, username FROM users WHERE id = $1";
+        let row = client.query_opt(stmt, &[&id]).await.map_err(db_error)?;
+        Ok(row.as_ref().map(user_from_row))
+    }
+
+    async fn calendar_token(&self, owner: i32) -> Result<Option<String>, RepositoryError> {
+        let client = self.client().await?;
+
+        let stmt = "SELECT calendar_token FROM users WHERE id = $1";
+        let row = client.query_opt(stmt, &[&owner]).await.map_err(db_error)?;
+        Ok(row.and_then(|row| row.get(0)))
+    }
+
+    async fn set_calendar_token(
+        &self,
+        owner: i32,
+        token: Option<String>,
+    ) -> Result<(), RepositoryError> {
+        let client = self.client().await?;
+
+        let stmt = "UPDATE users SET calendar_token = $1 WHERE id = $2";
+        client
+            .execute(stmt, &[&token, &owner])
+            .await
+            .map_err(db_error)?;
+        Ok(())
+    }
+
+    async fn calendar_owner(&self, token: &str) -> Result<Option<i32>, RepositoryError> {
+        let client = self.client().await?;
+
+        let stmt = "SELECT id FROM users WHERE calendar_token = $1";
+        let row = client.query_opt(stmt, &[&token]).await.map_err(db_error)?;
+        Ok(row.map(|row| row.get(0)))
+    }
+
+    async fn owner_of(&self, resource: Resource) -> Result<Option<i32>, RepositoryError> {
+        let client = self.client().await?;
+
+        let row = client
+            .query_opt(owner_statement(resource), &[&resource.id()])
+            .await
+            .map_err(db_error)?
+            .ok_or(resource.not_found())?;
+        Ok(row.get(0))
+    }
+
+    async fn count_unowned(&self) -> Result<u64, RepositoryError> {
+        let client = self.client().await?;
+
+        let stmt = "SELECT count(*) FROM todos WHERE owner_id IS NULL";
+        let count: i64 = client.query_one(stmt, &[]).await.map_err(db_error)?.get(0);
+        Ok(count as u64)
+    }
+
+    async fn claim_unowned(&self, owner: i32) -> Result<u64, RepositoryError> {
+        let mut client = self.client().await?;
+        let tx = client.transaction().await.map_err(db_error)?;
+
+        // Names are unique for each user, so namesakes of the owner's tags and lists hand
+        // their items over and go.
+        let merges = [
+            "UPDATE todo_tags SET tag_id = mine.id FROM tags unowned, tags mine \
+             WHERE todo_tags.tag_id = unowned.id AND unowned.owner_id IS NULL \
+             AND mine.owner_id = $1 AND mine.name = unowned.name",
+            "DELETE FROM tags WHERE owner_id IS NULL \
+             AND name IN (SELECT name FROM tags WHERE owner_id = $1)",
+            "UPDATE todos SET list_id = mine.id FROM lists unowned, lists mine \
+             WHERE todos.list_id = unowned.id AND unowned.owner_id IS NULL \
+             AND mine.owner_id = $1 AND mine.name = unowned.name",
+            "DELETE FROM lists WHERE owner_id IS NULL \
+             AND name IN (SELECT name FROM lists WHERE owner_id = $1)",
+        ];
+        for stmt in merges {
+            tx.execute(stmt, &[&owner]).await.map_err(db_error)?;
+        }
+
+        // The claimed lists go after the owner's own.
+        let stmt = "SELECT coalesce(max(position) + 1, 0) FROM lists WHERE owner_id = $1";
+        let next_position: i32 = tx
+            .query_one(stmt, &[&owner])
+            .await
+            .map_err(db_error)?
+            .get(0);
+        let stmt =
+            "UPDATE lists SET owner_id = $1, position = position + $2 WHERE owner_id IS NULL";
+        tx.execute(stmt, &[&owner, &next_position])
+            .await
+            .map_err(db_error)?;
+        let stmt = "UPDATE tags SET owner_id = $1 WHERE owner_id IS NULL";
+        tx.execute(stmt, &[&owner]).await.map_err(db_error)?;
+        let stmt = "UPDATE todos SET owner_id = $1 WHERE owner_id IS NULL";
+        let claimed = tx.execute(stmt, &[&owner]).await.map_err(db_error)?;
+
+        tx.commit().await.map_err(db_error)?;
+        Ok(claimed)
+    }
+
+    async fn paginate(
+        &self,
+        owner: i32,
+        page: u32,
+        per_page: u32,
+        list: Option<i32>,
+        tags: &[i32],
+        sort: SortOrder,
+    ) -> Result<PaginatedTodos, RepositoryError> {
+        let order = match sort {
+            SortOrder::Newest => "created DESC",
+            SortOrder::Priority => "priority DESC, due_date, created DESC",
+        };
+        self.paginate_where(
+            owner,
+            "completed = false AND deleted_at IS NULL AND parent_id IS NULL",
+            order,
+            page,
+            per_page,
+            list,
+            tags,
+        )
+        .await
+    }
+
+    async fn paginate_completed(
+        &self,
+        owner: i32,
+        page: u32,
+        per_page: u32,
+    ) -> Result<PaginatedTodos, RepositoryError> {
+        self.paginate_where(
+            owner,
+            "completed = true AND deleted_at IS NULL AND parent_id IS NULL",
+            "completed_at DESC NULLS LAST, id DESC",
+            page,
+            per_page,
+            None,
+            &[],
+        )
+        .await
+    }
+
+    async fn create(
+        &self,
+        owner: i32,
+        input: TodoInput,
+        list_id: Option<i32>,
+        parent_id: Option<i32>,
+    ) -> Result<Todo, RepositoryError> {
+        let mut client = self.client().await?;
+        let tx = client.transaction().await.map_err(db_error)?;
+
+        let list_id = target_list(&*tx, owner, list_id, parent_id).await?;
+        let mut todo = insert_todo(&*tx, owner, &input, list_id, parent_id).await?;
+
+        if parent_id.is_some() {
+            tx.execute(REOPEN_PARENTS, &[&todo.id])
+                .await
+                .map_err(db_error)?;
+        }
+
+        attach_details(&*tx, std::slice::from_mut(&mut todo)).await?;
+        tx.commit().await.map_err(db_error)?;
+        Ok(todo)
+    }
+
+    async fn create_many(
+        &self,
+        owner: i32,
+        inputs: Vec<TodoInput>,
+        list_id: Option<i32>,
+    ) -> Result<u32, RepositoryError> {
+        let mut client = self.client().await?;
+        let tx = client.transaction().await.map_err(db_error)?;
+
+        let list_id = target_list(&*tx, owner, list_id, None).await?;
+        for input in &inputs {
+            insert_todo(&*tx, owner, input, list_id, None).await?;
+        }
+        tx.commit().await.map_err(db_error)?;
+        Ok(inputs.len() as u32)
+    }
+
+    async fn create_from_calendar(
+        &self,
+        owner: i32,
+        todos: Vec<CalendarTodo>,
+        list_id: Option<i32>,
+    ) -> Result<Vec<String>, RepositoryError> {
+        let mut client = self.client().await?;
+        let tx = client.transaction().await.map_err(db_error)?;
+
+        let list_id = target_list(&*tx, owner, list_id, None).await?;
+        let mut skipped = Vec::new();
+        for todo in &todos {
+            if let Some(uid) = &todo.uid {
+                let taken: bool = tx
+                    .query_one(
+                        "SELECT EXISTS(SELECT 1 FROM todos WHERE owner_id = $1 AND ical_uid = $2)",
+                        &[&owner, uid],
+                    )
+                    .await
+                    .map_err(db_error)?
+                    .get(0);
+                if taken {
+                    skipped.push(uid.clone());
+                    continue;
+                }
+            }
+            let id = insert_todo(&*tx, owner, &todo.input, list_id, None)
+                .await?
+                .id;
+            tx.execute(
+                "UPDATE todos SET completed = $2, completed_at = $3, ical_uid = $4 WHERE id = $1",
+                &[
+                    &id,
+                    &todo.completed_at.is_some(),
+                    &todo.completed_at,
+                    &todo.uid,
+                ],
+            )
+            .await
+            .map_err(db_error)?;
+        }
+        tx.commit().await.map_err(db_error)?;
+        Ok(skipped)
+    }
+
+    async fn get(&self, owner: i32, id: i32) -> Result<Todo, RepositoryError> {
+        let client = self.client().await?;
+
+        let stmt = format!(
+            "SELECT {TODO_COLUMNS} FROM todos WHERE id = $1 AND owner_id = $2 AND deleted_at IS NULL"
+        );
+        let row = client
+            .query_opt(&stmt, &[&id, &owner])
+            .await
+            .map_err(db_error)?
+            .ok_or(RepositoryError::NotFound(id))?;
+        let mut todos = vec![todo_from_row(&row)];
+        attach_details(&**client, &mut todos).await?;
+        attach_children(&**client, &mut todos).await?;
+        Ok(todos.remove(0))
+    }
+
+    async fn update(&self, owner: i32, id: i32, input: TodoInput) -> Result<(), RepositoryError> {
+        let mut client = self.client().await?;
+        let tx = client.transaction().await.map_err(db_error)?;
+
+        let stmt = "UPDATE todos SET title = $1, description = $2, due_date = $3, priority = $4, recurrence = $6 WHERE id = $5 AND owner_id = $7 AND deleted_at IS NULL";
+        let affected = tx
+            .execute(
+                stmt,
+                &[
+                    &input.title,
+                    &input.description,
+                    &input.due_date,
+                    &input.priority.level(),
+                    &id,
+                    &input.recurrence.as_ref().map(ToString::to_string),
+                    &owner,
+                ],
+            )
+            .await
+            .map_err(db_error)?;
+        expect_one(id, affected)?;
+
+        set_tags(&*tx, owner, id, &input.tag_ids).await?;
+        tx.commit().await.map_err(db_error)
+    }
+
+    async fn patch(
+        &self,
+        owner: i32,
+        id: i32,
+        changes: TodoChanges,
+    ) -> Result<(), RepositoryError> {
+        let mut client = self.client().await?;
+        let tx = client.transaction().await.map_err(db_error)?;
+
+        let stmt = "UPDATE todos SET title = coalesce($1, title), \
+            description = coalesce($2, description), due_date = coalesce($3, due_date), \
+            priority = coalesce($4, priority), \
+            recurrence = CASE WHEN $5 THEN $6 ELSE recurrence END \
+            WHERE id = $7 AND owner_id = $8 AND deleted_at IS NULL";
+        let affected = tx
+            .execute(
+                stmt,
+                &[
+                    &changes.title,
+                    &changes.description,
+                    &changes.due_date,
+                    &changes.priority.map(Priority::level),
+                    &changes.recurrence.is_some(),
+                    &changes.recurrence.flatten().map(|rule| rule.to_string()),
+                    &id,
+                    &owner,
+                ],
+            )
+            .await
+            .map_err(db_error)?;
+        expect_one(id, affected)?;
+
+        if let Some(tag_ids) = &changes.tag_ids {
+            set_tags(&*tx, owner, id, tag_ids).await?;
+        }
+        if let Some(list_id) = changes.list_id {
+            move_subtree_to_list(&*tx, owner, id, list_id).await?;
+        }
+        tx.commit().await.map_err(db_error)
+    }
+
+    async fn complete(
+        &self,
+        owner: i32,
+        id: i32,
+        include_checklist: bool,
+    ) -> Result<(), RepositoryError> {
+        let mut client = self.client().await?;
+        let tx = client.transaction().await.map_err(db_error)?;
+
+        let stmt = "SELECT completed, due_date, recurrence FROM todos \
+            WHERE id = $1 AND owner_id = $2 AND deleted_at IS NULL FOR UPDATE";
+        let row = tx
+            .query_opt(stmt, &[&id, &owner])
+            .await
+            .map_err(db_error)?
+            .ok_or(RepositoryError::NotFound(id))?;
+        let (was_completed, due_date): (bool, NaiveDate) = (row.get(0), row.get(1));
+        let recurrence = row
+            .get::<_, Option<String>>(2)
+            .and_then(|rule| rule.parse::<Recurrence>().ok());
+
+        let stmt = format!(
+            "{SUBTREE} UPDATE todos SET completed = true, completed_at = now() \
+             WHERE id IN (SELECT id FROM subtree) AND deleted_at IS NULL AND (id = $1 OR NOT completed)"
+        );
+        tx.execute(&stmt, &[&id]).await.map_err(db_error)?;
+
+        if include_checklist {
+            let stmt = "UPDATE checklist_items SET done = true WHERE todo_id = $1";
+            tx.execute(stmt, &[&id]).await.map_err(db_error)?;
+        }
+        if let Some(recurrence) = recurrence.filter(|_| !was_completed) {
+            let next = recurrence.next_due(due_date, Utc::now().date_naive());
+            schedule_next(&*tx, id, next).await?;
+        }
+        tx.commit().await.map_err(db_error)
+    }
+
+    async fn uncomplete(&self, owner: i32, id: i32) -> Result<(), RepositoryError> {
+        let mut client = self.client().await?;
+        let tx = client.transaction().await.map_err(db_error)?;
+
+        let stmt = "UPDATE todos SET completed = false, completed_at = NULL \
+            WHERE id = $1 AND owner_id = $2 AND deleted_at IS NULL";
+        let affected = tx.execute(stmt, &[&id, &owner]).await.map_err(db_error)?;
+        expect_one(id, affected)?;
+
+        tx.execute(REOPEN_PARENTS, &[&id]).await.map_err(db_error)?;
+        tx.commit().await.map_err(db_error)
+    }
+
+    async fn delete(&self, owner: i32, id: i32) -> Result<(), RepositoryError> {
+        let client = self.client().await?;
+
+        let stmt = format!(
+            "{SUBTREE} UPDATE todos SET deleted_at = now() \
+             WHERE id IN (SELECT id FROM subtree) AND owner_id = $2 AND deleted_at IS NULL"
+        );
+        let affected = client
+            .execute(&stmt, &[&id, &owner])
+            .await
+            .map_err(db_error)?;
+        expect_one(id, affected)
+    }
+
+    async fn paginate_trash(
+        &self,
+        owner: i32,
+        page: u32,
+        per_page: u32,
+    ) -> Result<PaginatedTodos, RepositoryError> {
+        self.paginate_where(
+            owner,
+            "deleted_at IS NOT NULL",
+            "deleted_at DESC, id DESC",
+            page,
+            per_page,
+            None,
+            &[],
+        )
+        .await
+    }
+
+    async fn restore(&self, owner: i32, id: i32) -> Result<(), RepositoryError> {
+        let mut client = self.client().await?;
+        let tx = client.transaction().await.map_err(db_error)?;
+
+        let stmt = "UPDATE todos SET parent_id = NULL \
+            WHERE id = $1 AND owner_id = $2 AND deleted_at IS NOT NULL \
+            AND parent_id IN (SELECT id FROM todos WHERE deleted_at IS NOT NULL)";
+        tx.execute(stmt, &[&id, &owner]).await.map_err(db_error)?;
+
+        // Sub-todos trashed along with the item share its deletion time.
+        let stmt = "WITH RECURSIVE subtree(id) AS (\
+                SELECT id FROM todos WHERE id = $1 AND owner_id = $2 AND deleted_at IS NOT NULL \
+                UNION SELECT todos.id FROM todos JOIN subtree ON todos.parent_id = subtree.id \
+                WHERE todos.deleted_at = (SELECT deleted_at FROM todos WHERE id = $1)) \
+            UPDATE todos SET deleted_at = NULL WHERE id IN (SELECT id FROM subtree)";
+        let affected = tx.execute(stmt, &[&id, &owner]).await.map_err(db_error)?;
+        expect_one(id, affected)?;
+        tx.commit().await.map_err(db_error)
+    }
+
+    async fn purge(&self, owner: i32, id: i32) -> Result<(), RepositoryError> {
+        let client = self.client().await?;
+
+        let stmt = "DELETE FROM todos WHERE id = $1 AND owner_id = $2 AND deleted_at IS NOT NULL";
+        let affected = client
+            .execute(stmt, &[&id, &owner])
+            .await
+            .map_err(db_error)?;
+        expect_one(id, affected)
+    }
+
+    async fn purge_trashed_before(&self, cutoff: DateTime<Utc>) -> Result<u64, RepositoryError> {
+        let client = self.client().await?;
+
+        let stmt = "DELETE FROM todos WHERE deleted_at < $1";
+        client.execute(stmt, &[&cutoff]).await.map_err(db_error)
+    }
+
+    async fn search(
+        &self,
+        owner: i32,
+        query: &str,
+        list: Option<i32>,
+        tags: &[i32],
+    ) -> Result<Vec<Todo>, RepositoryError> {
+        let client = self.client().await?;
+
+        let stmt = format!(
+            "SELECT {TODO_COLUMNS} FROM todos WHERE owner_id = $4 AND title::tsvector @@ plainto_tsquery($1) AND completed = false AND deleted_at IS NULL AND {} AND {}",
+            list_filter(3),
+            tag_filter(2)
+        );
+        let mut todos = client
+            .query(&stmt, &[&query, &tags, &list, &owner])
+            .await
+            .map_err(db_error)?
+            .iter()
+            .map(todo_from_row)
+            .collect::<Vec<_>>();
+        attach_details(&**client, &mut todos).await?;
+        Ok(todos)
+    }
+
+    async fn move_todo(
+        &self,
+        owner: i32,
+        id: i32,
+        parent_id: Option<i32>,
+    ) -> Result<(), RepositoryError> {
+        let mut client = self.client().await?;
+        let tx = client.transaction().await.map_err(db_error)?;
+
+        expect_todo(&*tx, owner, id).await?;
+        if let Some(parent_id) = parent_id {
+            expect_todo(&*tx, owner, parent_id).await?;
+
+            let stmt = format!("{SUBTREE} SELECT EXISTS(SELECT 1 FROM subtree WHERE id = $2)");
+            let row = tx
+                .query_one(&stmt, &[&id, &parent_id])
+                .await
+                .map_err(db_error)?;
+            if row.get(0) {
+                return Err(RepositoryError::Conflict(MOVE_CYCLE_MESSAGE.into()));
+            }
+
+            let list_id = target_list(&*tx, owner, None, Some(parent_id)).await?;
+            tx.execute(MOVE_SUBTREE_TO_LIST, &[&id, &list_id])
+                .await
+                .map_err(db_error)?;
+        }
+
+        let stmt = "UPDATE todos SET parent_id = $2 WHERE id = $1";
+        tx.execute(stmt, &[&id, &parent_id])
+            .await
+            .map_err(db_error)?;
+        tx.execute(REOPEN_PARENTS, &[&id]).await.map_err(db_error)?;
+        tx.commit().await.map_err(db_error)
+    }
+
+    async fn move_to_list(&self, owner: i32, id: i32, list_id: i32) -> Result<(), RepositoryError> {
+        let mut client = self.client().await?;
+        let tx = client.transaction().await.map_err(db_error)?;
+
+        move_subtree_to_list(&*tx, owner, id, list_id).await?;
+        tx.commit().await.map_err(db_error)
+    }
+
+    async fn list_tags(&self, owner: i32) -> Result<Vec<Tag>, RepositoryError> {
+        let client = self.client().await?;
+
+        let stmt = format!("SELECT {TAG_COLUMNS} FROM tags WHERE owner_id = $1 ORDER BY name");
+        let tags = client
+            .query(&stmt, &[&owner])
+            .await
+            .map_err(db_error)?
+            .iter()
+            .map(tag_from_row)
+            .collect();
+        Ok(tags)
+    }
+
+    async fn create_tag(
+        &self,
+        owner: i32,
+        name: String,
+        colour: String,
+    ) -> Result<Tag, RepositoryError> {
+        let client = self.client().await?;
+
+        let stmt = format!(
+            "INSERT INTO tags(name, colour, owner_id) VALUES($1, $2, $3) RETURNING {TAG_COLUMNS}"
+        );
+        let row = client
+            .query_one(&stmt, &[&name, &colour, &owner])
+            .await
+            .map_err(db_error)?;
+        Ok(tag_from_row(&row))
+    }
+
+    async fn rename_tag(&self, owner: i32, id: i32, name: String) -> Result<(), RepositoryError> {
+        let client = self.client().await?;
+
+        let stmt = "UPDATE tags SET name = $1 WHERE id = $2 AND owner_id = $3";
+        let affected = client
+            .execute(stmt, &[&name, &id, &owner])
+            .await
+            .map_err(db_error)?;
+        expect_tag(id, affected)
+    }
+
+    async fn set_tag_colour(
+        &self,
+        owner: i32,
+        id: i32,
+        colour: String,
+    ) -> Result<(), RepositoryError> {
+        let client = self.client().await?;
+
+        let stmt = "UPDATE tags SET colour = $1 WHERE id = $2 AND owner_id = $3";
+        let affected = client
+            .execute(stmt, &[&colour, &id, &owner])
+            .await
+            .map_err(db_error)?;
+        expect_tag(id, affected)
+    }
+
+    async fn delete_tag(&self, owner: i32, id: i32) -> Result<(), RepositoryError> {
+        let client = self.client().await?;
+
+        let stmt = "DELETE FROM tags WHERE id = $1 AND owner_id = $2";
+        let affected = client
+            .execute(stmt, &[&id, &owner])
+            .await
+            .map_err(db_error)?;
+        expect_tag(id, affected)
+    }
+
+    async fn list_lists(&self, owner: i32) -> Result<Vec<List>, RepositoryError> {
+        let client = self.client().await?;
+
+        let lists = client
+            .query(LISTS, &[&owner])
+            .await
+            .map_err(db_error)?
+            .iter()
+            .map(list_from_row)
+            .collect();
+        Ok(lists)
+    }
+
+    async fn create_list(
+        &self,
+        owner: i32,
+        name: String,
+        colour: String,
+    ) -> Result<List, RepositoryError> {
+        let client = self.client().await?;
+
+        let stmt = "INSERT INTO lists(name, colour, position, owner_id) \
+            SELECT $1, $2, coalesce(max(position) + 1, 0), $3 FROM lists WHERE owner_id = $3 \
+            RETURNING id, name, colour, 0::bigint";
+        let row = client
+            .query_one(stmt, &[&name, &colour, &owner])
+            .await
+            .map_err(db_error)?;
+        Ok(list_from_row(&row))
+    }
+
+    async fn rename_list(&self, owner: i32, id: i32, name: String) -> Result<(), RepositoryError> {
+        let client = self.client().await?;
+
+        let stmt = "UPDATE lists SET name = $1 WHERE id = $2 AND owner_id = $3";
+        let affected = client
+            .execute(stmt, &[&name, &id, &owner])
+            .await
+            .map_err(db_error)?;
+        expect_list(id, affected)
+    }
+
+    async fn set_list_colour(
+        &self,
+        owner: i32,
+        id: i32,
+        colour: String,
+    ) -> Result<(), RepositoryError> {
+        let client = self.client().await?;
+
+        let stmt = "UPDATE lists SET colour = $1 WHERE id = $2 AND owner_id = $3";
+        let affected = client
+            .execute(stmt, &[&colour, &id, &owner])
+            .await
+            .map_err(db_error)?;
+        expect_list(id, affected)
+    }
+
+    async fn move_list(&self, owner: i32, id: i32, position: u32) -> Result<(), RepositoryError> {
+        let mut client = self.client().await?;
+        let tx = client.transaction().await.map_err(db_error)?;
+
+        let mut ids = lock_lists(&*tx, owner, id).await?;
+        move_to(&mut ids, id, position);
+
+        let stmt = "UPDATE lists SET position = $1 WHERE id = $2";
+        for (position, id) in ids.iter().enumerate() {
+            tx.execute(stmt, &[&(position as i32), id])
+                .await
+                .map_err(db_error)?;
+        }
+        tx.commit().await.map_err(db_error)
+    }
+
+    async fn delete_list(&self, owner: i32, id: i32) -> Result<(), RepositoryError> {
+        let mut client = self.client().await?;
+        let tx = client.transaction().await.map_err(db_error)?;
+
+        if lock_lists(&*tx, owner, id).await?.len() == 1 {
+            return Err(RepositoryError::Conflict(LAST_LIST_MESSAGE.into()));
+        }
+        tx.execute("DELETE FROM lists WHERE id = $1", &[&id])
+            .await
+            .map_err(db_error)?;
+        tx.commit().await.map_err(db_error)
+    }
+
+    async fn add_checklist_item(
+        &self,
+        owner: i32,
+        todo_id: i32,
+        text: String,
+    ) -> Result<ChecklistItem, RepositoryError> {
+        let client = self.client().await?;
+
+        let stmt = "INSERT INTO checklist_items(todo_id, position, text) \
+            SELECT id, (SELECT coalesce(max(position) + 1, 0) FROM checklist_items WHERE todo_id = $1), $2 \
+            FROM todos WHERE id = $1 AND owner_id = $3 AND deleted_at IS NULL \
+            RETURNING id, text, done";
+        let row = client
+            .query_opt(stmt, &[&todo_id, &text, &owner])
+            .await
+            .map_err(db_error)?
+            .ok_or(RepositoryError::NotFound(todo_id))?;
+        Ok(checklist_item_from_row(&row))
+    }
+
+    async fn set_checklist_item_done(
+        &self,
+        owner: i32,
+        id: i32,
+        done: bool,
+    ) -> Result<(), RepositoryError> {
+        let client = self.client().await?;
+
+        let stmt = "UPDATE checklist_items SET done = $1 \
+            WHERE id = $2 AND todo_id IN (SELECT id FROM todos WHERE owner_id = $3)";
+        let affected = client
+            .execute(stmt, &[&done, &id, &owner])
+            .await
+            .map_err(db_error)?;
+        expect_checklist_item(id, affected)
+    }
+
+    async fn move_checklist_item(
+        &self,
+        owner: i32,
+        id: i32,
+        position: u32,
+    ) -> Result<(), RepositoryError> {
+        let mut client = self.client().await?;
+        let tx = client.transaction().await.map_err(db_error)?;
+
+        // Locking the parent serializes concurrent reorders of the same checklist.
+        let stmt = "SELECT todos.id FROM todos JOIN checklist_items ON checklist_items.todo_id = todos.id \
+            WHERE checklist_items.id = $1 AND todos.owner_id = $2 FOR UPDATE OF todos";
+        let todo_id: i32 = tx
+            .query_opt(stmt, &[&id, &owner])
+            .await
+            .map_err(db_error)?
+            .ok_or(RepositoryError::ChecklistItemNotFound(id))?
+            .get(0);
+
+        let stmt = "SELECT id FROM checklist_items WHERE todo_id = $1 ORDER BY position, id";
+        let mut ids = tx
+            .query(stmt, &[&todo_id])
+            .await
+            .map_err(db_error)?
+            .iter()
+            .map(|row| row.get::<usize, i32>(0))
+            .collect::<Vec<_>>();
+        move_to(&mut ids, id, position);
+
+        let stmt = "UPDATE checklist_items SET position = $1 WHERE id = $2";
+        for (position, id) in ids.iter().enumerate() {
+            tx.execute(stmt, &[&(position as i32), id])
+                .await
+                .map_err(db_error)?;
+        }
+        tx.commit().await.map_err(db_error)
+    }
+
+    async fn delete_checklist_item(&self, owner: i32, id: i32) -> Result<(), RepositoryError> {
+        let client = self.client().await?;
+
+        let stmt = "DELETE FROM checklist_items \
+            WHERE id = $1 AND todo_id IN (SELECT id FROM todos WHERE owner_id = $2)";
+        let affected = client
+            .execute(stmt, &[&id, &owner])
+            .await
+            .map_err(db_error)?;
+        expect_checklist_item(id, affected)
+    }
+
+    async fn export(&self, owner: i32) -> Result<Snapshot, RepositoryError> {
+        let mut client = self.client().await?;
+        let tx = client
+            .build_transaction()
+            .isolation_level(IsolationLevel::RepeatableRead)
+            .read_only(true)
+            .start()
+            .await
+            .map_err(db_error)?;
+
+        let lists = tx
+            .query(LISTS, &[&owner])
+            .await
+            .map_err(db_error)?
+            .iter()
+            .map(list_from_row)
+            .collect();
+        let stmt = format!("SELECT {TAG_COLUMNS} FROM tags WHERE owner_id = $1 ORDER BY name");
+        let tags = tx
+            .query(&stmt, &[&owner])
+            .await
+            .map_err(db_error)?
+            .iter()
+            .map(tag_from_row)
+            .collect();
+        let stmt =
+            format!("SELECT {TODO_COLUMNS} FROM todos WHERE owner_id = $1 ORDER BY created, id");
+        let mut todos = tx
+            .query(&stmt, &[&owner])
+            .await
+            .map_err(db_error)?
+            .iter()
+            .map(todo_from_row)
+            .collect::<Vec<_>>();
+        attach_details(&*tx, &mut todos).await?;
+        tx.commit().await.map_err(db_error)?;
+
+        Ok(Snapshot { lists, tags, todos })
+    }
+
+    async fn import(
+        &self,
+        owner: i32,
+        backup: BackupImport,
+        dry_run: bool,
+    ) -> Result<ImportReport, RepositoryError> {
+        let mut client = self.client().await?;
+        let tx = client.transaction().await.map_err(db_error)?;
+        let mut report = backup.report(dry_run);
+
+        let mut lists = HashMap::new();
+        for (id, list) in &backup.lists {
+            let stmt = "SELECT id FROM lists WHERE owner_id = $1 AND name = $2";
+            let existing = tx
+                .query_opt(stmt, &[&owner, &list.name])
+                .await
+                .map_err(db_error)?;
+            let new_id: i32 = match existing {
+                Some(row) => {
+                    report.merged_lists.push(list.name.clone());
+                    row.get(0)
+                }
+                None => {
+                    report.new_lists.push(list.name.clone());
+                    let stmt = "INSERT INTO lists(name, colour, position, owner_id) \
+                        SELECT $1, $2, coalesce(max(position) + 1, 0), $3 FROM lists WHERE owner_id = $3 \
+                        RETURNING id";
+                    tx.query_one(stmt, &[&list.name, &list.colour, &owner])
+                        .await
+                        .map_err(db_error)?
+                        .get(0)
+                }
+            };
+            lists.insert(*id, new_id);
+        }
+
+        let mut tags = HashMap::new();
+        for (id, tag) in &backup.tags {
+            let stmt = "SELECT id FROM tags WHERE owner_id = $1 AND name = $2";
+            let existing = tx
+                .query_opt(stmt, &[&owner, &tag.name])
+                .await
+                .map_err(db_error)?;
+            let new_id: i32 = match existing {
+                Some(row) => {
+                    report.merged_tags.push(tag.name.clone());
+                    row.get(0)
+                }
+                None => {
+                    report.new_tags.push(tag.name.clone());
+                    let stmt =
+                        "INSERT INTO tags(name, colour, owner_id) VALUES($1, $2, $3) RETURNING id";
+                    tx.query_one(stmt, &[&tag.name, &tag.colour, &owner])
+                        .await
+                        .map_err(db_error)?
+                        .get(0)
+                }
+            };
+            tags.insert(*id, new_id);
+        }
+
+        let mut todos = HashMap::new();
+        for todo in &backup.todos {
+            let parent_id = todo.parent_id.map(|id| remap(&todos, id)).transpose()?;
+            let stmt = "INSERT INTO todos(title, description, due_date, priority, parent_id, recurrence, list_id, owner_id, \
+                completed, completed_at, deleted_at, created) \
+                VALUES($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, coalesce($12, now())) RETURNING id";
+            let new_id: i32 = tx
+                .query_one(
+                    stmt,
+                    &[
+                        &todo.input.title,
+                        &todo.input.description,
+                        &todo.input.due_date,
+                        &todo.input.priority.level(),
+                        &parent_id,
+                        &todo.input.recurrence.as_ref().map(ToString::to_string),
+                        &remap(&lists, todo.list_id)?,
+                        &owner,
+                        &todo.completed,
+                        &todo.completed_at,
+                        &todo.deleted_at,
+                        &todo.created,
+                    ],
+                )
+                .await
+                .map_err(db_error)?
+                .get(0);
+            todos.insert(todo.id, new_id);
+
+            let tag_ids = todo
+                .input
+                .tag_ids
+                .iter()
+                .map(|id| remap(&tags, *id))
+                .collect::<Result<Vec<_>, _>>()?;
+            set_tags(&*tx, owner, new_id, &tag_ids).await?;
+
+            let stmt =
+                "INSERT INTO checklist_items(todo_id, position, text, done) VALUES($1, $2, $3, $4)";
+            for (position, item) in todo.checklist.iter().enumerate() {
+                tx.execute(stmt, &[&new_id, &(position as i32), &item.text, &item.done])
+                    .await
+                    .map_err(db_error)?;
+            }
+        }
+
+        if dry_run {
+            tx.rollback().await.map_err(db_error)?;
+        } else {
+            tx.commit().await.map_err(db_error)?;
+        }
+        Ok(report)
+    }
+}
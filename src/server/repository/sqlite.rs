@@ -0,0 +1,1588 @@
+use super::{
+    move_to, nest, page_offset, remap, BackupImport, CalendarTodo, RepositoryError, Resource,
+    Snapshot, TodoChanges, TodoInput, TodoRepository, UserCredentials, DEFAULT_LIST_NAME,
+    LAST_LIST_MESSAGE, MOVE_CYCLE_MESSAGE, USERNAME_TAKEN_MESSAGE,
+};
+use crate::server::auth::User;
+use crate::server::backup::ImportReport;
+use crate::server::checklist::ChecklistItem;
+use crate::server::list::List;
+use crate::server::recurrence::Recurrence;
+use crate::server::tag::Tag;
+use crate::server::todo::{PaginatedTodos, Priority, SortOrder, Todo};
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use rusqlite::{Connection, Row};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+const TODO_COLUMNS: &str = "todos.id, todos.title, todos.description, todos.due_date, \
+    todos.completed, strftime('%Y-%m-%dT%H:%M:%SZ', todos.completed_at), \
+    strftime('%Y-%m-%dT%H:%M:%SZ', todos.deleted_at), todos.priority, todos.parent_id, \
+    todos.recurrence, todos.list_id, strftime('%Y-%m-%dT%H:%M:%SZ', todos.created)";
+
+/// Looks up who `resource` belongs to, given its id as `?1`.
+fn owner_statement(resource: Resource) -> &'static str {
+    match resource {
+        Resource::Todo(_) => "SELECT owner_id FROM todos WHERE id = ?1",
+        Resource::Tag(_) => "SELECT owner_id FROM tags WHERE id = ?1",
+        Resource::List(_) => "SELECT owner_id FROM lists WHERE id = ?1",
+        Resource::ChecklistItem(_) => {
+            "SELECT todos.owner_id FROM checklist_items \
+                JOIN todos ON todos.id = checklist_items.todo_id WHERE checklist_items.id = ?1"
+        }
+    }
+}
+
+/// Starts a statement with `subtree`, the ids of item `?1` and its sub-todos outside the
+/// trash.
+const SUBTREE: &str = "WITH RECURSIVE subtree(id) AS (SELECT ?1 \
+    UNION SELECT todos.id FROM todos JOIN subtree ON todos.parent_id = subtree.id \
+    WHERE todos.deleted_at IS NULL)";
+
+/// Reopens the completed parents of item `?1`, unless the item itself is completed.
+const REOPEN_PARENTS: &str = "WITH RECURSIVE parents(id) AS (\
+        SELECT parent_id FROM todos WHERE id = ?1 \
+        UNION SELECT todos.parent_id FROM todos JOIN parents ON todos.id = parents.id) \
+    UPDATE todos SET completed = 0, completed_at = NULL \
+    WHERE id IN (SELECT id FROM parents) AND completed = 1 \
+    AND NOT (SELECT completed FROM todos WHERE id = ?1)";
+
+/// Files item `?1` and all of its sub-todos, trashed ones included, under list `?2`.
+const MOVE_SUBTREE_TO_LIST: &str = "WITH RECURSIVE subtree(id) AS (SELECT ?1 \
+    UNION SELECT todos.id FROM todos JOIN subtree ON todos.parent_id = subtree.id) \
+    UPDATE todos SET list_id = ?2 WHERE id IN (SELECT id FROM subtree)";
+
+const TAG_COLUMNS: &str = "tags.id, tags.name, tags.colour";
+
+/// Every list of user `?1` with its open-item count, in the user's order.
+const LISTS: &str = "SELECT lists.id, lists.name, lists.colour, count(todos.id) FROM lists \
+    LEFT JOIN todos ON todos.list_id = lists.id AND todos.completed = 0 AND todos.deleted_at IS NULL \
+    WHERE lists.owner_id = ?1 GROUP BY lists.id ORDER BY lists.position, lists.id";
+
+/// How timestamps are stored, so they sort and compare as text.
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%dT%H:%M:%fZ";
+/// [`TIMESTAMP_FORMAT`] in chrono's syntax, where `%f` means something else.
+const TIMESTAMP_FORMAT_CHRONO: &str = "%Y-%m-%dT%H:%M:%S%.3fZ";
+
+/// Stores todo items in a single SQLite file. Queries run on the blocking thread pool
+/// since `rusqlite` is synchronous.
+///
+/// The connection must have `PRAGMA foreign_keys` turned on, so deleting a todo item or
+/// a tag also detaches them.
+pub struct SqliteTodoRepository {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl SqliteTodoRepository {
+    pub fn new(conn: Connection) -> Self {
+        Self {
+            conn: Arc::new(Mutex::new(conn)),
+        }
+    }
+
+    async fn with_conn<T, F>(&self, f: F) -> Result<T, RepositoryError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Connection) -> Result<T, RepositoryError> + Send + 'static,
+    {
+        let conn = self.conn.clone();
+        tokio::task::spawn_blocking(move || {
+            let conn = conn.lock().map_err(|_| RepositoryError::Unavailable)?;
+            f(&conn)
+        })
+        .await
+        .map_err(|e| {
+            log::error!("sqlite task failed: {e}");
+            RepositoryError::Unavailable
+        })?
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn paginate_where(
+        &self,
+        owner: i32,
+        filter: &'static str,
+        order: &'static str,
+        page: u32,
+        per_page: u32,
+        list: Option<i32>,
+        tags: &[i32],
+    ) -> Result<PaginatedTodos, RepositoryError> {
+        let tags = tag_array(tags);
+        self.with_conn(move |conn| {
+            let mut stmt = conn
+                .prepare(&format!(
+                    "SELECT {TODO_COLUMNS} FROM todos WHERE todos.owner_id = ?5 AND {filter} AND {} AND {} ORDER BY {order} LIMIT ?1 OFFSET ?2",
+                    list_filter(4),
+                    tag_filter(3)
+                ))
+                .map_err(db_error)?;
+            let mut items = stmt
+                .query_map((per_page, page_offset(page, per_page), &tags, list, owner), todo_from_row)
+                .map_err(db_error)?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(db_error)?;
+            attach_details(conn, &mut items)?;
+            attach_children(conn, &mut items)?;
+
+            let total: u32 = conn
+                .query_row(
+                    &format!(
+                        "SELECT count(1) FROM todos WHERE todos.owner_id = ?3 AND {filter} AND {} AND {}",
+                        list_filter(2),
+                        tag_filter(1)
+                    ),
+                    (&tags, list, owner),
+                    |row| row.get(0),
+                )
+                .map_err(db_error)?;
+
+            Ok(PaginatedTodos {
+                items,
+                total,
+                page,
+                total_pages: total.div_ceil(per_page),
+            })
+        })
+        .await
+    }
+}
+
+fn db_error(e: rusqlite::Error) -> RepositoryError {
+    if let rusqlite::Error::SqliteFailure(failure, _) = &e {
+        if failure.extended_code == rusqlite::ffi::SQLITE_CONSTRAINT_UNIQUE {
+            return RepositoryError::Conflict("a matching item already exists".into());
+        }
+    }
+    log::error!("database error: {e}");
+    RepositoryError::Database(e.to_string())
+}
+
+fn todo_from_row(row: &Row) -> rusqlite::Result<Todo> {
+    Ok(Todo {
+        id: row.get(0)?,
+        title: row.get(1)?,
+        description: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+        completed: row.get(4)?,
+        completed_at: row.get(5)?,
+        deleted_at: row.get(6)?,
+        created: row.get(11)?,
+        due_date: row.get(3)?,
+        priority: Priority::from_level(row.get(7)?),
+        tags: Vec::new(),
+        checklist: Vec::new(),
+        parent_id: row.get(8)?,
+        recurrence: row
+            .get::<_, Option<String>>(9)?
+            .and_then(|rule| rule.parse().ok()),
+        list_id: row.get(10)?,
+        children: Vec::new(),
+    })
+}
+
+fn list_from_row(row: &Row) -> rusqlite::Result<List> {
+    Ok(List {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        colour: row.get(2)?,
+        open_count: row.get(3)?,
+    })
+}
+
+fn user_from_row(row: &Row) -> rusqlite::Result<User> {
+    Ok(User {
+        id: row.get(0)?,
+        username: row.get(1)?,
+    })
+}
+
+fn tag_from_row(row: &Row) -> rusqlite::Result<Tag> {
+    Ok(Tag {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        colour: row.get(2)?,
+    })
+}
+
+/// Maps "no rows affected" to `NotFound`.
+fn expect_one(id: i32, affected: usize) -> Result<(), RepositoryError> {
+    if affected == 0 {
+        Err(RepositoryError::NotFound(id))
+    } else {
+        Ok(())
+    }
+}
+
+/// Maps "no rows affected" to `TagNotFound`.
+fn expect_tag(id: i32, affected: usize) -> Result<(), RepositoryError> {
+    if affected == 0 {
+        Err(RepositoryError::TagNotFound(id))
+    } else {
+        Ok(())
+    }
+}
+
+/// Maps "no rows affected" to `ListNotFound`.
+fn expect_list(id: i32, affected: usize) -> Result<(), RepositoryError> {
+    if affected == 0 {
+        Err(RepositoryError::ListNotFound(id))
+    } else {
+        Ok(())
+    }
+}
+
+/// Tag ids as a JSON array, which queries unpack with `json_each`.
+fn tag_array(tags: &[i32]) -> String {
+    serde_json::to_string(tags).unwrap_or_else(|_| "[]".into())
+}
+
+/// Matches todo items in the list given by parameter `?param`, or every item if it's null.
+fn list_filter(param: usize) -> String {
+    format!("(?{param} IS NULL OR todos.list_id = ?{param})")
+}
+
+/// Matches todo items carrying every tag in the JSON array parameter `?param`, or every
+/// item if the array is empty. The array must not contain duplicates.
+fn tag_filter(param: usize) -> String {
+    format!(
+        "(json_array_length(?{param}) = 0 OR todos.id IN (\
+            SELECT todo_id FROM todo_tags WHERE tag_id IN (SELECT value FROM json_each(?{param})) \
+            GROUP BY todo_id HAVING count(*) = json_array_length(?{param})))"
+    )
+}
+
+/// Turns free text into an FTS5 query matching rows that contain every word, like
+/// `plainto_tsquery` does. Each word is quoted so FTS5 syntax in the input is inert.
+fn fts_query(query: &str) -> Option<String> {
+    let words = query
+        .split_whitespace()
+        .map(|word| format!("\"{}\"", word.replace('"', "\"\"")))
+        .collect::<Vec<_>>();
+    (!words.is_empty()).then(|| words.join(" "))
+}
+
+/// Fills in the tags of each todo item with one query.
+fn attach_tags(conn: &Connection, todos: &mut [Todo]) -> Result<(), RepositoryError> {
+    if todos.is_empty() {
+        return Ok(());
+    }
+
+    let ids = tag_array(&todos.iter().map(|todo| todo.id).collect::<Vec<_>>());
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {TAG_COLUMNS}, todo_tags.todo_id FROM todo_tags JOIN tags ON tags.id = todo_tags.tag_id \
+             WHERE todo_tags.todo_id IN (SELECT value FROM json_each(?1)) ORDER BY tags.name"
+        ))
+        .map_err(db_error)?;
+    let rows = stmt
+        .query_map([ids], |row| Ok((row.get::<_, i32>(3)?, tag_from_row(row)?)))
+        .map_err(db_error)?;
+    for row in rows {
+        let (todo_id, tag) = row.map_err(db_error)?;
+        if let Some(todo) = todos.iter_mut().find(|todo| todo.id == todo_id) {
+            todo.tags.push(tag);
+        }
+    }
+    Ok(())
+}
+
+/// Fills in the checklist of each todo item with one query.
+fn attach_checklists(conn: &Connection, todos: &mut [Todo]) -> Result<(), RepositoryError> {
+    if todos.is_empty() {
+        return Ok(());
+    }
+
+    let ids = tag_array(&todos.iter().map(|todo| todo.id).collect::<Vec<_>>());
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, text, done, todo_id FROM checklist_items \
+             WHERE todo_id IN (SELECT value FROM json_each(?1)) ORDER BY position, id",
+        )
+        .map_err(db_error)?;
+    let rows = stmt
+        .query_map([ids], |row| {
+            Ok((row.get::<_, i32>(3)?, checklist_item_from_row(row)?))
+        })
+        .map_err(db_error)?;
+    for row in rows {
+        let (todo_id, item) = row.map_err(db_error)?;
+        if let Some(todo) = todos.iter_mut().find(|todo| todo.id == todo_id) {
+            todo.checklist.push(item);
+        }
+    }
+    Ok(())
+}
+
+/// Fills in everything stored alongside each todo item.
+fn attach_details(conn: &Connection, todos: &mut [Todo]) -> Result<(), RepositoryError> {
+    attach_tags(conn, todos)?;
+    attach_checklists(conn, todos)
+}
+
+/// Fills in the sub-todos of each todo item, all the way down.
+fn attach_children(conn: &Connection, todos: &mut [Todo]) -> Result<(), RepositoryError> {
+    if todos.is_empty() {
+        return Ok(());
+    }
+
+    let ids = tag_array(&todos.iter().map(|todo| todo.id).collect::<Vec<_>>());
+    let mut stmt = conn
+        .prepare(&format!(
+            "WITH RECURSIVE subtree(id) AS (\
+                SELECT id FROM todos WHERE parent_id IN (SELECT value FROM json_each(?1)) AND deleted_at IS NULL \
+                UNION SELECT todos.id FROM todos JOIN subtree ON todos.parent_id = subtree.id \
+                WHERE todos.deleted_at IS NULL) \
+             SELECT {TODO_COLUMNS} FROM todos WHERE id IN (SELECT id FROM subtree) ORDER BY created, id"
+        ))
+        .map_err(db_error)?;
+    let mut descendants = stmt
+        .query_map([ids], todo_from_row)
+        .map_err(db_error)?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(db_error)?;
+    attach_details(conn, &mut descendants)?;
+    nest(todos, descendants);
+    Ok(())
+}
+
+/// Copies item `id` as its next occurrence, due on `due_date`, and moves the repeat rule
+/// to the copy.
+fn schedule_next(conn: &Connection, id: i32, due_date: NaiveDate) -> Result<(), RepositoryError> {
+    let next: i32 = conn
+        .query_row(
+            "INSERT INTO todos(title, description, due_date, priority, parent_id, recurrence, list_id, owner_id) \
+             SELECT title, description, ?2, priority, parent_id, recurrence, list_id, owner_id FROM todos WHERE id = ?1 \
+             RETURNING id",
+            (id, due_date.to_string()),
+            |row| row.get(0),
+        )
+        .map_err(db_error)?;
+
+    conn.execute(
+        "INSERT INTO todo_tags(todo_id, tag_id) SELECT ?2, tag_id FROM todo_tags WHERE todo_id = ?1",
+        (id, next),
+    )
+    .map_err(db_error)?;
+    conn.execute(
+        "INSERT INTO checklist_items(todo_id, position, text) \
+         SELECT ?2, position, text FROM checklist_items WHERE todo_id = ?1",
+        (id, next),
+    )
+    .map_err(db_error)?;
+    conn.execute("UPDATE todos SET recurrence = NULL WHERE id = ?1", [id])
+        .map_err(db_error)?;
+    Ok(())
+}
+
+/// Fails with `NotFound` unless `owner` has item `id` outside the trash.
+fn expect_todo(conn: &Connection, owner: i32, id: i32) -> Result<(), RepositoryError> {
+    match conn.query_row(
+        "SELECT 1 FROM todos WHERE id = ?1 AND owner_id = ?2 AND deleted_at IS NULL",
+        [id, owner],
+        |_| Ok(()),
+    ) {
+        Err(rusqlite::Error::QueryReturnedNoRows) => Err(RepositoryError::NotFound(id)),
+        result => result.map_err(db_error),
+    }
+}
+
+/// The list a new item goes in: its parent's for a sub-todo of `parent_id`, otherwise
+/// `list_id`, or the owner's first list if that's `None`.
+fn target_list(
+    conn: &Connection,
+    owner: i32,
+    list_id: Option<i32>,
+    parent_id: Option<i32>,
+) -> Result<i32, RepositoryError> {
+    if let Some(parent_id) = parent_id {
+        return match conn.query_row(
+            "SELECT list_id FROM todos WHERE id = ?1 AND owner_id = ?2 AND deleted_at IS NULL",
+            [parent_id, owner],
+            |row| row.get(0),
+        ) {
+            Err(rusqlite::Error::QueryReturnedNoRows) => Err(RepositoryError::NotFound(parent_id)),
+            result => result.map_err(db_error),
+        };
+    }
+
+    match conn.query_row(
+        "SELECT id FROM lists WHERE owner_id = ?2 AND (?1 IS NULL OR id = ?1) \
+         ORDER BY position, id LIMIT 1",
+        (list_id, owner),
+        |row| row.get(0),
+    ) {
+        Err(rusqlite::Error::QueryReturnedNoRows) => {
+            Err(RepositoryError::ListNotFound(list_id.unwrap_or_default()))
+        }
+        result => result.map_err(db_error),
+    }
+}
+
+/// The ids of every list of `owner` in the user's order, failing with `ListNotFound`
+/// unless `id` is one of them.
+fn list_ids(conn: &Connection, owner: i32, id: i32) -> Result<Vec<i32>, RepositoryError> {
+    let ids = conn
+        .prepare("SELECT id FROM lists WHERE owner_id = ?1 ORDER BY position, id")
+        .map_err(db_error)?
+        .query_map([owner], |row| row.get(0))
+        .map_err(db_error)?
+        .collect::<Result<Vec<i32>, _>>()
+        .map_err(db_error)?;
+    if !ids.contains(&id) {
+        return Err(RepositoryError::ListNotFound(id));
+    }
+    Ok(ids)
+}
+
+fn checklist_item_from_row(row: &Row) -> rusqlite::Result<ChecklistItem> {
+    Ok(ChecklistItem {
+        id: row.get(0)?,
+        text: row.get(1)?,
+        done: row.get(2)?,
+    })
+}
+
+/// Maps "no rows affected" to `ChecklistItemNotFound`.
+fn expect_checklist_item(id: i32, affected: usize) -> Result<(), RepositoryError> {
+    if affected == 0 {
+        Err(RepositoryError::ChecklistItemNotFound(id))
+    } else {
+        Ok(())
+    }
+}
+
+/// Moves an item of `owner` and its sub-todos to `list_id`, detaching it from a parent
+/// in another list.
+fn move_subtree_to_list(
+    conn: &Connection,
+    owner: i32,
+    id: i32,
+    list_id: i32,
+) -> Result<(), RepositoryError> {
+    expect_todo(conn, owner, id)?;
+    let exists: bool = conn
+        .query_row(
+            "SELECT EXISTS(SELECT 1 FROM lists WHERE id = ?1 AND owner_id = ?2)",
+            [list_id, owner],
+            |row| row.get(0),
+        )
+        .map_err(db_error)?;
+    if !exists {
+        return Err(RepositoryError::ListNotFound(list_id));
+    }
+
+    conn.execute(
+        "UPDATE todos SET parent_id = NULL WHERE id = ?1 \
+         AND parent_id IN (SELECT id FROM todos WHERE list_id <> ?2)",
+        (id, list_id),
+    )
+    .map_err(db_error)?;
+    conn.execute(MOVE_SUBTREE_TO_LIST, (id, list_id))
+        .map_err(db_error)?;
+    Ok(())
+}
+
+/// Replaces the tags attached to a todo item with those of `tag_ids` that `owner` has.
+fn set_tags(
+    conn: &Connection,
+    owner: i32,
+    id: i32,
+    tag_ids: &[i32],
+) -> Result<(), RepositoryError> {
+    conn.execute("DELETE FROM todo_tags WHERE todo_id = ?1", [id])
+        .map_err(db_error)?;
+    conn.execute(
+        "INSERT INTO todo_tags(todo_id, tag_id) SELECT ?1, id FROM tags \
+         WHERE id IN (SELECT value FROM json_each(?2)) AND owner_id = ?3",
+        (id, tag_array(tag_ids), owner),
+    )
+    .map_err(db_error)?;
+    Ok(())
+}
+
+/// Inserts an open item of `owner` in `list_id`, which must be theirs, with its tags.
+fn insert_todo(
+    conn: &Connection,
+    owner: i32,
+    input: &TodoInput,
+    list_id: i32,
+    parent_id: Option<i32>,
+) -> Result<Todo, RepositoryError> {
+    let todo = conn
+        .query_row(
+            &format!("INSERT INTO todos(title, description, due_date, priority, parent_id, recurrence, list_id, owner_id) VALUES(?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8) RETURNING {TODO_COLUMNS}"),
+            (
+                &input.title,
+                &input.description,
+                input.due_date.to_string(),
+                input.priority.level(),
+                parent_id,
+                input.recurrence.as_ref().map(ToString::to_string),
+                list_id,
+                owner,
+            ),
+            todo_from_row,
+        )
+        .map_err(db_error)?;
+    set_tags(conn, owner, todo.id, &input.tag_ids)?;
+    Ok(todo)
+}
+
+#[async_trait]
+impl TodoRepository for SqliteTodoRepository {
+    async fn create_user(
+        &self,
+        username: String,
+        password_hash: String,
+    ) -> Result<User, RepositoryError> {
+        self.with_conn(move |conn| {
+            let tx = conn.unchecked_transaction().map_err(db_error)?;
+            let user = tx
+                .query_row(
+                    "INSERT INTO users(username, password_hash) VALUES(?1, ?2) RETURNING id, username",
+                    (&username, &password_hash),
+                    user_from_row,
+                )
+                .map_err(|e| match db_error(e) {
+                    RepositoryError::Conflict(_) => {
+                        RepositoryError::Conflict(USERNAME_TAKEN_MESSAGE.into())
+                    }
+                    e => e,
+                })?;
+
+            tx.execute(
+                "INSERT INTO lists(name, position, owner_id) SELECT ?1, 0, ?2 \
+                 WHERE NOT EXISTS (SELECT 1 FROM lists WHERE owner_id = ?2)",
+                (DEFAULT_LIST_NAME, user.id),
+            )
+            .map_err(db_error)?;
+            tx.commit().map_err(db_error)?;
+            Ok(user)
+        })
+        .await
+    }
+
+    async fn find_user(&self, username: &str) -> Result<Option<UserCredentials>, RepositoryError> {
+        let username = username.to_string();
+        self.with_conn(move |conn| {
+            match conn.query_row(
+                "SELECT id, username, password_hash FROM users WHERE username = ?1",
+                [username],
+                |row| {
+                    Ok(UserCredentials {
+                        user: user_from_row(row)?,
+                        password_hash: row.get(2)?,
+                    })
+                },
+            ) {
+                Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+                result => result.map(Some).map_err(db_error),
+            }
+        })
+        .await
+    }
+
+    async fn get_user(&self, id: i32) -> Result<Option<User>, RepositoryError> {
+        self.with_conn(move |conn| {
+            match conn.query_row(
+                "SELECT id, username FROM users WHERE id = ?1",
+                [id],
+                user_from_row,
+            ) {
+                Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+                result => result.map(Some).map_err(db_error),
+            }
+        })
+        .await
+    }
+
+    async fn calendar_token(&self, owner: i32) -> Result<Option<String>, RepositoryError> {
+        self.with_conn(move |conn| {
+            match conn.query_row(
+                "SELECT calendar_token FROM users WHERE id = ?1",
+                [owner],
+                |row| row.get(0),
+            ) {
+                Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+                result => result.map_err(db_error),
+            }
+        })
+        .await
+    }
+
+    async fn set_calendar_token(
+        &self,
+        owner: i32,
+        token: Option<String>,
+    ) -> Result<(), RepositoryError> {
+        self.with_conn(move |conn| {
+            conn.execute(
+                "UPDATE users SET calendar_token = ?1 WHERE id = ?2",
+                (token, owner),
+            )
+            .map_err(db_error)?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn calendar_owner(&self, token: &str) -> Result<Option<i32>, RepositoryError> {
+        let token = token.to_string();
+        self.with_conn(move |conn| {
+            match conn.query_row(
+                "SELECT id FROM users WHERE calendar_token = ?1",
+                [token],
+                |row| row.get(0),
+            ) {
+                Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+                result => result.map(Some).map_err(db_error),
+            }
+        })
+        .await
+    }
+
+    async fn owner_of(&self, resource: Resource) -> Result<Option<i32>, RepositoryError> {
+        self.with_conn(move |conn| {
+            match conn.query_row(owner_statement(resource), [resource.id()], |row| row.get(0)) {
+                Err(rusqlite::Error::QueryReturnedNoRows) => Err(resource.not_found()),
+                result => result.map_err(db_error),
+            }
+        })
+        .await
+    }
+
+    async fn count_unowned(&self) -> Result<u64, RepositoryError> {
+        self.with_conn(|conn| {
+            conn.query_row(
+                "SELECT count(*) FROM todos WHERE owner_id IS NULL",
+                [],
+                |row| row.get(0),
+            )
+            .map_err(db_error)
+        })
+        .await
+    }
+
+    async fn claim_unowned(&self, owner: i32) -> Result<u64, RepositoryError> {
+        self.with_conn(move |conn| {
+            let tx = conn.unchecked_transaction().map_err(db_error)?;
+
+            // Names are unique for each user, so namesakes of the owner's tags and lists
+            // hand their items over and go.
+            let merges = [
+                "UPDATE todo_tags SET tag_id = mine.id FROM tags unowned, tags mine \
+                 WHERE todo_tags.tag_id = unowned.id AND unowned.owner_id IS NULL \
+                 AND mine.owner_id = ?1 AND mine.name = unowned.name",
+                "DELETE FROM tags WHERE owner_id IS NULL \
+                 AND name IN (SELECT name FROM tags WHERE owner_id = ?1)",
+                "UPDATE todos SET list_id = mine.id FROM lists unowned, lists mine \
+                 WHERE todos.list_id = unowned.id AND unowned.owner_id IS NULL \
+                 AND mine.owner_id = ?1 AND mine.name = unowned.name",
+                "DELETE FROM lists WHERE owner_id IS NULL \
+                 AND name IN (SELECT name FROM lists WHERE owner_id = ?1)",
+            ];
+            for stmt in merges {
+                tx.execute(stmt, [owner]).map_err(db_error)?;
+            }
+
+            // The claimed lists go after the owner's own.
+            let next_position: i32 = tx
+                .query_row(
+                    "SELECT coalesce(max(position) + 1, 0) FROM lists WHERE owner_id = ?1",
+                    [owner],
+                    |row| row.get(0),
+                )
+                .map_err(db_error)?;
+            tx.execute(
+                "UPDATE lists SET owner_id = ?1, position = position + ?2 WHERE owner_id IS NULL",
+                (owner, next_position),
+            )
+            .map_err(db_error)?;
+            tx.execute(
+                "UPDATE tags SET owner_id = ?1 WHERE owner_id IS NULL",
+                [owner],
+            )
+            .map_err(db_error)?;
+            let claimed = tx
+                .execute(
+                    "UPDATE todos SET owner_id = ?1 WHERE owner_id IS NULL",
+                    [owner],
+                )
+                .map_err(db_error)?;
+
+            tx.commit().map_err(db_error)?;
+            Ok(claimed as u64)
+        })
+        .await
+    }
+
+    async fn paginate(
+        &self,
+        owner: i32,
+        page: u32,
+        per_page: u32,
+        list: Option<i32>,
+        tags: &[i32],
+        sort: SortOrder,
+    ) -> Result<PaginatedTodos, RepositoryError> {
+        let order = match sort {
+            SortOrder::Newest => "created DESC, id DESC",
+            SortOrder::Priority => "priority DESC, due_date, created DESC, id DESC",
+        };
+        self.paginate_where(
+            owner,
+            "completed = 0 AND deleted_at IS NULL AND parent_id IS NULL",
+            order,
+            page,
+            per_page,
+            list,
+            tags,
+        )
+        .await
+    }
+
+    async fn paginate_completed(
+        &self,
+        owner: i32,
+        page: u32,
+        per_page: u32,
+    ) -> Result<PaginatedTodos, RepositoryError> {
+        self.paginate_where(
+            owner,
+            "completed = 1 AND deleted_at IS NULL AND parent_id IS NULL",
+            "completed_at IS NULL, completed_at DESC, id DESC",
+            page,
+            per_page,
+            None,
+            &[],
+        )
+        .await
+    }
+
+    async fn create(
+        &self,
+        owner: i32,
+        input: TodoInput,
+        list_id: Option<i32>,
+        parent_id: Option<i32>,
+    ) -> Result<Todo, RepositoryError> {
+        self.with_conn(move |conn| {
+            let tx = conn.unchecked_transaction().map_err(db_error)?;
+            let list_id = target_list(&tx, owner, list_id, parent_id)?;
+            let mut todo = insert_todo(&tx, owner, &input, list_id, parent_id)?;
+
+            if parent_id.is_some() {
+                tx.execute(REOPEN_PARENTS, [todo.id]).map_err(db_error)?;
+            }
+
+            attach_details(&tx, std::slice::from_mut(&mut todo))?;
+            tx.commit().map_err(db_error)?;
+            Ok(todo)
+        })
+        .await
+    }
+
+    async fn create_many(
+        &self,
+        owner: i32,
+        inputs: Vec<TodoInput>,
+        list_id: Option<i32>,
+    ) -> Result<u32, RepositoryError> {
+        self.with_conn(move |conn| {
+            let tx = conn.unchecked_transaction().map_err(db_error)?;
+            let list_id = target_list(&tx, owner, list_id, None)?;
+            for input in &inputs {
+                insert_todo(&tx, owner, input, list_id, None)?;
+            }
+            tx.commit().map_err(db_error)?;
+            Ok(inputs.len() as u32)
+        })
+        .await
+    }
+
+    async fn create_from_calendar(
+        &self,
+        owner: i32,
+        todos: Vec<CalendarTodo>,
+        list_id: Option<i32>,
+    ) -> Result<Vec<String>, RepositoryError> {
+        self.with_conn(move |conn| {
+            let tx = conn.unchecked_transaction().map_err(db_error)?;
+            let list_id = target_list(&tx, owner, list_id, None)?;
+            let mut skipped = Vec::new();
+            for todo in &todos {
+                if let Some(uid) = &todo.uid {
+                    let taken: bool = tx
+                        .query_row(
+                            "SELECT EXISTS(SELECT 1 FROM todos WHERE owner_id = ?1 AND ical_uid = ?2)",
+                            (owner, uid),
+                            |row| row.get(0),
+                        )
+                        .map_err(db_error)?;
+                    if taken {
+                        skipped.push(uid.clone());
+                        continue;
+                    }
+                }
+                let id = insert_todo(&tx, owner, &todo.input, list_id, None)?.id;
+                tx.execute(
+                    "UPDATE todos SET completed = ?2, completed_at = ?3, ical_uid = ?4 WHERE id = ?1",
+                    (
+                        id,
+                        todo.completed_at.is_some(),
+                        todo.completed_at
+                            .map(|at| at.format(TIMESTAMP_FORMAT_CHRONO).to_string()),
+                        &todo.uid,
+                    ),
+                )
+                .map_err(db_error)?;
+            }
+            tx.commit().map_err(db_error)?;
+            Ok(skipped)
+        })
+        .await
+    }
+
+    async fn get(&self, owner: i32, id: i32) -> Result<Todo, RepositoryError> {
+        self.with_conn(move |conn| {
+            let stmt = format!(
+                "SELECT {TODO_COLUMNS} FROM todos WHERE todos.id = ?1 AND todos.owner_id = ?2 AND todos.deleted_at IS NULL"
+            );
+            let todo = match conn.query_row(&stmt, [id, owner], todo_from_row) {
+                Err(rusqlite::Error::QueryReturnedNoRows) => Err(RepositoryError::NotFound(id)),
+                result => result.map_err(db_error),
+            }?;
+            let mut todos = vec![todo];
+            attach_details(conn, &mut todos)?;
+            attach_children(conn, &mut todos)?;
+            Ok(todos.remove(0))
+        })
+        .await
+    }
+
+    async fn update(&self, owner: i32, id: i32, input: TodoInput) -> Result<(), RepositoryError> {
+        self.with_conn(move |conn| {
+            let tx = conn.unchecked_transaction().map_err(db_error)?;
+            let affected = tx
+                .execute(
+                    "UPDATE todos SET title = ?1, description = ?2, due_date = ?3, priority = ?4, recurrence = ?6 WHERE id = ?5 AND owner_id = ?7 AND deleted_at IS NULL",
+                    (
+                        &input.title,
+                        &input.description,
+                        input.due_date.to_string(),
+                        input.priority.level(),
+                        id,
+                        input.recurrence.as_ref().map(ToString::to_string),
+                        owner,
+                    ),
+                )
+                .map_err(db_error)?;
+            expect_one(id, affected)?;
+
+            set_tags(&tx, owner, id, &input.tag_ids)?;
+            tx.commit().map_err(db_error)
+        })
+        .await
+    }
+
+    async fn patch(
+        &self,
+        owner: i32,
+        id: i32,
+        changes: TodoChanges,
+    ) -> Result<(), RepositoryError> {
+        self.with_conn(move |conn| {
+            let tx = conn.unchecked_transaction().map_err(db_error)?;
+            let affected = tx
+                .execute(
+                    "UPDATE todos SET title = coalesce(?1, title), \
+                     description = coalesce(?2, description), due_date = coalesce(?3, due_date), \
+                     priority = coalesce(?4, priority), \
+                     recurrence = CASE WHEN ?5 THEN ?6 ELSE recurrence END \
+                     WHERE id = ?7 AND owner_id = ?8 AND deleted_at IS NULL",
+                    (
+                        &changes.title,
+                        &changes.description,
+                        changes.due_date.map(|date| date.to_string()),
+                        changes.priority.map(Priority::level),
+                        changes.recurrence.is_some(),
+                        changes
+                            .recurrence
+                            .clone()
+                            .flatten()
+                            .map(|rule| rule.to_string()),
+                        id,
+                        owner,
+                    ),
+                )
+                .map_err(db_error)?;
+            expect_one(id, affected)?;
+
+            if let Some(tag_ids) = &changes.tag_ids {
+                set_tags(&tx, owner, id, tag_ids)?;
+            }
+            if let Some(list_id) = changes.list_id {
+                move_subtree_to_list(&tx, owner, id, list_id)?;
+            }
+            tx.commit().map_err(db_error)
+        })
+        .await
+    }
+
+    async fn complete(
+        &self,
+        owner: i32,
+        id: i32,
+        include_checklist: bool,
+    ) -> Result<(), RepositoryError> {
+        self.with_conn(move |conn| {
+            let tx = conn.unchecked_transaction().map_err(db_error)?;
+            let (was_completed, due_date, recurrence) = match tx.query_row(
+                "SELECT completed, due_date, recurrence FROM todos \
+                 WHERE id = ?1 AND owner_id = ?2 AND deleted_at IS NULL",
+                [id, owner],
+                |row| {
+                    Ok((
+                        row.get::<_, bool>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, Option<String>>(2)?,
+                    ))
+                },
+            ) {
+                Err(rusqlite::Error::QueryReturnedNoRows) => {
+                    return Err(RepositoryError::NotFound(id))
+                }
+                result => result.map_err(db_error)?,
+            };
+
+            tx.execute(
+                &format!("{SUBTREE} UPDATE todos SET completed = 1, completed_at = strftime('{TIMESTAMP_FORMAT}', 'now') \
+                    WHERE id IN (SELECT id FROM subtree) AND deleted_at IS NULL AND (id = ?1 OR completed = 0)"),
+                [id],
+            )
+            .map_err(db_error)?;
+
+            if include_checklist {
+                tx.execute("UPDATE checklist_items SET done = 1 WHERE todo_id = ?1", [id])
+                    .map_err(db_error)?;
+            }
+            let recurrence = recurrence.and_then(|rule| rule.parse::<Recurrence>().ok());
+            if let (Some(recurrence), Ok(due_date)) = (recurrence, due_date.parse::<NaiveDate>()) {
+                if !was_completed {
+                    let next = recurrence.next_due(due_date, Utc::now().date_naive());
+                    schedule_next(&tx, id, next)?;
+                }
+            }
+            tx.commit().map_err(db_error)
+        })
+        .await
+    }
+
+    async fn uncomplete(&self, owner: i32, id: i32) -> Result<(), RepositoryError> {
+        self.with_conn(move |conn| {
+            let tx = conn.unchecked_transaction().map_err(db_error)?;
+            let affected = tx
+                .execute(
+                    "UPDATE todos SET completed = 0, completed_at = NULL \
+                     WHERE id = ?1 AND owner_id = ?2 AND deleted_at IS NULL",
+                    [id, owner],
+                )
+                .map_err(db_error)?;
+            expect_one(id, affected)?;
+
+            tx.execute(REOPEN_PARENTS, [id]).map_err(db_error)?;
+            tx.commit().map_err(db_error)
+        })
+        .await
+    }
+
+    async fn delete(&self, owner: i32, id: i32) -> Result<(), RepositoryError> {
+        self.with_conn(move |conn| {
+            let affected = conn
+                .execute(
+                    &format!("{SUBTREE} UPDATE todos SET deleted_at = strftime('{TIMESTAMP_FORMAT}', 'now') \
+                        WHERE id IN (SELECT id FROM subtree) AND owner_id = ?2 AND deleted_at IS NULL"),
+                    [id, owner],
+                )
+                .map_err(db_error)?;
+            expect_one(id, affected)
+        })
+        .await
+    }
+
+    async fn paginate_trash(
+        &self,
+        owner: i32,
+        page: u32,
+        per_page: u32,
+    ) -> Result<PaginatedTodos, RepositoryError> {
+        self.paginate_where(
+            owner,
+            "deleted_at IS NOT NULL",
+            "deleted_at DESC, id DESC",
+            page,
+            per_page,
+            None,
+            &[],
+        )
+        .await
+    }
+
+    async fn restore(&self, owner: i32, id: i32) -> Result<(), RepositoryError> {
+        self.with_conn(move |conn| {
+            let tx = conn.unchecked_transaction().map_err(db_error)?;
+            tx.execute(
+                "UPDATE todos SET parent_id = NULL \
+                 WHERE id = ?1 AND owner_id = ?2 AND deleted_at IS NOT NULL \
+                 AND parent_id IN (SELECT id FROM todos WHERE deleted_at IS NOT NULL)",
+                [id, owner],
+            )
+            .map_err(db_error)?;
+
+            // Sub-todos trashed along with the item share its deletion time.
+            let affected = tx
+                .execute(
+                    "WITH RECURSIVE subtree(id) AS (\
+                        SELECT id FROM todos WHERE id = ?1 AND owner_id = ?2 AND deleted_at IS NOT NULL \
+                        UNION SELECT todos.id FROM todos JOIN subtree ON todos.parent_id = subtree.id \
+                        WHERE todos.deleted_at = (SELECT deleted_at FROM todos WHERE id = ?1)) \
+                     UPDATE todos SET deleted_at = NULL WHERE id IN (SELECT id FROM subtree)",
+                    [id, owner],
+                )
+                .map_err(db_error)?;
+            expect_one(id, affected)?;
+            tx.commit().map_err(db_error)
+        })
+        .await
+    }
+
+    async fn purge(&self, owner: i32, id: i32) -> Result<(), RepositoryError> {
+        self.with_conn(move |conn| {
+            let affected = conn
+                .execute(
+                    "DELETE FROM todos WHERE id = ?1 AND owner_id = ?2 AND deleted_at IS NOT NULL",
+                    [id, owner],
+                )
+                .map_err(db_error)?;
+            expect_one(id, affected)
+        })
+        .await
+    }
+
+    async fn purge_trashed_before(&self, cutoff: DateTime<Utc>) -> Result<u64, RepositoryError> {
+        let cutoff = cutoff.format(TIMESTAMP_FORMAT_CHRONO).to_string();
+        self.with_conn(move |conn| {
+            let purged = conn
+                .execute("DELETE FROM todos WHERE deleted_at < ?1", [cutoff])
+                .map_err(db_error)?;
+            Ok(purged as u64)
+        })
+        .await
+    }
+
+    async fn search(
+        &self,
+        owner: i32,
+        query: &str,
+        list: Option<i32>,
+        tags: &[i32],
+    ) -> Result<Vec<Todo>, RepositoryError> {
+        let Some(query) = fts_query(query) else {
+            return Ok(Vec::new());
+        };
+        let tags = tag_array(tags);
+
+        self.with_conn(move |conn| {
+            let mut stmt = conn
+                .prepare(&format!(
+                    "SELECT {TODO_COLUMNS} FROM todos JOIN todos_fts ON todos_fts.rowid = todos.id WHERE todos_fts MATCH ?1 AND todos.owner_id = ?4 AND todos.completed = 0 AND todos.deleted_at IS NULL AND {} AND {}",
+                    list_filter(3),
+                    tag_filter(2)
+                ))
+                .map_err(db_error)?;
+            let mut todos = stmt
+                .query_map((query, tags, list, owner), todo_from_row)
+                .map_err(db_error)?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(db_error)?;
+            attach_details(conn, &mut todos)?;
+            Ok(todos)
+        })
+        .await
+    }
+
+    async fn move_todo(
+        &self,
+        owner: i32,
+        id: i32,
+        parent_id: Option<i32>,
+    ) -> Result<(), RepositoryError> {
+        self.with_conn(move |conn| {
+            let tx = conn.unchecked_transaction().map_err(db_error)?;
+            expect_todo(&tx, owner, id)?;
+            if let Some(parent_id) = parent_id {
+                expect_todo(&tx, owner, parent_id)?;
+
+                let cycle: bool = tx
+                    .query_row(
+                        &format!("{SUBTREE} SELECT EXISTS(SELECT 1 FROM subtree WHERE id = ?2)"),
+                        (id, parent_id),
+                        |row| row.get(0),
+                    )
+                    .map_err(db_error)?;
+                if cycle {
+                    return Err(RepositoryError::Conflict(MOVE_CYCLE_MESSAGE.into()));
+                }
+
+                let list_id = target_list(&tx, owner, None, Some(parent_id))?;
+                tx.execute(MOVE_SUBTREE_TO_LIST, (id, list_id))
+                    .map_err(db_error)?;
+            }
+
+            tx.execute(
+                "UPDATE todos SET parent_id = ?2 WHERE id = ?1",
+                (id, parent_id),
+            )
+            .map_err(db_error)?;
+            tx.execute(REOPEN_PARENTS, [id]).map_err(db_error)?;
+            tx.commit().map_err(db_error)
+        })
+        .await
+    }
+
+    async fn move_to_list(&self, owner: i32, id: i32, list_id: i32) -> Result<(), RepositoryError> {
+        self.with_conn(move |conn| {
+            let tx = conn.unchecked_transaction().map_err(db_error)?;
+            move_subtree_to_list(&tx, owner, id, list_id)?;
+            tx.commit().map_err(db_error)
+        })
+        .await
+    }
+
+    async fn list_tags(&self, owner: i32) -> Result<Vec<Tag>, RepositoryError> {
+        self.with_conn(move |conn| {
+            let mut stmt = conn
+                .prepare(&format!(
+                    "SELECT {TAG_COLUMNS} FROM tags WHERE owner_id = ?1 ORDER BY name"
+                ))
+                .map_err(db_error)?;
+            let tags = stmt
+                .query_map([owner], tag_from_row)
+                .map_err(db_error)?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(db_error)?;
+            Ok(tags)
+        })
+        .await
+    }
+
+    async fn create_tag(
+        &self,
+        owner: i32,
+        name: String,
+        colour: String,
+    ) -> Result<Tag, RepositoryError> {
+        self.with_conn(move |conn| {
+            conn.query_row(
+                &format!(
+                    "INSERT INTO tags(name, colour, owner_id) VALUES(?1, ?2, ?3) RETURNING {TAG_COLUMNS}"
+                ),
+                (&name, &colour, owner),
+                tag_from_row,
+            )
+            .map_err(db_error)
+        })
+        .await
+    }
+
+    async fn rename_tag(&self, owner: i32, id: i32, name: String) -> Result<(), RepositoryError> {
+        self.with_conn(move |conn| {
+            let affected = conn
+                .execute(
+                    "UPDATE tags SET name = ?1 WHERE id = ?2 AND owner_id = ?3",
+                    (&name, id, owner),
+                )
+                .map_err(db_error)?;
+            expect_tag(id, affected)
+        })
+        .await
+    }
+
+    async fn set_tag_colour(
+        &self,
+        owner: i32,
+        id: i32,
+        colour: String,
+    ) -> Result<(), RepositoryError> {
+        self.with_conn(move |conn| {
+            let affected = conn
+                .execute(
+                    "UPDATE tags SET colour = ?1 WHERE id = ?2 AND owner_id = ?3",
+                    (&colour, id, owner),
+                )
+                .map_err(db_error)?;
+            expect_tag(id, affected)
+        })
+        .await
+    }
+
+    async fn delete_tag(&self, owner: i32, id: i32) -> Result<(), RepositoryError> {
+        self.with_conn(move |conn| {
+            let affected = conn
+                .execute(
+                    "DELETE FROM tags WHERE id = ?1 AND owner_id = ?2",
+                    [id, owner],
+                )
+                .map_err(db_error)?;
+            expect_tag(id, affected)
+        })
+        .await
+    }
+
+    async fn list_lists(&self, owner: i32) -> Result<Vec<List>, RepositoryError> {
+        self.with_conn(move |conn| {
+            let mut stmt = conn.prepare(LISTS).map_err(db_error)?;
+            let lists = stmt
+                .query_map([owner], list_from_row)
+                .map_err(db_error)?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(db_error)?;
+            Ok(lists)
+        })
+        .await
+    }
+
+    async fn create_list(
+        &self,
+        owner: i32,
+        name: String,
+        colour: String,
+    ) -> Result<List, RepositoryError> {
+        self.with_conn(move |conn| {
+            conn.query_row(
+                "INSERT INTO lists(name, colour, position, owner_id) \
+                 SELECT ?1, ?2, coalesce(max(position) + 1, 0), ?3 FROM lists WHERE owner_id = ?3 \
+                 RETURNING id, name, colour, 0",
+                (&name, &colour, owner),
+                list_from_row,
+            )
+            .map_err(db_error)
+        })
+        .await
+    }
+
+    async fn rename_list(&self, owner: i32, id: i32, name: String) -> Result<(), RepositoryError> {
+        self.with_conn(move |conn| {
+            let affected = conn
+                .execute(
+                    "UPDATE lists SET name = ?1 WHERE id = ?2 AND owner_id = ?3",
+                    (&name, id, owner),
+                )
+                .map_err(db_error)?;
+            expect_list(id, affected)
+        })
+        .await
+    }
+
+    async fn set_list_colour(
+        &self,
+        owner: i32,
+        id: i32,
+        colour: String,
+    ) -> Result<(), RepositoryError> {
+        self.with_conn(move |conn| {
+            let affected = conn
+                .execute(
+                    "UPDATE lists SET colour = ?1 WHERE id = ?2 AND owner_id = ?3",
+                    (&colour, id, owner),
+                )
+                .map_err(db_error)?;
+            expect_list(id, affected)
+        })
+        .await
+    }
+
+    async fn move_list(&self, owner: i32, id: i32, position: u32) -> Result<(), RepositoryError> {
+        self.with_conn(move |conn| {
+            let tx = conn.unchecked_transaction().map_err(db_error)?;
+            let mut ids = list_ids(&tx, owner, id)?;
+            move_to(&mut ids, id, position);
+
+            for (position, id) in ids.iter().enumerate() {
+                tx.execute(
+                    "UPDATE lists SET position = ?1 WHERE id = ?2",
+                    (position, id),
+                )
+                .map_err(db_error)?;
+            }
+            tx.commit().map_err(db_error)
+        })
+        .await
+    }
+
+    async fn delete_list(&self, owner: i32, id: i32) -> Result<(), RepositoryError> {
+        self.with_conn(move |conn| {
+            let tx = conn.unchecked_transaction().map_err(db_error)?;
+            if list_ids(&tx, owner, id)?.len() == 1 {
+                return Err(RepositoryError::Conflict(LAST_LIST_MESSAGE.into()));
+            }
+            tx.execute("DELETE FROM lists WHERE id = ?1", [id])
+                .map_err(db_error)?;
+            tx.commit().map_err(db_error)
+        })
+        .await
+    }
+
+    async fn add_checklist_item(
+        &self,
+        owner: i32,
+        todo_id: i32,
+        text: String,
+    ) -> Result<ChecklistItem, RepositoryError> {
+        self.with_conn(move |conn| {
+            let stmt = "INSERT INTO checklist_items(todo_id, position, text) \
+                SELECT id, (SELECT coalesce(max(position) + 1, 0) FROM checklist_items WHERE todo_id = ?1), ?2 \
+                FROM todos WHERE id = ?1 AND owner_id = ?3 AND deleted_at IS NULL \
+                RETURNING id, text, done";
+            match conn.query_row(stmt, (todo_id, &text, owner), checklist_item_from_row) {
+                Err(rusqlite::Error::QueryReturnedNoRows) => Err(RepositoryError::NotFound(todo_id)),
+                result => result.map_err(db_error),
+            }
+        })
+        .await
+    }
+
+    async fn set_checklist_item_done(
+        &self,
+        owner: i32,
+        id: i32,
+        done: bool,
+    ) -> Result<(), RepositoryError> {
+        self.with_conn(move |conn| {
+            let affected = conn
+                .execute(
+                    "UPDATE checklist_items SET done = ?1 \
+                     WHERE id = ?2 AND todo_id IN (SELECT id FROM todos WHERE owner_id = ?3)",
+                    (done, id, owner),
+                )
+                .map_err(db_error)?;
+            expect_checklist_item(id, affected)
+        })
+        .await
+    }
+
+    async fn move_checklist_item(
+        &self,
+        owner: i32,
+        id: i32,
+        position: u32,
+    ) -> Result<(), RepositoryError> {
+        self.with_conn(move |conn| {
+            let tx = conn.unchecked_transaction().map_err(db_error)?;
+            let todo_id: i32 = match tx.query_row(
+                "SELECT todo_id FROM checklist_items \
+                 WHERE id = ?1 AND todo_id IN (SELECT id FROM todos WHERE owner_id = ?2)",
+                [id, owner],
+                |row| row.get(0),
+            ) {
+                Err(rusqlite::Error::QueryReturnedNoRows) => {
+                    return Err(RepositoryError::ChecklistItemNotFound(id))
+                }
+                result => result.map_err(db_error)?,
+            };
+
+            let mut ids = tx
+                .prepare("SELECT id FROM checklist_items WHERE todo_id = ?1 ORDER BY position, id")
+                .map_err(db_error)?
+                .query_map([todo_id], |row| row.get(0))
+                .map_err(db_error)?
+                .collect::<Result<Vec<i32>, _>>()
+                .map_err(db_error)?;
+            move_to(&mut ids, id, position);
+
+            for (position, id) in ids.iter().enumerate() {
+                tx.execute(
+                    "UPDATE checklist_items SET position = ?1 WHERE id = ?2",
+                    (position, id),
+                )
+                .map_err(db_error)?;
+            }
+            tx.commit().map_err(db_error)
+        })
+        .await
+    }
+
+    async fn delete_checklist_item(&self, owner: i32, id: i32) -> Result<(), RepositoryError> {
+        self.with_conn(move |conn| {
+            let affected = conn
+                .execute(
+                    "DELETE FROM checklist_items \
+                     WHERE id = ?1 AND todo_id IN (SELECT id FROM todos WHERE owner_id = ?2)",
+                    [id, owner],
+                )
+                .map_err(db_error)?;
+            expect_checklist_item(id, affected)
+        })
+        .await
+    }
+
+    async fn export(&self, owner: i32) -> Result<Snapshot, RepositoryError> {
+        self.with_conn(move |conn| {
+            let tx = conn.unchecked_transaction().map_err(db_error)?;
+            let lists = tx
+                .prepare(LISTS)
+                .map_err(db_error)?
+                .query_map([owner], list_from_row)
+                .map_err(db_error)?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(db_error)?;
+            let tags = tx
+                .prepare(&format!(
+                    "SELECT {TAG_COLUMNS} FROM tags WHERE owner_id = ?1 ORDER BY name"
+                ))
+                .map_err(db_error)?
+                .query_map([owner], tag_from_row)
+                .map_err(db_error)?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(db_error)?;
+            let mut todos = tx
+                .prepare(&format!(
+                    "SELECT {TODO_COLUMNS} FROM todos WHERE todos.owner_id = ?1 ORDER BY todos.created, todos.id"
+                ))
+                .map_err(db_error)?
+                .query_map([owner], todo_from_row)
+                .map_err(db_error)?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(db_error)?;
+            attach_details(&tx, &mut todos)?;
+            tx.commit().map_err(db_error)?;
+
+            Ok(Snapshot { lists, tags, todos })
+        })
+        .await
+    }
+
+    async fn import(
+        &self,
+        owner: i32,
+        backup: BackupImport,
+        dry_run: bool,
+    ) -> Result<ImportReport, RepositoryError> {
+        self.with_conn(move |conn| {
+            let tx = conn.unchecked_transaction().map_err(db_error)?;
+            let mut report = backup.report(dry_run);
+
+            let mut lists = HashMap::new();
+            for (id, list) in &backup.lists {
+                let existing = tx.query_row(
+                    "SELECT id FROM lists WHERE owner_id = ?1 AND name = ?2",
+                    (owner, &list.name),
+                    |row| row.get(0),
+                );
+                let new_id: i32 = match existing {
+                    Ok(id) => {
+                        report.merged_lists.push(list.name.clone());
+                        id
+                    }
+                    Err(rusqlite::Error::QueryReturnedNoRows) => {
+                        report.new_lists.push(list.name.clone());
+                        tx.query_row(
+                            "INSERT INTO lists(name, colour, position, owner_id) \
+                             SELECT ?1, ?2, coalesce(max(position) + 1, 0), ?3 FROM lists WHERE owner_id = ?3 \
+                             RETURNING id",
+                            (&list.name, &list.colour, owner),
+                            |row| row.get(0),
+                        )
+                        .map_err(db_error)?
+                    }
+                    Err(e) => return Err(db_error(e)),
+                };
+                lists.insert(*id, new_id);
+            }
+
+            let mut tags = HashMap::new();
+            for (id, tag) in &backup.tags {
+                let existing = tx.query_row(
+                    "SELECT id FROM tags WHERE owner_id = ?1 AND name = ?2",
+                    (owner, &tag.name),
+                    |row| row.get(0),
+                );
+                let new_id: i32 = match existing {
+                    Ok(id) => {
+                        report.merged_tags.push(tag.name.clone());
+                        id
+                    }
+                    Err(rusqlite::Error::QueryReturnedNoRows) => {
+                        report.new_tags.push(tag.name.clone());
+                        tx.query_row(
+                            "INSERT INTO tags(name, colour, owner_id) VALUES(?1, ?2, ?3) RETURNING id",
+                            (&tag.name, &tag.colour, owner),
+                            |row| row.get(0),
+                        )
+                        .map_err(db_error)?
+                    }
+                    Err(e) => return Err(db_error(e)),
+                };
+                tags.insert(*id, new_id);
+            }
+
+            let timestamp =
+                |at: Option<DateTime<Utc>>| at.map(|at| at.format(TIMESTAMP_FORMAT_CHRONO).to_string());
+            let mut todos = HashMap::new();
+            for todo in &backup.todos {
+                let parent_id = todo
+                    .parent_id
+                    .map(|id| remap(&todos, id))
+                    .transpose()?;
+                let new_id: i32 = tx
+                    .query_row(
+                        &format!("INSERT INTO todos(title, description, due_date, priority, parent_id, recurrence, list_id, owner_id, \
+                            completed, completed_at, deleted_at, created) \
+                            VALUES(?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, coalesce(?12, strftime('{TIMESTAMP_FORMAT}', 'now'))) RETURNING id"),
+                        rusqlite::params![
+                            &todo.input.title,
+                            &todo.input.description,
+                            todo.input.due_date.to_string(),
+                            todo.input.priority.level(),
+                            parent_id,
+                            todo.input.recurrence.as_ref().map(ToString::to_string),
+                            remap(&lists, todo.list_id)?,
+                            owner,
+                            todo.completed,
+                            timestamp(todo.completed_at),
+                            timestamp(todo.deleted_at),
+                            timestamp(todo.created),
+                        ],
+                        |row| row.get(0),
+                    )
+                    .map_err(db_error)?;
+                todos.insert(todo.id, new_id);
+
+                let tag_ids = todo
+                    .input
+                    .tag_ids
+                    .iter()
+                    .map(|id| remap(&tags, *id))
+                    .collect::<Result<Vec<_>, _>>()?;
+                set_tags(&tx, owner, new_id, &tag_ids)?;
+
+                for (position, item) in todo.checklist.iter().enumerate() {
+                    tx.execute(
+                        "INSERT INTO checklist_items(todo_id, position, text, done) VALUES(?1, ?2, ?3, ?4)",
+                        (new_id, position, &item.text, item.done),
+                    )
+                    .map_err(db_error)?;
+                }
+            }
+
+            if dry_run {
+                tx.rollback().map_err(db_error)?;
+            } else {
+                tx.commit().map_err(db_error)?;
+            }
+            Ok(report)
+        })
+        .await
+    }
+}
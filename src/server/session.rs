@@ -0,0 +1,107 @@
+use crate::server::error::TodoError;
+use actix_session::storage::CookieSessionStore;
+use actix_session::{Session, SessionExt, SessionMiddleware};
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::cookie::Key;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::{header, Method};
+use actix_web::middleware::Next;
+use actix_web::HttpResponse;
+use serde::Deserialize;
+
+/// The cookie holding the session.
+pub const SESSION_COOKIE: &str = "todo_session";
+
+/// The session entry holding the logged-in user's id.
+const USER_ID_KEY: &str = "user_id";
+
+/// The pages visitors can see without logging in.
+const LOGIN_PAGES: [&str; 2] = ["/login", "/signup"];
+
+/// Paths served without a session check: server functions, which check for themselves,
+/// and static files.
+const PUBLIC_PREFIXES: [&str; 4] = ["/api/", "/pkg/", "/assets/", "/favicon.ico"];
+
+/// How login sessions are kept. The whole session lives in an encrypted cookie.
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct SessionSettings {
+    /// At least 64 bytes used to sign and encrypt session cookies. Without one, a random
+    /// key is made at startup and everyone is logged out when the server restarts.
+    pub key: Option<String>,
+    /// Only send the cookie over HTTPS. Turn this on when serving over HTTPS.
+    pub secure_cookie: bool,
+}
+
+impl SessionSettings {
+    /// The cookie key. Build it once and share it between workers.
+    pub fn key(&self) -> Key {
+        match &self.key {
+            Some(key) => Key::from(key.as_bytes()),
+            None => {
+                log::warn!("session.key is not set, sessions won't survive a restart");
+                Key::generate()
+            }
+        }
+    }
+
+    pub fn middleware(&self, key: Key) -> SessionMiddleware<CookieSessionStore> {
+        SessionMiddleware::builder(CookieSessionStore::default(), key)
+            .cookie_name(SESSION_COOKIE.into())
+            .cookie_secure(self.secure_cookie)
+            .build()
+    }
+}
+
+/// The id of the user logged into `session`, if any.
+pub fn session_user(session: &Session) -> Option<i32> {
+    session.get(USER_ID_KEY).ok().flatten()
+}
+
+/// Logs `user_id` into `session`, under a fresh session id.
+pub fn log_in(session: &Session, user_id: i32) -> Result<(), TodoError> {
+    session.renew();
+    session.insert(USER_ID_KEY, user_id).map_err(|e| {
+        log::error!("failed to store the session: {e}");
+        TodoError::Internal
+    })
+}
+
+pub fn log_out(session: &Session) {
+    session.purge();
+}
+
+/// Sends visitors who aren't logged in to the login page, and logged-in users away from
+/// it. Only page loads are redirected; see [`PUBLIC_PREFIXES`].
+pub async fn redirect_to_login(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    let path = req.path();
+    let logged_in = session_user(&req.get_session()).is_some();
+
+    let location = if req.method() != Method::GET
+        || PUBLIC_PREFIXES
+            .iter()
+            .any(|prefix| path.starts_with(prefix))
+    {
+        None
+    } else if LOGIN_PAGES.contains(&path) {
+        logged_in.then_some("/")
+    } else {
+        (!logged_in).then_some(LOGIN_PAGES[0])
+    };
+
+    match location {
+        Some(location) => {
+            let response = HttpResponse::SeeOther()
+                .insert_header((header::LOCATION, location))
+                .finish();
+            Ok(req.into_response(response).map_into_right_body())
+        }
+        None => next
+            .call(req)
+            .await
+            .map(ServiceResponse::map_into_left_body),
+    }
+}
@@ -0,0 +1,251 @@
+use crate::server::db::PoolSettings;
+use crate::server::session::SessionSettings;
+use crate::server::trash::TrashSettings;
+use config::{Config, ConfigError, Environment, File};
+use serde::Deserialize;
+use std::fmt;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+/// The file read when `TODO_SETTINGS` is not set. It is optional.
+const DEFAULT_SETTINGS_FILE: &str = "settings.toml";
+
+/// The shortest cookie key `actix_web::cookie::Key::from` accepts.
+const SESSION_KEY_MIN_BYTES: usize = 64;
+
+/// Application settings, loaded once at startup and shared by the actix server and
+/// every server function.
+///
+/// Values come from `settings.toml` (or the file named by `TODO_SETTINGS`), and can be
+/// overridden by environment variables prefixed with `TODO_`, using `__` between
+/// nested keys, e.g. `TODO_DATABASE__URL` or `TODO_DATABASE__POOL__MAX_SIZE`.
+#[derive(Deserialize, Clone)]
+pub struct Settings {
+    #[serde(default)]
+    pub database: DatabaseSettings,
+    #[serde(default)]
+    pub server: ServerSettings,
+    #[serde(default)]
+    pub trash: TrashSettings,
+    #[serde(default)]
+    pub session: SessionSettings,
+    #[serde(default = "default_log_level")]
+    pub log_level: log::LevelFilter,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct DatabaseSettings {
+    pub backend: DatabaseBackend,
+    /// The SQLite database file, used when `backend = "sqlite"`.
+    pub sqlite_path: PathBuf,
+    /// A `postgres://` URL or a `key=value` connection string.
+    pub url: String,
+    /// Overrides the user in `url`.
+    pub user: Option<String>,
+    /// Overrides the password in `url`.
+    pub password: Option<String>,
+    /// Overrides the sslmode in `url`.
+    pub sslmode: Option<SslMode>,
+    pub pool: PoolSettings,
+}
+
+impl Default for DatabaseSettings {
+    fn default() -> Self {
+        Self {
+            backend: DatabaseBackend::default(),
+            sqlite_path: PathBuf::from("todos.db"),
+            url: "host=localhost dbname=leptos".into(),
+            user: None,
+            password: None,
+            sslmode: None,
+            pool: PoolSettings::default(),
+        }
+    }
+}
+
+/// Where todo items are stored.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DatabaseBackend {
+    #[default]
+    Postgres,
+    /// Requires the `sqlite` cargo feature.
+    Sqlite,
+}
+
+#[derive(Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct ServerSettings {
+    /// The address to listen on. Defaults to the `site-addr` from the leptos options.
+    pub bind_address: Option<SocketAddr>,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SslMode {
+    Disable,
+    Prefer,
+    Require,
+}
+
+impl From<SslMode> for tokio_postgres::config::SslMode {
+    fn from(mode: SslMode) -> Self {
+        match mode {
+            SslMode::Disable => Self::Disable,
+            SslMode::Prefer => Self::Prefer,
+            SslMode::Require => Self::Require,
+        }
+    }
+}
+
+fn default_log_level() -> log::LevelFilter {
+    log::LevelFilter::Info
+}
+
+#[derive(Debug)]
+pub enum SettingsError {
+    /// The settings file or environment could not be read or deserialized.
+    Load(ConfigError),
+    /// A value was read but is not acceptable.
+    Invalid(String),
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingsError::Load(e) => write!(f, "could not load settings: {e}"),
+            SettingsError::Invalid(msg) => write!(f, "invalid settings: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for SettingsError {}
+
+impl From<ConfigError> for SettingsError {
+    fn from(e: ConfigError) -> Self {
+        SettingsError::Load(e)
+    }
+}
+
+impl Settings {
+    /// Loads and validates the settings, failing on the first bad value.
+    pub fn load() -> Result<Self, SettingsError> {
+        let path = std::env::var_os("TODO_SETTINGS")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_SETTINGS_FILE));
+
+        let settings: Settings = Config::builder()
+            .add_source(File::from(path).required(false))
+            .add_source(
+                Environment::with_prefix("TODO")
+                    .prefix_separator("_")
+                    .separator("__")
+                    .try_parsing(true),
+            )
+            .build()?
+            .try_deserialize()?;
+
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    fn validate(&self) -> Result<(), SettingsError> {
+        if self.database.backend == DatabaseBackend::Sqlite && !cfg!(feature = "sqlite") {
+            return Err(SettingsError::Invalid(
+                "database.backend = \"sqlite\" needs a build with the sqlite feature".into(),
+            ));
+        }
+
+        let pg_config = self.database.pg_config()?;
+        if pg_config.get_ssl_mode() == tokio_postgres::config::SslMode::Require {
+            return Err(SettingsError::Invalid(
+                "sslmode \"require\" needs TLS, which this build does not support".into(),
+            ));
+        }
+        if self.database.pool.max_size == 0 {
+            return Err(SettingsError::Invalid(
+                "database.pool.max_size must be at least 1".into(),
+            ));
+        }
+        if self.database.pool.acquire_timeout_secs == 0 {
+            return Err(SettingsError::Invalid(
+                "database.pool.acquire_timeout_secs must be at least 1".into(),
+            ));
+        }
+        if self
+            .session
+            .key
+            .as_ref()
+            .is_some_and(|key| key.len() < SESSION_KEY_MIN_BYTES)
+        {
+            return Err(SettingsError::Invalid(format!(
+                "session.key must be at least {SESSION_KEY_MIN_BYTES} bytes"
+            )));
+        }
+        if self.trash.purge_interval_secs == 0 {
+            return Err(SettingsError::Invalid(
+                "trash.purge_interval_secs must be at least 1".into(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl DatabaseSettings {
+    /// Builds the connection config from `url`, applying whichever of the user, password
+    /// and sslmode overrides are set.
+    pub fn pg_config(&self) -> Result<tokio_postgres::Config, SettingsError> {
+        let mut config: tokio_postgres::Config = self
+            .url
+            .parse()
+            .map_err(|e| SettingsError::Invalid(format!("database.url: {e}")))?;
+
+        if config.get_hosts().is_empty() {
+            return Err(SettingsError::Invalid(
+                "database.url must name a host".into(),
+            ));
+        }
+        if let Some(user) = &self.user {
+            config.user(user);
+        }
+        if let Some(password) = &self.password {
+            config.password(password);
+        }
+        if let Some(sslmode) = self.sslmode {
+            config.ssl_mode(sslmode.into());
+        }
+
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_postgres::config::SslMode as PgSslMode;
+
+    fn database(url: &str, sslmode: Option<SslMode>) -> DatabaseSettings {
+        DatabaseSettings {
+            url: url.into(),
+            sslmode,
+            ..DatabaseSettings::default()
+        }
+    }
+
+    #[test]
+    fn url_sslmode_is_kept_without_an_override() {
+        let config = database("host=db sslmode=disable", None)
+            .pg_config()
+            .unwrap();
+        assert_eq!(config.get_ssl_mode(), PgSslMode::Disable);
+    }
+
+    #[test]
+    fn sslmode_setting_overrides_the_url() {
+        let config = database("host=db sslmode=disable", Some(SslMode::Prefer))
+            .pg_config()
+            .unwrap();
+        assert_eq!(config.get_ssl_mode(), PgSslMode::Prefer);
+    }
+}
@@ -0,0 +1,76 @@
+use crate::server::error::TodoError;
+use leptos::{server, ServerFnError};
+use serde::{Deserialize, Serialize};
+
+/// A label that can be attached to any number of todo items.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Tag {
+    pub id: i32,
+    pub name: String,
+    /// A `#rrggbb` colour for the tag's chip.
+    pub colour: String,
+}
+
+#[cfg(feature = "ssr")]
+pub mod ssr {
+    pub use crate::server::auth::ssr::{authorize, Authorized};
+    pub use crate::server::error::{check_field, TodoError};
+    pub use crate::server::repository::Resource;
+    pub use crate::validation::tag::{check_colour, check_tag_name, validate_tag};
+}
+
+#[server]
+pub async fn get_tags() -> Result<Vec<Tag>, ServerFnError<TodoError>> {
+    use self::ssr::*;
+
+    let Authorized { owner, repository } = authorize([]).await?;
+    Ok(repository.list_tags(owner).await?)
+}
+
+#[server]
+pub async fn create_tag(name: String, colour: String) -> Result<(), ServerFnError<TodoError>> {
+    use self::ssr::*;
+
+    let valid = validate_tag(&name, &colour).map_err(TodoError::from)?;
+    let Authorized { owner, repository } = authorize([]).await?;
+
+    repository
+        .create_tag(owner, valid.name, valid.colour)
+        .await?;
+    Ok(())
+}
+
+#[server]
+pub async fn rename_tag(id: i32, name: String) -> Result<(), ServerFnError<TodoError>> {
+    use self::ssr::*;
+
+    check_field("name", &name, check_tag_name)?;
+    let Authorized { owner, repository } = authorize([Resource::Tag(id)]).await?;
+
+    repository
+        .rename_tag(owner, id, name.trim().to_string())
+        .await?;
+    Ok(())
+}
+
+#[server]
+pub async fn set_tag_colour(id: i32, colour: String) -> Result<(), ServerFnError<TodoError>> {
+    use self::ssr::*;
+
+    check_field("colour", &colour, check_colour)?;
+    let Authorized { owner, repository } = authorize([Resource::Tag(id)]).await?;
+
+    repository
+        .set_tag_colour(owner, id, colour.trim().to_ascii_lowercase())
+        .await?;
+    Ok(())
+}
+
+#[server]
+pub async fn delete_tag(id: i32) -> Result<(), ServerFnError<TodoError>> {
+    use self::ssr::*;
+
+    let Authorized { owner, repository } = authorize([Resource::Tag(id)]).await?;
+    repository.delete_tag(owner, id).await?;
+    Ok(())
+}
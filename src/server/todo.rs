@@ -1,17 +1,133 @@
+use crate::server::checklist::ChecklistItem;
+use crate::server::error::TodoError;
+use crate::server::recurrence::Recurrence;
+use crate::server::tag::Tag;
 use leptos::{server, ServerFnError};
 use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 
-#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct Todo {
     pub id: i32, // serial
     pub title: String,
     pub description: String,
     pub completed: bool,
+    /// When the item was completed, as an RFC 3339 UTC timestamp.
+    pub completed_at: Option<String>,
+    /// When the item was moved to the trash, as an RFC 3339 UTC timestamp.
+    pub deleted_at: Option<String>,
+    /// When the item was created, as an RFC 3339 UTC timestamp.
     pub created: Option<String>,
     pub due_date: String,
+    pub priority: Priority,
+    /// The attached tags, ordered by name.
+    pub tags: Vec<Tag>,
+    /// The checklist, in the user's order.
+    pub checklist: Vec<ChecklistItem>,
+    /// The list the item is filed under.
+    pub list_id: i32,
+    /// The item this is a sub-todo of, if any.
+    pub parent_id: Option<i32>,
+    /// How the item repeats once completed, if it does.
+    pub recurrence: Option<Recurrence>,
+    /// The sub-todos that aren't in the trash, oldest first. Only filled in by the
+    /// paginated lists.
+    pub children: Vec<Todo>,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+/// How urgent a todo item is. Stored as its level, from 0 for `None` to 4 for `Urgent`.
+#[derive(
+    Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+#[cfg_attr(feature = "ssr", derive(utoipa::ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    #[default]
+    None,
+    Low,
+    Medium,
+    High,
+    Urgent,
+}
+
+impl Priority {
+    /// Every priority, from least to most urgent.
+    pub const ALL: [Priority; 5] = [
+        Priority::None,
+        Priority::Low,
+        Priority::Medium,
+        Priority::High,
+        Priority::Urgent,
+    ];
+
+    pub fn level(self) -> i16 {
+        self as i16
+    }
+
+    /// The priority stored as `level`. Unknown levels are treated as `None`.
+    pub fn from_level(level: i16) -> Self {
+        Self::ALL
+            .get(level as usize)
+            .copied()
+            .unwrap_or(Priority::None)
+    }
+
+    /// The serialized name, as used in form fields.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Priority::None => "none",
+            Priority::Low => "low",
+            Priority::Medium => "medium",
+            Priority::High => "high",
+            Priority::Urgent => "urgent",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Priority::None => "None",
+            Priority::Low => "Low",
+            Priority::Medium => "Medium",
+            Priority::High => "High",
+            Priority::Urgent => "Urgent",
+        }
+    }
+}
+
+impl FromStr for Priority {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|priority| priority.as_str() == s)
+            .ok_or(())
+    }
+}
+
+/// How the open todo items are ordered.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "ssr", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    /// Newest first.
+    #[default]
+    Newest,
+    /// Most urgent first, then by due date, soonest first.
+    Priority,
+}
+
+impl SortOrder {
+    /// The serialized name, as used in query strings.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SortOrder::Newest => "newest",
+            SortOrder::Priority => "priority",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct PaginatedTodos {
     pub items: Vec<Todo>,
     pub total: u32,
@@ -21,105 +137,143 @@ pub struct PaginatedTodos {
 
 #[cfg(feature = "ssr")]
 pub mod ssr {
-    pub use chrono::{self, Datelike};
-    pub use tokio;
-    pub use tokio_postgres;
-    pub use tokio_postgres::{Client, NoTls};
+    pub use super::Priority;
+    pub use crate::server::auth::ssr::{authorize, Authorized};
+    pub use crate::server::error::TodoError;
+    pub use crate::server::repository::{Resource, TodoInput};
+    pub use crate::validation::recurrence::parse_recurrence;
+    pub use crate::validation::todo::{validate_todo, FieldError};
+
+    /// Validates the submitted fields, collecting an error for each bad one.
+    pub fn todo_input(
+        title: &str,
+        description: &str,
+        due_date: &str,
+        priority: Priority,
+        tag_ids: Vec<i32>,
+        recurrence: &str,
+    ) -> Result<TodoInput, TodoError> {
+        match (
+            validate_todo(title, description, due_date),
+            parse_recurrence(recurrence),
+        ) {
+            (Ok(valid), Ok(recurrence)) => Ok(TodoInput {
+                title: valid.title,
+                description: valid.description,
+                due_date: valid.due_date,
+                priority,
+                tag_ids: tag_set(tag_ids),
+                recurrence,
+            }),
+            (valid, recurrence) => {
+                let mut errors = valid.err().unwrap_or_default();
+                if let Err(e) = recurrence {
+                    errors.push(FieldError::new("recurrence", e.to_string()));
+                }
+                Err(errors.into())
+            }
+        }
+    }
+
+    /// Sorts the tag ids and drops duplicates, so a filter counts each tag once.
+    pub fn tag_set(mut tag_ids: Vec<i32>) -> Vec<i32> {
+        tag_ids.sort_unstable();
+        tag_ids.dedup();
+        tag_ids
+    }
 }
 
+/// Returns a page of open todo items, from every list unless `list` is given.
 #[server]
-pub async fn get_paginated_todos(page: u32) -> Result<PaginatedTodos, ServerFnError> {
+pub async fn get_paginated_todos(
+    page: u32,
+    #[server(default)] tags: Vec<i32>,
+    #[server(default)] sort: SortOrder,
+    #[server(default)] list: Option<i32>,
+) -> Result<PaginatedTodos, ServerFnError<TodoError>> {
     use self::ssr::*;
 
-    let (client, connection) =
-        tokio_postgres::connect("host=localhost dbname=leptos", NoTls).await?;
+    let Authorized { owner, repository } = authorize(
+        tags.iter()
+            .copied()
+            .map(Resource::Tag)
+            .chain(list.map(Resource::List)),
+    )
+    .await?;
+    Ok(repository
+        .paginate(owner, page, 10, list, &tag_set(tags), sort)
+        .await?)
+}
 
-    tokio::spawn(async move {
-        if let Err(e) = connection.await {
-            eprintln!("connection error: {}", e);
-        }
-    });
-
-    let offset = (&page * 10) as i64;
-    let stmt = "SELECT id, title, description, to_char(due_date, 'YYYY-MM-DD') FROM todos WHERE completed = false ORDER BY created DESC LIMIT 10 OFFSET $1";
-
-    let todos = client
-        .query(stmt, &[&offset])
-        .await?
-        .into_iter()
-        .map(|row| Todo {
-            id: row.get(0),
-            title: row.get(1),
-            description: row.get(2),
-            completed: false,
-            created: None,
-            due_date: row.get(3),
-        })
-        .collect::<Vec<_>>();
-
-    let total = client
-        .query_one("SELECT count(1) FROM todos WHERE completed = false", &[])
-        .await?
-        .get::<usize, i64>(0) as u32;
-
-    Ok(PaginatedTodos {
-        items: todos,
-        total,
-        page,
-        total_pages: (total + 10 - 1) / 10,
-    })
+#[server]
+pub async fn get_completed_todos(page: u32) -> Result<PaginatedTodos, ServerFnError<TodoError>> {
+    use self::ssr::*;
+
+    let Authorized { owner, repository } = authorize([]).await?;
+    Ok(repository.paginate_completed(owner, page, 10).await?)
 }
 
+/// Adds a todo item to `list_id`, or to the first list if it's missing. A sub-todo of
+/// `parent_id` goes in its parent's list. `recurrence` is a rule as written by
+/// [`Recurrence`], or empty if the item doesn't repeat.
+// Each argument is a field of the add form.
+#[allow(clippy::too_many_arguments)]
 #[server]
 pub async fn add_todo(
     title: String,
     description: String,
     due_date: String,
-) -> Result<(), ServerFnError> {
+    #[server(default)] priority: Priority,
+    #[server(default)] tag_ids: Vec<i32>,
+    #[server(default)] parent_id: Option<i32>,
+    #[server(default)] recurrence: String,
+    #[server(default)] list_id: Option<i32>,
+) -> Result<(), ServerFnError<TodoError>> {
     use self::ssr::*;
 
-    let (client, connection) =
-        tokio_postgres::connect("host=localhost dbname=leptos", NoTls).await?;
-
-    tokio::spawn(async move {
-        if let Err(e) = connection.await {
-            eprintln!("connection error: {}", e);
-        }
-    });
-
-    let today = chrono::offset::Local::now().date_naive();
-    let (year, month, day) = {
-        let ymd: Vec<&str> = due_date.split("-").collect();
-        (
-            ymd[0].parse::<i32>().unwrap_or(today.year()),
-            ymd[1].parse::<u32>().unwrap_or(today.month()),
-            ymd[2].parse::<u32>().unwrap_or(today.day()),
-        )
-    };
-    let pg_date = chrono::NaiveDate::from_ymd_opt(year, month, day).unwrap_or(today);
-
-    let stmt = "INSERT INTO todos(title, description, due_date) VALUES($1, $2, $3)";
-    let _ = client
-        .execute(stmt, &[&title, &description, &pg_date])
-        .await?;
+    let input = todo_input(
+        &title,
+        &description,
+        &due_date,
+        priority,
+        tag_ids,
+        &recurrence,
+    )?;
+    let Authorized { owner, repository } = authorize(
+        input
+            .tag_ids
+            .iter()
+            .copied()
+            .map(Resource::Tag)
+            .chain(parent_id.map(Resource::Todo))
+            .chain(list_id.map(Resource::List)),
+    )
+    .await?;
+
+    repository.create(owner, input, list_id, parent_id).await?;
     Ok(())
 }
 
+/// Completes the todo item and its sub-todos, and every item of its checklist if
+/// `complete_checklist` is set. A repeating item is followed by its next occurrence.
 #[server]
-pub async fn complete_todo(id: i32) -> Result<(), ServerFnError> {
+pub async fn complete_todo(
+    id: i32,
+    #[server(default)] complete_checklist: bool,
+) -> Result<(), ServerFnError<TodoError>> {
     use self::ssr::*;
 
-    let (client, connection) =
-        tokio_postgres::connect("host=localhost dbname=leptos", NoTls).await?;
+    let Authorized { owner, repository } = authorize([Resource::Todo(id)]).await?;
+    repository.complete(owner, id, complete_checklist).await?;
+    Ok(())
+}
 
-    tokio::spawn(async move {
-        if let Err(e) = connection.await {
-            eprintln!("connection error: {}", e);
-        }
-    });
+#[server]
+pub async fn uncomplete_todo(id: i32) -> Result<(), ServerFnError<TodoError>> {
+    use self::ssr::*;
 
-    let stmt = "UPDATE todos SET completed = true WHERE id = $1";
-    let _ = client.execute(stmt, &[&id]).await?;
+    let Authorized { owner, repository } = authorize([Resource::Todo(id)]).await?;
+    repository.uncomplete(owner, id).await?;
     Ok(())
 }
 
@@ -129,89 +283,115 @@ pub async fn update_todo(
     title: String,
     description: String,
     due_date: String,
-) -> Result<(), ServerFnError> {
+    #[server(default)] priority: Priority,
+    #[server(default)] tag_ids: Vec<i32>,
+    #[server(default)] recurrence: String,
+) -> Result<(), ServerFnError<TodoError>> {
     use self::ssr::*;
 
-    if title == "" {
-        return Err(ServerFnError::Args("title cannot be empty".into()));
-    }
+    let input = todo_input(
+        &title,
+        &description,
+        &due_date,
+        priority,
+        tag_ids,
+        &recurrence,
+    )?;
+    let Authorized { owner, repository } = authorize(
+        input
+            .tag_ids
+            .iter()
+            .copied()
+            .map(Resource::Tag)
+            .chain([Resource::Todo(id)]),
+    )
+    .await?;
+
+    repository.update(owner, id, input).await?;
+    Ok(())
+}
 
-    if due_date == "" {
-        return Err(ServerFnError::Args("due_date cannot be empty".into()));
-    }
+/// Makes the todo item a sub-todo of `parent_id`, or a top-level item if it's missing.
+#[server]
+pub async fn move_todo(
+    id: i32,
+    #[server(default)] parent_id: Option<i32>,
+) -> Result<(), ServerFnError<TodoError>> {
+    use self::ssr::*;
 
-    let (client, connection) =
-        tokio_postgres::connect("host=localhost dbname=leptos", NoTls).await?;
+    let Authorized { owner, repository } = authorize(
+        parent_id
+            .map(Resource::Todo)
+            .into_iter()
+            .chain([Resource::Todo(id)]),
+    )
+    .await?;
+    repository.move_todo(owner, id, parent_id).await?;
+    Ok(())
+}
 
-    tokio::spawn(async move {
-        if let Err(e) = connection.await {
-            eprintln!("connection error: {}", e);
-        }
-    });
-
-    let today = chrono::offset::Local::now().date_naive();
-    let (year, month, day) = {
-        let ymd: Vec<&str> = due_date.split("-").collect();
-        (
-            ymd[0].parse::<i32>().unwrap_or(today.year()),
-            ymd[1].parse::<u32>().unwrap_or(today.month()),
-            ymd[2].parse::<u32>().unwrap_or(today.day()),
-        )
-    };
-    let pg_date = chrono::NaiveDate::from_ymd_opt(year, month, day).unwrap_or(today);
-
-    let stmt = "UPDATE todos SET title = $1, description = $2, due_date = $3 WHERE id = $4";
-    let _ = client
-        .execute(stmt, &[&title, &description, &pg_date, &id])
-        .await?;
+/// Files the todo item and its sub-todos under another list.
+#[server]
+pub async fn move_todo_to_list(id: i32, list_id: i32) -> Result<(), ServerFnError<TodoError>> {
+    use self::ssr::*;
+
+    let Authorized { owner, repository } =
+        authorize([Resource::Todo(id), Resource::List(list_id)]).await?;
+    repository.move_to_list(owner, id, list_id).await?;
     Ok(())
 }
 
 #[server]
-pub async fn delete_todo(id: i32) -> Result<(), ServerFnError> {
+pub async fn delete_todo(id: i32) -> Result<(), ServerFnError<TodoError>> {
     use self::ssr::*;
 
-    let (client, connection) =
-        tokio_postgres::connect("host=localhost dbname=leptos", NoTls).await?;
+    let Authorized { owner, repository } = authorize([Resource::Todo(id)]).await?;
+    repository.delete(owner, id).await?;
+    Ok(())
+}
 
-    tokio::spawn(async move {
-        if let Err(e) = connection.await {
-            eprintln!("connection error: {}", e);
-        }
-    });
+#[server]
+pub async fn get_trashed_todos(page: u32) -> Result<PaginatedTodos, ServerFnError<TodoError>> {
+    use self::ssr::*;
+
+    let Authorized { owner, repository } = authorize([]).await?;
+    Ok(repository.paginate_trash(owner, page, 10).await?)
+}
 
-    let stmt = "DELETE FROM todos WHERE id = $1";
-    let _ = client.execute(stmt, &[&id]).await?;
+#[server]
+pub async fn restore_todo(id: i32) -> Result<(), ServerFnError<TodoError>> {
+    use self::ssr::*;
+
+    let Authorized { owner, repository } = authorize([Resource::Todo(id)]).await?;
+    repository.restore(owner, id).await?;
     Ok(())
 }
 
 #[server]
-pub async fn search_todo(query: String) -> Result<Vec<Todo>, ServerFnError> {
+pub async fn purge_todo(id: i32) -> Result<(), ServerFnError<TodoError>> {
     use self::ssr::*;
 
-    let (client, connection) =
-        tokio_postgres::connect("host=localhost dbname=leptos", NoTls).await?;
+    let Authorized { owner, repository } = authorize([Resource::Todo(id)]).await?;
+    repository.purge(owner, id).await?;
+    Ok(())
+}
+
+#[server]
+pub async fn search_todo(
+    query: String,
+    #[server(default)] tags: Vec<i32>,
+    #[server(default)] list: Option<i32>,
+) -> Result<Vec<Todo>, ServerFnError<TodoError>> {
+    use self::ssr::*;
 
-    tokio::spawn(async move {
-        if let Err(e) = connection.await {
-            eprintln!("connection error: {}", e);
-        }
-    });
-
-    let stmt =
-        "SELECT id, title, description, to_char(due_date, 'YYYY-MM-DD') FROM todos WHERE title::tsvector @@ plainto_tsquery($1) AND completed = false";
-    let rows = client
-        .query(stmt, &[&query])
-        .await?
-        .into_iter()
-        .map(|r| Todo {
-            id: r.get(0),
-            title: r.get(1),
-            description: r.get(2),
-            completed: false,
-            created: None,
-            due_date: r.get(3),
-        })
-        .collect::<Vec<_>>();
-    Ok(rows)
+    let Authorized { owner, repository } = authorize(
+        tags.iter()
+            .copied()
+            .map(Resource::Tag)
+            .chain(list.map(Resource::List)),
+    )
+    .await?;
+    Ok(repository
+        .search(owner, &query, list, &tag_set(tags))
+        .await?)
 }
@@ -0,0 +1,60 @@
+use crate::server::repository::SharedTodoRepository;
+use chrono::Utc;
+use serde::Deserialize;
+use std::time::Duration;
+
+/// How long trashed todo items are kept before they are deleted for good.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct TrashSettings {
+    /// Trashed items older than this are purged. 0 keeps them until deleted by hand.
+    pub retention_days: u64,
+    /// How often to look for items past the retention period.
+    pub purge_interval_secs: u64,
+}
+
+impl Default for TrashSettings {
+    fn default() -> Self {
+        Self {
+            retention_days: 30,
+            purge_interval_secs: 3600,
+        }
+    }
+}
+
+impl TrashSettings {
+    pub fn retention(&self) -> Option<Duration> {
+        (self.retention_days > 0).then(|| Duration::from_secs(self.retention_days * 24 * 60 * 60))
+    }
+
+    pub fn purge_interval(&self) -> Duration {
+        Duration::from_secs(self.purge_interval_secs)
+    }
+}
+
+/// Periodically purges items that have been in the trash for longer than `retention`.
+pub fn spawn_purge_task(
+    repository: SharedTodoRepository,
+    retention: Duration,
+    purge_interval: Duration,
+) {
+    let Ok(retention) = chrono::Duration::from_std(retention) else {
+        log::warn!("trash retention period is too long, trashed items won't be purged");
+        return;
+    };
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(purge_interval);
+        loop {
+            interval.tick().await;
+            match repository
+                .purge_trashed_before(Utc::now() - retention)
+                .await
+            {
+                Ok(0) => {}
+                Ok(purged) => log::info!("purged {purged} todo items from the trash"),
+                Err(e) => log::warn!("failed to purge the trash: {e:?}"),
+            }
+        }
+    });
+}
@@ -0,0 +1,15 @@
+/// Matches the `varchar(200)` text column.
+pub const CHECKLIST_TEXT_MAX_CHARS: usize = 200;
+
+pub fn check_checklist_text(text: &str) -> Option<String> {
+    let text = text.trim();
+    if text.is_empty() {
+        Some("checklist item text is required".into())
+    } else if text.chars().count() > CHECKLIST_TEXT_MAX_CHARS {
+        Some(format!(
+            "checklist item text must be at most {CHECKLIST_TEXT_MAX_CHARS} characters"
+        ))
+    } else {
+        None
+    }
+}
@@ -0,0 +1,106 @@
+use chrono::NaiveDate;
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DateError {
+    Empty,
+    /// Not in the `YYYY-MM-DD` format.
+    Malformed,
+    /// Well-formed but not on the calendar, e.g. `2024-02-30`.
+    Impossible,
+}
+
+impl fmt::Display for DateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DateError::Empty => write!(f, "due date is required"),
+            DateError::Malformed => write!(f, "due date must be in the format YYYY-MM-DD"),
+            DateError::Impossible => write!(f, "due date is not a real calendar date"),
+        }
+    }
+}
+
+/// Parses a due date in the `YYYY-MM-DD` format produced by `<input type="date">`.
+pub fn parse_due_date(input: &str) -> Result<NaiveDate, DateError> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Err(DateError::Empty);
+    }
+
+    let parts = input.split('-').collect::<Vec<_>>();
+    let [year, month, day] = parts[..] else {
+        return Err(DateError::Malformed);
+    };
+    let well_formed = [(year, 4), (month, 2), (day, 2)]
+        .iter()
+        .all(|(part, len)| part.len() == *len && part.bytes().all(|b| b.is_ascii_digit()));
+    if !well_formed {
+        return Err(DateError::Malformed);
+    }
+
+    // The digit checks above guarantee these parse.
+    let (year, month, day) = (
+        year.parse().unwrap_or_default(),
+        month.parse().unwrap_or_default(),
+        day.parse().unwrap_or_default(),
+    );
+    NaiveDate::from_ymd_opt(year, month, day).ok_or(DateError::Impossible)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_calendar_dates() {
+        let expected = NaiveDate::from_ymd_opt(2024, 2, 29).unwrap();
+        assert_eq!(parse_due_date("2024-02-29"), Ok(expected));
+        assert_eq!(parse_due_date("  2024-02-29\n"), Ok(expected));
+        assert_eq!(
+            parse_due_date("0001-01-01"),
+            Ok(NaiveDate::from_ymd_opt(1, 1, 1).unwrap())
+        );
+    }
+
+    #[test]
+    fn rejects_months_and_days_off_the_calendar() {
+        for input in ["2024-00-10", "2024-13-10", "2024-01-00", "2024-01-32"] {
+            assert_eq!(parse_due_date(input), Err(DateError::Impossible), "{input}");
+        }
+        assert_eq!(parse_due_date("2023-02-29"), Err(DateError::Impossible));
+        assert_eq!(parse_due_date("2024-04-31"), Err(DateError::Impossible));
+    }
+
+    #[test]
+    fn rejects_parts_that_are_not_digits() {
+        for input in [
+            "20x4-01-10",
+            "2024-o1-10",
+            "2024-01-1a",
+            "+024-01-10",
+            "2024-+1-10",
+            "2024-01-١٠",
+        ] {
+            assert_eq!(parse_due_date(input), Err(DateError::Malformed), "{input}");
+        }
+    }
+
+    #[test]
+    fn rejects_other_layouts() {
+        for input in [
+            "2024-1-10",
+            "24-01-10",
+            "2024/01/10",
+            "2024-01",
+            "2024-01-10-01",
+        ] {
+            assert_eq!(parse_due_date(input), Err(DateError::Malformed), "{input}");
+        }
+    }
+
+    #[test]
+    fn rejects_empty_input() {
+        assert_eq!(parse_due_date(""), Err(DateError::Empty));
+        assert_eq!(parse_due_date("   "), Err(DateError::Empty));
+    }
+}
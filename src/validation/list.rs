@@ -0,0 +1,39 @@
+use super::tag::check_colour;
+use super::todo::{check_all, FieldError};
+
+/// Matches the `varchar(50)` name column.
+pub const LIST_NAME_MAX_CHARS: usize = 50;
+
+/// The fields of a list after they passed validation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValidList {
+    pub name: String,
+    pub colour: String,
+}
+
+pub fn check_list_name(name: &str) -> Option<String> {
+    let name = name.trim();
+    if name.is_empty() {
+        Some("list name is required".into())
+    } else if name.chars().count() > LIST_NAME_MAX_CHARS {
+        Some(format!(
+            "list name must be at most {LIST_NAME_MAX_CHARS} characters"
+        ))
+    } else {
+        None
+    }
+}
+
+/// Validates the name and colour a list is created or edited with, taking the colour
+/// rules from tags.
+pub fn validate_list(name: &str, colour: &str) -> Result<ValidList, Vec<FieldError>> {
+    check_all([
+        ("name", check_list_name(name)),
+        ("colour", check_colour(colour)),
+    ])?;
+
+    Ok(ValidList {
+        name: name.trim().to_string(),
+        colour: colour.trim().to_ascii_lowercase(),
+    })
+}
@@ -0,0 +1,10 @@
+//! Input rules shared by the server functions and the forms, so the browser can reject
+//! bad input before it is submitted.
+
+pub mod checklist;
+pub mod date;
+pub mod list;
+pub mod recurrence;
+pub mod tag;
+pub mod todo;
+pub mod user;
@@ -0,0 +1,14 @@
+use crate::server::recurrence::{Recurrence, RecurrenceError};
+
+/// Parses the rule submitted by the repeat picker. An empty rule means the item doesn't
+/// repeat.
+pub fn parse_recurrence(rule: &str) -> Result<Option<Recurrence>, RecurrenceError> {
+    match rule.trim() {
+        "" => Ok(None),
+        rule => rule.parse().map(Some),
+    }
+}
+
+pub fn check_recurrence(rule: &str) -> Option<String> {
+    parse_recurrence(rule).err().map(|e| e.to_string())
+}
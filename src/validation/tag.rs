@@ -0,0 +1,48 @@
+use super::todo::{check_all, FieldError};
+
+/// Matches the `varchar(30)` name column.
+pub const TAG_NAME_MAX_CHARS: usize = 30;
+
+/// The fields of a tag after they passed validation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValidTag {
+    pub name: String,
+    pub colour: String,
+}
+
+pub fn check_tag_name(name: &str) -> Option<String> {
+    let name = name.trim();
+    if name.is_empty() {
+        Some("tag name is required".into())
+    } else if name.chars().count() > TAG_NAME_MAX_CHARS {
+        Some(format!(
+            "tag name must be at most {TAG_NAME_MAX_CHARS} characters"
+        ))
+    } else {
+        None
+    }
+}
+
+/// Accepts a `#rrggbb` colour, as produced by `<input type="color">`.
+pub fn check_colour(colour: &str) -> Option<String> {
+    let hex = colour.trim().strip_prefix('#').unwrap_or_default();
+    if hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        None
+    } else {
+        Some("colour must be in the format #rrggbb".into())
+    }
+}
+
+/// Validates a tag's name and colour. The name comes back trimmed, and the colour
+/// lowercased so the same colour is always stored the same way.
+pub fn validate_tag(name: &str, colour: &str) -> Result<ValidTag, Vec<FieldError>> {
+    check_all([
+        ("name", check_tag_name(name)),
+        ("colour", check_colour(colour)),
+    ])?;
+
+    Ok(ValidTag {
+        name: name.trim().to_string(),
+        colour: colour.trim().to_ascii_lowercase(),
+    })
+}
@@ -0,0 +1,177 @@
+use super::date::parse_due_date;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+pub const TITLE_MAX_CHARS: usize = 100;
+/// Matches the `varchar(200)` description column.
+pub const DESCRIPTION_MAX_CHARS: usize = 200;
+
+/// A rejected form field, named as in the form and the server function arguments.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "ssr", derive(utoipa::ToSchema))]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: &str, message: impl Into<String>) -> Self {
+        Self {
+            field: field.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+/// The fields of a todo item after they passed validation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValidTodo {
+    pub title: String,
+    pub description: String,
+    pub due_date: NaiveDate,
+}
+
+pub fn check_title(title: &str) -> Option<String> {
+    let title = title.trim();
+    if title.is_empty() {
+        Some("title is required".into())
+    } else if title.chars().count() > TITLE_MAX_CHARS {
+        Some(format!(
+            "title must be at most {TITLE_MAX_CHARS} characters"
+        ))
+    } else {
+        None
+    }
+}
+
+pub fn check_description(description: &str) -> Option<String> {
+    (description.chars().count() > DESCRIPTION_MAX_CHARS)
+        .then(|| format!("description must be at most {DESCRIPTION_MAX_CHARS} characters"))
+}
+
+pub fn check_due_date(due_date: &str) -> Option<String> {
+    parse_due_date(due_date).err().map(|e| e.to_string())
+}
+
+/// Runs the checks of a form, each paired with the field it checks, and fails with an
+/// error for every field whose check returned a message.
+pub fn check_all<const N: usize>(
+    checks: [(&str, Option<String>); N],
+) -> Result<(), Vec<FieldError>> {
+    let errors = checks
+        .into_iter()
+        .filter_map(|(field, error)| error.map(|message| FieldError::new(field, message)))
+        .collect::<Vec<_>>();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Validates the fields of the add and edit forms, returning the title trimmed and the
+/// due date parsed.
+pub fn validate_todo(
+    title: &str,
+    description: &str,
+    due_date: &str,
+) -> Result<ValidTodo, Vec<FieldError>> {
+    check_all([
+        ("title", check_title(title)),
+        ("description", check_description(description)),
+        ("due_date", check_due_date(due_date)),
+    ])?;
+
+    let due_date =
+        parse_due_date(due_date).map_err(|e| vec![FieldError::new("due_date", e.to_string())])?;
+    Ok(ValidTodo {
+        title: title.trim().to_string(),
+        description: description.to_string(),
+        due_date,
+    })
+}
+
+/// The message for `field`, if it has one.
+pub fn error_for(errors: &[FieldError], field: &str) -> Option<String> {
+    errors
+        .iter()
+        .find(|e| e.field == field)
+        .map(|e| e.message.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn titles_are_required_and_limited_after_trimming() {
+        assert_eq!(check_title("Buy milk"), None);
+        assert_eq!(check_title("  \t"), Some("title is required".into()));
+        assert_eq!(check_title(&"é".repeat(TITLE_MAX_CHARS)), None);
+        assert_eq!(
+            check_title(&format!("  {}  ", "x".repeat(TITLE_MAX_CHARS))),
+            None
+        );
+        assert_eq!(
+            check_title(&"x".repeat(TITLE_MAX_CHARS + 1)),
+            Some("title must be at most 100 characters".into())
+        );
+    }
+
+    #[test]
+    fn descriptions_may_be_empty_but_not_long() {
+        assert_eq!(check_description(""), None);
+        assert_eq!(check_description(&"ü".repeat(DESCRIPTION_MAX_CHARS)), None);
+        assert_eq!(
+            check_description(&"x".repeat(DESCRIPTION_MAX_CHARS + 1)),
+            Some("description must be at most 200 characters".into())
+        );
+    }
+
+    #[test]
+    fn due_dates_report_the_parser_error() {
+        assert_eq!(check_due_date("2024-06-30"), None);
+        assert_eq!(check_due_date(""), Some("due date is required".into()));
+        assert_eq!(
+            check_due_date("2024-06-31"),
+            Some("due date is not a real calendar date".into())
+        );
+    }
+
+    #[test]
+    fn every_invalid_field_is_reported_in_form_order() {
+        let errors = validate_todo("", &"x".repeat(DESCRIPTION_MAX_CHARS + 1), "tomorrow")
+            .unwrap_err()
+            .into_iter()
+            .map(|e| e.field)
+            .collect::<Vec<_>>();
+        assert_eq!(errors, ["title", "description", "due_date"]);
+    }
+
+    #[test]
+    fn valid_fields_are_trimmed_and_parsed() {
+        let valid = validate_todo("  Buy milk ", " two litres ", "2024-06-30").unwrap();
+        assert_eq!(
+            valid,
+            ValidTodo {
+                title: "Buy milk".into(),
+                description: " two litres ".into(),
+                due_date: NaiveDate::from_ymd_opt(2024, 6, 30).unwrap(),
+            }
+        );
+    }
+
+    #[test]
+    fn error_for_finds_the_fields_message() {
+        let errors = [
+            FieldError::new("title", "title is required"),
+            FieldError::new("due_date", "due date is required"),
+        ];
+        assert_eq!(
+            error_for(&errors, "due_date"),
+            Some("due date is required".into())
+        );
+        assert_eq!(error_for(&errors, "description"), None);
+        assert_eq!(error_for(&[], "title"), None);
+    }
+}
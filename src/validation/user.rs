@@ -0,0 +1,61 @@
+use super::todo::{check_all, FieldError};
+
+/// Matches the `varchar(50)` username column.
+pub const USERNAME_MAX_CHARS: usize = 50;
+pub const USERNAME_MIN_CHARS: usize = 3;
+pub const PASSWORD_MIN_CHARS: usize = 8;
+/// Keeps hashing cheap enough that long passwords can't tie up the server.
+pub const PASSWORD_MAX_CHARS: usize = 128;
+
+/// Usernames are compared after trimming and lowercasing, so "Alice" and "alice " are
+/// the same account.
+pub fn normalize_username(username: &str) -> String {
+    username.trim().to_lowercase()
+}
+
+pub fn check_username(username: &str) -> Option<String> {
+    let username = normalize_username(username);
+    let chars = username.chars().count();
+    if chars < USERNAME_MIN_CHARS {
+        Some(format!(
+            "username must be at least {USERNAME_MIN_CHARS} characters"
+        ))
+    } else if chars > USERNAME_MAX_CHARS {
+        Some(format!(
+            "username must be at most {USERNAME_MAX_CHARS} characters"
+        ))
+    } else if !username
+        .chars()
+        .all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.'))
+    {
+        Some("username may only contain letters, digits, '_', '-' and '.'".into())
+    } else {
+        None
+    }
+}
+
+pub fn check_password(password: &str) -> Option<String> {
+    let chars = password.chars().count();
+    if chars < PASSWORD_MIN_CHARS {
+        Some(format!(
+            "password must be at least {PASSWORD_MIN_CHARS} characters"
+        ))
+    } else if chars > PASSWORD_MAX_CHARS {
+        Some(format!(
+            "password must be at most {PASSWORD_MAX_CHARS} characters"
+        ))
+    } else {
+        None
+    }
+}
+
+/// Validates the username and password of a new account, returning the username as
+/// accounts are looked up by.
+pub fn validate_signup(username: &str, password: &str) -> Result<String, Vec<FieldError>> {
+    check_all([
+        ("username", check_username(username)),
+        ("password", check_password(password)),
+    ])?;
+
+    Ok(normalize_username(username))
+}
@@ -0,0 +1,403 @@
+//! Every server function is called as one user with another user's ids, and must refuse
+//! with `Forbidden` without touching anything.
+
+use actix_http::Request;
+use actix_web::body::MessageBody;
+use actix_web::cookie::{Cookie, Key};
+use actix_web::dev::{Service, ServiceResponse};
+use actix_web::http::header;
+use actix_web::{test, App, Error};
+use leptos::server_fn::error::ServerFnErrorSerde;
+use leptos::server_fn::ServerFn;
+use leptos::ServerFnError;
+use leptos_actix::handle_server_fns_with_context;
+use leptos_todo_new::server::auth::{GetCurrentUser, Login, Logout, Signup};
+use leptos_todo_new::server::backup::{ExportBackup, ImportBackup};
+use leptos_todo_new::server::calendar::{
+    GetCalendarFeed, ImportIcs, ResetCalendarFeed, TurnOffCalendarFeed,
+};
+use leptos_todo_new::server::checklist::{
+    AddChecklistItem, DeleteChecklistItem, MoveChecklistItem, SetChecklistItemDone,
+};
+use leptos_todo_new::server::csv::{ImportCsv, PreviewCsv};
+use leptos_todo_new::server::error::TodoError;
+use leptos_todo_new::server::list::{
+    CreateList, DeleteList, GetLists, List, MoveList, RenameList, SetListColour,
+};
+use leptos_todo_new::server::repository::{InMemoryTodoRepository, SharedTodoRepository};
+use leptos_todo_new::server::session::SessionSettings;
+use leptos_todo_new::server::tag::{CreateTag, DeleteTag, GetTags, RenameTag, SetTagColour, Tag};
+use leptos_todo_new::server::todo::{
+    AddTodo, CompleteTodo, DeleteTodo, GetCompletedTodos, GetPaginatedTodos, GetTrashedTodos,
+    MoveTodo, MoveTodoToList, PaginatedTodos, PurgeTodo, RestoreTodo, SearchTodo, UncompleteTodo,
+    UpdateTodo,
+};
+use serde::de::DeserializeOwned;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+/// A service made by `test::init_service`.
+trait TestApp: Service<Request, Response = ServiceResponse<Self::Body>, Error = Error> {
+    type Body: MessageBody;
+}
+
+impl<S, B> TestApp for S
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = Error>,
+    B: MessageBody,
+{
+    type Body = B;
+}
+
+/// The server functions anyone may call.
+const PUBLIC: [&str; 4] = [
+    Signup::PATH,
+    Login::PATH,
+    Logout::PATH,
+    GetCurrentUser::PATH,
+];
+
+/// Calls the server function at `path` with form-encoded `args`, as whoever `session`
+/// belongs to. Returns the JSON body, or the error the function failed with.
+async fn call(
+    app: &impl TestApp,
+    session: Option<&Cookie<'static>>,
+    path: &str,
+    args: &str,
+) -> Result<String, TodoError> {
+    let (body, _) = send(app, session, path, args).await;
+    body
+}
+
+async fn send(
+    app: &impl TestApp,
+    session: Option<&Cookie<'static>>,
+    path: &str,
+    args: &str,
+) -> (Result<String, TodoError>, Option<Cookie<'static>>) {
+    let mut req = test::TestRequest::post()
+        .uri(path)
+        .insert_header((header::ACCEPT, "application/json"))
+        .insert_header((header::CONTENT_TYPE, "application/x-www-form-urlencoded"))
+        .set_payload(args.to_string());
+    if let Some(cookie) = session {
+        req = req.cookie(cookie.clone());
+    }
+
+    let res = test::call_service(app, req.to_request()).await;
+    let success = res.status().is_success();
+    let cookie = res
+        .response()
+        .cookies()
+        .find(|cookie| cookie.name() == "todo_session")
+        .map(|cookie| cookie.into_owned());
+    let body = String::from_utf8(test::read_body(res).await.to_vec()).unwrap();
+
+    let result = if success {
+        Ok(body)
+    } else {
+        match ServerFnError::<TodoError>::de(&body) {
+            ServerFnError::WrappedServerError(e) => Err(e),
+            other => panic!("{path} failed outside the server function: {other:?}"),
+        }
+    };
+    (result, cookie)
+}
+
+/// Calls a server function that must succeed and parses what it returns.
+async fn fetch<T: DeserializeOwned>(
+    app: &impl TestApp,
+    session: &Cookie<'static>,
+    path: &str,
+    args: &str,
+) -> T {
+    let body = call(app, Some(session), path, args)
+        .await
+        .unwrap_or_else(|e| panic!("{path} failed: {e:?}"));
+    serde_json::from_str(&body).unwrap()
+}
+
+async fn sign_up(app: &impl TestApp, username: &str) -> Cookie<'static> {
+    let args = format!("username={username}&password=correct-horse");
+    let (result, cookie) = send(app, None, Signup::PATH, &args).await;
+    result.unwrap();
+    cookie.expect("signing up sets the session cookie")
+}
+
+/// Everything Alice owns that Bob goes after.
+struct AliceIds {
+    todo: i32,
+    trashed: i32,
+    tag: i32,
+    list: i32,
+    checklist_item: i32,
+}
+
+/// What Bob owns, to pair with Alice's ids.
+struct BobIds {
+    todo: i32,
+    list: i32,
+}
+
+async fn open_todos(app: &impl TestApp, session: &Cookie<'static>) -> PaginatedTodos {
+    fetch(app, session, GetPaginatedTodos::PATH, "page=0").await
+}
+
+async fn add(app: &impl TestApp, session: &Cookie<'static>, title: &str) -> i32 {
+    let args = format!("title={title}&description=&due_date=2030-01-01");
+    call(app, Some(session), AddTodo::PATH, &args)
+        .await
+        .unwrap();
+    open_todos(app, session)
+        .await
+        .items
+        .into_iter()
+        .find(|todo| todo.title == title)
+        .unwrap()
+        .id
+}
+
+async fn set_up_alice(app: &impl TestApp, alice: &Cookie<'static>) -> AliceIds {
+    let tag_args = "name=private&colour=%23ff0000";
+    call(app, Some(alice), CreateTag::PATH, tag_args)
+        .await
+        .unwrap();
+    let tags: Vec<Tag> = fetch(app, alice, GetTags::PATH, "").await;
+
+    let list_args = "name=Secrets&colour=%23ff0000";
+    call(app, Some(alice), CreateList::PATH, list_args)
+        .await
+        .unwrap();
+    let lists: Vec<List> = fetch(app, alice, GetLists::PATH, "").await;
+
+    let todo = add(app, alice, "alice-todo").await;
+    let args = format!("todo_id={todo}&text=alice-step");
+    call(app, Some(alice), AddChecklistItem::PATH, &args)
+        .await
+        .unwrap();
+    let trashed = add(app, alice, "alice-trashed").await;
+    call(app, Some(alice), DeleteTodo::PATH, &format!("id={trashed}"))
+        .await
+        .unwrap();
+
+    let todos = open_todos(app, alice).await;
+    AliceIds {
+        todo,
+        trashed,
+        tag: tags[0].id,
+        list: lists.iter().find(|list| list.name == "Secrets").unwrap().id,
+        checklist_item: todos.items[0].checklist[0].id,
+    }
+}
+
+async fn set_up_bob(app: &impl TestApp, bob: &Cookie<'static>) -> BobIds {
+    let todo = add(app, bob, "bob-todo").await;
+    let lists: Vec<List> = fetch(app, bob, GetLists::PATH, "").await;
+    BobIds {
+        todo,
+        list: lists[0].id,
+    }
+}
+
+/// Each server function that names rows, called as Bob with at least one of Alice's.
+fn cross_user_calls(alice: &AliceIds, bob: &BobIds) -> Vec<(&'static str, String)> {
+    let AliceIds {
+        todo,
+        trashed,
+        tag,
+        list,
+        checklist_item,
+    } = *alice;
+    let fields = "title=x&description=&due_date=2030-01-01";
+    vec![
+        (GetPaginatedTodos::PATH, format!("page=0&list={list}")),
+        (GetPaginatedTodos::PATH, format!("page=0&tags[0]={tag}")),
+        (AddTodo::PATH, format!("{fields}&list_id={list}")),
+        (AddTodo::PATH, format!("{fields}&parent_id={todo}")),
+        (AddTodo::PATH, format!("{fields}&tag_ids[0]={tag}")),
+        (CompleteTodo::PATH, format!("id={todo}")),
+        (UncompleteTodo::PATH, format!("id={todo}")),
+        (UpdateTodo::PATH, format!("id={todo}&{fields}")),
+        (
+            UpdateTodo::PATH,
+            format!("id={}&{fields}&tag_ids[0]={tag}", bob.todo),
+        ),
+        (MoveTodo::PATH, format!("id={todo}")),
+        (MoveTodo::PATH, format!("id={}&parent_id={todo}", bob.todo)),
+        (
+            MoveTodoToList::PATH,
+            format!("id={todo}&list_id={}", bob.list),
+        ),
+        (
+            MoveTodoToList::PATH,
+            format!("id={}&list_id={list}", bob.todo),
+        ),
+        (DeleteTodo::PATH, format!("id={todo}")),
+        (RestoreTodo::PATH, format!("id={trashed}")),
+        (PurgeTodo::PATH, format!("id={trashed}")),
+        (SearchTodo::PATH, format!("query=x&list={list}")),
+        (SearchTodo::PATH, format!("query=x&tags[0]={tag}")),
+        (
+            ImportIcs::PATH,
+            format!("contents=BEGIN:VCALENDAR%0AEND:VCALENDAR&list_id={list}"),
+        ),
+        (
+            ImportCsv::PATH,
+            format!("contents=title,due%0Ax,2030-01-01&fields[0]=title&fields[1]=due_date&list_id={list}"),
+        ),
+        (RenameTag::PATH, format!("id={tag}&name=mine")),
+        (SetTagColour::PATH, format!("id={tag}&colour=%23000000")),
+        (DeleteTag::PATH, format!("id={tag}")),
+        (RenameList::PATH, format!("id={list}&name=Mine")),
+        (SetListColour::PATH, format!("id={list}&colour=%23000000")),
+        (MoveList::PATH, format!("id={list}&position=0")),
+        (DeleteList::PATH, format!("id={list}")),
+        (AddChecklistItem::PATH, format!("todo_id={todo}&text=mine")),
+        (
+            SetChecklistItemDone::PATH,
+            format!("id={checklist_item}&done=true"),
+        ),
+        (
+            MoveChecklistItem::PATH,
+            format!("id={checklist_item}&position=0"),
+        ),
+        (DeleteChecklistItem::PATH, format!("id={checklist_item}")),
+    ]
+}
+
+/// The server functions that only read or add the caller's own rows.
+const OWN_ROWS_ONLY: [&str; 12] = [
+    GetCompletedTodos::PATH,
+    GetTrashedTodos::PATH,
+    GetTags::PATH,
+    GetLists::PATH,
+    CreateTag::PATH,
+    CreateList::PATH,
+    ExportBackup::PATH,
+    ImportBackup::PATH,
+    PreviewCsv::PATH,
+    GetCalendarFeed::PATH,
+    ResetCalendarFeed::PATH,
+    TurnOffCalendarFeed::PATH,
+];
+
+fn registered_paths() -> BTreeSet<&'static str> {
+    leptos::server_fn::actix::server_fn_paths()
+        .map(|(path, _)| path)
+        .collect()
+}
+
+/// The server functions over an empty in-memory store, with sessions.
+async fn app() -> impl TestApp {
+    let repository: SharedTodoRepository = Arc::new(InMemoryTodoRepository::new());
+    test::init_service(
+        App::new()
+            .route(
+                "/api/{tail:.*}",
+                handle_server_fns_with_context(move || leptos::provide_context(repository.clone())),
+            )
+            .wrap(SessionSettings::default().middleware(Key::generate())),
+    )
+    .await
+}
+
+#[actix_web::test]
+async fn every_server_function_is_covered() {
+    let alice = AliceIds {
+        todo: 1,
+        trashed: 2,
+        tag: 3,
+        list: 4,
+        checklist_item: 5,
+    };
+    let bob = BobIds { todo: 6, list: 7 };
+    let covered = cross_user_calls(&alice, &bob)
+        .into_iter()
+        .map(|(path, _)| path)
+        .chain(OWN_ROWS_ONLY)
+        .chain(PUBLIC)
+        .collect::<BTreeSet<_>>();
+    assert_eq!(covered, registered_paths());
+}
+
+#[actix_web::test]
+async fn other_users_rows_are_forbidden() {
+    let app = app().await;
+    let alice = sign_up(&app, "alice").await;
+    let bob = sign_up(&app, "bob").await;
+    let alice_ids = set_up_alice(&app, &alice).await;
+    let bob_ids = set_up_bob(&app, &bob).await;
+    let before = open_todos(&app, &alice).await;
+
+    for (path, args) in cross_user_calls(&alice_ids, &bob_ids) {
+        let result = call(&app, Some(&bob), path, &args).await;
+        assert_eq!(result, Err(TodoError::Forbidden), "{path} with {args}");
+    }
+
+    let after = open_todos(&app, &alice).await;
+    assert_eq!(
+        serde_json::to_string(&after.items).unwrap(),
+        serde_json::to_string(&before.items).unwrap()
+    );
+    let trash: PaginatedTodos = fetch(&app, &alice, GetTrashedTodos::PATH, "page=0").await;
+    assert_eq!(trash.items[0].id, alice_ids.trashed);
+    let tags: Vec<Tag> = fetch(&app, &alice, GetTags::PATH, "").await;
+    assert_eq!(tags[0].name, "private");
+    let lists: Vec<List> = fetch(&app, &alice, GetLists::PATH, "").await;
+    assert!(lists.iter().any(|list| list.name == "Secrets"));
+}
+
+#[actix_web::test]
+async fn other_users_rows_are_not_listed() {
+    let app = app().await;
+    let alice = sign_up(&app, "alice").await;
+    let bob = sign_up(&app, "bob").await;
+    let alice_ids = set_up_alice(&app, &alice).await;
+    set_up_bob(&app, &bob).await;
+
+    let todos = open_todos(&app, &bob).await;
+    assert!(todos.items.iter().all(|todo| todo.id != alice_ids.todo));
+    let trash: PaginatedTodos = fetch(&app, &bob, GetTrashedTodos::PATH, "page=0").await;
+    assert!(trash.items.is_empty());
+    let tags: Vec<Tag> = fetch(&app, &bob, GetTags::PATH, "").await;
+    assert!(tags.is_empty());
+    let lists: Vec<List> = fetch(&app, &bob, GetLists::PATH, "").await;
+    assert!(lists.iter().all(|list| list.id != alice_ids.list));
+
+    // Bob can use Alice's tag and list names for his own.
+    call(
+        &app,
+        Some(&bob),
+        CreateTag::PATH,
+        "name=private&colour=%23ff0000",
+    )
+    .await
+    .unwrap();
+    call(
+        &app,
+        Some(&bob),
+        CreateList::PATH,
+        "name=Secrets&colour=%23ff0000",
+    )
+    .await
+    .unwrap();
+}
+
+#[actix_web::test]
+async fn visitors_are_unauthorized() {
+    let app = app().await;
+    let alice = sign_up(&app, "alice").await;
+    let alice_ids = set_up_alice(&app, &alice).await;
+    let bob_ids = BobIds {
+        todo: alice_ids.todo,
+        list: alice_ids.list,
+    };
+
+    let calls = cross_user_calls(&alice_ids, &bob_ids).into_iter().chain(
+        OWN_ROWS_ONLY.map(|path| (path, "page=0&name=x&colour=%23ff0000&contents=".to_string())),
+    );
+    for (path, args) in calls {
+        let result = call(&app, None, path, &args).await;
+        assert_eq!(result, Err(TodoError::Unauthorized), "{path} with {args}");
+    }
+}
@@ -0,0 +1,265 @@
+//! The calendar feed, fetched the way calendar apps fetch it: by the secret in its URL,
+//! asking again with the ETag they were sent. And VTODOs added to the in-memory store
+//! the way `import_ics` adds them.
+
+use actix_http::Request;
+use actix_web::body::MessageBody;
+use actix_web::dev::{Service, ServiceResponse};
+use actix_web::http::{header, StatusCode};
+use actix_web::{test, web, App, Error};
+use chrono::{DateTime, NaiveDate, Utc};
+use leptos_todo_new::server::calendar::ssr::calendar_feed;
+use leptos_todo_new::server::calendar::CALENDAR_PATH;
+use leptos_todo_new::server::recurrence::Recurrence;
+use leptos_todo_new::server::repository::{
+    CalendarTodo, InMemoryTodoRepository, SharedTodoRepository, TodoInput,
+};
+use leptos_todo_new::server::todo::{Priority, Todo};
+use std::sync::Arc;
+
+/// A service made by `test::init_service`.
+trait TestApp: Service<Request, Response = ServiceResponse<Self::Body>, Error = Error> {
+    type Body: MessageBody;
+}
+
+impl<S, B> TestApp for S
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = Error>,
+    B: MessageBody,
+{
+    type Body = B;
+}
+
+const TOKEN: &str = "secret-token";
+
+/// The calendar feed route over `repository`.
+async fn app(repository: SharedTodoRepository) -> impl TestApp {
+    test::init_service(App::new().app_data(web::Data::new(repository)).route(
+        &format!("{CALENDAR_PATH}/{{file}}"),
+        web::get().to(calendar_feed),
+    ))
+    .await
+}
+
+/// A store holding alice's feed, with the secret [`TOKEN`], and an item of hers.
+/// Returns it with her id and the item's.
+async fn repository() -> (SharedTodoRepository, i32, i32) {
+    let repository: SharedTodoRepository = Arc::new(InMemoryTodoRepository::new());
+    let alice = repository
+        .create_user("alice".to_string(), "hash".to_string())
+        .await
+        .unwrap();
+    repository
+        .set_calendar_token(alice.id, Some(TOKEN.to_string()))
+        .await
+        .unwrap();
+    let todo = repository
+        .create(alice.id, input("buy milk"), None, None)
+        .await
+        .unwrap();
+    (repository, alice.id, todo.id)
+}
+
+fn input(title: &str) -> TodoInput {
+    TodoInput {
+        title: title.to_string(),
+        description: String::new(),
+        due_date: NaiveDate::from_ymd_opt(2030, 1, 1).unwrap(),
+        priority: Priority::None,
+        tag_ids: Vec::new(),
+        recurrence: None,
+    }
+}
+
+fn from_calendar(uid: &str, title: &str) -> CalendarTodo {
+    CalendarTodo {
+        uid: Some(uid.to_string()),
+        input: input(title),
+        completed_at: None,
+    }
+}
+
+/// Every item of `owner`, completed and trashed ones included, by title.
+async fn find(repository: &SharedTodoRepository, owner: i32, title: &str) -> Todo {
+    let todos = repository.export(owner).await.unwrap().todos;
+    let found = todos.into_iter().find(|todo| todo.title == title);
+    found.unwrap_or_else(|| panic!("{title} was added"))
+}
+
+/// Fetches the feed with `token`, sending `if_none_match` if given. Returns the status,
+/// the ETag and the body.
+async fn fetch(
+    app: &impl TestApp,
+    token: &str,
+    if_none_match: Option<&str>,
+) -> (StatusCode, Option<String>, String) {
+    let mut req = test::TestRequest::get().uri(&format!("{CALENDAR_PATH}/{token}.ics"));
+    if let Some(etag) = if_none_match {
+        req = req.insert_header((header::IF_NONE_MATCH, etag));
+    }
+    let res = test::call_service(app, req.to_request()).await;
+    let status = res.status();
+    let etag = res
+        .headers()
+        .get(header::ETAG)
+        .map(|etag| etag.to_str().unwrap().to_string());
+    let body = String::from_utf8(test::read_body(res).await.to_vec()).unwrap();
+    (status, etag, body)
+}
+
+#[actix_web::test]
+async fn unchanged_feeds_are_not_sent_again() {
+    let (repository, _, _) = repository().await;
+    let app = app(repository).await;
+
+    let (status, etag, body) = fetch(&app, TOKEN, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.contains("SUMMARY:buy milk"));
+    let etag = etag.expect("feeds have an ETag");
+
+    let (status, same_etag, body) = fetch(&app, TOKEN, Some(&etag)).await;
+    assert_eq!(status, StatusCode::NOT_MODIFIED);
+    assert_eq!(same_etag.as_ref(), Some(&etag));
+    assert!(body.is_empty());
+
+    let (status, _, _) = fetch(&app, TOKEN, Some("*")).await;
+    assert_eq!(status, StatusCode::NOT_MODIFIED);
+    let (status, _, _) = fetch(&app, TOKEN, Some("\"something-else\"")).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn changed_feeds_are_sent_with_a_new_etag() {
+    let (repository, alice, todo) = repository().await;
+    let app = app(repository.clone()).await;
+    let (_, etag, _) = fetch(&app, TOKEN, None).await;
+    let etag = etag.unwrap();
+
+    repository.complete(alice, todo, false).await.unwrap();
+
+    let (status, new_etag, body) = fetch(&app, TOKEN, Some(&etag)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_ne!(new_etag, Some(etag));
+    assert!(body.contains("STATUS:COMPLETED"));
+}
+
+#[actix_web::test]
+async fn unknown_secrets_are_not_found() {
+    let (repository, _, _) = repository().await;
+    let app = app(repository).await;
+
+    let (status, _, _) = fetch(&app, "wrong-token", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn imported_items_are_made_like_added_ones() {
+    let (repository, alice, _) = repository().await;
+    let tag = repository
+        .create_tag(alice, "home".to_string(), "#ff0000".to_string())
+        .await
+        .unwrap();
+    let list = repository
+        .create_list(alice, "Chores".to_string(), "#00ff00".to_string())
+        .await
+        .unwrap();
+    let mut input = input("water plants");
+    input.description = "the ones by the window".to_string();
+    input.priority = Priority::High;
+    input.tag_ids = vec![tag.id];
+    input.recurrence = Some(Recurrence::Daily);
+
+    let added = repository
+        .create(alice, input.clone(), Some(list.id), None)
+        .await
+        .unwrap();
+    let imported = CalendarTodo {
+        uid: Some("plants@example.com".to_string()),
+        input: TodoInput {
+            title: "watered plants".to_string(),
+            ..input
+        },
+        completed_at: None,
+    };
+    let skipped = repository
+        .create_from_calendar(alice, vec![imported], Some(list.id))
+        .await
+        .unwrap();
+    assert!(skipped.is_empty());
+
+    let added = find(&repository, alice, &added.title).await;
+    let imported = find(&repository, alice, "watered plants").await;
+    assert_eq!(
+        Todo {
+            id: added.id,
+            title: added.title.clone(),
+            created: added.created.clone(),
+            ..imported
+        },
+        added
+    );
+}
+
+#[actix_web::test]
+async fn imported_items_keep_their_completion() {
+    let (repository, alice, _) = repository().await;
+    let completed_at = "2029-12-31T10:00:00Z".parse().unwrap();
+    let done = CalendarTodo {
+        completed_at: Some(completed_at),
+        ..from_calendar("done@example.com", "done")
+    };
+    repository
+        .create_from_calendar(alice, vec![done], None)
+        .await
+        .unwrap();
+
+    let done = find(&repository, alice, "done").await;
+    assert!(done.completed);
+    let stored = done.completed_at.unwrap().parse::<DateTime<Utc>>();
+    assert_eq!(stored.unwrap(), completed_at);
+}
+
+#[actix_web::test]
+async fn uids_are_imported_once() {
+    let (repository, alice, _) = repository().await;
+    let todos = vec![
+        from_calendar("a@example.com", "first a"),
+        from_calendar("b@example.com", "b"),
+        from_calendar("a@example.com", "second a"),
+    ];
+    let skipped = repository
+        .create_from_calendar(alice, todos, None)
+        .await
+        .unwrap();
+    assert_eq!(skipped, ["a@example.com"]);
+
+    let todos = vec![
+        from_calendar("b@example.com", "b again"),
+        from_calendar("c@example.com", "c"),
+    ];
+    let skipped = repository
+        .create_from_calendar(alice, todos, None)
+        .await
+        .unwrap();
+    assert_eq!(skipped, ["b@example.com"]);
+
+    let todos = repository.export(alice).await.unwrap().todos;
+    let mut titles = todos
+        .iter()
+        .map(|todo| todo.title.as_str())
+        .collect::<Vec<_>>();
+    titles.sort();
+    assert_eq!(titles, ["b", "buy milk", "c", "first a"]);
+
+    // UIDs are per user.
+    let bob = repository
+        .create_user("bob".to_string(), "hash".to_string())
+        .await
+        .unwrap();
+    let todos = vec![from_calendar("a@example.com", "bob's a")];
+    let skipped = repository
+        .create_from_calendar(bob.id, todos, None)
+        .await
+        .unwrap();
+    assert!(skipped.is_empty());
+}
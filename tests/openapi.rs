@@ -0,0 +1,264 @@
+//! The served OpenAPI document must describe exactly the routes the JSON API serves, and
+//! the shapes of what it answers with.
+
+use actix_http::Request;
+use actix_web::body::MessageBody;
+use actix_web::cookie::Key;
+use actix_web::dev::{Service, ServiceResponse};
+use actix_web::http::{header, Method, StatusCode};
+use actix_web::{test, web, App, Error};
+use leptos_todo_new::server::api::{self, OPENAPI_PATH};
+use leptos_todo_new::server::auth::ssr::hash_password;
+use leptos_todo_new::server::repository::{InMemoryTodoRepository, SharedTodoRepository};
+use leptos_todo_new::server::session::SessionSettings;
+use serde_json::{json, Value};
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+/// A service made by `test::init_service`.
+trait TestApp: Service<Request, Response = ServiceResponse<Self::Body>, Error = Error> {
+    type Body: MessageBody;
+}
+
+impl<S, B> TestApp for S
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = Error>,
+    B: MessageBody,
+{
+    type Body = B;
+}
+
+/// The methods tried on every documented path.
+const METHODS: [Method; 5] = [
+    Method::GET,
+    Method::POST,
+    Method::PUT,
+    Method::PATCH,
+    Method::DELETE,
+];
+
+/// Basic credentials of the user [`app`] sets up.
+const ALICE: &str = "Basic YWxpY2U6Y29ycmVjdC1ob3JzZQ==";
+
+/// The API as `main` serves it, with the user `alice` (password `correct-horse`).
+async fn app() -> impl TestApp {
+    let repository: SharedTodoRepository = Arc::new(InMemoryTodoRepository::new());
+    let hash = hash_password("correct-horse".into()).await.unwrap();
+    repository.create_user("alice".into(), hash).await.unwrap();
+
+    test::init_service(
+        App::new()
+            .service(api::scope())
+            .service(api::docs())
+            .app_data(web::Data::new(repository))
+            .wrap(SessionSettings::default().middleware(Key::generate())),
+    )
+    .await
+}
+
+/// Sends a request as `alice`, or as nobody, and returns the status and JSON body.
+async fn send(
+    app: &impl TestApp,
+    method: Method,
+    path: &str,
+    body: Option<Value>,
+    logged_in: bool,
+) -> (StatusCode, Value) {
+    let mut req = test::TestRequest::default().method(method).uri(path);
+    if let Some(body) = body {
+        req = req.set_json(body);
+    }
+    if logged_in {
+        req = req.insert_header((header::AUTHORIZATION, ALICE));
+    }
+
+    let res = test::call_service(app, req.to_request()).await;
+    let status = res.status();
+    let body = test::read_body(res).await;
+    let body = serde_json::from_slice(&body).unwrap_or(Value::Null);
+    (status, body)
+}
+
+async fn spec(app: &impl TestApp) -> Value {
+    let (status, spec) = send(app, Method::GET, OPENAPI_PATH, None, false).await;
+    assert_eq!(status, StatusCode::OK);
+    spec
+}
+
+/// Every documented operation, as its method and full path.
+fn documented(spec: &Value) -> BTreeSet<(String, String)> {
+    let base = spec["servers"][0]["url"].as_str().unwrap();
+    spec["paths"]
+        .as_object()
+        .unwrap()
+        .iter()
+        .flat_map(|(path, operations)| {
+            operations
+                .as_object()
+                .unwrap()
+                .keys()
+                .map(move |method| (method.to_uppercase(), format!("{base}{path}")))
+        })
+        .collect()
+}
+
+/// `path` with every parameter filled in.
+fn concrete(path: &str) -> String {
+    path.split('/')
+        .map(|part| if part.starts_with('{') { "1" } else { part })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Checks that `value` has the shape `schema` describes: objects carry every required
+/// property and nothing undocumented, all the way down.
+fn check_shape(spec: &Value, schema: &Value, value: &Value, at: &str) {
+    if let Some(reference) = schema["$ref"].as_str() {
+        let target = spec
+            .pointer(reference.trim_start_matches('#'))
+            .unwrap_or_else(|| panic!("{at}: {reference} isn't in the document"));
+        return check_shape(spec, target, value, at);
+    }
+    match value {
+        Value::Object(fields) => {
+            let properties = schema["properties"]
+                .as_object()
+                .unwrap_or_else(|| panic!("{at}: the schema doesn't describe an object"));
+            for (name, field) in fields {
+                let property = properties
+                    .get(name)
+                    .unwrap_or_else(|| panic!("{at}.{name} isn't documented"));
+                check_shape(spec, property, field, &format!("{at}.{name}"));
+            }
+            for required in schema["required"].as_array().into_iter().flatten() {
+                let required = required.as_str().unwrap();
+                assert!(fields.contains_key(required), "{at}.{required} is missing");
+            }
+        }
+        Value::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                check_shape(spec, &schema["items"], item, &format!("{at}[{i}]"));
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The schema of the JSON body `method` `path` answers with when it returns `status`.
+fn response_schema<'a>(spec: &'a Value, method: &str, path: &str, status: StatusCode) -> &'a Value {
+    let operation = &spec["paths"][path][method];
+    let response = &operation["responses"][status.as_str()];
+    assert!(
+        !response.is_null(),
+        "{method} {path} doesn't document a {status} response"
+    );
+    &response["content"]["application/json"]["schema"]
+}
+
+#[actix_web::test]
+async fn every_route_is_documented() {
+    let app = app().await;
+    let spec = spec(&app).await;
+
+    let served = api::routes()
+        .into_iter()
+        .map(|(method, path)| (method.to_string(), path))
+        .collect::<BTreeSet<_>>();
+    assert_eq!(documented(&spec), served);
+}
+
+#[actix_web::test]
+async fn documented_operations_are_served() {
+    let app = app().await;
+    let spec = spec(&app).await;
+    let documented = documented(&spec);
+
+    let paths = documented
+        .iter()
+        .map(|(_, path)| path.clone())
+        .collect::<BTreeSet<_>>();
+    for path in paths {
+        for method in METHODS {
+            let (status, body) = send(&app, method.clone(), &concrete(&path), None, false).await;
+            let served = body["error"]["code"] != "unknown_endpoint";
+            let expected = documented.contains(&(method.to_string(), path.clone()));
+            assert_eq!(
+                served, expected,
+                "{method} {path} answered {status} {body}, but is documented: {expected}"
+            );
+        }
+    }
+}
+
+#[actix_web::test]
+async fn every_reference_resolves() {
+    fn references<'a>(value: &'a Value, found: &mut Vec<&'a str>) {
+        match value {
+            Value::Object(fields) => {
+                found.extend(fields.get("$ref").and_then(Value::as_str));
+                fields.values().for_each(|field| references(field, found));
+            }
+            Value::Array(items) => items.iter().for_each(|item| references(item, found)),
+            _ => {}
+        }
+    }
+
+    let app = app().await;
+    let spec = spec(&app).await;
+    let mut found = Vec::new();
+    references(&spec, &mut found);
+    assert!(!found.is_empty());
+    for reference in found {
+        assert!(
+            spec.pointer(reference.trim_start_matches('#')).is_some(),
+            "{reference} isn't in the document"
+        );
+    }
+}
+
+#[actix_web::test]
+async fn answers_match_the_documented_schemas() {
+    let app = app().await;
+    let spec = spec(&app).await;
+
+    let new = json!({
+        "title": "Water the plants",
+        "due_date": "2030-01-01",
+        "priority": "high",
+        "recurrence": "FREQ=WEEKLY;BYDAY=MO",
+    });
+    let (status, parent) = send(&app, Method::POST, "/api/v1/todos", Some(new), true).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let schema = response_schema(&spec, "post", "/todos", status);
+    check_shape(&spec, schema, &parent, "POST /todos");
+
+    let child =
+        json!({"title": "Fill the can", "due_date": "2030-01-01", "parent_id": parent["id"]});
+    let (status, _) = send(&app, Method::POST, "/api/v1/todos", Some(child), true).await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let (status, page) = send(&app, Method::GET, "/api/v1/todos", None, true).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(page["items"][0]["subtodos"].as_array().unwrap().len(), 1);
+    let schema = response_schema(&spec, "get", "/todos", status);
+    check_shape(&spec, schema, &page, "GET /todos");
+
+    let id = parent["id"].as_i64().unwrap();
+    let patch = json!({"description": "Both windows", "recurrence": null});
+    let path = format!("/api/v1/todos/{id}");
+    let (status, updated) = send(&app, Method::PATCH, &path, Some(patch), true).await;
+    assert_eq!(status, StatusCode::OK);
+    let schema = response_schema(&spec, "patch", "/todos/{id}", status);
+    check_shape(&spec, schema, &updated, "PATCH /todos/{id}");
+
+    let invalid = json!({"title": "", "due_date": "soon"});
+    let (status, error) = send(&app, Method::POST, "/api/v1/todos", Some(invalid), true).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let schema = response_schema(&spec, "post", "/todos", status);
+    check_shape(&spec, schema, &error, "POST /todos");
+
+    let (status, error) = send(&app, Method::GET, "/api/v1/todos/999", None, true).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let schema = response_schema(&spec, "get", "/todos/{id}", status);
+    check_shape(&spec, schema, &error, "GET /todos/{id}");
+}
@@ -0,0 +1,266 @@
+//! The todo server functions, called over HTTP against the in-memory store: adding,
+//! editing, completing, trashing and searching items, and paging through them.
+
+use actix_http::Request;
+use actix_web::body::MessageBody;
+use actix_web::cookie::{Cookie, Key};
+use actix_web::dev::{Service, ServiceResponse};
+use actix_web::http::header;
+use actix_web::{test, App, Error};
+use leptos::server_fn::error::ServerFnErrorSerde;
+use leptos::server_fn::ServerFn;
+use leptos::ServerFnError;
+use leptos_actix::handle_server_fns_with_context;
+use leptos_todo_new::server::auth::Signup;
+use leptos_todo_new::server::error::TodoError;
+use leptos_todo_new::server::repository::{InMemoryTodoRepository, SharedTodoRepository};
+use leptos_todo_new::server::session::SessionSettings;
+use leptos_todo_new::server::todo::{
+    AddTodo, CompleteTodo, DeleteTodo, GetCompletedTodos, GetPaginatedTodos, GetTrashedTodos,
+    PaginatedTodos, SearchTodo, Todo, UpdateTodo,
+};
+use serde::de::DeserializeOwned;
+use std::sync::Arc;
+
+/// A service made by `test::init_service`.
+trait TestApp: Service<Request, Response = ServiceResponse<Self::Body>, Error = Error> {
+    type Body: MessageBody;
+}
+
+impl<S, B> TestApp for S
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = Error>,
+    B: MessageBody,
+{
+    type Body = B;
+}
+
+/// The server functions over an empty in-memory store, with sessions.
+async fn app() -> impl TestApp {
+    let repository: SharedTodoRepository = Arc::new(InMemoryTodoRepository::new());
+    test::init_service(
+        App::new()
+            .route(
+                "/api/{tail:.*}",
+                handle_server_fns_with_context(move || leptos::provide_context(repository.clone())),
+            )
+            .wrap(SessionSettings::default().middleware(Key::generate())),
+    )
+    .await
+}
+
+/// Calls the server function at `path` with form-encoded `args` as whoever `session`
+/// belongs to. Returns the JSON body, or the error the function failed with.
+async fn call(
+    app: &impl TestApp,
+    session: &Cookie<'static>,
+    path: &str,
+    args: &str,
+) -> Result<String, TodoError> {
+    let req = test::TestRequest::post()
+        .uri(path)
+        .insert_header((header::ACCEPT, "application/json"))
+        .insert_header((header::CONTENT_TYPE, "application/x-www-form-urlencoded"))
+        .cookie(session.clone())
+        .set_payload(args.to_string());
+    let res = test::call_service(app, req.to_request()).await;
+    let success = res.status().is_success();
+    let body = String::from_utf8(test::read_body(res).await.to_vec()).unwrap();
+
+    if success {
+        return Ok(body);
+    }
+    match ServerFnError::<TodoError>::de(&body) {
+        ServerFnError::WrappedServerError(e) => Err(e),
+        other => panic!("{path} failed outside the server function: {other:?}"),
+    }
+}
+
+/// Calls a server function that must succeed and parses what it returns.
+async fn fetch<T: DeserializeOwned>(
+    app: &impl TestApp,
+    session: &Cookie<'static>,
+    path: &str,
+    args: &str,
+) -> T {
+    let body = call(app, session, path, args)
+        .await
+        .unwrap_or_else(|e| panic!("{path} failed: {e:?}"));
+    serde_json::from_str(&body).unwrap()
+}
+
+async fn sign_up(app: &impl TestApp) -> Cookie<'static> {
+    let req = test::TestRequest::post()
+        .uri(Signup::PATH)
+        .insert_header((header::ACCEPT, "application/json"))
+        .insert_header((header::CONTENT_TYPE, "application/x-www-form-urlencoded"))
+        .set_payload("username=alice&password=correct-horse");
+    let res = test::call_service(app, req.to_request()).await;
+    assert!(res.status().is_success());
+    res.response()
+        .cookies()
+        .find(|cookie| cookie.name() == "todo_session")
+        .map(|cookie| cookie.into_owned())
+        .expect("signing up sets the session cookie")
+}
+
+async fn page(app: &impl TestApp, session: &Cookie<'static>, page: u32) -> PaginatedTodos {
+    let args = format!("page={page}");
+    fetch(app, session, GetPaginatedTodos::PATH, &args).await
+}
+
+/// Adds an item due on 2030-01-01 and returns it. `title` must not need escaping other
+/// than its spaces.
+async fn add(app: &impl TestApp, session: &Cookie<'static>, title: &str) -> Todo {
+    let encoded = title.replace(' ', "+");
+    let args = format!("title={encoded}&description=about+{encoded}&due_date=2030-01-01");
+    call(app, session, AddTodo::PATH, &args).await.unwrap();
+    page(app, session, 0)
+        .await
+        .items
+        .into_iter()
+        .find(|todo| todo.title == title)
+        .unwrap()
+}
+
+fn titles(todos: &[Todo]) -> Vec<&str> {
+    todos.iter().map(|todo| todo.title.as_str()).collect()
+}
+
+#[actix_web::test]
+async fn added_items_are_listed_newest_first() {
+    let app = app().await;
+    let alice = sign_up(&app).await;
+    add(&app, &alice, "first").await;
+    let second = add(&app, &alice, "second").await;
+
+    assert_eq!(second.description, "about second");
+    assert_eq!(second.due_date, "2030-01-01");
+    assert!(!second.completed);
+    let todos = page(&app, &alice, 0).await;
+    assert_eq!(titles(&todos.items), ["second", "first"]);
+    assert_eq!((todos.total, todos.page, todos.total_pages), (2, 0, 1));
+}
+
+#[actix_web::test]
+async fn invalid_fields_are_rejected() {
+    let app = app().await;
+    let alice = sign_up(&app).await;
+
+    let args = "title=&description=&due_date=2030-02-30";
+    let Err(TodoError::Validation { errors }) = call(&app, &alice, AddTodo::PATH, args).await
+    else {
+        panic!("an empty title and a bad date are validation errors");
+    };
+    let fields = errors.iter().map(|e| e.field.as_str()).collect::<Vec<_>>();
+    assert_eq!(fields, ["title", "due_date"]);
+    assert_eq!(page(&app, &alice, 0).await.total, 0);
+}
+
+#[actix_web::test]
+async fn updates_replace_the_fields() {
+    let app = app().await;
+    let alice = sign_up(&app).await;
+    let todo = add(&app, &alice, "draft").await;
+
+    let args = format!(
+        "id={}&title=final&description=done+right&due_date=2031-06-15",
+        todo.id
+    );
+    call(&app, &alice, UpdateTodo::PATH, &args).await.unwrap();
+
+    let updated = &page(&app, &alice, 0).await.items[0];
+    assert_eq!(updated.id, todo.id);
+    assert_eq!(updated.title, "final");
+    assert_eq!(updated.description, "done right");
+    assert_eq!(updated.due_date, "2031-06-15");
+}
+
+#[actix_web::test]
+async fn completed_items_leave_the_open_list() {
+    let app = app().await;
+    let alice = sign_up(&app).await;
+    let todo = add(&app, &alice, "chore").await;
+    add(&app, &alice, "errand").await;
+
+    let args = format!("id={}", todo.id);
+    call(&app, &alice, CompleteTodo::PATH, &args).await.unwrap();
+
+    assert_eq!(titles(&page(&app, &alice, 0).await.items), ["errand"]);
+    let completed: PaginatedTodos = fetch(&app, &alice, GetCompletedTodos::PATH, "page=0").await;
+    assert_eq!(titles(&completed.items), ["chore"]);
+    assert!(completed.items[0].completed);
+    assert!(completed.items[0].completed_at.is_some());
+}
+
+#[actix_web::test]
+async fn deleted_items_go_to_the_trash() {
+    let app = app().await;
+    let alice = sign_up(&app).await;
+    let todo = add(&app, &alice, "mistake").await;
+
+    let args = format!("id={}", todo.id);
+    call(&app, &alice, DeleteTodo::PATH, &args).await.unwrap();
+
+    assert_eq!(page(&app, &alice, 0).await.total, 0);
+    let trashed: PaginatedTodos = fetch(&app, &alice, GetTrashedTodos::PATH, "page=0").await;
+    assert_eq!(titles(&trashed.items), ["mistake"]);
+    assert_eq!(
+        call(&app, &alice, DeleteTodo::PATH, "id=999").await,
+        Err(TodoError::NotFound { id: 999 })
+    );
+}
+
+#[actix_web::test]
+async fn search_matches_every_word_of_the_title() {
+    let app = app().await;
+    let alice = sign_up(&app).await;
+    add(&app, &alice, "buy milk").await;
+    add(&app, &alice, "buy bread").await;
+    add(&app, &alice, "bake bread").await;
+
+    let found: Vec<Todo> = fetch(&app, &alice, SearchTodo::PATH, "query=BREAD+buy").await;
+    assert_eq!(titles(&found), ["buy bread"]);
+    let found: Vec<Todo> = fetch(&app, &alice, SearchTodo::PATH, "query=bread").await;
+    assert_eq!(found.len(), 2);
+    let found: Vec<Todo> = fetch(&app, &alice, SearchTodo::PATH, "query=+").await;
+    assert!(found.is_empty());
+}
+
+#[actix_web::test]
+async fn pages_split_at_ten_items() {
+    let app = app().await;
+    let alice = sign_up(&app).await;
+    assert_eq!(page(&app, &alice, 0).await.total_pages, 0);
+
+    for n in 1..=20 {
+        add(&app, &alice, &format!("item-{n:02}")).await;
+    }
+    let first = page(&app, &alice, 0).await;
+    let second = page(&app, &alice, 1).await;
+    assert_eq!((first.total, first.total_pages), (20, 2));
+    assert_eq!(first.items.len(), 10);
+    assert_eq!(first.items[0].title, "item-20");
+    assert_eq!(second.items.len(), 10);
+    assert_eq!(second.items[9].title, "item-01");
+    assert!(page(&app, &alice, 2).await.items.is_empty());
+
+    add(&app, &alice, "item-21").await;
+    let last = page(&app, &alice, 2).await;
+    assert_eq!((last.total, last.page, last.total_pages), (21, 2, 3));
+    assert_eq!(titles(&last.items), ["item-01"]);
+}
+
+#[actix_web::test]
+async fn pages_past_the_end_are_empty() {
+    let app = app().await;
+    let alice = sign_up(&app).await;
+    add(&app, &alice, "only").await;
+
+    // 2^31 * 10 wraps to 0 in u32, which would show the first page again.
+    for far in [1 << 31, u32::MAX] {
+        let todos = page(&app, &alice, far).await;
+        assert!(todos.items.is_empty(), "page {far} is past the end");
+        assert_eq!((todos.total, todos.total_pages), (1, 1));
+    }
+}